@@ -1,19 +1,72 @@
 //! Persistent storage for in-progress review drafts.
 
-use crate::app::state::{PendingReviewCommentDraft, PendingReviewCommentSide, ReviewScreenState};
+use crate::app::{
+    files::{FileLock, critic_dir, sanitize_path_fragment, sibling_path, write_atomically},
+    state::{PendingReviewCommentDraft, PendingReviewCommentSide, ReviewScreenState},
+};
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, env, fmt::Write, path::PathBuf};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use tokio::fs;
 
-const CONFIG_DIR: &str = ".critic";
 const DRAFTS_DIR: &str = "drafts";
 const DRAFT_FORMAT_VERSION: u8 = 1;
+const BACKUP_EXTENSION: &str = "bak";
+const HISTORY_DIR: &str = "history";
+/// Number of superseded draft snapshots retained per pull request.
+const DRAFT_HISTORY_LIMIT: usize = 10;
+/// Minimum age of the newest snapshot before routine saves add another, so
+/// autosave does not flush history with every keystroke-sized edit.
+const DRAFT_HISTORY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+type DraftMigration = fn(Value) -> Result<Value>;
+type Drafts = (Vec<PendingReviewCommentDraft>, HashMap<String, String>);
+
+/// Upgrade steps keyed by the version they migrate from. Each step must emit
+/// the next version, so a draft walks the chain until it reaches
+/// [`DRAFT_FORMAT_VERSION`].
+const MIGRATIONS: &[(u8, DraftMigration)] = &[(0, migrate_v0_to_v1)];
 
 /// Disk-backed draft storage rooted at `~/.critic/drafts`.
+///
+/// Every save replaces the draft file atomically. Before a save drops a
+/// draft, or once [`DRAFT_HISTORY_INTERVAL`] has passed since the last
+/// snapshot, the superseded version is copied into
+/// `~/.critic/drafts/history/<draft>/`, so earlier drafts can be restored after
+/// an accidental wipe.
 #[derive(Debug, Clone)]
 pub struct DraftStore {
     root: PathBuf,
+    history_interval: Duration,
+    /// What this session last read from or wrote to each draft file, used to
+    /// detect edits made by other critic sessions.
+    known: Arc<Mutex<HashMap<PathBuf, KnownDraft>>>,
+}
+
+#[derive(Debug, Clone)]
+struct KnownDraft {
+    modified: Option<SystemTime>,
+    signature: String,
+    /// The draft as last read or written, the common base when merging
+    /// another session's edits.
+    draft: PersistedReviewDraft,
+}
+
+enum DiskDraft {
+    Missing,
+    Present(PersistedReviewDraft),
+    Unreadable {
+        backup_path: PathBuf,
+        reason: String,
+    },
 }
 
 /// Outcome of trying to restore a draft for a pull request.
@@ -24,59 +77,107 @@ pub enum LoadOutcome {
         pending_comments: Vec<PendingReviewCommentDraft>,
         reply_drafts: HashMap<String, String>,
     },
+    /// The draft could not be upgraded to the current format. The original file
+    /// was moved to `backup_path` so nothing is lost.
+    Unrecoverable {
+        backup_path: PathBuf,
+        reason: String,
+    },
+}
+
+/// Outcome of persisting a review's drafts.
+#[derive(Debug, Clone)]
+pub enum SaveOutcome {
+    Saved,
+    /// Another session changed the draft file; the merged drafts were written
+    /// and should replace the review's in-memory drafts.
+    Merged(MergedDrafts),
+    /// Another session holds the draft lock; nothing was written and the save
+    /// should be retried on a later tick.
+    Locked,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergedDrafts {
+    pub pending_comments: Vec<PendingReviewCommentDraft>,
+    pub reply_drafts: HashMap<String, String>,
+    pub added_comments: usize,
+    pub added_replies: usize,
+    /// Drafts the other session sent or deleted, dropped from this session.
+    pub removed_drafts: usize,
+    /// The other session's version of each reply draft both sessions edited,
+    /// keyed by thread. The merged drafts keep this session's version.
+    pub conflicting_replies: HashMap<String, String>,
 }
 
 impl DraftStore {
     pub async fn new() -> Result<Self> {
-        let home =
-            env::var_os("HOME").ok_or_else(|| anyhow!("HOME environment variable is not set"))?;
-        let root = PathBuf::from(home).join(CONFIG_DIR).join(DRAFTS_DIR);
+        Self::with_root(critic_dir()?.join(DRAFTS_DIR)).await
+    }
+
+    async fn with_root(root: PathBuf) -> Result<Self> {
         fs::create_dir_all(&root)
             .await
             .with_context(|| format!("failed to create draft directory {}", root.display()))?;
-        Ok(Self { root })
+        Ok(Self {
+            root,
+            history_interval: DRAFT_HISTORY_INTERVAL,
+            known: Arc::default(),
+        })
     }
 
     pub async fn load_for_review(&self, review: &ReviewScreenState) -> Result<LoadOutcome> {
         let path = self.file_path_for_review(review);
-        if !fs::try_exists(&path)
-            .await
-            .with_context(|| format!("failed to check draft file {}", path.display()))?
-        {
-            return Ok(LoadOutcome::None);
-        }
-
-        let raw = fs::read_to_string(&path)
-            .await
-            .with_context(|| format!("failed to read draft file {}", path.display()))?;
-        let persisted: PersistedReviewDraft = serde_json::from_str(&raw)
-            .with_context(|| format!("failed to parse draft file {}", path.display()))?;
-
-        if persisted.version != DRAFT_FORMAT_VERSION {
-            fs::remove_file(&path).await.with_context(|| {
-                format!("failed to delete outdated draft file {}", path.display())
-            })?;
-            return Ok(LoadOutcome::None);
-        }
+        let persisted = match self.read_draft_file(&path).await? {
+            DiskDraft::Missing => return Ok(LoadOutcome::None),
+            DiskDraft::Unreadable {
+                backup_path,
+                reason,
+            } => {
+                return Ok(LoadOutcome::Unrecoverable {
+                    backup_path,
+                    reason,
+                });
+            }
+            DiskDraft::Present(persisted) => persisted,
+        };
 
+        self.remember(&path, &persisted).await;
+        let (pending_comments, reply_drafts) = persisted.into_drafts();
         Ok(LoadOutcome::Loaded {
-            pending_comments: persisted
-                .pending_review_comments
-                .into_iter()
-                .map(PendingReviewCommentDraft::from)
-                .collect(),
-            reply_drafts: persisted.reply_drafts,
+            pending_comments,
+            reply_drafts,
         })
     }
 
-    pub async fn save_for_review(&self, review: &ReviewScreenState) -> Result<()> {
-        let pending_review_comments = review
-            .pending_review_comments()
-            .iter()
-            .cloned()
-            .map(PersistedPendingReviewComment::from)
-            .collect::<Vec<_>>();
-        let reply_drafts = review.reply_drafts.clone();
+    /// Persists the review's drafts.
+    ///
+    /// When the file on disk was changed by another critic session since this
+    /// session last read or wrote it, both sets of drafts are merged against the
+    /// version this session last saw instead of overwriting the other session's
+    /// work; the merged drafts are returned so the caller can adopt them.
+    pub async fn save_for_review(&self, review: &ReviewScreenState) -> Result<SaveOutcome> {
+        let path = self.file_path_for_review(review);
+        let Some(_lock) = FileLock::try_acquire(&path).await? else {
+            return Ok(SaveOutcome::Locked);
+        };
+
+        let mut pending_comments = review.pending_review_comments().to_vec();
+        let mut reply_drafts = review.reply_drafts.clone();
+        let mut outcome = SaveOutcome::Saved;
+
+        if self.changed_externally(&path).await
+            && let DiskDraft::Present(theirs) = self.read_draft_file(&path).await?
+        {
+            let base = self
+                .known_draft(&path)
+                .map(|known| known.draft.into_drafts())
+                .unwrap_or_default();
+            let merged = merge_drafts(base, (pending_comments, reply_drafts), theirs.into_drafts());
+            pending_comments = merged.pending_comments.clone();
+            reply_drafts = merged.reply_drafts.clone();
+            outcome = SaveOutcome::Merged(merged);
+        }
 
         let persisted = PersistedReviewDraft {
             version: DRAFT_FORMAT_VERSION,
@@ -84,83 +185,527 @@ impl DraftStore {
             repo: review.pull.repo.clone(),
             pull_number: review.pull.number,
             head_sha: review.pull.head_sha.clone(),
-            pending_review_comments,
+            pending_review_comments: pending_comments
+                .into_iter()
+                .map(PersistedPendingReviewComment::from)
+                .collect(),
             reply_drafts,
         };
 
-        let path = self.file_path_for_review(review);
         let content =
             serde_json::to_string_pretty(&persisted).context("failed to serialize draft file")?;
-        fs::write(&path, content)
-            .await
-            .with_context(|| format!("failed to write draft file {}", path.display()))?;
-        Ok(())
+        let existing = fs::read_to_string(&path).await.ok();
+        if existing.as_deref() == Some(content.as_str()) {
+            self.remember(&path, &persisted).await;
+            return Ok(outcome);
+        }
+
+        // The current draft is copied, not moved, so it survives a failed write.
+        if let Some(existing) = existing
+            && self.should_archive(review, &existing, &persisted).await?
+        {
+            self.archive_current_draft(review).await?;
+        }
+        write_atomically(&path, content.as_bytes()).await?;
+        self.remember(&path, &persisted).await;
+        Ok(outcome)
     }
 
-    pub async fn clear_for_review(&self, review: &ReviewScreenState) -> Result<()> {
+    /// Removes the active draft, keeping it in history so it can be restored.
+    ///
+    /// A draft written by another critic session since this session last saw
+    /// the file is left untouched. Returns `false` without touching anything
+    /// while another session holds the draft lock.
+    pub async fn clear_for_review(&self, review: &ReviewScreenState) -> Result<bool> {
         let path = self.file_path_for_review(review);
-        if !fs::try_exists(&path)
-            .await
-            .with_context(|| format!("failed to check draft file {}", path.display()))?
-        {
-            return Ok(());
+        let Some(_lock) = FileLock::try_acquire(&path).await? else {
+            return Ok(false);
+        };
+
+        let changed_externally = self.changed_externally(&path).await;
+        self.forget(&path);
+        if !changed_externally {
+            self.archive_current_draft(review).await?;
+            match fs::remove_file(&path).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(err)
+                        .with_context(|| format!("failed to remove draft {}", path.display()));
+                }
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// Swaps the active draft with the most recent history snapshot.
+    ///
+    /// The draft being replaced becomes the newest snapshot, so restoring
+    /// again undoes the restore. Returns `None` while another session holds
+    /// the draft lock.
+    pub async fn restore_previous_for_review(
+        &self,
+        review: &ReviewScreenState,
+    ) -> Result<Option<LoadOutcome>> {
+        let path = self.file_path_for_review(review);
+        let Some(lock) = FileLock::try_acquire(&path).await? else {
+            return Ok(None);
+        };
+
+        let history = self.history_snapshots(review).await?;
+        let Some(latest) = history.last() else {
+            return Ok(Some(LoadOutcome::None));
+        };
+
+        self.archive_current_draft(review).await?;
+        fs::rename(latest, &path).await.with_context(|| {
+            format!(
+                "failed to restore draft snapshot {} to {}",
+                latest.display(),
+                path.display()
+            )
+        })?;
+        drop(lock);
+        self.load_for_review(review).await.map(Some)
+    }
+
+    /// Reads and migrates the draft file at `path`, moving it aside when it
+    /// cannot be decoded.
+    async fn read_draft_file(&self, path: &Path) -> Result<DiskDraft> {
+        let raw = match fs::read_to_string(path).await {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(DiskDraft::Missing),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read draft file {}", path.display()));
+            }
+        };
+
+        match decode_draft(&raw) {
+            Ok(persisted) => Ok(DiskDraft::Present(persisted)),
+            Err(err) => {
+                let backup_path = next_backup_path(path).await;
+                fs::rename(path, &backup_path).await.with_context(|| {
+                    format!(
+                        "failed to back up draft file {} to {}",
+                        path.display(),
+                        backup_path.display()
+                    )
+                })?;
+                self.forget(path);
+                Ok(DiskDraft::Unreadable {
+                    backup_path,
+                    reason: format!("{err:#}"),
+                })
+            }
+        }
+    }
+
+    /// Returns whether the draft file differs from what this session last read
+    /// or wrote. The modification time is checked first so unchanged files are
+    /// not re-read on every save. The file is only read, never moved aside; a
+    /// file that no longer decodes counts as changed.
+    async fn changed_externally(&self, path: &Path) -> bool {
+        let modified = match fs::metadata(path).await {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(_) => return false,
+        };
+
+        let Some(known) = self.known_draft(path) else {
+            // A draft this session has never seen belongs to someone else.
+            return true;
+        };
+        if modified.is_some() && modified == known.modified {
+            return false;
+        }
+
+        match fs::read_to_string(path).await {
+            Ok(raw) => decode_draft(&raw)
+                .map_or(true, |persisted| persisted.signature() != known.signature),
+            Err(_) => false,
         }
-        fs::remove_file(&path)
+    }
+
+    async fn remember(&self, path: &Path, persisted: &PersistedReviewDraft) {
+        let modified = fs::metadata(path)
             .await
-            .with_context(|| format!("failed to delete draft file {}", path.display()))?;
+            .ok()
+            .and_then(|metadata| metadata.modified().ok());
+        let known = KnownDraft {
+            modified,
+            signature: persisted.signature(),
+            draft: persisted.clone(),
+        };
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_path_buf(), known);
+    }
+
+    fn forget(&self, path: &Path) {
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(path);
+    }
+
+    fn known_draft(&self, path: &Path) -> Option<KnownDraft> {
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+            .cloned()
+    }
+
+    /// Whether replacing the draft `existing` with `next` should snapshot it:
+    /// when `next` drops drafts, or when the newest snapshot is older than
+    /// the history interval.
+    async fn should_archive(
+        &self,
+        review: &ReviewScreenState,
+        existing: &str,
+        next: &PersistedReviewDraft,
+    ) -> Result<bool> {
+        if decode_draft(existing).is_ok_and(|current| next.draft_count() < current.draft_count()) {
+            return Ok(true);
+        }
+        let newest = self
+            .history_snapshots(review)
+            .await?
+            .last()
+            .and_then(|latest| snapshot_stamp(latest));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        Ok(newest
+            .is_none_or(|newest| now.saturating_sub(newest) >= self.history_interval.as_millis()))
+    }
+
+    /// Copies the active draft file, if any, into the history directory and
+    /// prunes snapshots beyond [`DRAFT_HISTORY_LIMIT`].
+    async fn archive_current_draft(&self, review: &ReviewScreenState) -> Result<()> {
+        let path = self.file_path_for_review(review);
+        let content = match fs::read(&path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read draft file {}", path.display()));
+            }
+        };
+
+        let history_dir = self.history_dir_for_review(review);
+        fs::create_dir_all(&history_dir).await.with_context(|| {
+            format!(
+                "failed to create draft history directory {}",
+                history_dir.display()
+            )
+        })?;
+
+        // Snapshots are ordered by stamp, so the archived draft must sort after
+        // every existing one even if the clock went backwards.
+        let newest = self
+            .history_snapshots(review)
+            .await?
+            .last()
+            .and_then(|latest| snapshot_stamp(latest));
+        let mut stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default()
+            .max(newest.map_or(0, |newest| newest + 1));
+        let mut snapshot = history_dir.join(format!("{stamp}.json"));
+        while fs::try_exists(&snapshot).await.unwrap_or(false) {
+            stamp += 1;
+            snapshot = history_dir.join(format!("{stamp}.json"));
+        }
+
+        write_atomically(&snapshot, &content).await?;
+
+        let history = self.history_snapshots(review).await?;
+        let excess = history.len().saturating_sub(DRAFT_HISTORY_LIMIT);
+        for stale in &history[..excess] {
+            fs::remove_file(stale)
+                .await
+                .with_context(|| format!("failed to prune draft snapshot {}", stale.display()))?;
+        }
         Ok(())
     }
 
+    /// Lists history snapshots for a review, oldest first.
+    async fn history_snapshots(&self, review: &ReviewScreenState) -> Result<Vec<PathBuf>> {
+        let history_dir = self.history_dir_for_review(review);
+        let mut entries = match fs::read_dir(&history_dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "failed to read draft history directory {}",
+                        history_dir.display()
+                    )
+                });
+            }
+        };
+
+        let mut snapshots = Vec::new();
+        while let Some(entry) = entries.next_entry().await.with_context(|| {
+            format!(
+                "failed to read draft history directory {}",
+                history_dir.display()
+            )
+        })? {
+            let path = entry.path();
+            if let Some(stamp) = snapshot_stamp(&path) {
+                snapshots.push((stamp, path));
+            }
+        }
+        snapshots.sort_by_key(|(stamp, _)| *stamp);
+        Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+    }
+
     pub fn draft_signature(review: &ReviewScreenState) -> String {
-        let mut signature = format!(
-            "{}/{}/{}@{}|{}|",
-            review.pull.owner,
-            review.pull.repo,
+        signature_for(
+            &review.pull.owner,
+            &review.pull.repo,
             review.pull.number,
-            review.pull.head_sha,
-            review.pending_review_comments().len()
+            &review.pull.head_sha,
+            review.pending_review_comments(),
+            &review.reply_drafts,
+        )
+    }
+
+    fn file_path_for_review(&self, review: &ReviewScreenState) -> PathBuf {
+        self.root.join(format!("{}.json", draft_file_stem(review)))
+    }
+
+    fn history_dir_for_review(&self, review: &ReviewScreenState) -> PathBuf {
+        self.root.join(HISTORY_DIR).join(draft_file_stem(review))
+    }
+}
+
+/// Parses the millisecond stamp a history snapshot is named after.
+fn snapshot_stamp(path: &Path) -> Option<u128> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(".json"))
+        .and_then(|stem| stem.parse::<u128>().ok())
+}
+
+fn signature_for(
+    owner: &str,
+    repo: &str,
+    pull_number: u64,
+    head_sha: &str,
+    pending_comments: &[PendingReviewCommentDraft],
+    reply_drafts: &HashMap<String, String>,
+) -> String {
+    let mut signature = format!(
+        "{owner}/{repo}/{pull_number}@{head_sha}|{}|",
+        pending_comments.len()
+    );
+    for comment in pending_comments {
+        let _ = write!(
+            signature,
+            "{}:{}:{:?}:{}:{:?}:{}|",
+            comment.id, comment.path, comment.side, comment.line, comment.start_line, comment.body
         );
-        for comment in review.pending_review_comments() {
-            let _ = write!(
-                signature,
-                "{}:{}:{:?}:{}:{:?}:{}|",
-                comment.id,
-                comment.path,
-                comment.side,
-                comment.line,
-                comment.start_line,
-                comment.body
-            );
+    }
+    let mut reply_entries = reply_drafts.iter().collect::<Vec<_>>();
+    reply_entries.sort_by_key(|(left, _)| *left);
+    for (key, value) in reply_entries {
+        let _ = write!(signature, "{key}:{value}|");
+    }
+    signature
+}
+
+/// Three-way merges this session's drafts with drafts written by another
+/// session, against `base`, the version this session last read or wrote.
+///
+/// Pending comments are matched by location and body. Comments either side
+/// added are kept, and comments from the other session get fresh ids; a
+/// comment in the base that the other session sent or deleted is dropped here
+/// too, so it does not come back. Reply drafts take whichever
+/// side changed them; when both sides changed the same reply differently,
+/// this session's reply wins and the other version is returned as a conflict.
+fn merge_drafts(base: Drafts, ours: Drafts, theirs: Drafts) -> MergedDrafts {
+    let (base_pending, base_replies) = base;
+    let (our_pending, mut reply_drafts) = ours;
+    let (their_pending, their_replies) = theirs;
+
+    let same_comment = |left: &PendingReviewCommentDraft, right: &PendingReviewCommentDraft| {
+        left.path == right.path
+            && left.side == right.side
+            && left.line == right.line
+            && left.start_line == right.start_line
+            && left.body == right.body
+    };
+    let contains = |comments: &[PendingReviewCommentDraft], comment: &PendingReviewCommentDraft| {
+        comments.iter().any(|other| same_comment(other, comment))
+    };
+
+    let mut removed_drafts = 0;
+    let mut pending_comments = Vec::with_capacity(our_pending.len());
+    for comment in &our_pending {
+        if contains(&base_pending, comment) && !contains(&their_pending, comment) {
+            removed_drafts += 1;
+        } else {
+            pending_comments.push(comment.clone());
         }
-        let mut reply_entries = review.reply_drafts.iter().collect::<Vec<_>>();
-        reply_entries.sort_by_key(|(left, _)| *left);
-        for (key, value) in reply_entries {
-            let _ = write!(signature, "{key}:{value}|");
+    }
+
+    let mut next_id = pending_comments
+        .iter()
+        .map(|comment| comment.id)
+        .max()
+        .unwrap_or(0)
+        .saturating_add(1);
+    let mut added_comments = 0;
+    for mut comment in their_pending {
+        if contains(&our_pending, &comment) || contains(&base_pending, &comment) {
+            continue;
         }
-        signature
+        comment.id = next_id;
+        next_id = next_id.saturating_add(1);
+        pending_comments.push(comment);
+        added_comments += 1;
     }
 
-    fn file_path_for_review(&self, review: &ReviewScreenState) -> PathBuf {
-        self.root.join(format!(
-            "{}__{}__{}.json",
-            sanitize_path_fragment(&review.pull.owner),
-            sanitize_path_fragment(&review.pull.repo),
-            review.pull.number
-        ))
-    }
-}
-
-fn sanitize_path_fragment(value: &str) -> String {
-    value
-        .chars()
-        .map(|ch| {
-            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
-                ch
-            } else {
-                '_'
+    let mut keys = reply_drafts
+        .keys()
+        .chain(their_replies.keys())
+        .chain(base_replies.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    let mut added_replies = 0;
+    let mut conflicting_replies = HashMap::new();
+    for key in keys {
+        let base = base_replies.get(&key);
+        let theirs = their_replies.get(&key);
+        let ours = reply_drafts.get(&key);
+        if ours == theirs || theirs == base {
+            continue;
+        }
+        if ours != base {
+            if let Some(theirs) = theirs {
+                conflicting_replies.insert(key, theirs.clone());
             }
-        })
-        .collect()
+            continue;
+        }
+        match theirs {
+            Some(theirs) => {
+                reply_drafts.insert(key, theirs.clone());
+                added_replies += 1;
+            }
+            None => {
+                reply_drafts.remove(&key);
+                removed_drafts += 1;
+            }
+        }
+    }
+
+    MergedDrafts {
+        pending_comments,
+        reply_drafts,
+        added_comments,
+        added_replies,
+        removed_drafts,
+        conflicting_replies,
+    }
+}
+
+fn draft_file_stem(review: &ReviewScreenState) -> String {
+    format!(
+        "{}__{}__{}",
+        sanitize_path_fragment(&review.pull.owner),
+        sanitize_path_fragment(&review.pull.repo),
+        review.pull.number
+    )
+}
+
+/// Returns the first unused backup path for `path`: `<draft>.bak`, then
+/// `<draft>.1.bak`, `<draft>.2.bak` and so on, so earlier backups are kept.
+async fn next_backup_path(path: &Path) -> PathBuf {
+    let mut attempt = 0;
+    loop {
+        let candidate = backup_path_for(path, attempt);
+        if !fs::try_exists(&candidate).await.unwrap_or(false) {
+            return candidate;
+        }
+        attempt += 1;
+    }
+}
+
+fn backup_path_for(path: &Path, attempt: usize) -> PathBuf {
+    if attempt == 0 {
+        sibling_path(path, BACKUP_EXTENSION)
+    } else {
+        sibling_path(path, &format!("{attempt}.{BACKUP_EXTENSION}"))
+    }
+}
+
+fn decode_draft(raw: &str) -> Result<PersistedReviewDraft> {
+    serde_json::from_str::<Value>(raw)
+        .context("draft file is not valid JSON")
+        .and_then(migrate_draft)
+}
+
+/// Upgrades a raw persisted draft to the current format and deserializes it.
+fn migrate_draft(mut value: Value) -> Result<PersistedReviewDraft> {
+    let mut version = draft_version(&value)?;
+    if version > DRAFT_FORMAT_VERSION {
+        return Err(anyhow!(
+            "draft format version {version} is newer than supported version {DRAFT_FORMAT_VERSION}"
+        ));
+    }
+
+    while version < DRAFT_FORMAT_VERSION {
+        let (_, step) = MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .ok_or_else(|| anyhow!("no migration available from draft format version {version}"))?;
+        value = step(value)
+            .with_context(|| format!("failed to migrate draft from version {version}"))?;
+        let next = draft_version(&value)?;
+        if next <= version {
+            return Err(anyhow!(
+                "migration from draft format version {version} did not advance the version"
+            ));
+        }
+        version = next;
+    }
+
+    serde_json::from_value(value).context("failed to decode migrated draft")
+}
+
+/// Reads the `version` field of a raw draft. Drafts written before versioning
+/// was introduced have no such field and are treated as version 0.
+fn draft_version(value: &Value) -> Result<u8> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| anyhow!("draft file is not a JSON object"))?;
+    match object.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u8::try_from(version).ok())
+            .ok_or_else(|| anyhow!("draft file has an invalid version field")),
+    }
+}
+
+/// Version 0 drafts predate the `version` field and reply drafts.
+fn migrate_v0_to_v1(mut value: Value) -> Result<Value> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("draft file is not a JSON object"))?;
+    object
+        .entry("reply_drafts")
+        .or_insert_with(|| Value::Object(Default::default()));
+    object.insert("version".to_owned(), Value::from(1u8));
+    Ok(value)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -175,6 +720,40 @@ struct PersistedReviewDraft {
     reply_drafts: HashMap<String, String>,
 }
 
+impl PersistedReviewDraft {
+    fn into_drafts(self) -> Drafts {
+        (
+            self.pending_review_comments
+                .into_iter()
+                .map(PendingReviewCommentDraft::from)
+                .collect(),
+            self.reply_drafts,
+        )
+    }
+
+    /// Number of pending comments and reply drafts.
+    fn draft_count(&self) -> usize {
+        self.pending_review_comments.len() + self.reply_drafts.len()
+    }
+
+    fn signature(&self) -> String {
+        let pending_comments = self
+            .pending_review_comments
+            .iter()
+            .cloned()
+            .map(PendingReviewCommentDraft::from)
+            .collect::<Vec<_>>();
+        signature_for(
+            &self.owner,
+            &self.repo,
+            self.pull_number,
+            &self.head_sha,
+            &pending_comments,
+            &self.reply_drafts,
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PersistedPendingReviewComment {
     id: u64,
@@ -235,3 +814,429 @@ impl From<PersistedPendingSide> for PendingReviewCommentSide {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{PullRequestData, PullRequestSummary};
+    use serde_json::json;
+    use std::{env, process};
+
+    fn v1_draft() -> Value {
+        json!({
+            "version": 1,
+            "owner": "clabby",
+            "repo": "critic",
+            "pull_number": 7,
+            "head_sha": "abc123",
+            "pending_review_comments": [{
+                "id": 1,
+                "path": "src/main.rs",
+                "side": "right",
+                "line": 10,
+                "start_line": null,
+                "body": "nit"
+            }],
+            "reply_drafts": { "thread:1": "thanks" }
+        })
+    }
+
+    #[test]
+    fn current_version_draft_is_loaded_unchanged() {
+        let draft = migrate_draft(v1_draft()).expect("draft should load");
+        assert_eq!(draft.version, DRAFT_FORMAT_VERSION);
+        assert_eq!(draft.pending_review_comments.len(), 1);
+        assert_eq!(
+            draft.reply_drafts.get("thread:1").map(String::as_str),
+            Some("thanks")
+        );
+    }
+
+    #[test]
+    fn unversioned_draft_is_migrated_to_current_version() {
+        let mut value = v1_draft();
+        let object = value.as_object_mut().expect("object");
+        object.remove("version");
+        object.remove("reply_drafts");
+
+        let draft = migrate_draft(value).expect("draft should migrate");
+        assert_eq!(draft.version, DRAFT_FORMAT_VERSION);
+        assert_eq!(draft.pending_review_comments[0].body, "nit");
+        assert!(draft.reply_drafts.is_empty());
+    }
+
+    #[test]
+    fn newer_draft_version_is_rejected() {
+        let mut value = v1_draft();
+        value["version"] = json!(DRAFT_FORMAT_VERSION + 1);
+        assert!(migrate_draft(value).is_err());
+    }
+
+    fn pending(id: u64, line: u64, body: &str) -> PendingReviewCommentDraft {
+        PendingReviewCommentDraft {
+            id,
+            path: "src/main.rs".to_owned(),
+            side: PendingReviewCommentSide::Right,
+            line,
+            start_line: None,
+            body: body.to_owned(),
+        }
+    }
+
+    #[test]
+    fn merge_unions_pending_comments_and_renumbers_theirs() {
+        let ours = (
+            vec![pending(1, 10, "ours"), pending(2, 20, "shared")],
+            HashMap::new(),
+        );
+        let theirs = (
+            vec![pending(1, 20, "shared"), pending(2, 30, "theirs")],
+            HashMap::new(),
+        );
+
+        let merged = merge_drafts(Drafts::default(), ours, theirs);
+
+        assert_eq!(merged.added_comments, 1);
+        let ids_and_bodies = merged
+            .pending_comments
+            .iter()
+            .map(|comment| (comment.id, comment.body.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids_and_bodies,
+            vec![(1, "ours"), (2, "shared"), (3, "theirs")]
+        );
+    }
+
+    #[test]
+    fn merge_keeps_our_reply_on_conflict() {
+        let ours = (
+            Vec::new(),
+            HashMap::from([("thread:1".to_owned(), "mine".to_owned())]),
+        );
+        let theirs = (
+            Vec::new(),
+            HashMap::from([
+                ("thread:1".to_owned(), "other".to_owned()),
+                ("thread:2".to_owned(), "new".to_owned()),
+            ]),
+        );
+
+        let merged = merge_drafts(Drafts::default(), ours, theirs);
+
+        assert_eq!(merged.added_replies, 1);
+        assert_eq!(
+            merged.conflicting_replies,
+            HashMap::from([("thread:1".to_owned(), "other".to_owned())])
+        );
+        assert_eq!(merged.reply_drafts["thread:1"], "mine");
+        assert_eq!(merged.reply_drafts["thread:2"], "new");
+    }
+
+    #[test]
+    fn merge_does_not_bring_back_drafts_either_side_removed() {
+        let base = (
+            vec![
+                pending(1, 10, "sent by them"),
+                pending(2, 20, "deleted by us"),
+            ],
+            HashMap::from([
+                ("thread:1".to_owned(), "sent by them".to_owned()),
+                ("thread:2".to_owned(), "edited by them".to_owned()),
+                ("thread:3".to_owned(), "deleted by us".to_owned()),
+            ]),
+        );
+        let ours = (
+            vec![pending(1, 10, "sent by them")],
+            HashMap::from([
+                ("thread:1".to_owned(), "sent by them".to_owned()),
+                ("thread:2".to_owned(), "edited by them".to_owned()),
+            ]),
+        );
+        let theirs = (
+            vec![pending(1, 20, "deleted by us")],
+            HashMap::from([
+                ("thread:2".to_owned(), "edited".to_owned()),
+                ("thread:3".to_owned(), "deleted by us".to_owned()),
+            ]),
+        );
+
+        let merged = merge_drafts(base, ours, theirs);
+
+        assert!(merged.pending_comments.is_empty());
+        assert_eq!(
+            merged.reply_drafts,
+            HashMap::from([("thread:2".to_owned(), "edited".to_owned())])
+        );
+        assert_eq!((merged.added_comments, merged.added_replies), (0, 1));
+        assert_eq!(merged.removed_drafts, 2);
+        assert!(merged.conflicting_replies.is_empty());
+    }
+
+    fn review_with_comment(body: &str) -> ReviewScreenState {
+        review_for_owner("clabby", body)
+    }
+
+    fn review_for_owner(owner: &str, body: &str) -> ReviewScreenState {
+        let pull = PullRequestSummary {
+            owner: owner.to_owned(),
+            repo: "critic".to_owned(),
+            number: 7,
+            title: "Example".to_owned(),
+            author: "dev".to_owned(),
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: "abc123".to_owned(),
+            base_sha: "def456".to_owned(),
+            html_url: None,
+            updated_at_unix_ms: 0,
+            created_at_unix_ms: 0,
+            is_draft: false,
+            reviewer_logins: Vec::new(),
+            review_status: None,
+            comment_count: None,
+        };
+        let data = PullRequestData {
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: "abc123".to_owned(),
+            base_sha: "def456".to_owned(),
+            changed_files: vec!["src/main.rs".to_owned()],
+            comments: Vec::new(),
+            description: None,
+        };
+        let mut review = ReviewScreenState::new(pull, data);
+        review.apply_restored_drafts(vec![pending(1, 10, body)], HashMap::new());
+        review
+    }
+
+    async fn temp_store(name: &str) -> DraftStore {
+        let root = env::temp_dir().join(format!("critic-draft-{name}-{}", process::id()));
+        DraftStore::with_root(root).await.expect("create store")
+    }
+
+    async fn file_names(dir: &Path) -> Vec<String> {
+        let mut entries = fs::read_dir(dir).await.expect("list dir");
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.expect("entry") {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn saves_replace_the_draft_without_leaving_temp_files() {
+        let store = temp_store("atomic").await;
+        for body in ["first", "second"] {
+            store
+                .save_for_review(&review_with_comment(body))
+                .await
+                .expect("save draft");
+        }
+
+        let review = review_with_comment("second");
+        let saved = fs::read_to_string(store.file_path_for_review(&review))
+            .await
+            .expect("read draft");
+        assert!(saved.contains("\"second\""));
+        assert_eq!(
+            file_names(&store.root).await,
+            vec!["clabby__critic__7.json", HISTORY_DIR]
+        );
+
+        fs::remove_dir_all(&store.root)
+            .await
+            .expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn history_keeps_only_the_latest_snapshots() {
+        let mut store = temp_store("history").await;
+        store.history_interval = Duration::ZERO;
+        for index in 0..DRAFT_HISTORY_LIMIT + 3 {
+            store
+                .save_for_review(&review_with_comment(&format!("draft {index}")))
+                .await
+                .expect("save draft");
+        }
+
+        let review = review_with_comment("latest");
+        let history = store.history_snapshots(&review).await.expect("history");
+        assert_eq!(history.len(), DRAFT_HISTORY_LIMIT);
+        let oldest = fs::read_to_string(&history[0])
+            .await
+            .expect("read snapshot");
+        assert!(oldest.contains("\"draft 2\""));
+
+        fs::remove_dir_all(&store.root)
+            .await
+            .expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn routine_saves_are_archived_at_most_once_per_interval() {
+        let store = temp_store("throttle").await;
+        for body in ["first", "second", "third"] {
+            store
+                .save_for_review(&review_with_comment(body))
+                .await
+                .expect("save draft");
+        }
+
+        // Only the first replaced draft is kept; later edits are too recent.
+        let review = review_with_comment("third");
+        let history = store.history_snapshots(&review).await.expect("history");
+        assert_eq!(history.len(), 1);
+        let snapshot = fs::read_to_string(&history[0])
+            .await
+            .expect("read snapshot");
+        assert!(snapshot.contains("\"first\""));
+
+        // Dropping a draft is always archived, however recent the last snapshot.
+        let mut emptied = review_with_comment("third");
+        emptied.apply_restored_drafts(Vec::new(), HashMap::new());
+        store.save_for_review(&emptied).await.expect("save draft");
+        let history = store.history_snapshots(&review).await.expect("history");
+        assert_eq!(history.len(), 2);
+        let snapshot = fs::read_to_string(&history[1])
+            .await
+            .expect("read snapshot");
+        assert!(snapshot.contains("\"third\""));
+
+        fs::remove_dir_all(&store.root)
+            .await
+            .expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn failed_writes_keep_the_current_draft_restorable() {
+        let store = temp_store("failed-write").await;
+        // The draft and lock file names fit, but the temp file written next
+        // to the draft is too long for the filesystem, so replacing it fails.
+        let owner = "o".repeat(234);
+        let original = review_for_owner(&owner, "original");
+        let path = store.file_path_for_review(&original);
+        let persisted = PersistedReviewDraft {
+            version: DRAFT_FORMAT_VERSION,
+            owner: owner.clone(),
+            repo: original.pull.repo.clone(),
+            pull_number: original.pull.number,
+            head_sha: original.pull.head_sha.clone(),
+            pending_review_comments: original
+                .pending_review_comments()
+                .iter()
+                .cloned()
+                .map(PersistedPendingReviewComment::from)
+                .collect(),
+            reply_drafts: HashMap::new(),
+        };
+        let content = serde_json::to_string_pretty(&persisted).expect("serialize");
+        fs::write(&path, &content).await.expect("write draft");
+        store.load_for_review(&original).await.expect("load draft");
+
+        let mut emptied = review_for_owner(&owner, "original");
+        emptied.apply_restored_drafts(Vec::new(), HashMap::new());
+        let error = store
+            .save_for_review(&emptied)
+            .await
+            .expect_err("temp file name is too long");
+        assert!(format!("{error:#}").contains("failed to create"));
+        assert_eq!(
+            fs::read_to_string(&path).await.expect("read draft"),
+            content
+        );
+
+        let restored = store
+            .restore_previous_for_review(&emptied)
+            .await
+            .expect("restore")
+            .expect("draft is unlocked");
+        match restored {
+            LoadOutcome::Loaded {
+                pending_comments, ..
+            } => assert_eq!(pending_comments[0].body, "original"),
+            other => panic!("expected a restored draft, got {other:?}"),
+        }
+
+        fs::remove_dir_all(&store.root)
+            .await
+            .expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn restoring_twice_brings_back_the_replaced_draft() {
+        let root = env::temp_dir().join(format!("critic-draft-restore-{}", process::id()));
+        let store = DraftStore::with_root(root.clone())
+            .await
+            .expect("create store");
+        store
+            .save_for_review(&review_with_comment("original"))
+            .await
+            .expect("save original");
+        let review = review_with_comment("replacement");
+        store
+            .save_for_review(&review)
+            .await
+            .expect("save replacement");
+
+        let restored_body = |outcome: LoadOutcome| match outcome {
+            LoadOutcome::Loaded {
+                pending_comments, ..
+            } => pending_comments[0].body.clone(),
+            other => panic!("expected a restored draft, got {other:?}"),
+        };
+        let first = store
+            .restore_previous_for_review(&review)
+            .await
+            .expect("first restore")
+            .expect("draft is unlocked");
+        assert_eq!(restored_body(first), "original");
+        let second = store
+            .restore_previous_for_review(&review)
+            .await
+            .expect("second restore")
+            .expect("draft is unlocked");
+        assert_eq!(restored_body(second), "replacement");
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[test]
+    fn backup_path_appends_extension() {
+        let path = PathBuf::from("/tmp/clabby__critic__7.json");
+        assert_eq!(
+            backup_path_for(&path, 0),
+            PathBuf::from("/tmp/clabby__critic__7.json.bak")
+        );
+        assert_eq!(
+            backup_path_for(&path, 2),
+            PathBuf::from("/tmp/clabby__critic__7.json.2.bak")
+        );
+    }
+
+    #[tokio::test]
+    async fn unreadable_drafts_do_not_overwrite_earlier_backups() {
+        let root = env::temp_dir().join(format!("critic-draft-backup-{}", process::id()));
+        let store = DraftStore::with_root(root.clone())
+            .await
+            .expect("create store");
+        let draft = root.join("draft.json");
+
+        for body in ["first", "second"] {
+            fs::write(&draft, body).await.expect("write draft");
+            let read = store.read_draft_file(&draft).await.expect("read draft");
+            assert!(matches!(read, DiskDraft::Unreadable { .. }));
+        }
+
+        let first = fs::read_to_string(backup_path_for(&draft, 0))
+            .await
+            .expect("first backup");
+        let second = fs::read_to_string(backup_path_for(&draft, 1))
+            .await
+            .expect("second backup");
+        assert_eq!((first.as_str(), second.as_str()), ("first", "second"));
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+}
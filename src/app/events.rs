@@ -1,30 +1,50 @@
 //! Background worker messages and async data-loading tasks.
 
 use crate::{
+    app::offline::{OfflineStore, QueuedMutation},
     domain::{PullRequestData, PullRequestDiffData, PullRequestSummary},
     github::{
+        client::GitHubClient,
         comments::{
-            SubmitPullRequestReviewRequest, SubmitReviewComment, fetch_pull_request_data,
-            reply_to_review_comment, set_review_thread_resolved, submit_pull_request_review,
+            PullRequestCommentsError, SubmitPullRequestReviewRequest, SubmitReviewComment,
+            fetch_pull_request_data, reply_to_review_comment, set_review_thread_resolved,
+            submit_pull_request_review,
         },
-        diff::fetch_pull_request_diff_data,
+        diff::{DiffEngine, fetch_pull_request_diff_data, read_cached_repository_file},
         pulls::{
-            fetch_open_pull_requests, fetch_pull_request_summary, fetch_viewer_login,
-            resolve_repository,
+            RepositoryRef, fetch_open_issue_titles, fetch_open_pull_requests,
+            fetch_pull_request_summary, fetch_viewer_login, resolve_repository,
         },
     },
 };
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 
+/// Per-repository config file read from the repository root.
+const REPO_CONFIG_FILE: &str = ".critic.toml";
+
 /// Message sent from background workers to the UI event loop.
 #[derive(Debug)]
 pub enum WorkerMessage {
+    RepositoryResolved {
+        repository_label: String,
+        /// Contents of `.critic.toml` from the cached clone, when present.
+        repo_config: Option<String>,
+    },
     ViewerLoginLoaded {
         viewer_login: Option<String>,
     },
+    /// Open issues and pull requests of the open review's repository, offered
+    /// for `#` completion.
+    IssueTitlesLoaded {
+        pull: PullRequestSummary,
+        issues: Vec<(u64, String)>,
+    },
     PullRequestsLoaded {
         repository_label: String,
         viewer_login: Option<String>,
+        /// Served from the response cache; a revalidated list follows.
+        from_cache: bool,
         result: Result<Vec<PullRequestSummary>, String>,
     },
     PullRequestResolved {
@@ -32,6 +52,11 @@ pub enum WorkerMessage {
         pull_number: u64,
         result: Result<PullRequestSummary, String>,
     },
+    /// A `#123` link in the same repository; `None` when it is an issue.
+    PullReferenceResolved {
+        url: String,
+        result: Result<Option<PullRequestSummary>, String>,
+    },
     PullRequestDataLoaded {
         pull: PullRequestSummary,
         result: Result<PullRequestData, String>,
@@ -46,10 +71,63 @@ pub enum WorkerMessage {
         clear_pending_review_comments: bool,
         result: Result<PullRequestData, String>,
     },
+    /// GitHub was unreachable, so the mutation was saved to the outbox instead.
+    /// Its drafts are kept until the replay delivers it.
+    MutationQueued {
+        pull: PullRequestSummary,
+        result: Result<(), String>,
+    },
+    /// Background refresh of the search list.
+    PullRequestsPolled {
+        result: Result<Vec<PullRequestSummary>, String>,
+    },
+    /// Background refresh of the open pull request.
+    PullRequestDataPolled {
+        pull: PullRequestSummary,
+        result: Result<PullRequestData, String>,
+    },
+    OutboxReplayed {
+        /// Queued mutations that were delivered.
+        sent: Vec<QueuedMutation>,
+        failures: Vec<String>,
+        remaining: usize,
+    },
 }
 
-/// Mutation actions supported by the review screen.
+/// Where worker tasks load pull requests from and send mutations to.
 #[derive(Debug, Clone)]
+pub enum DataSource {
+    /// The GitHub API; opened pull requests are also saved to the offline store.
+    Online {
+        client: GitHubClient,
+        store: Option<OfflineStore>,
+    },
+    /// Snapshots saved by earlier sessions; mutations are queued in the outbox.
+    Offline(OfflineStore),
+}
+
+impl DataSource {
+    pub fn client(&self) -> Option<&GitHubClient> {
+        match self {
+            Self::Online { client, .. } => Some(client),
+            Self::Offline(_) => None,
+        }
+    }
+
+    pub fn store(&self) -> Option<&OfflineStore> {
+        match self {
+            Self::Online { store, .. } => store.as_ref(),
+            Self::Offline(store) => Some(store),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Offline(_))
+    }
+}
+
+/// Mutation actions supported by the review screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MutationRequest {
     ReplyToReviewComment {
         owner: String,
@@ -76,24 +154,44 @@ pub enum MutationRequest {
 /// Spawns async loading of the open pull request list.
 pub fn spawn_load_pull_requests(
     tx: UnboundedSender<WorkerMessage>,
-    client: octocrab::Octocrab,
+    source: DataSource,
     owner: Option<String>,
     repo: Option<String>,
 ) {
     tokio::spawn(async move {
+        let client = match source {
+            DataSource::Online { client, .. } => client,
+            DataSource::Offline(store) => {
+                let result = store
+                    .list_pulls(owner.as_deref(), repo.as_deref())
+                    .await
+                    .map_err(|error| format!("{error:#}"));
+                let _ = tx.send(WorkerMessage::PullRequestsLoaded {
+                    repository_label: offline_label(owner.as_deref(), repo.as_deref()),
+                    viewer_login: None,
+                    from_cache: false,
+                    result,
+                });
+                return;
+            }
+        };
         let message = match resolve_repository(owner, repo).await {
             Ok(repository) => {
+                send_repository_resolved(&tx, &client, &repository).await;
                 let label = repository.label();
+                send_cached_pull_requests(&tx, &client, &repository).await;
                 let viewer_login = fetch_viewer_login(&client).await.ok();
                 match fetch_open_pull_requests(&client, &repository).await {
                     Ok(pulls) => WorkerMessage::PullRequestsLoaded {
                         repository_label: label,
                         viewer_login,
+                        from_cache: false,
                         result: Ok(pulls),
                     },
                     Err(error) => WorkerMessage::PullRequestsLoaded {
                         repository_label: label,
                         viewer_login,
+                        from_cache: false,
                         result: Err(error.to_string()),
                     },
                 }
@@ -101,6 +199,7 @@ pub fn spawn_load_pull_requests(
             Err(error) => WorkerMessage::PullRequestsLoaded {
                 repository_label: "(unknown repository)".to_owned(),
                 viewer_login: None,
+                from_cache: false,
                 result: Err(error.to_string()),
             },
         };
@@ -109,25 +208,104 @@ pub fn spawn_load_pull_requests(
     });
 }
 
+/// Sends the pull request list from the last session's cached responses so the
+/// search screen is populated while the live list is revalidated.
+async fn send_cached_pull_requests(
+    tx: &UnboundedSender<WorkerMessage>,
+    client: &GitHubClient,
+    repository: &RepositoryRef,
+) {
+    let cached = client.cache_only();
+    let Ok(pulls) = fetch_open_pull_requests(&cached, repository).await else {
+        return;
+    };
+    let _ = tx.send(WorkerMessage::PullRequestsLoaded {
+        repository_label: repository.label(),
+        viewer_login: fetch_viewer_login(&cached).await.ok(),
+        from_cache: true,
+        result: Ok(pulls),
+    });
+}
+
+/// Reports the resolved repository along with its checked-in `.critic.toml`.
+async fn send_repository_resolved(
+    tx: &UnboundedSender<WorkerMessage>,
+    client: &GitHubClient,
+    repository: &RepositoryRef,
+) {
+    let repo_config = read_cached_repository_file(
+        client,
+        &repository.owner,
+        &repository.repo,
+        REPO_CONFIG_FILE,
+    )
+    .await
+    .ok()
+    .flatten();
+    let _ = tx.send(WorkerMessage::RepositoryResolved {
+        repository_label: repository.label(),
+        repo_config,
+    });
+}
+
 /// Loads authenticated viewer login for header display.
-pub fn spawn_load_viewer_login(tx: UnboundedSender<WorkerMessage>, client: octocrab::Octocrab) {
+pub fn spawn_load_viewer_login(tx: UnboundedSender<WorkerMessage>, client: GitHubClient) {
     tokio::spawn(async move {
         let viewer_login = fetch_viewer_login(&client).await.ok();
         let _ = tx.send(WorkerMessage::ViewerLoginLoaded { viewer_login });
     });
 }
 
+/// Loads the repository's open issues for `#` completion. Best effort: the
+/// composer falls back to the pull request list when this fails.
+pub fn spawn_load_issue_titles(
+    tx: UnboundedSender<WorkerMessage>,
+    client: GitHubClient,
+    pull: PullRequestSummary,
+) {
+    tokio::spawn(async move {
+        let repository = RepositoryRef {
+            owner: pull.owner.clone(),
+            repo: pull.repo.clone(),
+        };
+        if let Ok(issues) = fetch_open_issue_titles(&client, &repository).await {
+            let _ = tx.send(WorkerMessage::IssueTitlesLoaded { pull, issues });
+        }
+    });
+}
+
 /// Resolves and loads a specific pull request summary for direct-open startup.
 pub fn spawn_load_specific_pull_request(
     tx: UnboundedSender<WorkerMessage>,
-    client: octocrab::Octocrab,
+    source: DataSource,
     owner: Option<String>,
     repo: Option<String>,
     pull_number: u64,
 ) {
     tokio::spawn(async move {
+        let client = match source {
+            DataSource::Online { client, .. } => client,
+            DataSource::Offline(store) => {
+                let result = match store.list_pulls(owner.as_deref(), repo.as_deref()).await {
+                    Ok(pulls) => pulls
+                        .into_iter()
+                        .find(|pull| pull.number == pull_number)
+                        .ok_or_else(|| {
+                            format!("pull request #{pull_number} has no offline snapshot")
+                        }),
+                    Err(error) => Err(format!("{error:#}")),
+                };
+                let _ = tx.send(WorkerMessage::PullRequestResolved {
+                    repository_label: offline_label(owner.as_deref(), repo.as_deref()),
+                    pull_number,
+                    result,
+                });
+                return;
+            }
+        };
         let message = match resolve_repository(owner, repo).await {
             Ok(repository) => {
+                send_repository_resolved(&tx, &client, &repository).await;
                 let label = repository.label();
                 match fetch_pull_request_summary(&client, &repository, pull_number).await {
                     Ok(pull) => WorkerMessage::PullRequestResolved {
@@ -163,98 +341,160 @@ pub fn spawn_load_specific_pull_request(
     });
 }
 
+/// Looks up `#number` in the open pull request's repository so a link to it
+/// can be opened in the review screen, or in the browser when it is an issue.
+pub fn spawn_resolve_pull_reference(
+    tx: UnboundedSender<WorkerMessage>,
+    source: DataSource,
+    repository: RepositoryRef,
+    pull_number: u64,
+    url: String,
+) {
+    tokio::spawn(async move {
+        let result = match &source {
+            DataSource::Online { client, .. } => {
+                match fetch_pull_request_summary(client, &repository, pull_number).await {
+                    Ok(pull) => Ok(Some(pull)),
+                    Err(error) if error.is_not_found() => Ok(None),
+                    Err(error) => Err(error.to_string()),
+                }
+            }
+            DataSource::Offline(store) => store
+                .list_pulls(Some(&repository.owner), Some(&repository.repo))
+                .await
+                .map(|pulls| pulls.into_iter().find(|pull| pull.number == pull_number))
+                .map_err(|error| format!("{error:#}")),
+        };
+
+        let _ = tx.send(WorkerMessage::PullReferenceResolved { url, result });
+    });
+}
+
 /// Spawns async loading of comments for a selected pull request.
 pub fn spawn_load_pull_request_data(
     tx: UnboundedSender<WorkerMessage>,
-    client: octocrab::Octocrab,
+    source: DataSource,
     pull: PullRequestSummary,
 ) {
     tokio::spawn(async move {
-        let result = fetch_pull_request_data(&client, &pull)
-            .await
-            .map_err(|error| error.to_string());
+        let result = match &source {
+            DataSource::Online { client, store } => {
+                fetch_and_save_pull_request_data(client, store.as_ref(), &pull).await
+            }
+            DataSource::Offline(store) => load_snapshot(store, &pull).await.map(|(data, _)| data),
+        };
 
         let _ = tx.send(WorkerMessage::PullRequestDataLoaded { pull, result });
     });
 }
 
+/// Re-fetches the open pull request list in the background.
+pub fn spawn_poll_pull_requests(
+    tx: UnboundedSender<WorkerMessage>,
+    client: GitHubClient,
+    owner: Option<String>,
+    repo: Option<String>,
+) {
+    tokio::spawn(async move {
+        let result = match resolve_repository(owner, repo).await {
+            Ok(repository) => fetch_open_pull_requests(&client, &repository)
+                .await
+                .map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        let _ = tx.send(WorkerMessage::PullRequestsPolled { result });
+    });
+}
+
+/// Re-fetches comments for the open pull request in the background.
+pub fn spawn_poll_pull_request_data(
+    tx: UnboundedSender<WorkerMessage>,
+    client: GitHubClient,
+    store: Option<OfflineStore>,
+    pull: PullRequestSummary,
+) {
+    tokio::spawn(async move {
+        let result = fetch_and_save_pull_request_data(&client, store.as_ref(), &pull).await;
+        let _ = tx.send(WorkerMessage::PullRequestDataPolled { pull, result });
+    });
+}
+
 /// Spawns async loading of pull request diffs for the active pull request.
 pub fn spawn_load_pull_request_diff(
     tx: UnboundedSender<WorkerMessage>,
+    source: DataSource,
     pull: PullRequestSummary,
     changed_files: Vec<String>,
+    engine: DiffEngine,
 ) {
     tokio::spawn(async move {
-        let result = fetch_pull_request_diff_data(&pull, &changed_files)
-            .await
-            .map_err(|error| error.to_string());
+        let result = match &source {
+            DataSource::Online { client, store } => {
+                let result = fetch_pull_request_diff_data(client, &pull, &changed_files, engine)
+                    .await
+                    .map_err(|error| error.to_string());
+                if let (Ok(diff), Some(store)) = (&result, store) {
+                    // Best effort: failing to save only affects later offline sessions.
+                    let _ = store.save_diff(&pull, diff).await;
+                }
+                result
+            }
+            DataSource::Offline(store) => {
+                load_snapshot(store, &pull)
+                    .await
+                    .and_then(|(_, diff)| {
+                        diff.ok_or_else(|| {
+                            format!(
+                                "diff for pull request #{} was not saved; open the Diff tab online first",
+                                pull.number
+                            )
+                        })
+                    })
+            }
+        };
         let _ = tx.send(WorkerMessage::PullRequestDiffLoaded { pull, result });
     });
 }
 
 /// Spawns a mutation followed by a pull request comment refresh.
+///
+/// When GitHub cannot be reached (or the source is offline) the mutation is
+/// queued in the outbox instead.
 pub fn spawn_apply_mutation(
     tx: UnboundedSender<WorkerMessage>,
-    client: octocrab::Octocrab,
+    source: DataSource,
     pull: PullRequestSummary,
     mutation: MutationRequest,
     clear_reply_root_key: Option<String>,
 ) {
     tokio::spawn(async move {
-        let (mutation_result, clear_pending_review_comments) = match mutation {
-            MutationRequest::ReplyToReviewComment {
-                owner,
-                repo,
-                pull_number,
-                comment_id,
-                body,
-            } => (
-                reply_to_review_comment(&client, &owner, &repo, pull_number, comment_id, &body)
-                    .await
-                    .map(|_| ()),
-                false,
-            ),
-            MutationRequest::SetReviewThreadResolved {
-                thread_id,
-                resolved,
-            } => (
-                set_review_thread_resolved(&client, &thread_id, resolved)
-                    .await
-                    .map(|_| ()),
-                false,
-            ),
-            MutationRequest::SubmitPullRequestReview {
-                owner,
-                repo,
-                pull_number,
-                event,
-                body,
-                comments,
-                expected_head_sha,
-            } => (
-                submit_pull_request_review(
-                    &client,
-                    SubmitPullRequestReviewRequest {
-                        owner: &owner,
-                        repo: &repo,
-                        pull_number,
-                        event: &event,
-                        body: &body,
-                        comments: &comments,
-                        expected_head_sha: &expected_head_sha,
-                    },
-                )
-                .await
-                .map(|_| ()),
-                true,
-            ),
+        let clear_pending_review_comments =
+            matches!(mutation, MutationRequest::SubmitPullRequestReview { .. });
+        let mutation_result = match &source {
+            DataSource::Online { client, .. } => apply_mutation(client, mutation.clone()).await,
+            DataSource::Offline(_) => Err(PullRequestCommentsError::Unreachable(
+                "running offline".to_owned(),
+            )),
         };
 
-        let result = match mutation_result {
-            Ok(()) => fetch_pull_request_data(&client, &pull)
+        if let Err(error) = &mutation_result
+            && error.is_unreachable()
+            && let Some(store) = source.store()
+        {
+            let result = store
+                .enqueue(&pull, &mutation, clear_reply_root_key)
                 .await
-                .map_err(|error| error.to_string()),
-            Err(error) => Err(error.to_string()),
+                .map_err(|error| format!("failed to queue mutation: {error:#}"));
+            let _ = tx.send(WorkerMessage::MutationQueued { pull, result });
+            return;
+        }
+
+        let result = match (mutation_result, source.client()) {
+            (Ok(()), Some(client)) => {
+                fetch_and_save_pull_request_data(client, source.store(), &pull).await
+            }
+            (Ok(()), None) => unreachable!("offline mutations are always queued"),
+            (Err(error), _) => Err(error.to_string()),
         };
 
         let _ = tx.send(WorkerMessage::MutationApplied {
@@ -265,3 +505,142 @@ pub fn spawn_apply_mutation(
         });
     });
 }
+
+/// Sends queued mutations in order, stopping at the first that still cannot
+/// reach GitHub or that another session is sending. Each entry is marked in
+/// flight before it is sent; rejected mutations are marked failed and skipped
+/// from then on.
+pub fn spawn_replay_outbox(
+    tx: UnboundedSender<WorkerMessage>,
+    client: GitHubClient,
+    store: OfflineStore,
+) {
+    tokio::spawn(async move {
+        let mut sent = Vec::new();
+        let mut failures = Vec::new();
+        loop {
+            let entry = match store.claim_next().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(error) => {
+                    failures.push(format!("{error:#}"));
+                    break;
+                }
+            };
+            match apply_mutation(&client, entry.mutation.clone()).await {
+                Err(error) if error.is_unreachable() => {
+                    if let Err(error) = store.release(entry.id).await {
+                        failures.push(format!("{error:#}"));
+                    }
+                    break;
+                }
+                Err(error) => {
+                    failures.push(format!(
+                        "queued mutation for #{}: {error}",
+                        entry.pull.number
+                    ));
+                    if let Err(error) = store.fail(entry.id, &error.to_string()).await {
+                        failures.push(format!("{error:#}"));
+                    }
+                }
+                Ok(()) => {
+                    if let Err(error) = store.complete(entry.id).await {
+                        failures.push(format!("{error:#}"));
+                    }
+                    sent.push(entry);
+                }
+            }
+        }
+
+        let remaining = match store.pending_mutations().await {
+            Ok(pending) => pending.len(),
+            Err(error) => {
+                failures.push(format!("{error:#}"));
+                0
+            }
+        };
+        let _ = tx.send(WorkerMessage::OutboxReplayed {
+            sent,
+            failures,
+            remaining,
+        });
+    });
+}
+
+async fn fetch_and_save_pull_request_data(
+    client: &GitHubClient,
+    store: Option<&OfflineStore>,
+    pull: &PullRequestSummary,
+) -> Result<PullRequestData, String> {
+    let data = fetch_pull_request_data(client, pull)
+        .await
+        .map_err(|error| error.to_string())?;
+    if let Some(store) = store {
+        // Best effort: failing to save only affects later offline sessions.
+        let _ = store.save_data(pull, &data).await;
+    }
+    Ok(data)
+}
+
+async fn load_snapshot(
+    store: &OfflineStore,
+    pull: &PullRequestSummary,
+) -> Result<(PullRequestData, Option<PullRequestDiffData>), String> {
+    match store.load(&pull.owner, &pull.repo, pull.number).await {
+        Ok(Some(snapshot)) => Ok((snapshot.data, snapshot.diff)),
+        Ok(None) => Err(format!(
+            "pull request #{} has no offline snapshot; open it online first",
+            pull.number
+        )),
+        Err(error) => Err(format!("{error:#}")),
+    }
+}
+
+fn offline_label(owner: Option<&str>, repo: Option<&str>) -> String {
+    match (owner, repo) {
+        (Some(owner), Some(repo)) => format!("{owner}/{repo} (offline)"),
+        _ => "saved pull requests (offline)".to_owned(),
+    }
+}
+
+async fn apply_mutation(
+    client: &GitHubClient,
+    mutation: MutationRequest,
+) -> Result<(), PullRequestCommentsError> {
+    match mutation {
+        MutationRequest::ReplyToReviewComment {
+            owner,
+            repo,
+            pull_number,
+            comment_id,
+            body,
+        } => reply_to_review_comment(client, &owner, &repo, pull_number, comment_id, &body).await,
+        MutationRequest::SetReviewThreadResolved {
+            thread_id,
+            resolved,
+        } => set_review_thread_resolved(client, &thread_id, resolved).await,
+        MutationRequest::SubmitPullRequestReview {
+            owner,
+            repo,
+            pull_number,
+            event,
+            body,
+            comments,
+            expected_head_sha,
+        } => {
+            submit_pull_request_review(
+                client,
+                SubmitPullRequestReviewRequest {
+                    owner: &owner,
+                    repo: &repo,
+                    pull_number,
+                    event: &event,
+                    body: &body,
+                    comments: &comments,
+                    expected_head_sha: &expected_head_sha,
+                },
+            )
+            .await
+        }
+    }
+}
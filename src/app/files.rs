@@ -0,0 +1,308 @@
+//! Filesystem helpers shared by the stores under `~/.critic`.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs, io::AsyncWriteExt, task::JoinHandle, time};
+
+const CONFIG_DIR: &str = ".critic";
+const TEMP_EXTENSION: &str = "tmp";
+const LOCK_EXTENSION: &str = "lock";
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+/// How often a held lock's modification time is refreshed; well under
+/// [`LOCK_STALE_AFTER`] so a live holder is never taken for a crashed one.
+const LOCK_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Delay between attempts of a waiting [`FileLock::acquire`].
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Distinguishes temp files of concurrent writes within this process.
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// `~/.critic`, where configuration and every store live.
+pub fn critic_dir() -> Result<PathBuf> {
+    let home =
+        env::var_os("HOME").ok_or_else(|| anyhow!("HOME environment variable is not set"))?;
+    Ok(PathBuf::from(home).join(CONFIG_DIR))
+}
+
+/// Milliseconds since the Unix epoch, or 0 if the clock is before it.
+pub fn unix_ms_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Writes `content` to a temp file next to `path`, syncs it, and renames it
+/// over `path` so readers never observe a partially written file.
+///
+/// The temp name is unique to this write, so concurrent writers never share
+/// one; the last rename wins.
+pub async fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let temp_path = sibling_path(
+        path,
+        &format!(
+            "{}.{}.{TEMP_EXTENSION}",
+            process::id(),
+            NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let mut file = fs::File::create(&temp_path)
+        .await
+        .with_context(|| format!("failed to create {}", temp_path.display()))?;
+    let written = async {
+        file.write_all(content)
+            .await
+            .with_context(|| format!("failed to write {}", temp_path.display()))?;
+        file.sync_all()
+            .await
+            .with_context(|| format!("failed to sync {}", temp_path.display()))
+    }
+    .await;
+    drop(file);
+
+    let renamed = match written {
+        Ok(()) => fs::rename(&temp_path, path)
+            .await
+            .with_context(|| format!("failed to replace {}", path.display())),
+        Err(err) => Err(err),
+    };
+    if renamed.is_err() {
+        let _ = fs::remove_file(&temp_path).await;
+    }
+    renamed
+}
+
+/// Advisory lock guarding read-modify-write cycles on one file shared between
+/// critic sessions.
+///
+/// The lock is a `<file>.lock` file created exclusively; it is removed on drop.
+/// While held, its modification time is refreshed every
+/// [`LOCK_HEARTBEAT_INTERVAL`]. Locks not refreshed for [`LOCK_STALE_AFTER`]
+/// are assumed to belong to a crashed session and are broken.
+pub struct FileLock {
+    path: PathBuf,
+    heartbeat: JoinHandle<()>,
+}
+
+impl FileLock {
+    /// Takes the lock if it is free, returning `None` while another session
+    /// holds it so callers on the UI loop can retry on a later tick.
+    pub async fn try_acquire(locked_path: &Path) -> Result<Option<Self>> {
+        Self::try_acquire_with_heartbeat(locked_path, LOCK_HEARTBEAT_INTERVAL).await
+    }
+
+    async fn try_acquire_with_heartbeat(
+        locked_path: &Path,
+        heartbeat: Duration,
+    ) -> Result<Option<Self>> {
+        let path = sibling_path(locked_path, LOCK_EXTENSION);
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(mut file) => {
+                    let _ = file.write_all(process::id().to_string().as_bytes()).await;
+                    let heartbeat = spawn_heartbeat(path.clone(), heartbeat);
+                    return Ok(Some(Self { path, heartbeat }));
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    if !lock_is_stale(&path).await || fs::remove_file(&path).await.is_err() {
+                        return Ok(None);
+                    }
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("failed to lock {}", locked_path.display()));
+                }
+            }
+        }
+    }
+
+    /// Waits for the lock, for background tasks that may block. Gives up once
+    /// a held lock would have been broken as stale but could not be.
+    pub async fn acquire(locked_path: &Path) -> Result<Self> {
+        let deadline = Instant::now() + 2 * LOCK_STALE_AFTER;
+        loop {
+            if let Some(lock) = Self::try_acquire(locked_path).await? {
+                return Ok(lock);
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "timed out waiting for the lock on {}",
+                    locked_path.display()
+                );
+            }
+            time::sleep(LOCK_RETRY_DELAY).await;
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Refreshes the lock file's modification time every `interval` until it is
+/// aborted or the file disappears.
+fn spawn_heartbeat(path: PathBuf, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = time::interval(interval);
+        // The first tick completes immediately; the file was just created.
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            if touch(&path).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+async fn touch(path: &Path) -> io::Result<()> {
+    let file = fs::OpenOptions::new().write(true).open(path).await?;
+    file.into_std().await.set_modified(SystemTime::now())
+}
+
+async fn lock_is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age >= LOCK_STALE_AFTER)
+}
+
+/// `path` with `.<extension>` appended to its full file name.
+pub fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(".");
+    sibling.push(extension);
+    PathBuf::from(sibling)
+}
+
+/// `value` with every character outside `[A-Za-z0-9._-]` replaced by `_`, so
+/// it can be used as a single path component.
+pub fn sanitize_path_fragment(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FileLock, LOCK_EXTENSION, LOCK_STALE_AFTER, lock_is_stale, sibling_path, write_atomically,
+    };
+    use std::{env, process, time::Duration};
+    use tokio::{fs, time};
+
+    #[tokio::test]
+    async fn file_lock_is_exclusive_until_dropped() {
+        let root = env::temp_dir().join(format!("critic-file-lock-{}", process::id()));
+        fs::create_dir_all(&root).await.expect("create temp dir");
+        let draft = root.join("draft.json");
+
+        let lock = FileLock::try_acquire(&draft)
+            .await
+            .expect("first lock")
+            .expect("lock is free");
+        assert!(
+            fs::try_exists(sibling_path(&draft, LOCK_EXTENSION))
+                .await
+                .expect("check lock file")
+        );
+        assert!(
+            FileLock::try_acquire(&draft)
+                .await
+                .expect("second lock")
+                .is_none()
+        );
+        drop(lock);
+        let relocked = FileLock::try_acquire(&draft)
+            .await
+            .expect("lock after drop");
+        assert!(relocked.is_some());
+        drop(relocked);
+
+        let waited = FileLock::acquire(&draft).await.expect("waiting lock");
+        drop(waited);
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn held_locks_are_kept_fresh() {
+        let root = env::temp_dir().join(format!("critic-file-lock-heartbeat-{}", process::id()));
+        fs::create_dir_all(&root).await.expect("create temp dir");
+        let draft = root.join("draft.json");
+        let lock_path = sibling_path(&draft, LOCK_EXTENSION);
+
+        let lock = FileLock::try_acquire_with_heartbeat(&draft, Duration::from_millis(20))
+            .await
+            .expect("lock")
+            .expect("lock is free");
+        // Pretend the holder has been working longer than the stale window.
+        let backdated = std::time::SystemTime::now() - 2 * LOCK_STALE_AFTER;
+        std::fs::File::options()
+            .write(true)
+            .open(&lock_path)
+            .and_then(|file| file.set_modified(backdated))
+            .expect("backdate lock");
+        assert!(lock_is_stale(&lock_path).await);
+
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(!lock_is_stale(&lock_path).await);
+        assert!(
+            FileLock::try_acquire(&draft)
+                .await
+                .expect("second lock")
+                .is_none()
+        );
+
+        drop(lock);
+        assert!(!fs::try_exists(&lock_path).await.expect("check lock file"));
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn atomic_writes_replace_the_file_without_leaving_temp_files() {
+        let root = env::temp_dir().join(format!("critic-atomic-write-{}", process::id()));
+        fs::create_dir_all(&root).await.expect("create temp dir");
+        let path = root.join("state.json");
+
+        write_atomically(&path, b"first")
+            .await
+            .expect("first write");
+        write_atomically(&path, b"second")
+            .await
+            .expect("second write");
+        assert_eq!(fs::read(&path).await.expect("read"), b"second");
+
+        let mut entries = fs::read_dir(&root).await.expect("list temp dir");
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.expect("entry") {
+            names.push(entry.file_name());
+        }
+        assert_eq!(names, vec!["state.json"]);
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+}
@@ -0,0 +1,550 @@
+//! Named actions and their configurable key bindings.
+
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+
+/// Screen a binding applies to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum KeyContext {
+    Search,
+    Review,
+    /// The in-app comment composer; unbound keys edit the text.
+    Composer,
+    /// The template picker; unbound keys edit the filter.
+    TemplatePicker,
+}
+
+impl KeyContext {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Search => "search",
+            Self::Review => "review",
+            Self::Composer => "composer",
+            Self::TemplatePicker => "template_picker",
+        }
+    }
+}
+
+macro_rules! actions {
+    ($($variant:ident => $name:literal),+ $(,)?) => {
+        /// A user-triggerable command that keys can be bound to.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+        pub enum Action {
+            $($variant),+
+        }
+
+        impl Action {
+            /// Every action, in the order they are documented.
+            pub const ALL: &[Action] = &[$(Self::$variant),+];
+
+            /// Name used for this action in `config.toml`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name),+
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    Quit => "quit",
+    MoveDown => "move_down",
+    MoveUp => "move_up",
+    Open => "open",
+    OpenInBrowser => "open_in_browser",
+    FocusSearch => "focus_search",
+    ToggleScope => "toggle_scope",
+    ToggleStatus => "toggle_status",
+    ToggleSort => "toggle_sort",
+    Refresh => "refresh",
+    Back => "back",
+    NextTab => "next_tab",
+    ToggleFocus => "toggle_focus",
+    ScrollDown => "scroll_down",
+    ScrollUp => "scroll_up",
+    ToggleCollapse => "toggle_collapse",
+    NextHunk => "next_hunk",
+    PrevHunk => "prev_hunk",
+    NextMatch => "next_match",
+    PrevMatch => "prev_match",
+    ExpandUp => "expand_up",
+    ExpandDown => "expand_down",
+    ToggleLayout => "toggle_layout",
+    ToggleWrap => "toggle_wrap",
+    ScrollLeft => "scroll_left",
+    ScrollRight => "scroll_right",
+    NextPending => "next_pending",
+    PrevPending => "prev_pending",
+    NextUnread => "next_unread",
+    PrevUnread => "prev_unread",
+    ToggleResolvedFilter => "toggle_resolved_filter",
+    ToggleGrouping => "toggle_grouping",
+    ShowInDiff => "show_in_diff",
+    ShowThread => "show_thread",
+    ToggleDetails => "toggle_details",
+    FollowLink => "follow_link",
+    ToggleThreadResolved => "toggle_thread_resolved",
+    Edit => "edit",
+    Clear => "clear",
+    SendOrSearch => "send_or_search",
+    SearchComments => "search_comments",
+    SubmitComment => "submit_comment",
+    SubmitApprove => "submit_approve",
+    SubmitRequestChanges => "submit_request_changes",
+    VisualRange => "visual_range",
+    Templates => "templates",
+    RestoreDraft => "restore_draft",
+    SwapDraftConflicts => "swap_draft_conflicts",
+    SaveDraft => "save_draft",
+    TogglePreview => "toggle_preview",
+    SystemEditor => "system_editor",
+    Accept => "accept",
+    CycleTarget => "cycle_target",
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// A single key press with modifiers, normalized so that `Char('G')` does not
+/// also carry `SHIFT`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+
+    /// Parses a chord such as `q`, `G`, `C-d`, `ctrl+d`, `M-x`, `S-tab`, or `enter`.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut rest = raw;
+        let mut modifiers = KeyModifiers::NONE;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let stripped = [
+                ("c-", KeyModifiers::CONTROL),
+                ("ctrl+", KeyModifiers::CONTROL),
+                ("ctrl-", KeyModifiers::CONTROL),
+                ("m-", KeyModifiers::ALT),
+                ("alt+", KeyModifiers::ALT),
+                ("alt-", KeyModifiers::ALT),
+                ("s-", KeyModifiers::SHIFT),
+                ("shift+", KeyModifiers::SHIFT),
+                ("shift-", KeyModifiers::SHIFT),
+            ]
+            .into_iter()
+            .find(|(prefix, _)| lower.starts_with(prefix) && rest.len() > prefix.len());
+            let Some((prefix, modifier)) = stripped else {
+                break;
+            };
+            modifiers |= modifier;
+            rest = &rest[prefix.len()..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => {
+                if modifiers.contains(KeyModifiers::SHIFT) {
+                    KeyCode::Char(ch.to_ascii_uppercase())
+                } else {
+                    KeyCode::Char(ch)
+                }
+            }
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                _ => return Err(anyhow!("unrecognized key `{raw}`")),
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("M-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("S-tab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Parses a whitespace-separated key sequence such as `g g`.
+pub fn parse_sequence(raw: &str) -> Result<Vec<KeyChord>> {
+    let sequence = raw
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if sequence.is_empty() {
+        return Err(anyhow!("key sequence must not be empty"));
+    }
+    Ok(sequence)
+}
+
+fn sequence_label(sequence: &[KeyChord]) -> String {
+    sequence
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    context: KeyContext,
+    action: Action,
+    sequence: Vec<KeyChord>,
+}
+
+/// Active key bindings for every screen.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+enum Resolution {
+    Action(Action),
+    Pending,
+    Unbound,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use KeyContext::{Composer, Review, Search, TemplatePicker};
+
+        let defaults: &[(KeyContext, Action, &[&str])] = &[
+            (Search, Quit, &["q"]),
+            (Search, MoveDown, &["j", "down"]),
+            (Search, MoveUp, &["k", "up"]),
+            (Search, Open, &["enter"]),
+            (Search, OpenInBrowser, &["W"]),
+            (Search, FocusSearch, &["s"]),
+            (Search, ToggleScope, &["u"]),
+            (Search, ToggleStatus, &["i"]),
+            (Search, ToggleSort, &["o"]),
+            (Search, Refresh, &["R"]),
+            (Review, Quit, &["q"]),
+            (Review, Back, &["b", "esc"]),
+            (Review, NextTab, &["S-tab"]),
+            (Review, ToggleFocus, &["tab"]),
+            (Review, MoveDown, &["j", "down"]),
+            (Review, MoveUp, &["k", "up"]),
+            (Review, ScrollDown, &["C-d"]),
+            (Review, ScrollUp, &["C-u"]),
+            (Review, OpenInBrowser, &["W"]),
+            (Review, ToggleCollapse, &["o", "z"]),
+            (Review, NextHunk, &["]"]),
+            (Review, PrevHunk, &["["]),
+            (Review, NextMatch, &["n"]),
+            (Review, PrevMatch, &["N"]),
+            (Review, ExpandUp, &["{"]),
+            (Review, ExpandDown, &["}"]),
+            (Review, ToggleLayout, &["L"]),
+            (Review, ToggleWrap, &["w"]),
+            (Review, ScrollLeft, &["h", "left"]),
+            (Review, ScrollRight, &["l", "right"]),
+            (Review, NextPending, &["p"]),
+            (Review, PrevPending, &["P"]),
+            (Review, NextUnread, &["J"]),
+            (Review, PrevUnread, &["K"]),
+            (Review, ToggleResolvedFilter, &["f"]),
+            (Review, ToggleGrouping, &["G"]),
+            (Review, ShowInDiff, &["d"]),
+            (Review, ShowThread, &["c"]),
+            (Review, ToggleDetails, &["D"]),
+            (Review, FollowLink, &["F"]),
+            (Review, ToggleThreadResolved, &["t"]),
+            (Review, Edit, &["e"]),
+            (Review, Clear, &["x"]),
+            (Review, SendOrSearch, &["s"]),
+            (Review, SearchComments, &["/"]),
+            (Review, SubmitComment, &["C"]),
+            (Review, SubmitApprove, &["A"]),
+            (Review, SubmitRequestChanges, &["X"]),
+            (Review, VisualRange, &["v"]),
+            (Review, Templates, &["T"]),
+            (Review, RestoreDraft, &["U"]),
+            (Review, SwapDraftConflicts, &["O"]),
+            (Review, Refresh, &["R"]),
+            (Composer, SaveDraft, &["C-s"]),
+            (Composer, TogglePreview, &["C-p"]),
+            (Composer, Templates, &["C-t"]),
+            (Composer, SystemEditor, &["C-e"]),
+            (TemplatePicker, MoveUp, &["up", "C-p"]),
+            (TemplatePicker, MoveDown, &["down", "C-n"]),
+            (TemplatePicker, Accept, &["enter"]),
+            (TemplatePicker, CycleTarget, &["tab"]),
+            (TemplatePicker, Back, &["esc"]),
+        ];
+
+        let bindings = defaults
+            .iter()
+            .flat_map(|(context, action, keys)| {
+                keys.iter().map(|key| Binding {
+                    context: *context,
+                    action: *action,
+                    sequence: parse_sequence(key).expect("default key bindings are valid"),
+                })
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Replaces every binding of `action` in `context` with `sequences`.
+    pub fn rebind(&mut self, context: KeyContext, action: Action, sequences: Vec<Vec<KeyChord>>) {
+        self.bindings
+            .retain(|binding| binding.context != context || binding.action != action);
+        self.bindings
+            .extend(sequences.into_iter().map(|sequence| Binding {
+                context,
+                action,
+                sequence,
+            }));
+    }
+
+    /// Describes bindings that can never fire: the same sequence bound to two
+    /// actions, or a sequence that is a prefix of another one.
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (index, left) in self.bindings.iter().enumerate() {
+            for right in &self.bindings[index + 1..] {
+                if left.context != right.context || left.action == right.action {
+                    continue;
+                }
+                let (shorter, longer) = if left.sequence.len() <= right.sequence.len() {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                if longer.sequence.starts_with(&shorter.sequence) {
+                    let relation = if shorter.sequence.len() == longer.sequence.len() {
+                        "is bound to both"
+                    } else {
+                        "shadows"
+                    };
+                    conflicts.push(format!(
+                        "{}: `{}` ({}) {relation} `{}` ({})",
+                        left.context.name(),
+                        sequence_label(&shorter.sequence),
+                        shorter.action.name(),
+                        sequence_label(&longer.sequence),
+                        longer.action.name(),
+                    ));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Key labels bound to `action`, e.g. `["j", "down"]`.
+    pub fn labels(&self, context: KeyContext, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.context == context && binding.action == action)
+            .map(|binding| sequence_label(&binding.sequence))
+            .collect()
+    }
+
+    /// Feeds a key press into the pending sequence and returns the action it
+    /// completes, if any. Keys that cannot extend a binding restart the
+    /// sequence from the latest key.
+    pub fn feed(
+        &self,
+        context: KeyContext,
+        pending: &mut Vec<KeyChord>,
+        key: KeyEvent,
+    ) -> Option<Action> {
+        pending.push(KeyChord::from(key));
+        loop {
+            match self.resolve(context, pending) {
+                Resolution::Action(action) => {
+                    pending.clear();
+                    return Some(action);
+                }
+                Resolution::Pending => return None,
+                Resolution::Unbound if pending.len() > 1 => {
+                    let last = pending[pending.len() - 1];
+                    pending.clear();
+                    pending.push(last);
+                }
+                Resolution::Unbound => {
+                    pending.clear();
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, context: KeyContext, pending: &[KeyChord]) -> Resolution {
+        let mut exact = None;
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.context == context)
+        {
+            if binding.sequence == pending {
+                exact = Some(binding.action);
+            } else if binding.sequence.starts_with(pending) {
+                return Resolution::Pending;
+            }
+        }
+        exact.map_or(Resolution::Unbound, Resolution::Action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, KeyChord, KeyContext, Keymap, parse_sequence};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_chords_with_modifiers() {
+        assert_eq!(
+            KeyChord::parse("C-d").unwrap(),
+            KeyChord::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyChord::parse("ctrl+d").unwrap(),
+            KeyChord::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyChord::parse("S-tab").unwrap(),
+            KeyChord::new(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            KeyChord::parse("-").unwrap(),
+            KeyChord::new(KeyCode::Char('-'), KeyModifiers::NONE)
+        );
+        assert!(KeyChord::parse("hyper").is_err());
+        assert_eq!(KeyChord::parse("C-d").unwrap().to_string(), "C-d");
+    }
+
+    #[test]
+    fn uppercase_chars_match_with_or_without_shift() {
+        let keymap = Keymap::default();
+        let mut pending = Vec::new();
+        let action = keymap.feed(
+            KeyContext::Review,
+            &mut pending,
+            press(KeyCode::Char('C'), KeyModifiers::SHIFT),
+        );
+        assert_eq!(action, Some(Action::SubmitComment));
+    }
+
+    #[test]
+    fn multi_key_sequences_wait_for_completion() {
+        let mut keymap = Keymap::default();
+        keymap.rebind(
+            KeyContext::Review,
+            Action::Refresh,
+            vec![parse_sequence("g r").unwrap()],
+        );
+        let mut pending = Vec::new();
+
+        let g = press(KeyCode::Char('g'), KeyModifiers::NONE);
+        let r = press(KeyCode::Char('r'), KeyModifiers::NONE);
+        let j = press(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(keymap.feed(KeyContext::Review, &mut pending, g), None);
+        assert_eq!(
+            keymap.feed(KeyContext::Review, &mut pending, r),
+            Some(Action::Refresh)
+        );
+
+        // An unrelated key abandons the sequence and is handled on its own.
+        assert_eq!(keymap.feed(KeyContext::Review, &mut pending, g), None);
+        assert_eq!(
+            keymap.feed(KeyContext::Review, &mut pending, j),
+            Some(Action::MoveDown)
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn reports_duplicate_and_prefix_conflicts() {
+        assert!(Keymap::default().conflicts().is_empty());
+
+        let mut keymap = Keymap::default();
+        keymap.rebind(
+            KeyContext::Review,
+            Action::Refresh,
+            vec![parse_sequence("q").unwrap(), parse_sequence("g g").unwrap()],
+        );
+        keymap.rebind(
+            KeyContext::Review,
+            Action::Templates,
+            vec![parse_sequence("g").unwrap()],
+        );
+
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2, "{conflicts:?}");
+    }
+}
@@ -4,24 +4,39 @@ pub mod browser;
 pub mod drafts;
 pub mod editor;
 pub mod events;
+pub mod files;
+pub mod keymap;
+pub mod offline;
+pub mod seen;
 pub mod state;
 
 use crate::{
     app::{
-        drafts::{DraftStore, LoadOutcome},
+        drafts::{DraftStore, LoadOutcome, SaveOutcome},
         events::{
-            MutationRequest, WorkerMessage, spawn_apply_mutation, spawn_load_pull_request_data,
-            spawn_load_pull_request_diff, spawn_load_pull_requests,
-            spawn_load_specific_pull_request, spawn_load_viewer_login,
+            DataSource, MutationRequest, WorkerMessage, spawn_apply_mutation,
+            spawn_load_issue_titles, spawn_load_pull_request_data, spawn_load_pull_request_diff,
+            spawn_load_pull_requests, spawn_load_specific_pull_request, spawn_load_viewer_login,
+            spawn_poll_pull_request_data, spawn_poll_pull_requests, spawn_replay_outbox,
+            spawn_resolve_pull_reference,
         },
+        keymap::{Action, KeyContext, Keymap},
+        offline::OfflineStore,
+        seen::SeenStore,
         state::{
-            AppState, PendingReviewCommentSide, ReviewSubmissionEvent, ReviewTab, SearchInputState,
+            AppState, ComposerCancel, ComposerTarget, FoldExpansion, PendingReviewCommentSide,
+            ReviewSubmissionEvent, ReviewTab, SearchInputState, TemplateTarget,
         },
     },
-    config,
-    domain::{CommentRef, PullRequestSummary, Route},
-    github::{client::create_client, comments::SubmitReviewComment},
-    render::markdown::MarkdownRenderer,
+    config::{self, ComposerPreference, DiffConfig},
+    domain::{PullRequestSummary, Route},
+    github::{
+        client::{create_client, resolve_host},
+        comments::SubmitReviewComment,
+        pulls::RepositoryRef,
+    },
+    render::markdown::{MarkdownRenderer, PreviewLink},
+    search::glob::glob_matches,
     ui,
     ui::theme::{self, ThemeMode},
 };
@@ -38,6 +53,9 @@ use crossterm::{
 use octocrab::models::pulls;
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Write,
     io::{Stdout, stdout},
     time::{Duration, Instant},
 };
@@ -45,37 +63,72 @@ use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 type WorkerTx = UnboundedSender<WorkerMessage>;
 
+/// How often queued mutations are retried while GitHub is unreachable.
+const OUTBOX_REPLAY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a footer notification stays visible.
+const NOTIFICATION_TTL: Duration = Duration::from_secs(6);
+
 /// Runtime configuration provided by CLI flags.
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub owner: Option<String>,
     pub repo: Option<String>,
     pub pull: Option<u64>,
+    /// Read saved snapshots instead of GitHub and queue all mutations.
+    pub offline: bool,
     pub initial_theme_mode: ThemeMode,
     pub initial_terminal_background: Option<(u8, u8, u8)>,
     pub theme_config: config::AppConfig,
 }
 
+/// The loaded `config.toml` plus the repository overrides layered on top of it.
+struct ConfigLayers {
+    base: config::AppConfig,
+    repository: Option<RepositoryLayer>,
+}
+
+struct RepositoryLayer {
+    label: String,
+    repo_file: Option<config::RepoOverrides>,
+}
+
+impl ConfigLayers {
+    fn effective(&self) -> anyhow::Result<config::AppConfig> {
+        match self.repository.as_ref() {
+            Some(layer) => self
+                .base
+                .for_repository(&layer.label, layer.repo_file.as_ref())
+                .with_context(|| format!("invalid settings for {}", layer.label)),
+            None => Ok(self.base.clone()),
+        }
+    }
+}
+
 struct DataContext {
-    client: octocrab::Octocrab,
+    source: DataSource,
     owner: Option<String>,
     repo: Option<String>,
 }
 
 struct EventLoopDependencies<'a> {
     context: &'a DataContext,
-    config: &'a AppConfig,
+    config: &'a mut AppConfig,
     tx: &'a WorkerTx,
     rx: &'a mut UnboundedReceiver<WorkerMessage>,
     markdown: &'a mut MarkdownRenderer,
     draft_store: Option<&'a DraftStore>,
+    seen_store: Option<&'a SeenStore>,
 }
 
 /// Runs the interactive TUI application.
-pub async fn run(config: AppConfig) -> anyhow::Result<()> {
+pub async fn run(mut config: AppConfig) -> anyhow::Result<()> {
     let (tx, mut rx) = mpsc::unbounded_channel::<WorkerMessage>();
 
     let mut state = AppState::default();
+    if !config.theme_config.warnings.is_empty() {
+        state.error_message = Some(config.theme_config.warnings.join("; "));
+    }
     let draft_store = match DraftStore::new().await {
         Ok(store) => Some(store),
         Err(err) => {
@@ -83,18 +136,56 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
             None
         }
     };
+    let host = resolve_host(&config.theme_config.github);
+    let seen_store = match SeenStore::new(host.name()).await {
+        Ok(store) => Some(store),
+        Err(err) => {
+            state.error_message = Some(format!("read markers unavailable: {err}"));
+            None
+        }
+    };
+    if let Some(store) = seen_store.as_ref() {
+        match store.load().await {
+            Ok(markers) => state.seen = markers,
+            Err(err) => {
+                state.error_message = Some(format!("failed to load read markers: {err:#}"));
+            }
+        }
+    }
 
-    let client = create_client()
-        .await
-        .context("failed to create authenticated GitHub client")?;
-
-    spawn_load_viewer_login(tx.clone(), client.clone());
+    let source = if config.offline {
+        let store = OfflineStore::new()
+            .await
+            .context("offline mode needs the offline store")?;
+        DataSource::Offline(store)
+    } else {
+        let client = create_client(&config.theme_config.github)
+            .await
+            .context("failed to create authenticated GitHub client")?;
+        spawn_load_viewer_login(tx.clone(), client.clone());
+        let store = match OfflineStore::new().await {
+            Ok(store) => Some(store),
+            Err(err) => {
+                state.error_message = Some(format!("offline snapshots unavailable: {err}"));
+                None
+            }
+        };
+        DataSource::Online { client, store }
+    };
+    state.offline = source.is_offline();
+    if let Some(store) = source.store() {
+        state.queued_mutations = store
+            .pending_mutations()
+            .await
+            .map(|queued| queued.len())
+            .unwrap_or_default();
+    }
 
     if let Some(pull_number) = config.pull {
         state.begin_operation(format!("Loading pull request #{pull_number}"));
         spawn_load_specific_pull_request(
             tx.clone(),
-            client.clone(),
+            source.clone(),
             config.owner.clone(),
             config.repo.clone(),
             pull_number,
@@ -103,29 +194,39 @@ pub async fn run(config: AppConfig) -> anyhow::Result<()> {
         state.begin_operation("Loading open pull requests");
         spawn_load_pull_requests(
             tx.clone(),
-            client.clone(),
+            source.clone(),
             config.owner.clone(),
             config.repo.clone(),
         );
     }
 
     let context = DataContext {
-        client,
+        source,
         owner: config.owner.clone(),
         repo: config.repo.clone(),
     };
 
+    let search = config.theme_config.search;
+    state.apply_search_defaults(search.scope, search.status, search.sort);
+
     let mut terminal = setup_terminal()?;
     let mut markdown = MarkdownRenderer::new();
-    let _ = markdown.set_ocean_theme(config.initial_theme_mode);
+    if let Err(err) = markdown.configure_syntax(&config.theme_config.syntax) {
+        state.error_message = Some(format!("{err:#}"));
+    }
+    let runtime_theme = config
+        .theme_config
+        .resolve_runtime_theme_for_mode(config.initial_theme_mode);
+    apply_syntax_theme(&mut state, &mut markdown, &runtime_theme);
 
     let mut deps = EventLoopDependencies {
         context: &context,
-        config: &config,
+        config: &mut config,
         tx: &tx,
         rx: &mut rx,
         markdown: &mut markdown,
         draft_store: draft_store.as_ref(),
+        seen_store: seen_store.as_ref(),
     };
     let result = run_event_loop(&mut terminal, &mut state, &mut deps).await;
 
@@ -145,28 +246,66 @@ async fn run_event_loop(
     let mut last_error_snapshot: Option<String> = None;
     let mut last_error_at: Option<Instant> = None;
     let mut last_persisted_draft_signature: Option<String> = None;
+    let mut last_outbox_replay: Option<Instant> = None;
+    let mut last_poll = Instant::now();
+    let mut config_watcher = config::ConfigWatcher::new(&deps.config.theme_config).ok();
+    let mut layers = ConfigLayers {
+        base: deps.config.theme_config.clone(),
+        repository: None,
+    };
 
     loop {
         state.advance_spinner();
 
         while let Ok(message) = deps.rx.try_recv() {
-            process_worker_message(
+            if let WorkerMessage::RepositoryResolved {
+                repository_label,
+                repo_config,
+            } = message
+            {
+                let layer = repository_layer(state, repository_label, repo_config.as_deref());
+                resolve_repository_config(
+                    state,
+                    deps.config,
+                    deps.markdown,
+                    &mut layers,
+                    layer,
+                    &mut active_theme_mode,
+                    active_terminal_background,
+                );
+                continue;
+            }
+            process_worker_message(state, message, deps, &mut last_persisted_draft_signature).await;
+            maybe_spawn_search_load(state, deps.context, deps.tx);
+            load_active_diff_if_needed(
                 state,
-                message,
-                deps.markdown,
                 deps.context,
                 deps.tx,
+                &deps.config.theme_config.diff,
+            );
+            // Persist immediately after worker-driven mutations (for example submit review).
+            persist_drafts_if_enabled(
+                state,
                 deps.draft_store,
+                &deps.config.theme_config.keymap,
                 &mut last_persisted_draft_signature,
             )
             .await;
-            maybe_spawn_search_load(state, deps.context, deps.tx);
-            load_active_diff_if_needed(state, deps.tx);
-            // Persist immediately after worker-driven mutations (for example submit review).
-            persist_drafts_if_enabled(state, deps.draft_store, &mut last_persisted_draft_signature)
-                .await;
         }
 
+        if let Some(client) = deps.context.source.client() {
+            state.rate_limit = client.rate_limits().most_constrained();
+        }
+        maybe_replay_outbox(state, deps.context, deps.tx, &mut last_outbox_replay);
+        maybe_poll(
+            state,
+            deps.context,
+            deps.tx,
+            deps.config.theme_config.refresh,
+            &mut last_poll,
+        );
+        state.expire_notification(NOTIFICATION_TTL);
+
         if state.error_message != last_error_snapshot {
             last_error_snapshot = state.error_message.clone();
             last_error_at = state.error_message.as_ref().map(|_| Instant::now());
@@ -180,6 +319,19 @@ async fn run_event_loop(
 
         if last_theme_poll.elapsed() >= Duration::from_secs(1) {
             last_theme_poll = Instant::now();
+            if let Some(watcher) = config_watcher.as_mut()
+                && watcher.poll()
+            {
+                reload_config(
+                    state,
+                    deps.config,
+                    deps.markdown,
+                    &mut layers,
+                    watcher,
+                    &mut active_theme_mode,
+                    active_terminal_background,
+                );
+            }
             maybe_refresh_theme(
                 deps.config,
                 deps.markdown,
@@ -189,10 +341,23 @@ async fn run_event_loop(
             );
         }
 
-        persist_drafts_if_enabled(state, deps.draft_store, &mut last_persisted_draft_signature)
-            .await;
-
-        terminal.draw(|frame| ui::render(frame, state, deps.markdown))?;
+        persist_drafts_if_enabled(
+            state,
+            deps.draft_store,
+            &deps.config.theme_config.keymap,
+            &mut last_persisted_draft_signature,
+        )
+        .await;
+        persist_seen_markers(state, deps.seen_store).await;
+
+        terminal.draw(|frame| {
+            ui::render(
+                frame,
+                state,
+                &deps.config.theme_config.keymap,
+                deps.markdown,
+            )
+        })?;
 
         if state.should_quit {
             break;
@@ -200,18 +365,24 @@ async fn run_event_loop(
 
         if event::poll(Duration::from_millis(60))? {
             match event::read()? {
-                Event::Key(key_event) => {
-                    if key_event.kind == KeyEventKind::Press {
-                        last_user_input = Instant::now();
-                        handle_key_event(terminal, state, deps.context, deps.tx, key_event);
-                        // Persist immediately after key-driven mutations (create/edit/delete).
-                        persist_drafts_if_enabled(
-                            state,
-                            deps.draft_store,
-                            &mut last_persisted_draft_signature,
-                        )
-                        .await;
-                    }
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    last_user_input = Instant::now();
+                    handle_key_event(
+                        terminal,
+                        state,
+                        deps.context,
+                        deps.config,
+                        deps.tx,
+                        key_event,
+                    );
+                    // Persist immediately after key-driven mutations (create/edit/delete).
+                    persist_drafts_if_enabled(
+                        state,
+                        deps.draft_store,
+                        &deps.config.theme_config.keymap,
+                        &mut last_persisted_draft_signature,
+                    )
+                    .await;
                 }
                 Event::Mouse(mouse_event) => {
                     last_user_input = Instant::now();
@@ -219,44 +390,77 @@ async fn run_event_loop(
                 }
                 _ => {}
             }
+            if let Some(review) = state.review.as_mut() {
+                review.mark_selected_seen();
+            }
         }
     }
 
     Ok(())
 }
 
+/// Updates the open review's read marker and saves the markers when they changed.
+async fn persist_seen_markers(state: &mut AppState, seen_store: Option<&SeenStore>) {
+    if let Some(review) = state.review.as_ref() {
+        state.seen.record_review(review);
+    }
+    if !state.seen.take_dirty() {
+        return;
+    }
+    if let Some(store) = seen_store
+        && let Err(err) = store.save(&state.seen).await
+    {
+        state.error_message = Some(format!("failed to save read markers: {err:#}"));
+    }
+}
+
 async fn persist_drafts_if_enabled(
     state: &mut AppState,
     draft_store: Option<&DraftStore>,
+    keymap: &Keymap,
     last_persisted_draft_signature: &mut Option<String>,
 ) {
     let Some(store) = draft_store else {
         return;
     };
-    persist_review_drafts(state, store, last_persisted_draft_signature).await;
+    restore_previous_drafts_if_requested(state, store, last_persisted_draft_signature).await;
+    persist_review_drafts(state, store, keymap, last_persisted_draft_signature).await;
 }
 
 async fn process_worker_message(
     state: &mut AppState,
     message: WorkerMessage,
-    markdown: &mut MarkdownRenderer,
-    context: &DataContext,
-    tx: &WorkerTx,
-    draft_store: Option<&DraftStore>,
+    deps: &mut EventLoopDependencies<'_>,
     last_persisted_draft_signature: &mut Option<String>,
 ) {
+    let (context, tx, draft_store) = (deps.context, deps.tx, deps.draft_store);
+    let markdown = &mut *deps.markdown;
+    let diff_config = &deps.config.theme_config.diff;
     match message {
+        // Needs the config layers, so the event loop handles it before dispatch.
+        WorkerMessage::RepositoryResolved { .. } => {}
         WorkerMessage::ViewerLoginLoaded { viewer_login } => {
             if let Some(login) = viewer_login {
                 state.set_viewer_login(Some(login));
             }
         }
+        WorkerMessage::IssueTitlesLoaded { pull, issues } => {
+            if let Some(review) = state.review.as_mut()
+                && review.pull.number == pull.number
+                && review.pull.owner == pull.owner
+                && review.pull.repo == pull.repo
+            {
+                review.issue_titles = issues;
+            }
+        }
         WorkerMessage::PullRequestsLoaded {
             repository_label,
             viewer_login,
+            from_cache,
             result,
         } => {
-            if state.route == Route::Search {
+            // Cached lists are shown while the live request is still running.
+            if state.route == Route::Search && !from_cache {
                 state.end_operation();
             }
             state.set_repository_label(repository_label);
@@ -300,7 +504,13 @@ async fn process_worker_message(
                         return;
                     }
 
+                    if let Some(client) = context.source.client()
+                        && !client.rate_limits().is_low("core")
+                    {
+                        spawn_load_issue_titles(tx.clone(), client.clone(), pull.clone());
+                    }
                     state.open_review(pull, data);
+                    apply_diff_config(state, diff_config);
                     if let (Some(store), Some(review)) = (draft_store, state.review.as_mut()) {
                         match store.load_for_review(review).await {
                             Ok(LoadOutcome::Loaded {
@@ -310,6 +520,15 @@ async fn process_worker_message(
                                 review.apply_restored_drafts(pending_comments, reply_drafts);
                             }
                             Ok(LoadOutcome::None) => {}
+                            Ok(LoadOutcome::Unrecoverable {
+                                backup_path,
+                                reason,
+                            }) => {
+                                state.error_message = Some(format!(
+                                    "saved draft could not be restored ({reason}); backup kept at {}",
+                                    backup_path.display()
+                                ));
+                            }
                             Err(err) => {
                                 state.error_message =
                                     Some(format!("failed to load saved draft: {err}"));
@@ -335,7 +554,7 @@ async fn process_worker_message(
                 Ok(pull) => {
                     state.error_message = None;
                     state.begin_operation(format!("Loading pull request #{pull_number}"));
-                    spawn_load_pull_request_data(tx.clone(), context.client.clone(), pull);
+                    spawn_load_pull_request_data(tx.clone(), context.source.clone(), pull);
                 }
                 Err(error) => {
                     state.error_message = Some(error);
@@ -343,6 +562,19 @@ async fn process_worker_message(
                 }
             }
         }
+        WorkerMessage::PullReferenceResolved { url, result } => {
+            state.end_operation();
+
+            match result {
+                Ok(Some(pull)) => {
+                    state.error_message = None;
+                    state.begin_operation(format!("Loading pull request #{}", pull.number));
+                    spawn_load_pull_request_data(tx.clone(), context.source.clone(), pull);
+                }
+                Ok(None) => open_url_in_browser(state, &url),
+                Err(error) => state.error_message = Some(error),
+            }
+        }
         WorkerMessage::PullRequestDiffLoaded { pull, result } => {
             state.end_operation();
             markdown.clear_diff_cache();
@@ -361,6 +593,9 @@ async fn process_worker_message(
                 Ok(diff) => {
                     state.error_message = None;
                     review.set_diff(diff);
+                    if let Err(message) = review.apply_pending_diff_jump() {
+                        state.error_message = Some(message.to_owned());
+                    }
                 }
                 Err(error) => {
                     review.set_diff_error(error);
@@ -399,12 +634,160 @@ async fn process_worker_message(
                 }
             }
         }
+        WorkerMessage::MutationQueued { pull, result } => {
+            state.end_operation();
+
+            if let Err(error) = result {
+                state.error_message = Some(error);
+                return;
+            }
+            state.queued_mutations += 1;
+            let reason = if state.offline {
+                "offline"
+            } else {
+                "GitHub is unreachable"
+            };
+            state.notify(format!(
+                "{reason}; #{} queued to send later, drafts kept until it is sent ({} waiting)",
+                pull.number, state.queued_mutations
+            ));
+        }
+        WorkerMessage::PullRequestsPolled { result } => {
+            state.polling = state.polling.saturating_sub(1);
+            let pulls = match result {
+                Ok(pulls) => pulls,
+                Err(error) => {
+                    state.notify(format!("auto-refresh failed: {error}"));
+                    return;
+                }
+            };
+            // A manual load is running and will replace the list anyway.
+            if state.is_busy() {
+                return;
+            }
+
+            let known: HashMap<u64, i64> = state
+                .pull_requests
+                .iter()
+                .map(|pull| (pull.number, pull.updated_at_unix_ms))
+                .collect();
+            let opened = pulls
+                .iter()
+                .filter(|pull| !known.contains_key(&pull.number))
+                .count();
+            let updated = pulls
+                .iter()
+                .filter(|pull| {
+                    known
+                        .get(&pull.number)
+                        .is_some_and(|updated_at| *updated_at < pull.updated_at_unix_ms)
+                })
+                .count();
+            state.set_pull_requests(pulls);
+
+            let mut changes = Vec::new();
+            if opened > 0 {
+                changes.push(format!("{opened} new pull request(s)"));
+            }
+            if updated > 0 {
+                changes.push(format!("{updated} updated"));
+            }
+            if !changes.is_empty() {
+                state.notify(changes.join(", "));
+            }
+        }
+        WorkerMessage::PullRequestDataPolled { pull, result } => {
+            state.polling = state.polling.saturating_sub(1);
+            let data = match result {
+                Ok(data) => data,
+                Err(error) => {
+                    state.notify(format!("auto-refresh failed: {error}"));
+                    return;
+                }
+            };
+            // A manual refresh or mutation is running and will bring newer data.
+            if state.is_busy() {
+                return;
+            }
+            let Some(review) = state.review.as_mut() else {
+                return;
+            };
+            if review.pull.number != pull.number
+                || review.pull.owner != pull.owner
+                || review.pull.repo != pull.repo
+            {
+                return;
+            }
+
+            let outcome = review.apply_refresh(data);
+            if outcome.head_changed {
+                markdown.clear_diff_cache();
+                review.clear_diff();
+            }
+            *last_persisted_draft_signature = None;
+
+            let mut changes = Vec::new();
+            if outcome.head_changed {
+                changes.push("new commits pushed".to_owned());
+            }
+            if outcome.new_comments > 0 {
+                changes.push(format!("{} new comment(s)", outcome.new_comments));
+            }
+            if !changes.is_empty() {
+                state.notify(format!("#{}: {}", pull.number, changes.join(", ")));
+            }
+        }
+        WorkerMessage::OutboxReplayed {
+            sent,
+            failures,
+            remaining,
+        } => {
+            state.replaying_outbox = false;
+            state.queued_mutations = remaining;
+            if !failures.is_empty() {
+                let kept = context
+                    .source
+                    .store()
+                    .map(|store| format!("; kept in {}", store.outbox_path().display()))
+                    .unwrap_or_default();
+                state.error_message = Some(format!("{}{kept}", failures.join("; ")));
+            }
+
+            // Drop the drafts of delivered mutations and show them on the open
+            // review.
+            let Some(review) = state.review.as_mut() else {
+                return;
+            };
+            let pull = review.pull.clone();
+            let mut delivered = false;
+            for entry in sent.into_iter().filter(|entry| {
+                entry.pull.number == pull.number
+                    && entry.pull.owner == pull.owner
+                    && entry.pull.repo == pull.repo
+            }) {
+                delivered = true;
+                if let Some(root_key) = entry.clear_reply_root_key {
+                    review.clear_reply_draft(&root_key);
+                }
+                if matches!(
+                    entry.mutation,
+                    MutationRequest::SubmitPullRequestReview { .. }
+                ) {
+                    review.clear_pending_review_comments();
+                }
+            }
+            if delivered && !state.is_busy() {
+                state.begin_operation(format!("Refreshing pull request #{}", pull.number));
+                spawn_load_pull_request_data(tx.clone(), context.source.clone(), pull);
+            }
+        }
     }
 }
 
 async fn persist_review_drafts(
     state: &mut AppState,
     draft_store: &DraftStore,
+    keymap: &Keymap,
     last_persisted_draft_signature: &mut Option<String>,
 ) {
     let Some(review) = state.review.as_ref() else {
@@ -419,10 +802,15 @@ async fn persist_review_drafts(
         .any(|body| !body.trim().is_empty());
     if !has_pending && !has_replies {
         if last_persisted_draft_signature.is_some() {
-            if let Err(err) = draft_store.clear_for_review(review).await {
-                state.error_message = Some(format!("failed to clear saved draft: {err}"));
+            match draft_store.clear_for_review(review).await {
+                Ok(true) => *last_persisted_draft_signature = None,
+                // Another session holds the lock; retry on the next tick.
+                Ok(false) => {}
+                Err(err) => {
+                    state.error_message = Some(format!("failed to clear saved draft: {err}"));
+                    *last_persisted_draft_signature = None;
+                }
             }
-            *last_persisted_draft_signature = None;
         }
         return;
     }
@@ -432,12 +820,256 @@ async fn persist_review_drafts(
         return;
     }
 
-    if let Err(err) = draft_store.save_for_review(review).await {
-        state.error_message = Some(format!("failed to save draft: {err}"));
+    match draft_store.save_for_review(review).await {
+        Ok(SaveOutcome::Saved) => {
+            *last_persisted_draft_signature = Some(signature);
+        }
+        // Another session holds the lock; retry on the next tick.
+        Ok(SaveOutcome::Locked) => {}
+        Ok(SaveOutcome::Merged(merged)) => {
+            let Some(review) = state.review.as_mut() else {
+                return;
+            };
+            let mut message = format!(
+                "drafts changed in another critic session; merged {} pending comment(s) and {} reply draft(s)",
+                merged.added_comments, merged.added_replies
+            );
+            if merged.removed_drafts > 0 {
+                let _ = write!(
+                    message,
+                    "; dropped {} draft(s) sent or deleted there",
+                    merged.removed_drafts
+                );
+            }
+            if !merged.conflicting_replies.is_empty() {
+                let _ = write!(
+                    message,
+                    "; kept this session's version of {} conflicting reply draft(s)",
+                    merged.conflicting_replies.len()
+                );
+                if let Some(press) = press_key(
+                    keymap,
+                    KeyContext::Review,
+                    Action::SwapDraftConflicts,
+                    "swap in the other",
+                ) {
+                    let _ = write!(message, ", {press}");
+                }
+            }
+            review.apply_restored_drafts(merged.pending_comments, merged.reply_drafts);
+            review.set_draft_conflicts(merged.conflicting_replies);
+            *last_persisted_draft_signature = Some(DraftStore::draft_signature(review));
+            state.error_message = Some(message);
+        }
+        Err(err) => {
+            state.error_message = Some(format!("failed to save draft: {err}"));
+        }
+    }
+}
+
+/// `press [key] to <purpose>` for the first key bound to `action`, or `None`
+/// when the action is unbound.
+fn press_key(
+    keymap: &Keymap,
+    context: KeyContext,
+    action: Action,
+    purpose: &str,
+) -> Option<String> {
+    let key = keymap.labels(context, action).into_iter().next()?;
+    Some(format!("press [{key}] to {purpose}"))
+}
+
+async fn restore_previous_drafts_if_requested(
+    state: &mut AppState,
+    draft_store: &DraftStore,
+    last_persisted_draft_signature: &mut Option<String>,
+) {
+    let Some(review) = state.review.as_mut() else {
+        return;
+    };
+    if !review.take_draft_restore_request() {
         return;
     }
 
-    *last_persisted_draft_signature = Some(signature);
+    match draft_store.restore_previous_for_review(review).await {
+        // Another session holds the lock; retry on the next tick.
+        Ok(None) => review.request_draft_restore(),
+        Ok(Some(LoadOutcome::Loaded {
+            pending_comments,
+            reply_drafts,
+        })) => {
+            let (pending, replies) = review.apply_restored_drafts(pending_comments, reply_drafts);
+            review.set_draft_conflicts(HashMap::new());
+            // The restored snapshot is already on disk; don't archive it again.
+            *last_persisted_draft_signature = Some(DraftStore::draft_signature(review));
+            state.error_message = Some(format!(
+                "restored previous draft ({pending} pending comment(s), {replies} reply draft(s))"
+            ));
+        }
+        Ok(Some(LoadOutcome::None)) => {
+            state.error_message = Some("no previous draft versions to restore".to_owned());
+        }
+        Ok(Some(LoadOutcome::Unrecoverable {
+            backup_path,
+            reason,
+        })) => {
+            state.error_message = Some(format!(
+                "previous draft could not be restored ({reason}); backup kept at {}",
+                backup_path.display()
+            ));
+        }
+        Err(err) => {
+            state.error_message = Some(format!("failed to restore previous draft: {err}"));
+        }
+    }
+}
+
+/// Re-reads `config.toml` (and the active theme file) after an edit, keeping
+/// the previous settings if the new ones fail to load.
+fn reload_config(
+    state: &mut AppState,
+    config: &mut AppConfig,
+    markdown: &mut MarkdownRenderer,
+    layers: &mut ConfigLayers,
+    watcher: &mut config::ConfigWatcher,
+    active_mode: &mut ThemeMode,
+    active_terminal_background: Option<(u8, u8, u8)>,
+) {
+    let reloaded = match config::load_or_create() {
+        Ok(reloaded) => reloaded,
+        Err(err) => {
+            state.error_message = Some(format!("config not reloaded: {err:#}"));
+            return;
+        }
+    };
+    let _ = watcher.rewatch(&reloaded);
+    let previous = std::mem::replace(&mut layers.base, reloaded);
+    let effective = match layers.effective() {
+        Ok(effective) => effective,
+        Err(err) => {
+            layers.base = previous;
+            state.error_message = Some(format!("config not reloaded: {err:#}"));
+            return;
+        }
+    };
+
+    state.error_message =
+        (!layers.base.warnings.is_empty()).then(|| layers.base.warnings.join("; "));
+    apply_effective_config(
+        state,
+        config,
+        markdown,
+        effective,
+        active_mode,
+        active_terminal_background,
+    );
+}
+
+fn repository_layer(
+    state: &mut AppState,
+    label: String,
+    repo_config: Option<&str>,
+) -> RepositoryLayer {
+    let repo_file = repo_config.and_then(|content| {
+        config::parse_repo_config_file(content)
+            .map_err(|err| {
+                state.error_message = Some(format!("ignoring .critic.toml for {label}: {err:#}"));
+            })
+            .ok()
+    });
+    RepositoryLayer { label, repo_file }
+}
+
+/// Layers the resolved repository's overrides (its `.critic.toml` and the
+/// matching `[repos]` table) over the base config. Search defaults are only
+/// applied when the repository changes so manual toggles survive refreshes.
+fn resolve_repository_config(
+    state: &mut AppState,
+    config: &mut AppConfig,
+    markdown: &mut MarkdownRenderer,
+    layers: &mut ConfigLayers,
+    layer: RepositoryLayer,
+    active_mode: &mut ThemeMode,
+    active_terminal_background: Option<(u8, u8, u8)>,
+) {
+    let repository_changed = layers
+        .repository
+        .as_ref()
+        .is_none_or(|current| !current.label.eq_ignore_ascii_case(&layer.label));
+    let previous = layers.repository.replace(layer);
+    let effective = match layers.effective() {
+        Ok(effective) => effective,
+        Err(err) => {
+            layers.repository = previous;
+            state.error_message = Some(format!("{err:#}"));
+            return;
+        }
+    };
+
+    if repository_changed {
+        let search = effective.search;
+        state.apply_search_defaults(search.scope, search.status, search.sort);
+    }
+    apply_effective_config(
+        state,
+        config,
+        markdown,
+        effective,
+        active_mode,
+        active_terminal_background,
+    );
+}
+
+fn apply_effective_config(
+    state: &mut AppState,
+    config: &mut AppConfig,
+    markdown: &mut MarkdownRenderer,
+    reloaded: config::AppConfig,
+    active_mode: &mut ThemeMode,
+    active_terminal_background: Option<(u8, u8, u8)>,
+) {
+    let mode = match reloaded.theme_preference {
+        config::ThemePreference::Auto => *active_mode,
+        config::ThemePreference::Dark => ThemeMode::Dark,
+        config::ThemePreference::Light => ThemeMode::Light,
+    };
+    let runtime_theme = reloaded.resolve_runtime_theme_for_mode(mode);
+    if let Err(err) = markdown.configure_syntax(&reloaded.syntax) {
+        state.error_message = Some(format!("{err:#}"));
+    }
+    apply_syntax_theme(state, markdown, &runtime_theme);
+    theme::apply(
+        runtime_theme.palette,
+        runtime_theme.mode,
+        active_terminal_background,
+    );
+    *active_mode = mode;
+    config.theme_config = reloaded;
+    apply_diff_config(state, &config.theme_config.diff);
+    state.pending_keys.clear();
+}
+
+/// Pushes the diff settings into the open review, when the review opens and
+/// whenever the configuration changes.
+fn apply_diff_config(state: &mut AppState, diff: &DiffConfig) {
+    if let Some(review) = state.review.as_mut() {
+        review.set_diff_context_lines(diff.context_lines);
+        review.set_diff_unified_below(diff.unified_below);
+    }
+}
+
+fn apply_syntax_theme(
+    state: &mut AppState,
+    markdown: &mut MarkdownRenderer,
+    runtime_theme: &config::RuntimeThemeConfig,
+) {
+    let syntax_theme = runtime_theme.syntax_theme.as_deref();
+    if !markdown.set_syntax_theme(syntax_theme, runtime_theme.mode) {
+        state.error_message = Some(format!(
+            "unknown syntax theme `{}`; using the default",
+            syntax_theme.unwrap_or_default()
+        ));
+    }
 }
 
 fn maybe_refresh_theme(
@@ -472,7 +1104,8 @@ fn maybe_refresh_theme(
         detected.background_rgb,
     );
     if detected.mode != *active_mode {
-        let _ = markdown.set_ocean_theme(runtime_theme.mode);
+        let _ =
+            markdown.set_syntax_theme(runtime_theme.syntax_theme.as_deref(), runtime_theme.mode);
     }
     *active_mode = detected.mode;
     *active_terminal_background = detected.background_rgb;
@@ -482,12 +1115,13 @@ fn handle_key_event(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     state: &mut AppState,
     context: &DataContext,
+    config: &AppConfig,
     tx: &WorkerTx,
     key: KeyEvent,
 ) {
     match state.route {
-        Route::Search => handle_search_key_event(state, context, tx, key),
-        Route::Review => handle_review_key_event(terminal, state, context, tx, key),
+        Route::Search => handle_search_key_event(state, context, config, tx, key),
+        Route::Review => handle_review_key_event(terminal, state, context, config, tx, key),
     }
 }
 
@@ -567,6 +1201,7 @@ fn handle_search_input_edit_key(key: KeyEvent, input: &mut SearchInputState) ->
 fn handle_search_key_event(
     state: &mut AppState,
     context: &DataContext,
+    config: &AppConfig,
     tx: &WorkerTx,
     key: KeyEvent,
 ) {
@@ -577,20 +1212,29 @@ fn handle_search_key_event(
         return;
     }
 
-    match key.code {
-        KeyCode::Char('q') => {
+    let Some(action) =
+        config
+            .theme_config
+            .keymap
+            .feed(KeyContext::Search, &mut state.pending_keys, key)
+    else {
+        return;
+    };
+
+    match action {
+        Action::Quit => {
             state.should_quit = true;
         }
-        KeyCode::Char('W') => {
+        Action::OpenInBrowser => {
             open_selected_pull_in_browser(state);
         }
-        KeyCode::Char('s') => state.focus_search(),
-        KeyCode::Char('u') => state.toggle_search_scope(),
-        KeyCode::Char('i') => state.toggle_search_status_filter(),
-        KeyCode::Char('o') => state.toggle_search_sort(),
-        KeyCode::Down | KeyCode::Char('j') => state.search_move_down(),
-        KeyCode::Up | KeyCode::Char('k') => state.search_move_up(),
-        KeyCode::Enter => {
+        Action::FocusSearch => state.focus_search(),
+        Action::ToggleScope => state.toggle_search_scope(),
+        Action::ToggleStatus => state.toggle_search_status_filter(),
+        Action::ToggleSort => state.toggle_search_sort(),
+        Action::MoveDown => state.search_move_down(),
+        Action::MoveUp => state.search_move_up(),
+        Action::Open => {
             if state.is_busy() {
                 return;
             }
@@ -602,9 +1246,9 @@ fn handle_search_key_event(
             state.error_message = None;
             state.begin_operation(format!("Loading pull request #{}", pull.number));
 
-            spawn_load_pull_request_data(tx.clone(), context.client.clone(), pull);
+            spawn_load_pull_request_data(tx.clone(), context.source.clone(), pull);
         }
-        KeyCode::Char('R') => {
+        Action::Refresh => {
             if state.is_busy() {
                 return;
             }
@@ -613,7 +1257,7 @@ fn handle_search_key_event(
             state.begin_operation("Refreshing open pull requests");
             spawn_load_pull_requests(
                 tx.clone(),
-                context.client.clone(),
+                context.source.clone(),
                 context.owner.clone(),
                 context.repo.clone(),
             );
@@ -626,9 +1270,28 @@ fn handle_review_key_event(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     state: &mut AppState,
     context: &DataContext,
+    config: &AppConfig,
     tx: &WorkerTx,
     key: KeyEvent,
 ) {
+    if state
+        .review
+        .as_ref()
+        .is_some_and(|review| review.template_picker.is_some())
+    {
+        handle_template_picker_key_event(terminal, state, context, config, tx, key);
+        return;
+    }
+
+    if state
+        .review
+        .as_ref()
+        .is_some_and(|review| review.composer.is_some())
+    {
+        handle_composer_key_event(terminal, state, config, key);
+        return;
+    }
+
     let active_tab = state
         .review
         .as_ref()
@@ -668,28 +1331,34 @@ fn handle_review_key_event(
         return;
     }
 
-    if key.modifiers.contains(KeyModifiers::CONTROL) {
-        match key.code {
-            KeyCode::Char('d') => {
-                if let Some(review) = state.review.as_mut()
-                    && (review.active_tab() == ReviewTab::Threads
-                        || review.is_diff_content_focused())
-                {
-                    review.fast_scroll_down();
-                    return;
-                }
-            }
-            KeyCode::Char('u') => {
-                if let Some(review) = state.review.as_mut()
-                    && (review.active_tab() == ReviewTab::Threads
-                        || review.is_diff_content_focused())
-                {
-                    review.fast_scroll_up();
-                    return;
+    if active_tab == ReviewTab::Diff
+        && state
+            .review
+            .as_ref()
+            .is_some_and(|review| review.is_diff_text_search_focused())
+    {
+        if let Some(review) = state.review.as_mut() {
+            if handle_search_input_edit_key(key, review.diff_text_search_input_mut()) {
+                review.refresh_diff_text_search();
+            } else if key.code == KeyCode::Enter && review.has_diff_text_search() {
+                review.jump_next_diff_match();
+            }
+        }
+        return;
+    }
+
+    if let Some(review) = state.review.as_mut()
+        && review.is_picking_link()
+    {
+        match key.code {
+            KeyCode::Char(ch) => {
+                if let Some(link) = review.type_link_hint(ch) {
+                    follow_preview_link(state, context, tx, link);
                 }
             }
-            _ => {}
+            _ => review.cancel_link_hints(),
         }
+        return;
     }
 
     if active_tab == ReviewTab::Diff
@@ -702,22 +1371,27 @@ fn handle_review_key_event(
         return;
     }
 
-    match key.code {
-        KeyCode::Char('q') => {
+    let Some(action) =
+        config
+            .theme_config
+            .keymap
+            .feed(KeyContext::Review, &mut state.pending_keys, key)
+    else {
+        return;
+    };
+
+    match action {
+        Action::Quit => {
             state.should_quit = true;
         }
-        KeyCode::Char('b') => {
+        Action::Back => {
             if is_visual_mode {
                 return;
             }
             state.back_to_search();
             maybe_spawn_search_load(state, context, tx);
         }
-        KeyCode::Esc => {
-            state.back_to_search();
-            maybe_spawn_search_load(state, context, tx);
-        }
-        KeyCode::Tab => {
+        Action::ToggleFocus => {
             if is_visual_mode {
                 return;
             }
@@ -731,7 +1405,7 @@ fn handle_review_key_event(
                 }
             }
         }
-        KeyCode::BackTab => {
+        Action::NextTab => {
             if is_visual_mode {
                 return;
             }
@@ -741,33 +1415,80 @@ fn handle_review_key_event(
                     review.focus_diff_files();
                 }
             }
-            load_active_diff_if_needed(state, tx);
+            load_active_diff_if_needed(state, context, tx, &config.theme_config.diff);
         }
-        KeyCode::Char('W') => {
-            if active_tab == ReviewTab::Threads {
-                open_selected_comment_in_browser(state);
-            }
+        Action::OpenInBrowser if active_tab == ReviewTab::Threads => {
+            open_selected_comment_in_browser(state);
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        Action::MoveDown => {
             if let Some(review) = state.review.as_mut() {
                 review.move_down();
             }
         }
-        KeyCode::Up | KeyCode::Char('k') => {
+        Action::MoveUp => {
             if let Some(review) = state.review.as_mut() {
                 review.move_up();
             }
         }
-        KeyCode::Char('o' | 'z') => {
+        Action::ToggleCollapse => {
             if let Some(review) = state.review.as_mut() {
                 if active_tab == ReviewTab::Threads {
                     review.toggle_selected_thread_collapsed();
                 } else if active_tab == ReviewTab::Diff {
-                    review.toggle_selected_diff_directory_collapsed();
+                    if review.is_diff_content_focused() {
+                        review.toggle_selected_diff_fold();
+                    } else {
+                        review.toggle_selected_diff_directory_collapsed();
+                    }
+                }
+            }
+        }
+        Action::ExpandUp | Action::ExpandDown => {
+            if active_tab == ReviewTab::Diff
+                && !is_visual_mode
+                && let Some(review) = state.review.as_mut()
+                && review.is_diff_content_focused()
+            {
+                let expansion = if action == Action::ExpandUp {
+                    FoldExpansion::Up
+                } else {
+                    FoldExpansion::Down
+                };
+                if review.expand_selected_diff_fold(expansion) {
+                    state.error_message = None;
+                } else {
+                    state.error_message = Some("no folded lines under the cursor".to_owned());
+                }
+            }
+        }
+        Action::ToggleLayout => {
+            if active_tab == ReviewTab::Diff
+                && !is_visual_mode
+                && let Some(review) = state.review.as_mut()
+            {
+                review.toggle_diff_layout();
+            }
+        }
+        Action::ToggleWrap => {
+            if active_tab == ReviewTab::Diff
+                && let Some(review) = state.review.as_mut()
+            {
+                review.toggle_diff_wrap();
+            }
+        }
+        Action::ScrollLeft | Action::ScrollRight => {
+            if active_tab == ReviewTab::Diff
+                && let Some(review) = state.review.as_mut()
+                && review.is_diff_content_focused()
+            {
+                if action == Action::ScrollLeft {
+                    review.scroll_diff_left();
+                } else {
+                    review.scroll_diff_right();
                 }
             }
         }
-        KeyCode::Char('n' | ']') => {
+        Action::NextHunk => {
             if active_tab == ReviewTab::Diff
                 && !is_visual_mode
                 && let Some(review) = state.review.as_mut()
@@ -776,7 +1497,7 @@ fn handle_review_key_event(
                 review.jump_next_hunk();
             }
         }
-        KeyCode::Char('N' | '[') => {
+        Action::PrevHunk => {
             if active_tab == ReviewTab::Diff
                 && !is_visual_mode
                 && let Some(review) = state.review.as_mut()
@@ -785,7 +1506,29 @@ fn handle_review_key_event(
                 review.jump_prev_hunk();
             }
         }
-        KeyCode::Char('p') => {
+        Action::NextMatch | Action::PrevMatch => {
+            if active_tab == ReviewTab::Diff
+                && !is_visual_mode
+                && let Some(review) = state.review.as_mut()
+            {
+                review.focus_diff_content();
+                // Without a diff text search these step through hunks.
+                match (review.has_diff_text_search(), action == Action::NextMatch) {
+                    (false, true) => review.jump_next_hunk(),
+                    (false, false) => review.jump_prev_hunk(),
+                    (true, next) => {
+                        let found = if next {
+                            review.jump_next_diff_match()
+                        } else {
+                            review.jump_prev_diff_match()
+                        };
+                        state.error_message =
+                            (!found).then(|| "no matches for the diff search".to_owned());
+                    }
+                }
+            }
+        }
+        Action::NextPending => {
             if active_tab == ReviewTab::Diff
                 && !is_visual_mode
                 && let Some(review) = state.review.as_mut()
@@ -798,7 +1541,7 @@ fn handle_review_key_event(
                 }
             }
         }
-        KeyCode::Char('P') => {
+        Action::PrevPending => {
             if active_tab == ReviewTab::Diff
                 && !is_visual_mode
                 && let Some(review) = state.review.as_mut()
@@ -811,14 +1554,70 @@ fn handle_review_key_event(
                 }
             }
         }
-        KeyCode::Char('f') => {
+        Action::NextUnread | Action::PrevUnread => {
+            if active_tab == ReviewTab::Threads
+                && let Some(review) = state.review.as_mut()
+            {
+                if review.jump_to_unread(action == Action::NextUnread) {
+                    state.error_message = None;
+                } else {
+                    state.notify("no unread comments");
+                }
+            }
+        }
+        Action::ToggleResolvedFilter => {
             if active_tab == ReviewTab::Threads
                 && let Some(review) = state.review.as_mut()
             {
                 review.toggle_resolved_filter();
             }
         }
-        KeyCode::Char('R') => {
+        Action::ToggleGrouping => {
+            if active_tab == ReviewTab::Threads
+                && let Some(review) = state.review.as_mut()
+            {
+                review.toggle_file_grouping();
+            }
+        }
+        Action::ShowInDiff => {
+            if active_tab == ReviewTab::Threads
+                && let Some(review) = state.review.as_mut()
+            {
+                match review.show_selected_thread_in_diff() {
+                    Ok(()) => state.error_message = None,
+                    Err(message) => state.error_message = Some(message.to_owned()),
+                }
+            }
+            load_active_diff_if_needed(state, context, tx, &config.theme_config.diff);
+        }
+        Action::ShowThread => {
+            if active_tab == ReviewTab::Diff
+                && let Some(review) = state.review.as_mut()
+                && review.is_diff_content_focused()
+            {
+                match review.show_diff_line_thread() {
+                    Ok(()) => state.error_message = None,
+                    Err(message) => state.error_message = Some(message.to_owned()),
+                }
+            }
+        }
+        Action::ToggleDetails => {
+            if active_tab == ReviewTab::Threads
+                && let Some(review) = state.review.as_mut()
+            {
+                review.expand_details = !review.expand_details;
+                review.right_scroll = 0;
+            }
+        }
+        Action::FollowLink => {
+            if active_tab == ReviewTab::Threads
+                && let Some(review) = state.review.as_mut()
+                && !review.start_link_hints()
+            {
+                state.error_message = Some("no links in the preview".to_owned());
+            }
+        }
+        Action::Refresh => {
             if is_visual_mode {
                 return;
             }
@@ -831,9 +1630,47 @@ fn handle_review_key_event(
             };
             state.error_message = None;
             state.begin_operation(format!("Refreshing pull request #{}", pull.number));
-            spawn_load_pull_request_data(tx.clone(), context.client.clone(), pull);
+            spawn_load_pull_request_data(tx.clone(), context.source.clone(), pull);
         }
-        KeyCode::Char('t') => {
+        Action::Templates => {
+            if is_visual_mode || state.is_busy() {
+                return;
+            }
+            open_template_picker(state, config);
+        }
+        Action::RestoreDraft => {
+            if is_visual_mode || state.is_busy() {
+                return;
+            }
+            if let Some(review) = state.review.as_mut() {
+                review.request_draft_restore();
+            }
+        }
+        Action::SwapDraftConflicts => {
+            if is_visual_mode {
+                return;
+            }
+            let Some(review) = state.review.as_mut() else {
+                return;
+            };
+            if !review.has_draft_conflicts() {
+                state.error_message =
+                    Some("no reply drafts conflict with another session".to_owned());
+                return;
+            }
+            let swapped = review.swap_draft_conflicts();
+            let mut message = format!("swapped {swapped} conflicting reply draft(s)");
+            if let Some(press) = press_key(
+                &config.theme_config.keymap,
+                KeyContext::Review,
+                Action::SwapDraftConflicts,
+                "swap back",
+            ) {
+                let _ = write!(message, "; {press}");
+            }
+            state.error_message = Some(message);
+        }
+        Action::ToggleThreadResolved => {
             if active_tab != ReviewTab::Threads {
                 return;
             }
@@ -875,14 +1712,14 @@ fn handle_review_key_event(
                 operation,
             );
         }
-        KeyCode::Char('e') => {
+        Action::Edit => {
             if active_tab == ReviewTab::Threads {
-                open_reply_editor(terminal, state);
+                open_reply_editor(terminal, state, config.theme_config.composer, None);
             } else if active_tab == ReviewTab::Diff {
-                open_pending_diff_comment_editor(terminal, state);
+                open_pending_diff_comment_editor(terminal, state, config, None);
             }
         }
-        KeyCode::Char('x') => {
+        Action::Clear => {
             if active_tab == ReviewTab::Threads
                 && let Some(review) = state.review.as_mut()
                 && let Some(context) = review.selected_thread_context()
@@ -892,7 +1729,7 @@ fn handle_review_key_event(
                 clear_selected_pending_diff_comment(state);
             }
         }
-        KeyCode::Char('s') => match active_tab {
+        Action::SendOrSearch => match active_tab {
             ReviewTab::Threads => {
                 let can_send_reply = state
                     .review
@@ -914,14 +1751,19 @@ fn handle_review_key_event(
                 }
             }
         },
-        KeyCode::Char('/') => {
-            if active_tab == ReviewTab::Threads
-                && let Some(review) = state.review.as_mut()
-            {
-                review.focus_thread_search();
+        Action::SearchComments => match active_tab {
+            ReviewTab::Threads => {
+                if let Some(review) = state.review.as_mut() {
+                    review.focus_thread_search();
+                }
             }
-        }
-        KeyCode::Char('C') => {
+            ReviewTab::Diff => {
+                if !is_visual_mode && let Some(review) = state.review.as_mut() {
+                    review.focus_diff_text_search();
+                }
+            }
+        },
+        Action::SubmitComment => {
             if is_visual_mode {
                 return;
             }
@@ -931,9 +1773,10 @@ fn handle_review_key_event(
                 context,
                 tx,
                 ReviewSubmissionEvent::Comment,
+                "",
             );
         }
-        KeyCode::Char('A') => {
+        Action::SubmitApprove => {
             if is_visual_mode {
                 return;
             }
@@ -943,9 +1786,10 @@ fn handle_review_key_event(
                 context,
                 tx,
                 ReviewSubmissionEvent::Approve,
+                "",
             );
         }
-        KeyCode::Char('X') => {
+        Action::SubmitRequestChanges => {
             if is_visual_mode {
                 return;
             }
@@ -955,9 +1799,10 @@ fn handle_review_key_event(
                 context,
                 tx,
                 ReviewSubmissionEvent::RequestChanges,
+                "",
             );
         }
-        KeyCode::Char('v') => {
+        Action::VisualRange => {
             if active_tab == ReviewTab::Diff
                 && let Some(review) = state.review.as_mut()
                 && review.is_diff_content_focused()
@@ -980,10 +1825,99 @@ fn handle_review_key_event(
                 }
             }
         }
+        Action::ScrollDown => {
+            if let Some(review) = state.review.as_mut()
+                && (active_tab == ReviewTab::Threads || review.is_diff_content_focused())
+            {
+                review.fast_scroll_down();
+            }
+        }
+        Action::ScrollUp => {
+            if let Some(review) = state.review.as_mut()
+                && (active_tab == ReviewTab::Threads || review.is_diff_content_focused())
+            {
+                review.fast_scroll_up();
+            }
+        }
         _ => {}
     }
 }
 
+/// Retries queued mutations at startup and then every [`OUTBOX_REPLAY_INTERVAL`]
+/// while any are left.
+fn maybe_replay_outbox(
+    state: &mut AppState,
+    context: &DataContext,
+    tx: &WorkerTx,
+    last_replay: &mut Option<Instant>,
+) {
+    if state.queued_mutations == 0 || state.replaying_outbox {
+        return;
+    }
+    if last_replay.is_some_and(|at| at.elapsed() < OUTBOX_REPLAY_INTERVAL) {
+        return;
+    }
+    let DataSource::Online {
+        client,
+        store: Some(store),
+    } = &context.source
+    else {
+        return;
+    };
+
+    *last_replay = Some(Instant::now());
+    state.replaying_outbox = true;
+    spawn_replay_outbox(tx.clone(), client.clone(), store.clone());
+}
+
+/// Starts a background refresh of the open pull request, or of the search list,
+/// every `refresh.interval` while nothing else is loading.
+fn maybe_poll(
+    state: &mut AppState,
+    context: &DataContext,
+    tx: &WorkerTx,
+    refresh: config::RefreshConfig,
+    last_poll: &mut Instant,
+) {
+    let Some(interval) = refresh.interval else {
+        return;
+    };
+    let Some(client) = context.source.client() else {
+        return;
+    };
+    if state.polling > 0 || state.is_busy() || last_poll.elapsed() < interval {
+        return;
+    }
+
+    *last_poll = Instant::now();
+    // Every poll costs quota (GraphQL responses cannot be revalidated), so
+    // wait for the next interval while either budget is running out.
+    let limits = client.rate_limits();
+    if limits.is_low("graphql") || limits.is_low("core") {
+        return;
+    }
+    if let (Route::Review, Some(review)) = (state.route, state.review.as_ref()) {
+        state.polling += 1;
+        spawn_poll_pull_request_data(
+            tx.clone(),
+            client.clone(),
+            context.source.store().cloned(),
+            review.pull.clone(),
+        );
+        // A review opened directly has no list yet; the search screen loads it.
+        if state.pull_requests.is_empty() {
+            return;
+        }
+    }
+    state.polling += 1;
+    spawn_poll_pull_requests(
+        tx.clone(),
+        client.clone(),
+        context.owner.clone(),
+        context.repo.clone(),
+    );
+}
+
 fn maybe_spawn_search_load(state: &mut AppState, context: &DataContext, tx: &WorkerTx) {
     if state.is_busy() || !state.pull_requests.is_empty() {
         return;
@@ -993,14 +1927,19 @@ fn maybe_spawn_search_load(state: &mut AppState, context: &DataContext, tx: &Wor
         state.begin_operation("Loading open pull requests");
         spawn_load_pull_requests(
             tx.clone(),
-            context.client.clone(),
+            context.source.clone(),
             context.owner.clone(),
             context.repo.clone(),
         );
     }
 }
 
-fn load_active_diff_if_needed(state: &mut AppState, tx: &WorkerTx) {
+fn load_active_diff_if_needed(
+    state: &mut AppState,
+    context: &DataContext,
+    tx: &WorkerTx,
+    diff: &config::DiffConfig,
+) {
     if state.is_busy() {
         return;
     }
@@ -1016,18 +1955,41 @@ fn load_active_diff_if_needed(state: &mut AppState, tx: &WorkerTx) {
     }
 
     let pull = review.pull.clone();
-    let changed_files = review.data.changed_files.clone();
+    let changed_files = review
+        .data
+        .changed_files
+        .iter()
+        .filter(|path| {
+            !diff
+                .ignored_paths
+                .iter()
+                .any(|pattern| glob_matches(pattern, path))
+        })
+        .cloned()
+        .collect();
 
     state.error_message = None;
     state.begin_operation(format!("Loading diff for pull request #{}", pull.number));
-    spawn_load_pull_request_diff(tx.clone(), pull, changed_files);
+    spawn_load_pull_request_diff(
+        tx.clone(),
+        context.source.clone(),
+        pull,
+        changed_files,
+        diff.engine,
+    );
 }
 
-fn open_reply_editor(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &mut AppState) {
+fn open_reply_editor(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &mut AppState,
+    composer: ComposerPreference,
+    template: Option<&str>,
+) {
     if state.is_busy() {
         return;
     }
 
+    let issues = composer_issue_candidates(state);
     let Some(review) = state.review.as_mut() else {
         return;
     };
@@ -1040,6 +2002,18 @@ fn open_reply_editor(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &
         .get(&context.root_key)
         .cloned()
         .unwrap_or_default();
+    let existing = append_template(existing, template);
+
+    if composer == ComposerPreference::Builtin {
+        review.open_composer(
+            ComposerTarget::Reply {
+                root_key: context.root_key,
+            },
+            &existing,
+            issues,
+        );
+        return;
+    }
 
     match editor::edit_with_system_editor(&existing, terminal) {
         Ok(Some(edited)) => {
@@ -1056,16 +2030,27 @@ fn open_reply_editor(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &
 fn open_pending_diff_comment_editor(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     state: &mut AppState,
+    config: &AppConfig,
+    template: Option<&str>,
 ) {
     if state.is_busy() {
         return;
     }
 
+    let issues = composer_issue_candidates(state);
     let Some(review) = state.review.as_mut() else {
         return;
     };
     if !review.is_diff_content_focused() {
-        state.error_message = Some("press [l] to focus diff lines before commenting".to_owned());
+        state.error_message = Some(
+            press_key(
+                &config.theme_config.keymap,
+                KeyContext::Review,
+                Action::ToggleFocus,
+                "focus diff lines before commenting",
+            )
+            .unwrap_or_else(|| "focus diff lines before commenting".to_owned()),
+        );
         return;
     }
 
@@ -1073,6 +2058,12 @@ fn open_pending_diff_comment_editor(
         .selected_pending_review_comment()
         .map(|comment| comment.body.clone())
         .unwrap_or_default();
+    let existing = append_template(existing, template);
+
+    if config.theme_config.composer == ComposerPreference::Builtin {
+        review.open_composer(ComposerTarget::PendingComment, &existing, issues);
+        return;
+    }
 
     match editor::edit_with_system_editor(&existing, terminal) {
         Ok(Some(edited)) => match review.upsert_pending_review_comment_from_selection(edited) {
@@ -1086,6 +2077,232 @@ fn open_pending_diff_comment_editor(
     }
 }
 
+/// Appends a template body to existing draft text, separated by a blank line.
+fn append_template(existing: String, template: Option<&str>) -> String {
+    match template {
+        None => existing,
+        Some(template) if existing.trim().is_empty() => template.to_owned(),
+        Some(template) => format!("{}\n\n{template}", existing.trim_end()),
+    }
+}
+
+fn open_template_picker(state: &mut AppState, config: &AppConfig) {
+    let Some(review) = state.review.as_mut() else {
+        return;
+    };
+    if config.theme_config.templates.is_empty() {
+        state.error_message = Some(
+            "no templates configured; add [[templates]] to config.toml or files to ~/.critic/templates"
+                .to_owned(),
+        );
+        return;
+    }
+    review.open_template_picker(config.theme_config.templates.clone());
+}
+
+fn handle_template_picker_key_event(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &mut AppState,
+    context: &DataContext,
+    config: &AppConfig,
+    tx: &WorkerTx,
+    key: KeyEvent,
+) {
+    let action =
+        config
+            .theme_config
+            .keymap
+            .feed(KeyContext::TemplatePicker, &mut state.pending_keys, key);
+    let Some(review) = state.review.as_mut() else {
+        return;
+    };
+    let Some(picker) = review.template_picker.as_mut() else {
+        return;
+    };
+
+    match action {
+        Some(Action::Back) => review.close_template_picker(),
+        Some(Action::MoveUp) => picker.move_up(),
+        Some(Action::MoveDown) => picker.move_down(),
+        Some(Action::CycleTarget) => picker.cycle_target(),
+        Some(Action::Accept) => {
+            let template = picker
+                .selected_template()
+                .map(|template| template.body.clone());
+            let target = picker.target().cloned();
+            review.close_template_picker();
+            let (Some(template), Some(target)) = (template, target) else {
+                return;
+            };
+            insert_template(terminal, state, context, config, tx, target, &template);
+        }
+        Some(_) => {}
+        // Unbound control chords are not filter text; keys starting a
+        // sequence wait for the rest of it.
+        None if key.modifiers.contains(KeyModifiers::CONTROL) || !state.pending_keys.is_empty() => {
+        }
+        None => match key.code {
+            KeyCode::Backspace => picker.backspace(),
+            KeyCode::Char(ch) => picker.push_char(ch),
+            _ => {}
+        },
+    }
+}
+
+fn insert_template(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &mut AppState,
+    context: &DataContext,
+    config: &AppConfig,
+    tx: &WorkerTx,
+    target: TemplateTarget,
+    template: &str,
+) {
+    match target {
+        TemplateTarget::Composer => {
+            if let Some(composer) = state
+                .review
+                .as_mut()
+                .and_then(|review| review.composer.as_mut())
+            {
+                composer.insert_str(template);
+            }
+        }
+        TemplateTarget::Reply { .. } => {
+            open_reply_editor(
+                terminal,
+                state,
+                config.theme_config.composer,
+                Some(template),
+            );
+        }
+        TemplateTarget::PendingComment => {
+            open_pending_diff_comment_editor(terminal, state, config, Some(template));
+        }
+        TemplateTarget::ReviewBody(event) => {
+            open_submit_review_editor_and_submit(terminal, state, context, tx, event, template);
+        }
+    }
+}
+
+/// Open issues and pull requests of the review's repository, offered for `#`
+/// completion: the ones fetched when the review opened, plus pull requests
+/// from the search list and the review itself.
+fn composer_issue_candidates(state: &AppState) -> Vec<(u64, String)> {
+    let Some(review) = state.review.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut issues = review.issue_titles.clone();
+    let known_pulls = state
+        .pull_requests
+        .iter()
+        .filter(|pull| pull.owner == review.pull.owner && pull.repo == review.pull.repo)
+        .chain(std::iter::once(&review.pull))
+        .map(|pull| (pull.number, pull.title.clone()))
+        .collect::<Vec<_>>();
+    for (number, title) in known_pulls {
+        if !issues.iter().any(|(known, _)| *known == number) {
+            issues.push((number, title));
+        }
+    }
+    issues.sort_by_key(|(number, _)| Reverse(*number));
+    issues
+}
+
+fn handle_composer_key_event(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &mut AppState,
+    config: &AppConfig,
+    key: KeyEvent,
+) {
+    let action =
+        config
+            .theme_config
+            .keymap
+            .feed(KeyContext::Composer, &mut state.pending_keys, key);
+    let Some(review) = state.review.as_mut() else {
+        return;
+    };
+
+    match action {
+        Some(Action::SaveDraft) => match review.save_composer() {
+            Ok(()) => state.error_message = None,
+            Err(message) => state.error_message = Some(message.to_owned()),
+        },
+        Some(Action::TogglePreview) => {
+            if let Some(composer) = review.composer.as_mut() {
+                composer.toggle_preview();
+            }
+        }
+        Some(Action::Templates) => open_template_picker(state, config),
+        Some(Action::SystemEditor) => {
+            let Some(composer) = review.composer.as_mut() else {
+                return;
+            };
+            match editor::edit_with_system_editor(&composer.text(), terminal) {
+                Ok(Some(edited)) => composer.set_text(&edited),
+                Ok(None) => {}
+                Err(err) => {
+                    state.error_message = Some(format!("failed to open editor: {err}"));
+                }
+            }
+        }
+        Some(_) => {}
+        // Unbound control chords are not text; keys starting a sequence wait
+        // for the rest of it.
+        None if key.modifiers.contains(KeyModifiers::CONTROL) || !state.pending_keys.is_empty() => {
+        }
+        None => handle_composer_edit_key(state, key),
+    }
+}
+
+fn handle_composer_edit_key(state: &mut AppState, key: KeyEvent) {
+    let Some(review) = state.review.as_mut() else {
+        return;
+    };
+    let Some(composer) = review.composer.as_mut() else {
+        return;
+    };
+    if key.code != KeyCode::Esc {
+        composer.disarm_discard();
+    }
+    match key.code {
+        KeyCode::Esc => {
+            if composer.has_completion() {
+                composer.dismiss_completion();
+                return;
+            }
+            match review.cancel_composer() {
+                ComposerCancel::Closed => {}
+                ComposerCancel::KeptReplyDraft => state.notify("reply kept as a draft"),
+                ComposerCancel::ConfirmDiscard => {
+                    state.notify("press [esc] again to discard the comment");
+                }
+            }
+        }
+        KeyCode::Enter => {
+            let completed = composer.accept_completion();
+            if !completed {
+                composer.insert_newline();
+            }
+        }
+        KeyCode::Tab => {
+            composer.accept_completion();
+        }
+        KeyCode::Backspace => composer.backspace(),
+        KeyCode::Delete => composer.delete(),
+        KeyCode::Left => composer.move_left(),
+        KeyCode::Right => composer.move_right(),
+        KeyCode::Up => composer.move_up(),
+        KeyCode::Down => composer.move_down(),
+        KeyCode::Home => composer.move_line_start(),
+        KeyCode::End => composer.move_line_end(),
+        KeyCode::Char(ch) => composer.insert_char(ch),
+        _ => {}
+    }
+}
+
 fn clear_selected_pending_diff_comment(state: &mut AppState) {
     let Some(review) = state.review.as_mut() else {
         return;
@@ -1147,11 +2364,24 @@ fn open_submit_review_editor_and_submit(
     context: &DataContext,
     tx: &WorkerTx,
     event: ReviewSubmissionEvent,
+    initial: &str,
 ) {
     if state.is_busy() {
         return;
     }
 
+    if let Some(reviewed_head_sha) = state
+        .review
+        .as_mut()
+        .and_then(|review| review.acknowledge_head_change())
+    {
+        let short_sha = reviewed_head_sha.get(..7).unwrap_or(&reviewed_head_sha);
+        state.error_message = Some(format!(
+            "new commits were pushed since {short_sha}; check the diff, then submit again"
+        ));
+        return;
+    }
+
     let Some(review) = state.review.as_ref() else {
         return;
     };
@@ -1171,9 +2401,8 @@ fn open_submit_review_editor_and_submit(
                 .map(|_| pending_comment_side_to_octocrab(comment.side)),
         })
         .collect::<Vec<_>>();
-    let initial = String::new();
 
-    let body = match editor::edit_with_system_editor(&initial, terminal) {
+    let body = match editor::edit_with_system_editor(initial, terminal) {
         Ok(Some(text)) => text.trim().to_owned(),
         Ok(None) => return,
         Err(err) => {
@@ -1228,7 +2457,7 @@ fn execute_mutation(
     state.begin_operation(operation_label);
     spawn_apply_mutation(
         tx.clone(),
-        context.client.clone(),
+        context.source.clone(),
         pull,
         mutation,
         clear_reply_root_key,
@@ -1249,12 +2478,8 @@ fn open_selected_pull_in_browser(state: &mut AppState) {
         return;
     };
 
-    match open_in_browser(url) {
-        Ok(()) => state.error_message = None,
-        Err(err) => {
-            state.error_message = Some(format!("failed to open browser: {err}"));
-        }
-    }
+    let url = url.to_owned();
+    open_url_in_browser(state, &url);
 }
 
 fn open_selected_comment_in_browser(state: &mut AppState) {
@@ -1265,17 +2490,55 @@ fn open_selected_comment_in_browser(state: &mut AppState) {
         return;
     };
 
-    let url = match &node.comment {
-        CommentRef::Review(comment) => comment.html_url.as_str(),
-        CommentRef::Issue(comment) => comment.html_url.as_str(),
-        CommentRef::ReviewSummary(review) => review.html_url.as_str(),
-    };
-
+    let url = node.comment.html_url();
     if url.trim().is_empty() {
         state.error_message = Some("selected comment has no web URL".to_owned());
         return;
     }
 
+    let url = url.to_owned();
+    open_url_in_browser(state, &url);
+}
+
+/// Opens a link picked from the preview. Pull requests in the same repository
+/// open in the review screen; everything else goes to the browser.
+fn follow_preview_link(
+    state: &mut AppState,
+    context: &DataContext,
+    tx: &WorkerTx,
+    link: PreviewLink,
+) {
+    let Some(review) = state.review.as_ref() else {
+        return;
+    };
+    let Some(number) = link.number else {
+        open_url_in_browser(state, &link.url);
+        return;
+    };
+    if number == review.pull.number {
+        state.notify(format!("#{number} is already open"));
+        return;
+    }
+    if state.is_busy() {
+        return;
+    }
+
+    let repository = RepositoryRef {
+        owner: review.pull.owner.clone(),
+        repo: review.pull.repo.clone(),
+    };
+    state.error_message = None;
+    state.begin_operation(format!("Opening #{number}"));
+    spawn_resolve_pull_reference(
+        tx.clone(),
+        context.source.clone(),
+        repository,
+        number,
+        link.url,
+    );
+}
+
+fn open_url_in_browser(state: &mut AppState, url: &str) {
     match open_in_browser(url) {
         Ok(()) => state.error_message = None,
         Err(err) => {
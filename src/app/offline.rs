@@ -0,0 +1,633 @@
+//! Local snapshots of opened pull requests and the queue of mutations that
+//! could not reach GitHub.
+
+use crate::{
+    app::{
+        events::MutationRequest,
+        files::{FileLock, critic_dir, sanitize_path_fragment, unix_ms_now, write_atomically},
+    },
+    domain::{PullRequestData, PullRequestDiffData, PullRequestSummary},
+};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+use tokio::{fs, sync::Mutex};
+
+const OFFLINE_DIR: &str = "offline";
+const PULLS_DIR: &str = "pulls";
+const OUTBOX_FILE: &str = "outbox.json";
+const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+const OUTBOX_FORMAT_VERSION: u8 = 1;
+/// Number of pull request snapshots kept; the least recently saved are dropped.
+const SNAPSHOT_LIMIT: usize = 30;
+/// Longest a replay may hold an entry in flight; past it the sending session
+/// is assumed to have crashed mid-request.
+const IN_FLIGHT_STALE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Disk-backed offline data rooted at `~/.critic/offline`.
+///
+/// Every pull request opened online is saved to `pulls/<owner>/<repo>/<number>.json`
+/// so it can be reviewed with `--offline`. Mutations that fail because GitHub is
+/// unreachable are appended to `outbox.json` and replayed later. The outbox is
+/// shared by every critic session, so its updates hold `outbox.json.lock`.
+#[derive(Debug, Clone)]
+pub struct OfflineStore {
+    root: PathBuf,
+    /// Serializes read-modify-write cycles of snapshot and outbox files.
+    write_lock: Arc<Mutex<()>>,
+}
+
+/// Everything saved for one pull request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestSnapshot {
+    version: u8,
+    saved_at_unix_ms: i64,
+    pub pull: PullRequestSummary,
+    pub data: PullRequestData,
+    #[serde(default)]
+    pub diff: Option<PullRequestDiffData>,
+}
+
+/// A mutation waiting for connectivity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedMutation {
+    pub id: u64,
+    pub queued_at_unix_ms: i64,
+    pub pull: PullRequestSummary,
+    pub mutation: MutationRequest,
+    /// Reply draft to clear once the mutation is delivered.
+    #[serde(default)]
+    pub clear_reply_root_key: Option<String>,
+    /// Set while a session is sending the entry, so no other session sends it
+    /// too.
+    #[serde(default)]
+    pub in_flight_since_unix_ms: Option<i64>,
+    /// Set when GitHub rejected the replay; failed entries are kept but not retried.
+    #[serde(default)]
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedOutbox {
+    version: u8,
+    entries: Vec<QueuedMutation>,
+}
+
+impl OfflineStore {
+    pub async fn new() -> Result<Self> {
+        Self::with_root(critic_dir()?.join(OFFLINE_DIR)).await
+    }
+
+    async fn with_root(root: PathBuf) -> Result<Self> {
+        fs::create_dir_all(root.join(PULLS_DIR))
+            .await
+            .with_context(|| format!("failed to create offline directory {}", root.display()))?;
+        Ok(Self {
+            root,
+            write_lock: Arc::default(),
+        })
+    }
+
+    /// Saves freshly loaded review data, keeping a previously saved diff only
+    /// while the head commit is unchanged.
+    pub async fn save_data(&self, pull: &PullRequestSummary, data: &PullRequestData) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let path = self.snapshot_path(&pull.owner, &pull.repo, pull.number);
+        let diff = read_snapshot(&path)
+            .await
+            .ok()
+            .flatten()
+            .filter(|snapshot| snapshot.data.head_sha == data.head_sha)
+            .and_then(|snapshot| snapshot.diff);
+        let snapshot = PullRequestSnapshot {
+            version: SNAPSHOT_FORMAT_VERSION,
+            saved_at_unix_ms: unix_ms_now(),
+            pull: pull.clone(),
+            data: data.clone(),
+            diff,
+        };
+        write_json(&path, &snapshot).await?;
+        self.prune_snapshots().await
+    }
+
+    /// Attaches a loaded diff to the pull request's snapshot.
+    pub async fn save_diff(
+        &self,
+        pull: &PullRequestSummary,
+        diff: &PullRequestDiffData,
+    ) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let path = self.snapshot_path(&pull.owner, &pull.repo, pull.number);
+        let Some(mut snapshot) = read_snapshot(&path).await? else {
+            return Ok(());
+        };
+        snapshot.diff = Some(diff.clone());
+        write_json(&path, &snapshot).await
+    }
+
+    pub async fn load(
+        &self,
+        owner: &str,
+        repo: &str,
+        pull_number: u64,
+    ) -> Result<Option<PullRequestSnapshot>> {
+        read_snapshot(&self.snapshot_path(owner, repo, pull_number)).await
+    }
+
+    /// Summaries of saved pull requests, optionally limited to one repository,
+    /// most recently updated first.
+    pub async fn list_pulls(
+        &self,
+        owner: Option<&str>,
+        repo: Option<&str>,
+    ) -> Result<Vec<PullRequestSummary>> {
+        let mut pulls = self
+            .snapshot_files()
+            .await?
+            .into_iter()
+            .filter(|(_, pull)| {
+                owner.is_none_or(|owner| pull.owner.eq_ignore_ascii_case(owner))
+                    && repo.is_none_or(|repo| pull.repo.eq_ignore_ascii_case(repo))
+            })
+            .map(|(_, pull)| pull)
+            .collect::<Vec<_>>();
+        pulls.sort_by_key(|pull| std::cmp::Reverse(pull.updated_at_unix_ms));
+        Ok(pulls)
+    }
+
+    /// Appends a mutation to the outbox.
+    pub async fn enqueue(
+        &self,
+        pull: &PullRequestSummary,
+        mutation: &MutationRequest,
+        clear_reply_root_key: Option<String>,
+    ) -> Result<()> {
+        self.update_outbox(|outbox| {
+            let queued_at_unix_ms = unix_ms_now();
+            let id = outbox
+                .entries
+                .iter()
+                .map(|entry| entry.id + 1)
+                .max()
+                .unwrap_or_default()
+                .max(queued_at_unix_ms.max(0) as u64);
+            outbox.entries.push(QueuedMutation {
+                id,
+                queued_at_unix_ms,
+                pull: pull.clone(),
+                mutation: mutation.clone(),
+                clear_reply_root_key,
+                in_flight_since_unix_ms: None,
+                failure: None,
+            });
+        })
+        .await
+    }
+
+    /// Queued mutations that have not failed, oldest first.
+    pub async fn pending_mutations(&self) -> Result<Vec<QueuedMutation>> {
+        Ok(self
+            .read_outbox()
+            .await?
+            .entries
+            .into_iter()
+            .filter(|entry| entry.failure.is_none())
+            .collect())
+    }
+
+    /// Marks the oldest pending mutation in flight and returns it, or `None`
+    /// when nothing is pending or another session is already sending it.
+    ///
+    /// An entry left in flight by a crashed session may or may not have
+    /// reached GitHub, so it is marked failed instead of being sent again.
+    pub async fn claim_next(&self) -> Result<Option<QueuedMutation>> {
+        let now = unix_ms_now();
+        let stale_after = IN_FLIGHT_STALE_AFTER.as_millis() as i64;
+        self.update_outbox(|outbox| {
+            for entry in &mut outbox.entries {
+                if entry.failure.is_some() {
+                    continue;
+                }
+                match entry.in_flight_since_unix_ms {
+                    Some(since) if now - since < stale_after => return None,
+                    Some(_) => {
+                        entry.failure = Some(
+                            "sending was interrupted; check the pull request before retrying"
+                                .to_owned(),
+                        );
+                    }
+                    None => {
+                        entry.in_flight_since_unix_ms = Some(now);
+                        return Some(entry.clone());
+                    }
+                }
+            }
+            None
+        })
+        .await
+    }
+
+    /// Returns a claimed mutation to the queue after GitHub could not be reached.
+    pub async fn release(&self, id: u64) -> Result<()> {
+        self.update_outbox(|outbox| {
+            if let Some(entry) = outbox.entries.iter_mut().find(|entry| entry.id == id) {
+                entry.in_flight_since_unix_ms = None;
+            }
+        })
+        .await
+    }
+
+    /// Removes a mutation that was sent successfully.
+    pub async fn complete(&self, id: u64) -> Result<()> {
+        self.update_outbox(|outbox| outbox.entries.retain(|entry| entry.id != id))
+            .await
+    }
+
+    /// Records why GitHub rejected a queued mutation so it is not retried.
+    pub async fn fail(&self, id: u64, reason: &str) -> Result<()> {
+        self.update_outbox(|outbox| {
+            if let Some(entry) = outbox.entries.iter_mut().find(|entry| entry.id == id) {
+                entry.in_flight_since_unix_ms = None;
+                entry.failure = Some(reason.to_owned());
+            }
+        })
+        .await
+    }
+
+    /// Applies `update` to the outbox while holding both the in-process and
+    /// the cross-session lock.
+    async fn update_outbox<T>(&self, update: impl FnOnce(&mut PersistedOutbox) -> T) -> Result<T> {
+        let _guard = self.write_lock.lock().await;
+        let _lock = FileLock::acquire(&self.outbox_path()).await?;
+        let mut outbox = self.read_outbox().await?;
+        let result = update(&mut outbox);
+        self.write_outbox(&outbox).await?;
+        Ok(result)
+    }
+
+    pub fn outbox_path(&self) -> PathBuf {
+        self.root.join(OUTBOX_FILE)
+    }
+
+    async fn read_outbox(&self) -> Result<PersistedOutbox> {
+        let path = self.outbox_path();
+        let raw = match fs::read(&path).await {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(PersistedOutbox::default());
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read outbox {}", path.display()));
+            }
+        };
+        let outbox: PersistedOutbox = serde_json::from_slice(&raw)
+            .with_context(|| format!("failed to parse outbox {}", path.display()))?;
+        if outbox.version > OUTBOX_FORMAT_VERSION {
+            bail!(
+                "outbox {} was written by a newer critic (format {})",
+                path.display(),
+                outbox.version
+            );
+        }
+        Ok(outbox)
+    }
+
+    async fn write_outbox(&self, outbox: &PersistedOutbox) -> Result<()> {
+        let path = self.outbox_path();
+        if outbox.entries.is_empty() {
+            return match fs::remove_file(&path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(err).with_context(|| format!("failed to remove outbox {}", path.display()))
+                }
+                _ => Ok(()),
+            };
+        }
+        write_json(
+            &path,
+            &PersistedOutbox {
+                version: OUTBOX_FORMAT_VERSION,
+                entries: outbox.entries.clone(),
+            },
+        )
+        .await
+    }
+
+    /// Drops the least recently saved snapshots beyond [`SNAPSHOT_LIMIT`].
+    async fn prune_snapshots(&self) -> Result<()> {
+        let mut files = Vec::new();
+        for (path, _) in self.snapshot_files().await? {
+            let modified = fs::metadata(&path)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(UNIX_EPOCH);
+            files.push((modified, path));
+        }
+        if files.len() <= SNAPSHOT_LIMIT {
+            return Ok(());
+        }
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in files.into_iter().skip(SNAPSHOT_LIMIT) {
+            let _ = fs::remove_file(path).await;
+        }
+        Ok(())
+    }
+
+    async fn snapshot_files(&self) -> Result<Vec<(PathBuf, PullRequestSummary)>> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.root.join(PULLS_DIR)];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to list {}", dir.display()));
+                }
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "json")
+                    && let Ok(Some(snapshot)) = read_snapshot(&path).await
+                {
+                    files.push((path, snapshot.pull));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn snapshot_path(&self, owner: &str, repo: &str, pull_number: u64) -> PathBuf {
+        self.root
+            .join(PULLS_DIR)
+            .join(sanitize_path_fragment(&owner.to_ascii_lowercase()))
+            .join(sanitize_path_fragment(&repo.to_ascii_lowercase()))
+            .join(format!("{pull_number}.json"))
+    }
+}
+
+async fn read_snapshot(path: &Path) -> Result<Option<PullRequestSnapshot>> {
+    let raw = match fs::read(path).await {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    let snapshot: PullRequestSnapshot = serde_json::from_slice(&raw)
+        .with_context(|| format!("failed to parse offline snapshot {}", path.display()))?;
+    // Snapshots are a cache of GitHub data; unknown formats are simply ignored.
+    Ok((snapshot.version == SNAPSHOT_FORMAT_VERSION).then_some(snapshot))
+}
+
+async fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let content = serde_json::to_vec(value).context("failed to serialize offline data")?;
+    write_atomically(path, &content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OfflineStore;
+    use crate::{
+        app::{events::MutationRequest, files::FileLock},
+        domain::{PullRequestData, PullRequestDiffData, PullRequestSummary},
+    };
+    use std::{env, path::PathBuf, process, time::Duration};
+    use tokio::{fs, time};
+
+    fn pull(number: u64) -> PullRequestSummary {
+        PullRequestSummary {
+            owner: "Acme".to_owned(),
+            repo: "widgets".to_owned(),
+            number,
+            title: format!("pull {number}"),
+            author: "octocat".to_owned(),
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: "abc".to_owned(),
+            base_sha: "def".to_owned(),
+            html_url: None,
+            updated_at_unix_ms: number as i64,
+            created_at_unix_ms: 0,
+            is_draft: false,
+            reviewer_logins: Vec::new(),
+            review_status: None,
+            comment_count: None,
+        }
+    }
+
+    fn data(head_sha: &str) -> PullRequestData {
+        PullRequestData {
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: head_sha.to_owned(),
+            base_sha: "def".to_owned(),
+            changed_files: vec!["src/lib.rs".to_owned()],
+            comments: Vec::new(),
+            description: None,
+        }
+    }
+
+    fn resolve(thread_id: &str) -> MutationRequest {
+        MutationRequest::SetReviewThreadResolved {
+            thread_id: thread_id.to_owned(),
+            resolved: true,
+        }
+    }
+
+    async fn temp_store(name: &str) -> (PathBuf, OfflineStore) {
+        let root = env::temp_dir().join(format!("critic-offline-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&root).await;
+        let store = OfflineStore::with_root(root.clone()).await.expect("store");
+        (root, store)
+    }
+
+    #[tokio::test]
+    async fn snapshots_round_trip_through_disk() {
+        let (root, store) = temp_store("snapshots").await;
+
+        store.save_data(&pull(1), &data("abc")).await.expect("save");
+        store
+            .save_diff(&pull(1), &PullRequestDiffData::default())
+            .await
+            .expect("save diff");
+        store.save_data(&pull(2), &data("abc")).await.expect("save");
+
+        // A second store over the same directory sees what the first saved.
+        let reopened = OfflineStore::with_root(root.clone()).await.expect("store");
+        let listed = reopened.list_pulls(Some("acme"), None).await.expect("list");
+        assert_eq!(
+            listed.iter().map(|pull| pull.number).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        let snapshot = reopened
+            .load("ACME", "Widgets", 1)
+            .await
+            .expect("load")
+            .expect("snapshot");
+        assert_eq!(snapshot.pull.title, "pull 1");
+        assert_eq!(snapshot.data.head_sha, "abc");
+        assert_eq!(snapshot.data.changed_files, vec!["src/lib.rs".to_owned()]);
+        assert!(snapshot.diff.is_some());
+        assert!(
+            reopened
+                .load("acme", "widgets", 3)
+                .await
+                .expect("load")
+                .is_none()
+        );
+
+        // A new head commit invalidates the saved diff.
+        store.save_data(&pull(1), &data("new")).await.expect("save");
+        let snapshot = store.load("acme", "widgets", 1).await.expect("load");
+        assert!(snapshot.is_some_and(|snapshot| snapshot.diff.is_none()));
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn replayed_mutations_leave_the_outbox_in_order() {
+        let (root, store) = temp_store("replay").await;
+
+        store
+            .enqueue(&pull(1), &resolve("PRRT_1"), None)
+            .await
+            .expect("enqueue");
+        store
+            .enqueue(&pull(1), &resolve("PRRT_2"), Some("reply:1".to_owned()))
+            .await
+            .expect("enqueue");
+        let queued = store.pending_mutations().await.expect("pending");
+        assert_eq!(queued.len(), 2);
+        assert!(queued[0].id < queued[1].id);
+        assert_eq!(queued[1].clear_reply_root_key.as_deref(), Some("reply:1"));
+
+        // A claimed entry is not handed out again until it is completed.
+        let first = store.claim_next().await.expect("claim").expect("entry");
+        assert_eq!(first.id, queued[0].id);
+        assert!(store.claim_next().await.expect("claim").is_none());
+        store.complete(first.id).await.expect("complete");
+
+        let second = store.claim_next().await.expect("claim").expect("entry");
+        assert_eq!(second.id, queued[1].id);
+        store.complete(second.id).await.expect("complete");
+
+        assert!(store.pending_mutations().await.expect("pending").is_empty());
+        assert!(store.claim_next().await.expect("claim").is_none());
+        assert!(!fs::try_exists(store.outbox_path()).await.expect("exists"));
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn mutations_that_fail_during_replay_stay_queued() {
+        let (root, store) = temp_store("replay-failure").await;
+        for thread_id in ["PRRT_1", "PRRT_2"] {
+            store
+                .enqueue(&pull(1), &resolve(thread_id), None)
+                .await
+                .expect("enqueue");
+        }
+        let queued = store.pending_mutations().await.expect("pending");
+
+        // GitHub unreachable: the entry goes back to the queue and is retried.
+        let claimed = store.claim_next().await.expect("claim").expect("entry");
+        store.release(claimed.id).await.expect("release");
+        assert_eq!(store.pending_mutations().await.expect("pending").len(), 2);
+        let reclaimed = store.claim_next().await.expect("claim").expect("entry");
+        assert_eq!(reclaimed.id, queued[0].id);
+
+        // GitHub rejected it: the entry is kept for the user but not retried.
+        store.fail(reclaimed.id, "rejected").await.expect("fail");
+        let pending = store.pending_mutations().await.expect("pending");
+        assert_eq!(
+            pending.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            vec![queued[1].id]
+        );
+        let next = store.claim_next().await.expect("claim").expect("entry");
+        assert_eq!(next.id, queued[1].id);
+        store.complete(next.id).await.expect("complete");
+        let outbox = store.read_outbox().await.expect("outbox");
+        assert_eq!(outbox.entries.len(), 1);
+        assert_eq!(outbox.entries[0].failure.as_deref(), Some("rejected"));
+
+        // An entry left in flight by a crashed session is not sent again.
+        store
+            .enqueue(&pull(1), &resolve("PRRT_3"), None)
+            .await
+            .expect("enqueue");
+        store
+            .update_outbox(|outbox| {
+                if let Some(entry) = outbox.entries.last_mut() {
+                    entry.in_flight_since_unix_ms = Some(0);
+                }
+            })
+            .await
+            .expect("mark in flight");
+        assert!(store.claim_next().await.expect("claim").is_none());
+        assert!(store.pending_mutations().await.expect("pending").is_empty());
+        assert_eq!(store.read_outbox().await.expect("outbox").entries.len(), 2);
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn sessions_contending_for_the_outbox_lock_keep_every_entry() {
+        let (root, first) = temp_store("contention").await;
+        // A second store has its own in-process mutex, like another session.
+        let second = OfflineStore::with_root(root.clone()).await.expect("store");
+
+        // While another session holds the lock, updates wait for it.
+        let held = FileLock::acquire(&first.outbox_path()).await.expect("lock");
+        let waiting = tokio::spawn({
+            let second = second.clone();
+            async move { second.enqueue(&pull(2), &resolve("PRRT_0"), None).await }
+        });
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        assert!(first.pending_mutations().await.expect("pending").is_empty());
+        drop(held);
+        waiting.await.expect("join").expect("enqueue");
+
+        let mut tasks = Vec::new();
+        for index in 0..10 {
+            let store = if index % 2 == 0 {
+                first.clone()
+            } else {
+                second.clone()
+            };
+            tasks.push(tokio::spawn(async move {
+                store
+                    .enqueue(&pull(1), &resolve(&format!("PRRT_{index}")), None)
+                    .await
+            }));
+        }
+        for task in tasks {
+            task.await.expect("join").expect("enqueue");
+        }
+
+        let pending = second.pending_mutations().await.expect("pending");
+        assert_eq!(pending.len(), 11);
+        let mut ids = pending.iter().map(|entry| entry.id).collect::<Vec<_>>();
+        ids.dedup();
+        assert_eq!(ids.len(), 11, "entry ids must be unique and ordered");
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Only one session may claim the head of the queue.
+        let claimed = first.claim_next().await.expect("claim").expect("entry");
+        assert!(second.claim_next().await.expect("claim").is_none());
+        first.release(claimed.id).await.expect("release");
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+}
@@ -0,0 +1,296 @@
+//! Per-pull-request read markers, so comments posted since the last visit can
+//! be flagged as unread.
+
+use crate::{
+    app::{
+        files::{critic_dir, unix_ms_now, write_atomically},
+        state::ReviewScreenState,
+    },
+    domain::PullRequestSummary,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+use tokio::fs;
+
+const SEEN_FILE: &str = "seen.json";
+const SEEN_FORMAT_VERSION: u8 = 1;
+/// Number of pull requests remembered; the least recently visited are dropped.
+const RECORD_LIMIT: usize = 500;
+
+/// Disk-backed read markers stored in `~/.critic/seen.json`.
+///
+/// Sessions do not merge each other's markers: the file is rewritten whole and
+/// the last session to save wins. Losing a marker only re-flags comments as
+/// unread, so this is not worth a lock.
+#[derive(Debug, Clone)]
+pub struct SeenStore {
+    path: PathBuf,
+    /// GitHub host the markers are recorded for, part of every key.
+    host: String,
+}
+
+/// What was known about a pull request's comments when it was last viewed.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SeenRecord {
+    /// Comments created after this time had not been loaded yet.
+    pub seen_at_unix_ms: i64,
+    /// When the pull request was last opened; the oldest are dropped first.
+    #[serde(default)]
+    pub visited_at_unix_ms: i64,
+    /// [`PullRequestData::comment_count`](crate::domain::PullRequestData::comment_count)
+    /// at that time.
+    pub comment_count: u64,
+    /// Identities of loaded comments that were never selected.
+    pub unread: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedSeen {
+    version: u8,
+    pulls: HashMap<String, SeenRecord>,
+}
+
+/// Read markers for every pull request, keyed by `host/owner/repo#number`.
+#[derive(Debug, Clone, Default)]
+pub struct SeenMarkers {
+    host: String,
+    records: HashMap<String, SeenRecord>,
+    /// Set when a record changed since the markers were last saved.
+    dirty: bool,
+}
+
+impl SeenStore {
+    pub async fn new(host: &str) -> Result<Self> {
+        Self::with_root(critic_dir()?, host).await
+    }
+
+    async fn with_root(root: PathBuf, host: &str) -> Result<Self> {
+        fs::create_dir_all(&root)
+            .await
+            .with_context(|| format!("failed to create {}", root.display()))?;
+        Ok(Self {
+            path: root.join(SEEN_FILE),
+            host: host.to_ascii_lowercase(),
+        })
+    }
+
+    pub async fn load(&self) -> Result<SeenMarkers> {
+        let content = match fs::read(&self.path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(SeenMarkers {
+                    host: self.host.clone(),
+                    ..SeenMarkers::default()
+                });
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", self.path.display()));
+            }
+        };
+        let persisted: PersistedSeen = serde_json::from_slice(&content)
+            .with_context(|| format!("failed to parse {}", self.path.display()))?;
+        Ok(SeenMarkers {
+            host: self.host.clone(),
+            records: persisted.pulls,
+            dirty: false,
+        })
+    }
+
+    /// Replaces the markers file atomically, keeping the most recently visited
+    /// [`RECORD_LIMIT`] pull requests.
+    pub async fn save(&self, markers: &SeenMarkers) -> Result<()> {
+        let mut records: Vec<(&String, &SeenRecord)> = markers.records.iter().collect();
+        records.sort_by_key(|(_, record)| std::cmp::Reverse(record.visited_at_unix_ms));
+        records.truncate(RECORD_LIMIT);
+        let persisted = PersistedSeen {
+            version: SEEN_FORMAT_VERSION,
+            pulls: records
+                .into_iter()
+                .map(|(key, record)| (key.clone(), record.clone()))
+                .collect(),
+        };
+
+        let content = serde_json::to_vec(&persisted).context("failed to serialize read markers")?;
+        write_atomically(&self.path, &content).await
+    }
+}
+
+impl SeenMarkers {
+    pub fn get(&self, pull: &PullRequestSummary) -> Option<&SeenRecord> {
+        self.records.get(&self.key(pull))
+    }
+
+    /// Comments on `pull` still unread: those left unread on the last visit
+    /// plus any posted since, going by the list's comment count. `None` for
+    /// pull requests that were never opened.
+    pub fn unread_count(&self, pull: &PullRequestSummary) -> Option<u64> {
+        let record = self.get(pull)?;
+        let posted_since = pull
+            .comment_count
+            .map_or(0, |count| count.saturating_sub(record.comment_count));
+        Some(record.unread.len() as u64 + posted_since)
+    }
+
+    /// Records what the open review has loaded and which comments are still
+    /// unread. The timestamp only moves when something changed, so comments
+    /// that have not been loaded yet stay newer than it.
+    pub fn record_review(&mut self, review: &ReviewScreenState) {
+        let comment_count = review.data.comment_count();
+        let unread = review.unread_identities();
+        let key = self.key(&review.pull);
+        let previous = self.records.get(&key);
+        if previous
+            .is_some_and(|record| record.comment_count == comment_count && record.unread == unread)
+        {
+            return;
+        }
+        let now = unix_ms_now();
+        let visited_at_unix_ms = previous.map_or(now, |record| record.visited_at_unix_ms);
+        self.records.insert(
+            key,
+            SeenRecord {
+                seen_at_unix_ms: now,
+                visited_at_unix_ms,
+                comment_count,
+                unread,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Marks `pull` as visited now, keeping it among the records that
+    /// [`SeenStore::save`] retains. Pull requests without a record get one
+    /// from [`SeenMarkers::record_review`].
+    pub fn record_visit(&mut self, pull: &PullRequestSummary) {
+        let key = self.key(pull);
+        if let Some(record) = self.records.get_mut(&key) {
+            record.visited_at_unix_ms = unix_ms_now();
+            self.dirty = true;
+        }
+    }
+
+    /// Returns whether the markers changed since this was last called.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    fn key(&self, pull: &PullRequestSummary) -> String {
+        format!(
+            "{}/{}/{}#{}",
+            self.host,
+            pull.owner.to_ascii_lowercase(),
+            pull.repo.to_ascii_lowercase(),
+            pull.number
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RECORD_LIMIT, SeenStore};
+    use crate::{
+        app::state::ReviewScreenState,
+        domain::{PullRequestData, PullRequestSummary},
+    };
+    use std::{env, process};
+    use tokio::fs;
+
+    fn pull(owner: &str, number: u64, comment_count: Option<u64>) -> PullRequestSummary {
+        PullRequestSummary {
+            owner: owner.to_owned(),
+            repo: "widgets".to_owned(),
+            number,
+            title: format!("pull {number}"),
+            author: "octocat".to_owned(),
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: "abc".to_owned(),
+            base_sha: "def".to_owned(),
+            html_url: None,
+            updated_at_unix_ms: 0,
+            created_at_unix_ms: 0,
+            is_draft: false,
+            reviewer_logins: Vec::new(),
+            review_status: None,
+            comment_count,
+        }
+    }
+
+    fn data() -> PullRequestData {
+        PullRequestData {
+            head_ref: "feature".to_owned(),
+            base_ref: "main".to_owned(),
+            head_sha: "abc".to_owned(),
+            base_sha: "def".to_owned(),
+            changed_files: Vec::new(),
+            comments: Vec::new(),
+            description: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_reviews_and_counts_comments_posted_since() {
+        let root = env::temp_dir().join(format!("critic-seen-{}", process::id()));
+        let store = SeenStore::with_root(root.clone(), "github.com")
+            .await
+            .expect("store");
+        let mut markers = store.load().await.expect("load");
+        assert_eq!(markers.unread_count(&pull("acme", 1, Some(3))), None);
+
+        let review = ReviewScreenState::new(pull("Acme", 1, Some(0)), data());
+        markers.record_review(&review);
+        assert!(markers.take_dirty());
+        markers.record_review(&review);
+        assert!(
+            !markers.take_dirty(),
+            "unchanged review should not be saved"
+        );
+
+        store.save(&markers).await.expect("save");
+        let reloaded = store.load().await.expect("load");
+        assert_eq!(reloaded.unread_count(&pull("acme", 1, Some(3))), Some(3));
+        assert_eq!(reloaded.unread_count(&pull("acme", 1, None)), Some(0));
+        assert_eq!(reloaded.unread_count(&pull("acme", 2, Some(3))), None);
+
+        let enterprise = SeenStore::with_root(root.clone(), "GHE.example.com")
+            .await
+            .expect("store");
+        let other_host = enterprise.load().await.expect("load");
+        assert_eq!(other_host.unread_count(&pull("acme", 1, Some(3))), None);
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+
+    #[tokio::test]
+    async fn trims_the_least_recently_visited_pull_requests() {
+        let root = env::temp_dir().join(format!("critic-seen-trim-{}", process::id()));
+        let store = SeenStore::with_root(root.clone(), "github.com")
+            .await
+            .expect("store");
+        let mut markers = store.load().await.expect("load");
+        for number in 0..=RECORD_LIMIT as u64 {
+            let review = ReviewScreenState::new(pull("acme", number, Some(0)), data());
+            markers.record_review(&review);
+        }
+        for record in markers.records.values_mut() {
+            record.visited_at_unix_ms = 1;
+        }
+        // The first pull request's counts have not changed for longest, but
+        // it was opened again most recently.
+        markers.record_visit(&pull("acme", 0, Some(0)));
+        markers
+            .records
+            .get_mut("github.com/acme/widgets#1")
+            .expect("record")
+            .visited_at_unix_ms = 0;
+
+        store.save(&markers).await.expect("save");
+        let reloaded = store.load().await.expect("load");
+        assert_eq!(reloaded.records.len(), RECORD_LIMIT);
+        assert!(reloaded.get(&pull("acme", 0, None)).is_some());
+        assert!(reloaded.get(&pull("acme", 1, None)).is_none());
+
+        fs::remove_dir_all(&root).await.expect("remove temp dir");
+    }
+}
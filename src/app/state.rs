@@ -1,25 +1,60 @@
 //! Application state models and route-local behavior.
 
+mod composer;
+mod diff_folds;
+mod diff_text_search;
 mod diff_tree;
 mod search_input;
+mod template_picker;
+mod thread_facets;
 mod thread_nodes;
 mod thread_search;
+mod thread_tree;
 mod tree_filter;
 
-pub use self::search_input::SearchInputState;
+pub use self::{
+    composer::{ComposerCancel, ComposerState, ComposerSuggestion, ComposerTarget},
+    diff_folds::{DiffLine, FoldExpansion},
+    diff_text_search::DiffTextMatch,
+    search_input::SearchInputState,
+    template_picker::{TemplatePickerState, TemplateTarget},
+    thread_facets::ThreadFacets,
+};
 use self::{
+    diff_folds::FileFolds,
+    diff_text_search::DiffTextQuery,
     diff_tree::{build_diff_tree_rows, filter_diff_tree_rows},
-    thread_nodes::{append_thread_nodes, is_review_group_key, review_group_key, thread_key},
+    thread_facets::{Viewer, parse_query},
+    thread_nodes::{
+        DESCRIPTION_NODE_KEY, append_thread_nodes, is_review_group_key, review_group_key,
+        thread_key,
+    },
     thread_search::filter_thread_nodes,
+    thread_tree::{directory_key, file_key},
 };
 use crate::{
+    app::{
+        keymap::KeyChord,
+        seen::{SeenMarkers, SeenRecord},
+    },
     domain::{
         CommentRef, ListNode, ListNodeKind, PullRequestComment, PullRequestData,
-        PullRequestDiffData, PullRequestDiffFile, PullRequestSummary, ReviewThread, Route,
+        PullRequestDiffData, PullRequestDiffFile, PullRequestSummary, ReviewComment, ReviewThread,
+        Route,
     },
+    github::ratelimit::RateLimitStatus,
+    render::markdown::{PreviewLink, link_hint_label},
     search::fuzzy::rank_pull_requests,
 };
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// Unchanged rows kept around each change until the configured value is applied.
+const DEFAULT_DIFF_CONTEXT_LINES: usize = 3;
+/// Columns one horizontal diff scroll step moves by.
+const DIFF_COLUMN_SCROLL_STEP: usize = 8;
 
 /// Spinner frames used for active async operations.
 pub const SPINNER_FRAMES: [&str; 8] = ["⢎⡰", "⢎⡡", "⢎⡑", "⢎⠱", "⠎⡱", "⢊⡱", "⢌⡱", "⢆⡱"];
@@ -40,6 +75,21 @@ pub struct AppState {
     pub search_status_filter: SearchStatusFilter,
     pub search_sort: SearchSort,
     pub review: Option<ReviewScreenState>,
+    /// Keys typed so far towards a multi-key binding such as `g g`.
+    pub pending_keys: Vec<KeyChord>,
+    /// Most constrained GitHub rate-limit resource seen so far.
+    pub rate_limit: Option<(String, RateLimitStatus)>,
+    /// Running with `--offline` against saved snapshots.
+    pub offline: bool,
+    /// Mutations waiting in the offline outbox.
+    pub queued_mutations: usize,
+    pub replaying_outbox: bool,
+    /// Background refreshes in flight.
+    pub polling: usize,
+    /// Transient message shown above the footer hints.
+    pub notification: Option<Notification>,
+    /// Per-pull-request read markers from `~/.critic/seen.json`.
+    pub seen: SeenMarkers,
     operation: Option<OperationState>,
 }
 
@@ -59,6 +109,14 @@ impl Default for AppState {
             search_status_filter: SearchStatusFilter::All,
             search_sort: SearchSort::UpdatedAt,
             review: None,
+            pending_keys: Vec::new(),
+            rate_limit: None,
+            offline: false,
+            queued_mutations: 0,
+            replaying_outbox: false,
+            polling: 0,
+            notification: None,
+            seen: SeenMarkers::default(),
             operation: None,
         }
     }
@@ -122,14 +180,42 @@ impl AppState {
         self.repository_label = label;
     }
 
+    pub fn apply_search_defaults(
+        &mut self,
+        scope: SearchScope,
+        status: SearchStatusFilter,
+        sort: SearchSort,
+    ) {
+        self.search_scope = scope;
+        self.search_status_filter = status;
+        self.search_sort = sort;
+        self.recompute_search();
+    }
+
+    /// Replaces the pull request list, keeping the cursor on the same pull
+    /// request when it is still listed.
     pub fn set_pull_requests(&mut self, pulls: Vec<PullRequestSummary>) {
+        let previous = self
+            .selected_search_pull()
+            .map(|pull| (pull.owner.clone(), pull.repo.clone(), pull.number));
         self.pull_requests = pulls;
         self.recompute_search();
-        self.search_selected = 0;
+        self.search_selected = previous
+            .and_then(|(owner, repo, number)| {
+                self.search_results.iter().position(|index| {
+                    let pull = &self.pull_requests[*index];
+                    pull.number == number && pull.owner == owner && pull.repo == repo
+                })
+            })
+            .unwrap_or(0);
     }
 
     pub fn set_viewer_login(&mut self, login: Option<String>) {
         self.viewer_login = login;
+        if let Some(review) = self.review.as_mut() {
+            review.viewer_login = self.viewer_login.clone();
+            review.rebuild_nodes();
+        }
         self.recompute_search();
     }
 
@@ -243,8 +329,16 @@ impl AppState {
         self.search_selected = self.search_selected.saturating_sub(1);
     }
 
+    /// Opens the review screen, flagging comments that are unread since the
+    /// pull request was last viewed.
     pub fn open_review(&mut self, pull: PullRequestSummary, data: PullRequestData) {
-        self.review = Some(ReviewScreenState::new(pull, data));
+        let mut review = ReviewScreenState::new(pull, data);
+        review.viewer_login = self.viewer_login.clone();
+        if let Some(record) = self.seen.get(&review.pull) {
+            review.restore_unread(record, self.viewer_login.as_deref());
+        }
+        self.seen.record_visit(&review.pull);
+        self.review = Some(review);
         self.route = Route::Review;
     }
 
@@ -282,6 +376,24 @@ impl AppState {
             .unwrap_or("⢎⡰");
         Some(format!("{frame} {}", operation.label))
     }
+
+    pub fn notify(&mut self, message: impl Into<String>) {
+        self.notification = Some(Notification {
+            message: message.into(),
+            posted_at: Instant::now(),
+        });
+    }
+
+    /// Drops the notification once it has been shown for `ttl`.
+    pub fn expire_notification(&mut self, ttl: Duration) {
+        if self
+            .notification
+            .as_ref()
+            .is_some_and(|notification| notification.posted_at.elapsed() >= ttl)
+        {
+            self.notification = None;
+        }
+    }
 }
 
 fn scope_matches(
@@ -319,6 +431,13 @@ fn status_matches(pull: &PullRequestSummary, status: SearchStatusFilter) -> bool
     }
 }
 
+/// Short-lived status message, such as the result of a background refresh.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    posted_at: Instant,
+}
+
 #[derive(Debug, Clone)]
 struct OperationState {
     label: String,
@@ -360,6 +479,13 @@ pub struct ThreadActionContext {
     pub is_resolved: bool,
 }
 
+/// What changed when a background refresh replaced the review data.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RefreshOutcome {
+    pub new_comments: usize,
+    pub head_changed: bool,
+}
+
 /// Route-local state for the review screen.
 #[derive(Debug, Clone)]
 pub struct ReviewScreenState {
@@ -367,6 +493,10 @@ pub struct ReviewScreenState {
     pub data: PullRequestData,
     pub active_tab: ReviewTab,
     pub hide_resolved: bool,
+    /// Nest threads under their file paths instead of conversation order.
+    pub group_by_file: bool,
+    /// Show `<details>` sections in the preview expanded.
+    pub expand_details: bool,
     pub selected_row: usize,
     pub right_scroll: u16,
     pub diff: Option<PullRequestDiffData>,
@@ -376,22 +506,72 @@ pub struct ReviewScreenState {
     pub selected_diff_file: usize,
     pub selected_diff_line: usize,
     pub diff_selection_anchor: Option<usize>,
+    /// First pane line shown, counting each fold as one line.
     pub diff_scroll: u16,
+    /// Unchanged rows kept around each change before the rest fold.
+    pub diff_context_lines: usize,
+    /// Folded rows of each diff file, by file index.
+    diff_folds: Vec<FileFolds>,
+    /// Layout the diff pane is drawn in, applied to [`Self::diff_folds`].
+    diff_layout: DiffLayout,
+    /// Layout picked with the toggle, overriding the width-based choice.
+    diff_layout_override: Option<DiffLayout>,
+    /// Pane width below which the diff switches to the unified layout; 0 never
+    /// switches.
+    pub diff_unified_below: u16,
+    diff_viewport_width: u16,
+    /// Side the cursor is on when a modified row takes two unified lines.
+    diff_cursor_side: PendingReviewCommentSide,
+    /// Long lines wrap onto extra pane lines instead of being clipped.
+    pub diff_wrap: bool,
+    /// Columns both sides are scrolled right by while lines are not wrapped.
+    pub diff_column_offset: usize,
     pending_preview_scroll: u16,
     pending_preview_comment_id: Option<u64>,
     pub selected_hunk: usize,
     pub diff_viewport_height: u16,
     pub thread_search: SearchInputState,
+    /// Facets from the comment search the node cache was last built with.
+    thread_facets: ThreadFacets,
+    /// Login of the signed-in user, for the facets that depend on it.
+    pub viewer_login: Option<String>,
     pub diff_search: SearchInputState,
+    /// Search of the row text of every diff file.
+    pub diff_text_search: SearchInputState,
+    diff_text_query: Option<DiffTextQuery>,
+    diff_text_error: Option<&'static str>,
+    /// Matches of [`Self::diff_text_query`], by file index.
+    diff_text_matches: Vec<DiffTextMatch>,
+    /// Index in [`Self::diff_text_matches`] of the match last jumped to.
+    diff_text_current: Option<usize>,
     pub diff_tree_rows_cache: Vec<DiffTreeRow>,
     pub pending_review_comments: Vec<PendingReviewCommentDraft>,
     thread_nodes_cache: Vec<ListNode>,
     pub nodes: Vec<ListNode>,
     pub reply_drafts: HashMap<String, String>,
+    pub composer: Option<ComposerState>,
+    /// Open issues and pull requests of the repository, for `#` completion.
+    pub issue_titles: Vec<(u64, String)>,
+    pub template_picker: Option<TemplatePickerState>,
     next_pending_review_comment_id: u64,
     collapsed: HashSet<String>,
     diff_collapsed_dirs: HashSet<String>,
     threads_by_key: HashMap<String, ReviewThread>,
+    draft_restore_requested: bool,
+    /// Another session's version of reply drafts that conflicted in the last
+    /// draft merge, keyed by thread, until they are swapped in.
+    draft_conflicts: HashMap<String, String>,
+    /// [`CommentRef::identity`] of comments that arrived in a background refresh,
+    /// or since the last visit, and have not been selected yet.
+    new_comments: HashSet<String>,
+    /// Head SHA the review was loaded at, once a background refresh moved it.
+    unacknowledged_head_sha: Option<String>,
+    /// Links in the thread preview as last rendered, in label order.
+    pub preview_links: Vec<PreviewLink>,
+    /// Label typed so far while picking a link; `Some` in link-hint mode.
+    link_hint_input: Option<String>,
+    /// Thread location to show once the diff finishes loading.
+    pending_diff_anchor: Option<DiffAnchor>,
 }
 
 impl ReviewScreenState {
@@ -407,6 +587,8 @@ impl ReviewScreenState {
             data,
             active_tab: ReviewTab::Threads,
             hide_resolved: true,
+            group_by_file: false,
+            expand_details: false,
             selected_row: 0,
             right_scroll: 0,
             diff: None,
@@ -417,21 +599,47 @@ impl ReviewScreenState {
             selected_diff_line: 0,
             diff_selection_anchor: None,
             diff_scroll: 0,
+            diff_context_lines: DEFAULT_DIFF_CONTEXT_LINES,
+            diff_folds: Vec::new(),
+            diff_layout: DiffLayout::Split,
+            diff_layout_override: None,
+            diff_unified_below: 0,
+            diff_viewport_width: 0,
+            diff_cursor_side: PendingReviewCommentSide::Left,
+            diff_wrap: false,
+            diff_column_offset: 0,
             pending_preview_scroll: 0,
             pending_preview_comment_id: None,
             selected_hunk: 0,
             diff_viewport_height: 0,
             thread_search: SearchInputState::default(),
+            thread_facets: ThreadFacets::default(),
+            viewer_login: None,
             diff_search: SearchInputState::default(),
+            diff_text_search: SearchInputState::default(),
+            diff_text_query: None,
+            diff_text_error: None,
+            diff_text_matches: Vec::new(),
+            diff_text_current: None,
             diff_tree_rows_cache: Vec::new(),
             pending_review_comments: Vec::new(),
             thread_nodes_cache: Vec::new(),
             nodes: Vec::new(),
             reply_drafts: HashMap::new(),
+            composer: None,
+            issue_titles: Vec::new(),
+            template_picker: None,
             next_pending_review_comment_id: 1,
             collapsed: HashSet::new(),
             diff_collapsed_dirs: HashSet::new(),
             threads_by_key: HashMap::new(),
+            draft_restore_requested: false,
+            draft_conflicts: HashMap::new(),
+            new_comments: HashSet::new(),
+            unacknowledged_head_sha: None,
+            preview_links: Vec::new(),
+            link_hint_input: None,
+            pending_diff_anchor: None,
         };
 
         state.initialize_collapsed_defaults();
@@ -453,7 +661,39 @@ impl ReviewScreenState {
         let selected_key = self.selected_node().map(|node| node.key.clone());
         self.thread_nodes_cache.clear();
         self.threads_by_key.clear();
+        self.thread_facets = parse_query(self.thread_search.query()).0;
+
+        if let Some(description) = &self.data.description
+            && self.thread_facets.is_empty()
+        {
+            self.thread_nodes_cache.push(ListNode {
+                key: DESCRIPTION_NODE_KEY.to_owned(),
+                kind: ListNodeKind::Description,
+                depth: 0,
+                root_key: None,
+                is_resolved: false,
+                is_outdated: false,
+                comment: CommentRef::Description(Box::new(description.clone())),
+            });
+        }
+
+        if self.group_by_file {
+            self.append_file_grouped_nodes();
+        } else {
+            self.append_conversation_nodes();
+        }
+
+        self.recompute_thread_nodes_cache(selected_key.as_deref());
+    }
 
+    /// Lists comments in creation order, nesting inline threads under the
+    /// review that posted them.
+    fn append_conversation_nodes(&mut self) {
+        let facets = &self.thread_facets;
+        let viewer = Viewer {
+            login: self.viewer_login.as_deref(),
+            pull_author: &self.pull.author,
+        };
         let summary_review_ids: HashSet<u64> = self
             .data
             .comments
@@ -498,6 +738,9 @@ impl ReviewScreenState {
                     if self.hide_resolved && thread.is_resolved {
                         continue;
                     }
+                    if !facets.matches_thread(thread, viewer) {
+                        continue;
+                    }
                     append_thread_nodes(
                         &mut self.thread_nodes_cache,
                         &mut self.threads_by_key,
@@ -507,20 +750,28 @@ impl ReviewScreenState {
                     );
                 }
                 PullRequestComment::IssueComment(comment) => {
+                    let comment = CommentRef::Issue((**comment).clone());
+                    if !facets.matches_comment(comment.author(), comment.body(), viewer) {
+                        continue;
+                    }
                     self.thread_nodes_cache.push(ListNode {
-                        key: format!("issue:{}", comment.id),
+                        key: comment.identity(),
                         kind: ListNodeKind::Issue,
                         depth: 0,
                         root_key: None,
                         is_resolved: false,
                         is_outdated: false,
-                        comment: CommentRef::Issue((**comment).clone()),
+                        comment,
                     });
                 }
                 PullRequestComment::ReviewSummary(review) => {
                     let review_id = review.id.into_inner();
                     let summary_threads = grouped_threads.remove(&review_id).unwrap_or_default();
                     if summary_threads.is_empty() {
+                        let comment = CommentRef::ReviewSummary((**review).clone());
+                        if !facets.matches_comment(comment.author(), comment.body(), viewer) {
+                            continue;
+                        }
                         self.thread_nodes_cache.push(ListNode {
                             key: format!("review-issue:{}", review.id),
                             kind: ListNodeKind::Issue,
@@ -528,15 +779,18 @@ impl ReviewScreenState {
                             root_key: None,
                             is_resolved: false,
                             is_outdated: false,
-                            comment: CommentRef::ReviewSummary((**review).clone()),
+                            comment,
                         });
                         continue;
                     }
 
-                    let has_unresolved_threads =
-                        summary_threads.iter().any(|thread| !thread.is_resolved);
-                    let group_resolved = !has_unresolved_threads;
-                    if self.hide_resolved && group_resolved {
+                    let group_resolved = summary_threads.iter().all(|thread| thread.is_resolved);
+                    let visible_threads: Vec<&ReviewThread> = summary_threads
+                        .iter()
+                        .filter(|thread| !(self.hide_resolved && thread.is_resolved))
+                        .filter(|thread| facets.matches_thread(thread, viewer))
+                        .collect();
+                    if visible_threads.is_empty() {
                         continue;
                     }
 
@@ -555,10 +809,7 @@ impl ReviewScreenState {
                         continue;
                     }
 
-                    for thread in &summary_threads {
-                        if self.hide_resolved && thread.is_resolved {
-                            continue;
-                        }
+                    for thread in visible_threads {
                         append_thread_nodes(
                             &mut self.thread_nodes_cache,
                             &mut self.threads_by_key,
@@ -570,12 +821,11 @@ impl ReviewScreenState {
                 }
             }
         }
-
-        self.recompute_thread_nodes_cache(selected_key.as_deref());
     }
 
     fn recompute_thread_nodes_cache(&mut self, selected_key: Option<&str>) {
-        self.nodes = filter_thread_nodes(&self.thread_nodes_cache, self.thread_search.query());
+        let text = parse_query(self.thread_search.query()).1;
+        self.nodes = filter_thread_nodes(&self.thread_nodes_cache, &text);
 
         if let Some(previous_key) = selected_key {
             self.selected_row = self
@@ -630,6 +880,38 @@ impl ReviewScreenState {
         self.reply_drafts.remove(root_key);
     }
 
+    /// Asks the event loop to swap in the previous saved draft snapshot.
+    pub fn request_draft_restore(&mut self) {
+        self.draft_restore_requested = true;
+    }
+
+    pub fn take_draft_restore_request(&mut self) -> bool {
+        std::mem::take(&mut self.draft_restore_requested)
+    }
+
+    /// Remembers the other session's version of conflicting reply drafts.
+    pub fn set_draft_conflicts(&mut self, conflicts: HashMap<String, String>) {
+        self.draft_conflicts = conflicts;
+    }
+
+    pub fn has_draft_conflicts(&self) -> bool {
+        !self.draft_conflicts.is_empty()
+    }
+
+    /// Exchanges the conflicting reply drafts with the other session's
+    /// version, so swapping again brings this session's back. Replies sent or
+    /// cleared since the merge are dropped. Returns how many were swapped.
+    pub fn swap_draft_conflicts(&mut self) -> usize {
+        let conflicts = std::mem::take(&mut self.draft_conflicts);
+        for (key, body) in conflicts {
+            if let Some(current) = self.reply_drafts.get_mut(&key) {
+                let previous = std::mem::replace(current, body);
+                self.draft_conflicts.insert(key, previous);
+            }
+        }
+        self.draft_conflicts.len()
+    }
+
     pub fn focus_thread_search(&mut self) {
         self.thread_search.focus();
     }
@@ -647,6 +929,10 @@ impl ReviewScreenState {
     }
 
     pub fn refresh_thread_search_results(&mut self) {
+        if parse_query(self.thread_search.query()).0 != self.thread_facets {
+            self.rebuild_nodes();
+            return;
+        }
         let selected_key = self.selected_node().map(|node| node.key.clone());
         self.recompute_thread_nodes_cache(selected_key.as_deref());
     }
@@ -736,7 +1022,11 @@ impl ReviewScreenState {
 
         let is_collapsible_review_group =
             node.kind == ListNodeKind::Review && is_review_group_key(&node.key);
-        if node.kind != ListNodeKind::Thread && !is_collapsible_review_group {
+        let is_collapsible = matches!(
+            node.kind,
+            ListNodeKind::Thread | ListNodeKind::Directory | ListNodeKind::File
+        );
+        if !is_collapsible && !is_collapsible_review_group {
             return;
         }
         let key = node.key.clone();
@@ -755,6 +1045,52 @@ impl ReviewScreenState {
         self.rebuild_nodes();
     }
 
+    /// Enters link-hint mode. Returns `false` when the preview has no links.
+    pub fn start_link_hints(&mut self) -> bool {
+        if self.preview_links.is_empty() {
+            return false;
+        }
+        self.link_hint_input = Some(String::new());
+        true
+    }
+
+    pub fn cancel_link_hints(&mut self) {
+        self.link_hint_input = None;
+    }
+
+    pub fn is_picking_link(&self) -> bool {
+        self.link_hint_input.is_some()
+    }
+
+    /// Number of labelled links while in link-hint mode.
+    pub fn link_hint_total(&self) -> Option<usize> {
+        self.link_hint_input
+            .as_ref()
+            .map(|_| self.preview_links.len())
+    }
+
+    /// Adds a typed label character. Returns the link once its label is
+    /// complete; leaves link-hint mode when no label starts with the input.
+    pub fn type_link_hint(&mut self, ch: char) -> Option<PreviewLink> {
+        let input = self.link_hint_input.as_mut()?;
+        input.push(ch.to_ascii_lowercase());
+
+        let total = self.preview_links.len();
+        let labels = (0..total).map(|index| link_hint_label(index, total));
+        let mut is_prefix = false;
+        for (index, label) in labels.enumerate() {
+            if label == *input {
+                self.link_hint_input = None;
+                return self.preview_links.get(index).cloned();
+            }
+            is_prefix |= label.starts_with(input.as_str());
+        }
+        if !is_prefix {
+            self.link_hint_input = None;
+        }
+        None
+    }
+
     /// Replaces PR payload data while preserving route-local interaction state.
     ///
     /// Returns `true` when the pull request head SHA changed.
@@ -770,6 +1106,116 @@ impl ReviewScreenState {
         head_changed
     }
 
+    /// Replaces PR payload data with a background refresh, highlighting comments
+    /// that were not loaded before and remembering a head change until it is
+    /// acknowledged.
+    pub fn apply_refresh(&mut self, data: PullRequestData) -> RefreshOutcome {
+        let previous_head_sha = self.pull.head_sha.clone();
+        let known = self.data.comment_identities();
+        let fresh: Vec<String> = data
+            .comment_identities()
+            .into_iter()
+            .filter(|identity| !known.contains(identity))
+            .collect();
+
+        let head_changed = self.set_data(data);
+        if head_changed && self.unacknowledged_head_sha.is_none() {
+            self.unacknowledged_head_sha = Some(previous_head_sha);
+        }
+        let new_comments = fresh.len();
+        self.new_comments.extend(fresh);
+        RefreshOutcome {
+            new_comments,
+            head_changed,
+        }
+    }
+
+    /// Whether the node, or any reply of a thread node, arrived in a background refresh.
+    pub fn is_new_node(&self, node: &ListNode) -> bool {
+        if self.new_comments.is_empty() {
+            return false;
+        }
+        match (node.kind, self.threads_by_key.get(&node.key)) {
+            (ListNodeKind::Thread, Some(thread)) => {
+                let mut identities = HashSet::new();
+                thread.collect_identities(&mut identities);
+                !identities.is_disjoint(&self.new_comments)
+            }
+            _ => self.new_comments.contains(&node.comment.identity()),
+        }
+    }
+
+    /// Flags comments posted after `record` was taken, other than the viewer's,
+    /// along with those left unread on that visit.
+    pub fn restore_unread(&mut self, record: &SeenRecord, viewer_login: Option<&str>) {
+        let present = self.data.comment_identities();
+        self.new_comments.extend(
+            record
+                .unread
+                .iter()
+                .filter(|identity| present.contains(*identity))
+                .cloned(),
+        );
+        self.new_comments.extend(
+            self.data
+                .comments_created_after(record.seen_at_unix_ms, viewer_login),
+        );
+    }
+
+    pub fn has_unread(&self) -> bool {
+        !self.new_comments.is_empty()
+    }
+
+    /// Unread comment identities, sorted so they can be compared and stored.
+    pub fn unread_identities(&self) -> Vec<String> {
+        let mut unread: Vec<String> = self.new_comments.iter().cloned().collect();
+        unread.sort();
+        unread
+    }
+
+    /// Selects the next (or previous) listed node with unread comments,
+    /// wrapping around. Returns `false` when no listed node is unread.
+    pub fn jump_to_unread(&mut self, forward: bool) -> bool {
+        let count = self.nodes.len();
+        let target = (1..=count)
+            .map(|step| {
+                if forward {
+                    (self.selected_row + step) % count
+                } else {
+                    (self.selected_row + count - step % count) % count
+                }
+            })
+            .find(|row| self.is_new_node(&self.nodes[*row]));
+        let Some(row) = target else {
+            return false;
+        };
+        self.selected_row = row;
+        self.right_scroll = 0;
+        true
+    }
+
+    /// Clears new-comment highlights for the selected comment, or its whole
+    /// thread since the preview shows every reply.
+    pub fn mark_selected_seen(&mut self) {
+        if self.new_comments.is_empty() {
+            return;
+        }
+        let mut seen = HashSet::new();
+        if let Some(thread) = self.selected_root_thread() {
+            thread.collect_identities(&mut seen);
+        } else if let Some(node) = self.selected_node() {
+            seen.insert(node.comment.identity());
+        }
+        self.new_comments
+            .retain(|identity| !seen.contains(identity));
+    }
+
+    /// Takes the head SHA the review was loaded at if new commits have been
+    /// pushed since, so submitting can warn once before going ahead.
+    pub fn acknowledge_head_change(&mut self) -> Option<String> {
+        self.unacknowledged_head_sha.take()
+    }
+
     /// Clears all loaded diff data and resets diff-focused interaction state.
     pub fn clear_diff(&mut self) {
         self.diff = None;
@@ -782,14 +1228,119 @@ impl ReviewScreenState {
         self.diff = Some(diff);
         self.diff_error = None;
         self.reset_diff_view_state();
+        self.rebuild_diff_folds();
         self.recompute_diff_tree_rows_cache();
         self.select_initial_diff_file_row();
     }
 
+    /// Width below which the diff is drawn unified unless a layout was toggled.
+    pub fn set_diff_unified_below(&mut self, width: u16) {
+        self.diff_unified_below = width;
+        self.update_diff_layout();
+    }
+
+    pub fn set_diff_viewport_width(&mut self, width: u16) {
+        self.diff_viewport_width = width;
+        self.update_diff_layout();
+    }
+
+    pub fn diff_layout(&self) -> DiffLayout {
+        self.diff_layout
+    }
+
+    /// Switches between the split and unified layouts. Toggling back to the
+    /// layout the pane width calls for resumes switching by width.
+    pub fn toggle_diff_layout(&mut self) {
+        let layout = match self.diff_layout {
+            DiffLayout::Split => DiffLayout::Unified,
+            DiffLayout::Unified => DiffLayout::Split,
+        };
+        self.diff_layout_override = (layout != self.width_diff_layout()).then_some(layout);
+        self.update_diff_layout();
+    }
+
+    /// Switches between wrapping long lines and scrolling them horizontally.
+    pub fn toggle_diff_wrap(&mut self) {
+        self.diff_wrap = !self.diff_wrap;
+        self.diff_column_offset = 0;
+    }
+
+    /// Scrolls both sides of the diff left by a step while lines are not
+    /// wrapped.
+    pub fn scroll_diff_left(&mut self) {
+        if !self.diff_wrap {
+            self.diff_column_offset = self
+                .diff_column_offset
+                .saturating_sub(DIFF_COLUMN_SCROLL_STEP);
+        }
+    }
+
+    /// Scrolls both sides of the diff right by a step while lines are not
+    /// wrapped, stopping at the end of the longest line of the file.
+    pub fn scroll_diff_right(&mut self) {
+        if self.diff_wrap {
+            return;
+        }
+        let longest = self.selected_diff_file().map_or(0, |file| {
+            file.rows
+                .iter()
+                .map(|row| {
+                    row.left_text
+                        .chars()
+                        .count()
+                        .max(row.right_text.chars().count())
+                })
+                .max()
+                .unwrap_or(0)
+        });
+        self.diff_column_offset =
+            (self.diff_column_offset + DIFF_COLUMN_SCROLL_STEP).min(longest.saturating_sub(1));
+    }
+
+    /// Side of the selected row the cursor is on. Only meaningful for
+    /// modified rows in the unified layout, where each side has its own line.
+    pub fn diff_cursor_side(&self) -> PendingReviewCommentSide {
+        self.diff_cursor_side
+    }
+
+    fn width_diff_layout(&self) -> DiffLayout {
+        if self.diff_viewport_width > 0 && self.diff_viewport_width < self.diff_unified_below {
+            DiffLayout::Unified
+        } else {
+            DiffLayout::Split
+        }
+    }
+
+    fn update_diff_layout(&mut self) {
+        let layout = self
+            .diff_layout_override
+            .unwrap_or_else(|| self.width_diff_layout());
+        if layout == self.diff_layout {
+            return;
+        }
+        self.diff_layout = layout;
+        for folds in &mut self.diff_folds {
+            folds.set_unified(layout == DiffLayout::Unified);
+        }
+        self.keep_selected_line_visible();
+    }
+
+    /// Side of the selected row shown on its own line in the unified layout.
+    fn selected_unified_half(&self) -> Option<PendingReviewCommentSide> {
+        let row = self
+            .selected_diff_file()?
+            .rows
+            .get(self.selected_diff_line)?;
+        (self.diff_layout == DiffLayout::Unified
+            && row.kind == crate::domain::PullRequestDiffRowKind::Modified)
+            .then_some(self.diff_cursor_side)
+    }
+
     /// Stores a diff loading error and resets diff browsing selections.
     pub fn set_diff_error(&mut self, error: String) {
         self.diff = None;
         self.diff_error = Some(error);
+        self.pending_diff_anchor = None;
         self.reset_diff_view_state();
     }
 
@@ -806,7 +1357,14 @@ impl ReviewScreenState {
         self.diff_viewport_height = 0;
         self.diff_collapsed_dirs.clear();
         self.diff_search = SearchInputState::default();
+        self.diff_text_search = SearchInputState::default();
+        self.diff_text_query = None;
+        self.diff_text_error = None;
+        self.diff_text_matches.clear();
+        self.diff_text_current = None;
         self.diff_tree_rows_cache.clear();
+        self.diff_folds.clear();
+        self.diff_column_offset = 0;
     }
 
     pub fn active_tab(&self) -> ReviewTab {
@@ -1058,6 +1616,96 @@ impl ReviewScreenState {
         true
     }
 
+    /// Opens the Diff tab on the lines the selected thread is anchored to. When
+    /// the diff has not loaded yet, the jump happens once it arrives.
+    pub fn show_selected_thread_in_diff(&mut self) -> Result<(), &'static str> {
+        let thread = self
+            .selected_root_thread()
+            .ok_or("select an inline comment thread")?;
+        let anchor = DiffAnchor::of_comment(&thread.comment)
+            .ok_or("thread is outdated; its lines are no longer in the diff")?;
+
+        self.active_tab = ReviewTab::Diff;
+        if self.diff.is_none() {
+            self.pending_diff_anchor = Some(anchor);
+            return Ok(());
+        }
+        self.jump_to_diff_anchor(&anchor)
+    }
+
+    /// Applies a thread jump requested before the diff loaded, unless the
+    /// Diff tab was left in the meantime.
+    pub fn apply_pending_diff_jump(&mut self) -> Result<(), &'static str> {
+        let Some(anchor) = self.pending_diff_anchor.take() else {
+            return Ok(());
+        };
+        if self.active_tab != ReviewTab::Diff {
+            return Ok(());
+        }
+        self.jump_to_diff_anchor(&anchor)
+    }
+
+    /// Opens the Threads tab on the thread anchored at the selected diff line,
+    /// preferring unresolved threads when several overlap.
+    pub fn show_diff_line_thread(&mut self) -> Result<(), &'static str> {
+        let file = self.selected_diff_file().ok_or("no diff file selected")?;
+        let row = file
+            .rows
+            .get(self.selected_diff_line)
+            .ok_or("no diff line selected")?;
+        let thread = self
+            .data
+            .comments
+            .iter()
+            .filter_map(|entry| match entry {
+                PullRequestComment::ReviewThread(thread) => Some(&**thread),
+                _ => None,
+            })
+            .filter(|thread| thread.comment.path == file.path)
+            .filter(|thread| {
+                DiffAnchor::of_comment(&thread.comment).is_some_and(|anchor| anchor.covers(row))
+            })
+            .min_by_key(|thread| thread.is_resolved)
+            .cloned()
+            .ok_or("no comment thread on this line")?;
+
+        self.active_tab = ReviewTab::Threads;
+        self.reveal_thread(&thread);
+        Ok(())
+    }
+
+    pub fn selected_diff_line_has_thread(&self) -> bool {
+        self.selected_diff_file().is_some_and(|file| {
+            self.thread_rows_for_file(file)
+                .contains(&self.selected_diff_line)
+        })
+    }
+
+    /// Rows of `file` covered by an existing comment thread.
+    pub fn thread_rows_for_file(&self, file: &PullRequestDiffFile) -> HashSet<usize> {
+        let anchors = self
+            .data
+            .comments
+            .iter()
+            .filter_map(|entry| match entry {
+                PullRequestComment::ReviewThread(thread) if thread.comment.path == file.path => {
+                    DiffAnchor::of_comment(&thread.comment)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if anchors.is_empty() {
+            return HashSet::new();
+        }
+
+        file.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| anchors.iter().any(|anchor| anchor.covers(row)))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
     pub fn is_collapsed(&self, key: &str) -> bool {
         self.collapsed.contains(key)
     }
@@ -1177,8 +1825,9 @@ impl ReviewScreenState {
     pub fn selected_pending_review_comment(&self) -> Option<&PendingReviewCommentDraft> {
         let file = self.selected_diff_file()?;
         let row = file.rows.get(self.selected_diff_line)?;
+        let half = self.selected_unified_half();
         self.pending_review_comments.iter().find(|comment| {
-            if comment.path != file.path {
+            if comment.path != file.path || half.is_some_and(|side| side != comment.side) {
                 return false;
             }
             let Some(line) = row_line_for_side(row, comment.side) else {
@@ -1298,6 +1947,9 @@ impl ReviewScreenState {
         if row.kind == crate::domain::PullRequestDiffRowKind::Context {
             return None;
         }
+        if let Some(side) = self.selected_unified_half() {
+            return Some(side);
+        }
 
         if row.right_line_number.is_some() {
             Some(PendingReviewCommentSide::Right)
@@ -1394,11 +2046,13 @@ impl ReviewScreenState {
             return;
         }
 
-        let max_index = file.rows.len().saturating_sub(1);
-        let mut next = (self.selected_diff_line + 1).min(max_index);
-        if self.diff_selection_anchor.is_some()
-            && let Some(side) = self.selected_diff_comment_side()
-        {
+        let line = self.selected_diff_pane_line();
+        let last_line = self.diff_lines().len().saturating_sub(1);
+        let (mut next, next_side) =
+            self.diff_row_at_line(self.selected_diff_file, (line + 1).min(last_line));
+        if self.diff_selection_anchor.is_none() {
+            self.diff_cursor_side = next_side.unwrap_or(self.diff_cursor_side);
+        } else if let Some(side) = self.selected_diff_comment_side() {
             let anchor = self
                 .diff_selection_anchor
                 .unwrap_or(self.selected_diff_line);
@@ -1424,9 +2078,12 @@ impl ReviewScreenState {
     }
 
     fn move_selected_diff_line_up(&mut self) {
-        let mut next = self.selected_diff_line.saturating_sub(1);
-        if self.diff_selection_anchor.is_some()
-            && let Some(file) = self.selected_diff_file()
+        let line = self.selected_diff_pane_line();
+        let (mut next, next_side) =
+            self.diff_row_at_line(self.selected_diff_file, line.saturating_sub(1));
+        if self.diff_selection_anchor.is_none() {
+            self.diff_cursor_side = next_side.unwrap_or(self.diff_cursor_side);
+        } else if let Some(file) = self.selected_diff_file()
             && let Some(side) = self.selected_diff_comment_side()
         {
             let anchor = self
@@ -1511,20 +2168,28 @@ impl ReviewScreenState {
     fn keep_selected_line_visible(&mut self) {
         let viewport = usize::from(self.diff_viewport_height.max(1));
         let current_scroll = usize::from(self.diff_scroll);
-        if self.selected_diff_line < current_scroll {
-            self.diff_scroll = u16::try_from(self.selected_diff_line).unwrap_or(u16::MAX);
+        let selected = self.selected_diff_pane_line();
+        if selected < current_scroll {
+            self.diff_scroll = u16::try_from(selected).unwrap_or(u16::MAX);
             return;
         }
 
         let lower_bound = current_scroll.saturating_add(viewport.saturating_sub(1));
-        if self.selected_diff_line > lower_bound {
-            let target_scroll = self
-                .selected_diff_line
-                .saturating_sub(viewport.saturating_sub(1));
+        if selected > lower_bound {
+            let target_scroll = selected.saturating_sub(viewport.saturating_sub(1));
             self.diff_scroll = u16::try_from(target_scroll).unwrap_or(u16::MAX);
         }
     }
 
+    /// Pane line the cursor is on.
+    pub fn selected_diff_pane_line(&self) -> usize {
+        self.diff_line_index(
+            self.selected_diff_file,
+            self.selected_diff_line,
+            self.diff_cursor_side,
+        )
+    }
+
     pub fn set_diff_viewport_height(&mut self, height: u16) {
         self.diff_viewport_height = height.max(1);
     }
@@ -1563,19 +2228,25 @@ impl ReviewScreenState {
         }
 
         self.selected_diff_file = file_index;
-        self.diff_scroll = self.first_hunk_scroll(file_index);
         self.selected_hunk = 0;
-        self.selected_diff_line = usize::from(self.diff_scroll);
-        self.diff_selection_anchor = None;
+        self.select_first_hunk(file_index);
     }
 
-    fn first_hunk_scroll(&self, file_index: usize) -> u16 {
-        self.diff
+    /// Puts the cursor on the first hunk of `file_index`, scrolled to the top.
+    fn select_first_hunk(&mut self, file_index: usize) {
+        let row = self
+            .diff
             .as_ref()
             .and_then(|diff| diff.files.get(file_index))
             .and_then(|file| file.hunk_starts.first().copied())
-            .and_then(|value| u16::try_from(value).ok())
-            .unwrap_or(0)
+            .unwrap_or(0);
+        let line = self.diff_line_index(file_index, row, PendingReviewCommentSide::Left);
+        self.diff_cursor_side = PendingReviewCommentSide::Left;
+        (self.diff_scroll, self.selected_diff_line) = match u16::try_from(line) {
+            Ok(scroll) => (scroll, row),
+            Err(_) => (0, 0),
+        };
+        self.diff_selection_anchor = None;
     }
 
     fn realign_diff_selection_for_filter(&mut self) {
@@ -1640,9 +2311,7 @@ impl ReviewScreenState {
         self.selected_diff_row = row_index;
         self.selected_diff_file = file_index;
         self.selected_hunk = 0;
-        self.diff_scroll = self.first_hunk_scroll(file_index);
-        self.selected_diff_line = usize::from(self.diff_scroll);
-        self.diff_selection_anchor = None;
+        self.select_first_hunk(file_index);
     }
 
     fn set_active_hunk(&mut self, file_index: usize, hunk_start: usize, hunk_index: usize) {
@@ -1650,21 +2319,21 @@ impl ReviewScreenState {
         self.selected_diff_file = file_index;
         self.selected_hunk = hunk_index;
         let viewport_height = usize::from(self.diff_viewport_height.max(1));
-        let centered_start = hunk_start.saturating_sub(viewport_height / 2);
-        let clamped_scroll = self
-            .diff
-            .as_ref()
-            .and_then(|diff| diff.files.get(file_index))
-            .map(|file| {
-                if file.rows.is_empty() {
-                    centered_start
-                } else {
-                    centered_start.min(file.rows.len().saturating_sub(viewport_height))
-                }
-            })
-            .unwrap_or(centered_start);
+        let centered_start = self
+            .diff_line_index(file_index, hunk_start, PendingReviewCommentSide::Left)
+            .saturating_sub(viewport_height / 2);
+        let line_count = self
+            .diff_folds
+            .get(file_index)
+            .map_or(0, |folds| folds.lines().len());
+        let clamped_scroll = if line_count == 0 {
+            centered_start
+        } else {
+            centered_start.min(line_count.saturating_sub(viewport_height))
+        };
         self.diff_scroll = u16::try_from(clamped_scroll).unwrap_or(u16::MAX);
         self.selected_diff_line = hunk_start;
+        self.diff_cursor_side = PendingReviewCommentSide::Left;
         self.diff_selection_anchor = None;
 
         if let Some(row_index) = self
@@ -1707,22 +2376,103 @@ impl ReviewScreenState {
     }
 
     fn jump_to_pending_comment_location(&mut self, location: PendingCommentLocation) {
-        self.ensure_diff_file_expanded(location.file_index);
-        self.set_selected_diff_file(location.file_index);
-        self.selected_diff_line = location.row_index;
+        let side = self
+            .pending_review_comments
+            .iter()
+            .find(|comment| comment.id == location.comment_id)
+            .map_or(PendingReviewCommentSide::Right, |comment| comment.side);
+        self.jump_to_diff_row(location.file_index, location.row_index, side);
+    }
+
+    /// Selects `side` of a row, unfolding it if needed.
+    fn jump_to_diff_row(
+        &mut self,
+        file_index: usize,
+        row_index: usize,
+        side: PendingReviewCommentSide,
+    ) {
+        self.ensure_diff_file_expanded(file_index);
+        self.set_selected_diff_file(file_index);
+        if let Some(folds) = self.diff_folds.get_mut(file_index)
+            && folds.is_hidden(row_index)
+        {
+            folds.expand(row_index, FoldExpansion::All);
+        }
+        self.selected_diff_line = row_index;
+        self.diff_cursor_side = side;
         self.diff_selection_anchor = None;
 
         if let Some(file) = self.selected_diff_file() {
-            self.selected_hunk = hunk_index_for_row(file, location.row_index).unwrap_or(0);
+            self.selected_hunk = hunk_index_for_row(file, row_index).unwrap_or(0);
         }
         self.keep_selected_line_visible();
 
-        if let Some(row_index) = self
+        if let Some(tree_row) = self
             .diff_tree_rows()
             .iter()
-            .position(|row| row.file_index == Some(location.file_index))
+            .position(|row| row.file_index == Some(file_index))
         {
-            self.selected_diff_row = row_index;
+            self.selected_diff_row = tree_row;
+        }
+    }
+
+    fn jump_to_diff_anchor(&mut self, anchor: &DiffAnchor) -> Result<(), &'static str> {
+        let (file_index, row_index) = self
+            .diff
+            .as_ref()
+            .and_then(|diff| {
+                let file_index = diff
+                    .files
+                    .iter()
+                    .position(|file| file.path == anchor.path)?;
+                let row_index = line_range_row_index(
+                    &diff.files[file_index],
+                    anchor.side,
+                    anchor.line,
+                    anchor.start_line,
+                )?;
+                Some((file_index, row_index))
+            })
+            .ok_or("the thread's lines are not part of this diff")?;
+
+        let filtered_out = !self
+            .diff_tree_rows()
+            .iter()
+            .any(|row| row.file_index == Some(file_index));
+        if filtered_out {
+            self.diff_search = SearchInputState::default();
+            self.recompute_diff_tree_rows_cache();
+        }
+        self.jump_to_diff_row(file_index, row_index, anchor.side);
+        self.diff_focus = DiffFocus::Content;
+        Ok(())
+    }
+
+    /// Makes the thread's node visible, lifting whichever filter or collapsed
+    /// heading hides it, and selects it.
+    fn reveal_thread(&mut self, thread: &ReviewThread) {
+        if self.hide_resolved && thread.is_resolved {
+            self.hide_resolved = false;
+        }
+        if let Some(review_id) = thread.comment.pull_request_review_id {
+            self.collapsed
+                .remove(&review_group_key(review_id.into_inner()));
+        }
+        let path = thread.comment.path.as_str();
+        self.collapsed.remove(&file_key(path));
+        for (index, _) in path.match_indices('/') {
+            self.collapsed.remove(&directory_key(&path[..index]));
+        }
+        self.rebuild_nodes();
+
+        let key = thread_key(thread);
+        if !self.nodes.iter().any(|node| node.key == key) {
+            self.thread_search = SearchInputState::default();
+            self.rebuild_nodes();
+        }
+        if let Some(index) = self.nodes.iter().position(|node| node.key == key) {
+            self.selected_row = index;
+            self.right_scroll = 0;
         }
     }
 
@@ -1817,6 +2567,15 @@ pub enum DiffFocus {
     Content,
 }
 
+/// How the diff pane lays out the two sides of a file.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DiffLayout {
+    /// Old and new side by side.
+    Split,
+    /// One column, with each modified row as a removed line then an added one.
+    Unified,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum PendingReviewCommentSide {
     Left,
@@ -1840,6 +2599,37 @@ struct PendingCommentLocation {
     row_index: usize,
 }
 
+/// Lines an inline thread is attached to in the current diff.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct DiffAnchor {
+    path: String,
+    side: PendingReviewCommentSide,
+    line: u64,
+    start_line: Option<u64>,
+}
+
+impl DiffAnchor {
+    /// `None` for outdated comments, whose lines are gone from the diff.
+    fn of_comment(comment: &ReviewComment) -> Option<Self> {
+        let line = comment.line?;
+        let side = match comment.side.as_deref() {
+            Some("LEFT") => PendingReviewCommentSide::Left,
+            _ => PendingReviewCommentSide::Right,
+        };
+        Some(Self {
+            path: comment.path.clone(),
+            side,
+            line,
+            start_line: comment.start_line,
+        })
+    }
+
+    fn covers(&self, row: &crate::domain::PullRequestDiffRow) -> bool {
+        let (start, end) = pending_comment_bounds(self.line, self.start_line);
+        row_line_for_side(row, self.side).is_some_and(|line| (start..=end).contains(&(line as u64)))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DiffTreeRow {
     pub key: String,
@@ -1906,10 +2696,18 @@ fn pending_comment_row_index(
     file: &PullRequestDiffFile,
     comment: &PendingReviewCommentDraft,
 ) -> Option<usize> {
-    let start = comment.start_line.unwrap_or(comment.line).min(comment.line);
-    let end = comment.start_line.unwrap_or(comment.line).max(comment.line);
+    line_range_row_index(file, comment.side, comment.line, comment.start_line)
+}
+
+fn line_range_row_index(
+    file: &PullRequestDiffFile,
+    side: PendingReviewCommentSide,
+    line: u64,
+    start_line: Option<u64>,
+) -> Option<usize> {
+    let (start, end) = pending_comment_bounds(line, start_line);
     file.rows.iter().position(|row| {
-        let Some(line) = row_line_for_side(row, comment.side).map(|line| line as u64) else {
+        let Some(line) = row_line_for_side(row, side).map(|line| line as u64) else {
             return false;
         };
         line >= start && line <= end
@@ -1977,14 +2775,22 @@ fn is_commentable_diff_row(
 #[cfg(test)]
 mod tests {
     use super::{
-        AppState, DiffFocus, PendingReviewCommentDraft, ReviewScreenState, ReviewTab,
-        build_diff_tree_rows,
+        AppState, ComposerCancel, ComposerTarget, DiffFocus, DiffLayout, PendingReviewCommentDraft,
+        PendingReviewCommentSide, RefreshOutcome, ReviewScreenState, ReviewSubmissionEvent,
+        ReviewTab, SearchInputState, TemplateTarget, build_diff_tree_rows,
     };
-    use crate::domain::{
-        PullRequestComment, PullRequestData, PullRequestDiffData, PullRequestDiffFile,
-        PullRequestDiffFileStatus, PullRequestDiffRow, PullRequestDiffRowKind, PullRequestSummary,
-        ReviewComment, ReviewThread,
+    use crate::{
+        app::seen::SeenRecord,
+        config::CommentTemplate,
+        domain::{
+            ListNodeKind, PullRequestComment, PullRequestData, PullRequestDescription,
+            PullRequestDiffData, PullRequestDiffFile, PullRequestDiffFileStatus,
+            PullRequestDiffRow, PullRequestDiffRowKind, PullRequestSummary, ReviewComment,
+            ReviewThread,
+        },
+        render::markdown::PreviewLink,
     };
+    use octocrab::models::Author;
     use serde_json::json;
     use std::collections::HashSet;
 
@@ -2054,6 +2860,7 @@ mod tests {
             is_draft: false,
             reviewer_logins: Vec::new(),
             review_status: None,
+            comment_count: None,
         };
 
         let data = PullRequestData {
@@ -2063,6 +2870,7 @@ mod tests {
             base_sha: "basesha".to_owned(),
             changed_files: Vec::new(),
             comments,
+            description: None,
         };
 
         ReviewScreenState::new(pull, data)
@@ -2236,6 +3044,169 @@ mod tests {
         assert_eq!(review.diff_scroll, 50);
     }
 
+    #[test]
+    fn navigation_steps_over_folded_unchanged_rows() {
+        let mut review = build_review_state();
+        let mut file = diff_file("alpha.rs");
+        file.rows = (1..=100)
+            .map(|line| {
+                if line == 11 || line == 61 {
+                    paired_row(line, line)
+                } else {
+                    numbered_context_row(line, line)
+                }
+            })
+            .collect();
+        file.hunk_starts = vec![10, 60];
+        review.set_diff(PullRequestDiffData { files: vec![file] });
+        review.set_diff_viewport_height(6);
+        review.active_tab = ReviewTab::Diff;
+        review.focus_diff_content();
+
+        assert_eq!(review.selected_diff_line(), 10);
+        assert_eq!(review.diff_scroll, 4);
+        assert_eq!(review.diff_lines().len(), 17);
+
+        review.selected_diff_line = 13;
+        review.move_down();
+        assert_eq!(review.selected_diff_fold(), Some((14, 56)));
+        review.move_down();
+        assert_eq!(review.selected_diff_line(), 57);
+        review.move_up();
+        review.move_up();
+        assert_eq!(review.selected_diff_line(), 13);
+
+        review.jump_next_hunk();
+        assert_eq!(review.selected_diff_line(), 60);
+        assert_eq!(review.diff_scroll, 9);
+
+        review.selected_diff_line = 30;
+        assert!(review.toggle_selected_diff_fold());
+        assert_eq!(review.selected_diff_line(), 14);
+        assert_eq!(review.diff_lines().len(), 59);
+        assert!(review.toggle_selected_diff_fold());
+        assert_eq!(review.selected_diff_fold(), Some((14, 56)));
+
+        review.set_diff_context_lines(0);
+        assert_eq!(review.diff_lines().len(), 5);
+        review.selected_diff_line = 59;
+        review.toggle_diff_selection_anchor();
+        assert!(!review.has_diff_selection_anchor());
+    }
+
+    #[test]
+    fn unified_layout_splits_modified_rows_by_side() {
+        let mut review = build_review_state();
+        let mut file = diff_file("alpha.rs");
+        file.rows = (1..=10)
+            .map(|line| {
+                if line == 5 || line == 6 {
+                    paired_row(line, line)
+                } else {
+                    numbered_context_row(line, line)
+                }
+            })
+            .collect();
+        file.hunk_starts = vec![4];
+        review.set_diff(PullRequestDiffData { files: vec![file] });
+        review.set_diff_viewport_height(20);
+        review.set_diff_unified_below(100);
+        review.set_diff_viewport_width(80);
+        review.active_tab = ReviewTab::Diff;
+        review.focus_diff_content();
+
+        assert_eq!(review.diff_layout(), DiffLayout::Unified);
+        assert_eq!(review.diff_lines().len(), 12);
+        assert_eq!(review.selected_diff_line(), 4);
+        assert_eq!(review.diff_cursor_side(), PendingReviewCommentSide::Left);
+
+        review.move_down();
+        assert_eq!(review.selected_diff_line(), 4);
+        assert_eq!(review.diff_cursor_side(), PendingReviewCommentSide::Right);
+        review
+            .upsert_pending_review_comment_from_selection("new side".to_owned())
+            .unwrap();
+        assert_eq!(
+            review.pending_review_comments()[0].side,
+            PendingReviewCommentSide::Right
+        );
+        review.move_up();
+        assert!(review.selected_pending_review_comment().is_none());
+        review.move_down();
+        assert!(review.selected_pending_review_comment().is_some());
+
+        review.toggle_diff_layout();
+        assert_eq!(review.diff_layout(), DiffLayout::Split);
+        assert_eq!(review.diff_lines().len(), 10);
+        review.toggle_diff_layout();
+        review.set_diff_viewport_width(120);
+        assert_eq!(review.diff_layout(), DiffLayout::Split);
+    }
+
+    #[test]
+    fn steps_through_diff_text_matches_across_files() {
+        let mut review = build_review_state();
+        let mut alpha = diff_file("alpha.rs");
+        alpha.rows = vec![
+            numbered_context_row(1, 1),
+            paired_row(2, 2),
+            numbered_context_row(3, 3),
+        ];
+        alpha.hunk_starts = vec![1];
+        let mut beta = diff_file("beta.rs");
+        beta.rows = vec![paired_row(1, 1), numbered_context_row(2, 2)];
+        beta.hunk_starts = vec![0];
+        review.set_diff(PullRequestDiffData {
+            files: vec![alpha, beta],
+        });
+        review.active_tab = ReviewTab::Diff;
+        let search = |review: &mut ReviewScreenState, query: &str| {
+            *review.diff_text_search_input_mut() = SearchInputState::default();
+            for ch in query.chars() {
+                review.diff_text_search_input_mut().push_char(ch);
+            }
+            review.refresh_diff_text_search();
+        };
+
+        search(&mut review, "-2");
+        assert_eq!(review.diff_text_matches_for_file(0).len(), 2);
+        assert_eq!(
+            review.diff_text_search_status().as_deref(),
+            Some("3 matches")
+        );
+        assert!(review.jump_next_diff_match());
+        assert_eq!(
+            (review.selected_diff_file, review.selected_diff_line),
+            (1, 1)
+        );
+        assert_eq!(
+            review.diff_text_search_status().as_deref(),
+            Some("3 of 3 matches")
+        );
+        assert!(review.jump_next_diff_match());
+        assert_eq!(
+            (review.selected_diff_file, review.selected_diff_line),
+            (0, 1)
+        );
+        assert!(review.jump_prev_diff_match());
+        assert_eq!(review.selected_diff_file, 1);
+
+        search(&mut review, "is:removed -2");
+        assert_eq!(
+            review.diff_text_search_status().as_deref(),
+            Some("1 match, removed lines")
+        );
+        assert!(review.jump_next_diff_match());
+        assert_eq!(
+            (review.selected_diff_file, review.selected_diff_line),
+            (0, 1)
+        );
+
+        search(&mut review, "/[/");
+        assert!(!review.has_diff_text_search());
+        assert!(!review.jump_next_diff_match());
+    }
+
     #[test]
     fn move_file_selection_jumps_to_first_hunk() {
         let mut review = build_review_state();
@@ -2449,6 +3420,140 @@ mod tests {
         assert_eq!(review.pull.head_sha, "new-head-sha");
     }
 
+    #[test]
+    fn background_refresh_highlights_new_replies_and_remembers_head_change() {
+        let mut thread = review_thread_with_reply(1, "root", 2, "first reply");
+        thread.replies.clear();
+        let mut review = build_review_state_with_comments(vec![PullRequestComment::ReviewThread(
+            Box::new(thread),
+        )]);
+
+        let mut refreshed = review.data.clone();
+        refreshed.head_sha = "new-head-sha".to_owned();
+        refreshed.comments = vec![PullRequestComment::ReviewThread(Box::new(
+            review_thread_with_reply(1, "root", 2, "first reply"),
+        ))];
+
+        let outcome = review.apply_refresh(refreshed);
+
+        assert_eq!(
+            outcome,
+            RefreshOutcome {
+                new_comments: 1,
+                head_changed: true,
+            }
+        );
+        let root = review.nodes[0].clone();
+        let reply = review.nodes[1].clone();
+        assert!(review.is_new_node(&root));
+        assert!(review.is_new_node(&reply));
+
+        review.mark_selected_seen();
+        assert!(!review.is_new_node(&root));
+        assert!(!review.is_new_node(&reply));
+
+        assert_eq!(review.acknowledge_head_change().as_deref(), Some("headsha"));
+        assert_eq!(review.acknowledge_head_change(), None);
+    }
+
+    #[test]
+    fn restores_unread_comments_and_jumps_between_them() {
+        let mut root = review_thread_with_reply(1, "root", 2, "reply");
+        root.replies.clear();
+        let mut review = build_review_state_with_comments(vec![
+            PullRequestComment::ReviewThread(Box::new(root)),
+            PullRequestComment::ReviewThread(Box::new(review_thread_with_reply(
+                3, "second", 4, "reply",
+            ))),
+        ]);
+        let record = SeenRecord {
+            seen_at_unix_ms: i64::MAX,
+            visited_at_unix_ms: 0,
+            comment_count: 3,
+            unread: vec!["review:4".to_owned(), "review:99".to_owned()],
+        };
+
+        review.restore_unread(&record, None);
+        assert_eq!(review.unread_identities(), vec!["review:4".to_owned()]);
+
+        assert!(review.jump_to_unread(true));
+        assert_eq!(
+            review.nodes[review.selected_row].comment.identity(),
+            "review:3"
+        );
+        assert!(review.jump_to_unread(true));
+        assert_eq!(
+            review.nodes[review.selected_row].comment.identity(),
+            "review:4"
+        );
+        assert!(review.jump_to_unread(true), "jumping wraps around");
+        assert_eq!(
+            review.nodes[review.selected_row].comment.identity(),
+            "review:3"
+        );
+
+        review.mark_selected_seen();
+        assert!(!review.jump_to_unread(false));
+
+        // Everything posted after the last visit is unread, except the viewer's own.
+        review.restore_unread(
+            &SeenRecord {
+                seen_at_unix_ms: 0,
+                ..record
+            },
+            None,
+        );
+        assert_eq!(review.unread_identities().len(), 3);
+    }
+
+    #[test]
+    fn description_is_pinned_above_comments() {
+        let mut review = build_review_state_with_comments(vec![PullRequestComment::ReviewThread(
+            Box::new(review_thread_with_reply(1, "root", 2, "reply")),
+        )]);
+        assert_ne!(review.nodes[0].kind, ListNodeKind::Description);
+
+        review.data.description = Some(PullRequestDescription {
+            author: "octocat".to_owned(),
+            body: "Adds the widget".to_owned(),
+            ..PullRequestDescription::default()
+        });
+        review.rebuild_nodes();
+
+        assert_eq!(review.nodes[0].kind, ListNodeKind::Description);
+        assert_eq!(review.nodes[0].comment.author(), "octocat");
+        assert_eq!(review.nodes[0].comment.body(), "Adds the widget");
+        assert_eq!(review.nodes[1].kind, ListNodeKind::Thread);
+        assert_eq!(review.selected_row, 1, "selection stays on the same thread");
+
+        review.selected_row = 0;
+        assert!(review.selected_thread_context().is_none());
+    }
+
+    #[test]
+    fn link_hints_pick_the_labelled_link() {
+        let mut review = build_review_state_with_comments(Vec::new());
+        assert!(!review.start_link_hints());
+
+        review.preview_links = (1..=3)
+            .map(|number| PreviewLink {
+                url: format!("https://github.com/acme/widgets/issues/{number}"),
+                number: Some(number),
+            })
+            .collect();
+        assert!(review.start_link_hints());
+        assert_eq!(review.link_hint_total(), Some(3));
+        assert_eq!(
+            review.type_link_hint('S').map(|link| link.number),
+            Some(Some(2))
+        );
+        assert!(!review.is_picking_link());
+
+        assert!(review.start_link_hints());
+        assert_eq!(review.type_link_hint('x'), None);
+        assert!(!review.is_picking_link());
+    }
+
     #[test]
     fn pending_comments_are_marked_outdated_when_diff_no_longer_matches() {
         let mut review = build_review_state();
@@ -2758,6 +3863,325 @@ mod tests {
         assert_eq!(numbers, vec![2]);
     }
 
+    #[test]
+    fn saving_reply_composer_stores_reply_draft() {
+        let mut review = build_review_state();
+        review.open_composer(
+            ComposerTarget::Reply {
+                root_key: "thread:1".to_owned(),
+            },
+            "",
+            Vec::new(),
+        );
+        if let Some(composer) = review.composer.as_mut() {
+            for ch in "looks good".chars() {
+                composer.insert_char(ch);
+            }
+        }
+
+        assert!(review.save_composer().is_ok());
+        assert!(review.composer.is_none());
+        assert_eq!(
+            review.reply_drafts.get("thread:1").map(String::as_str),
+            Some("looks good")
+        );
+    }
+
+    #[test]
+    fn swapping_draft_conflicts_twice_restores_our_replies() {
+        let mut review = build_review_state();
+        review.set_reply_draft("thread:1".to_owned(), "ours".to_owned());
+        review.set_draft_conflicts(std::collections::HashMap::from([
+            ("thread:1".to_owned(), "theirs".to_owned()),
+            ("thread:2".to_owned(), "sent since".to_owned()),
+        ]));
+
+        assert_eq!(review.swap_draft_conflicts(), 1);
+        assert_eq!(review.reply_drafts["thread:1"], "theirs");
+        assert!(!review.reply_drafts.contains_key("thread:2"));
+        assert_eq!(review.swap_draft_conflicts(), 1);
+        assert_eq!(review.reply_drafts["thread:1"], "ours");
+        assert!(review.has_draft_conflicts());
+    }
+
+    #[test]
+    fn cancelling_composer_keeps_typed_text() {
+        let mut review = build_review_state();
+        review.open_composer(
+            ComposerTarget::Reply {
+                root_key: "thread:1".to_owned(),
+            },
+            "",
+            Vec::new(),
+        );
+        if let Some(composer) = review.composer.as_mut() {
+            composer.insert_str("on second thought");
+        }
+        assert_eq!(review.cancel_composer(), ComposerCancel::KeptReplyDraft);
+        assert!(review.composer.is_none());
+        assert_eq!(
+            review.reply_drafts.get("thread:1").map(String::as_str),
+            Some("on second thought")
+        );
+
+        review.open_composer(ComposerTarget::PendingComment, "nit", Vec::new());
+        assert_eq!(review.cancel_composer(), ComposerCancel::ConfirmDiscard);
+        assert!(review.composer.is_some());
+        if let Some(composer) = review.composer.as_mut() {
+            composer.disarm_discard();
+        }
+        assert_eq!(review.cancel_composer(), ComposerCancel::ConfirmDiscard);
+        assert_eq!(review.cancel_composer(), ComposerCancel::Closed);
+        assert!(review.composer.is_none());
+
+        review.open_composer(ComposerTarget::PendingComment, "  ", Vec::new());
+        assert_eq!(review.cancel_composer(), ComposerCancel::Closed);
+        assert!(review.composer.is_none());
+    }
+
+    #[test]
+    fn template_picker_targets_review_bodies_and_filters_templates() {
+        let mut review = build_review_state();
+        let templates = vec![
+            CommentTemplate {
+                name: "nit".to_owned(),
+                body: "nit: ".to_owned(),
+            },
+            CommentTemplate {
+                name: "lgtm".to_owned(),
+                body: "Looks good to me!".to_owned(),
+            },
+        ];
+
+        review.open_template_picker(templates);
+        let picker = review.template_picker.as_mut().expect("picker open");
+        assert_eq!(
+            picker.target(),
+            Some(&TemplateTarget::ReviewBody(ReviewSubmissionEvent::Comment))
+        );
+        picker.cycle_target();
+        assert_eq!(
+            picker.target(),
+            Some(&TemplateTarget::ReviewBody(ReviewSubmissionEvent::Approve))
+        );
+
+        for ch in "lg".chars() {
+            picker.push_char(ch);
+        }
+        assert_eq!(
+            picker
+                .selected_template()
+                .map(|template| template.name.as_str()),
+            Some("lgtm")
+        );
+    }
+
+    #[test]
+    fn template_picker_inside_composer_targets_composer() {
+        let mut review = build_review_state();
+        review.open_composer(ComposerTarget::PendingComment, "", Vec::new());
+        review.open_template_picker(Vec::new());
+
+        let picker = review.template_picker.as_ref().expect("picker open");
+        assert_eq!(picker.target(), Some(&TemplateTarget::Composer));
+        assert!(!picker.has_multiple_targets());
+    }
+
+    fn author(login: &str) -> Author {
+        serde_json::from_value(json!({
+            "login": login,
+            "id": 3,
+            "node_id": "U_3",
+            "avatar_url": "https://example.invalid/a",
+            "gravatar_id": "",
+            "url": "https://example.invalid/u",
+            "html_url": "https://example.invalid/u",
+            "followers_url": "https://example.invalid/u",
+            "following_url": "https://example.invalid/u",
+            "gists_url": "https://example.invalid/u",
+            "starred_url": "https://example.invalid/u",
+            "subscriptions_url": "https://example.invalid/u",
+            "organizations_url": "https://example.invalid/u",
+            "repos_url": "https://example.invalid/u",
+            "events_url": "https://example.invalid/u",
+            "received_events_url": "https://example.invalid/u",
+            "type": "User",
+            "site_admin": false
+        }))
+        .expect("valid author fixture")
+    }
+
+    fn located_thread(
+        id: u64,
+        path: &str,
+        line: u64,
+        login: &str,
+        body: &str,
+        replies: Vec<ReviewThread>,
+    ) -> ReviewThread {
+        let mut comment = review_comment(id, body, None);
+        comment.path = path.to_owned();
+        comment.line = Some(line);
+        comment.user = Some(author(login));
+        ReviewThread {
+            thread_id: Some(format!("THREAD_{id}")),
+            is_resolved: false,
+            comment,
+            replies,
+        }
+    }
+
+    fn faceted_review() -> ReviewScreenState {
+        // Fixture comments share a timestamp, so the reply counts as the latest.
+        let mut reply = located_thread(3, "src/b.rs", 5, "dev", "done", Vec::new());
+        reply.comment = review_comment(3, "done", Some(2));
+        reply.comment.user = Some(author("dev"));
+        let threads = vec![
+            located_thread(1, "src/b.rs", 20, "alice", "naming", Vec::new()),
+            located_thread(2, "src/b.rs", 5, "bob", "typo", vec![reply]),
+            located_thread(4, "README.md", 3, "bob", "@dev thoughts?", Vec::new()),
+        ];
+        let mut review = build_review_state_with_comments(
+            threads
+                .into_iter()
+                .map(|thread| PullRequestComment::ReviewThread(Box::new(thread)))
+                .collect(),
+        );
+        review.viewer_login = Some("dev".to_owned());
+        review
+    }
+
+    fn visible_identities(review: &ReviewScreenState) -> Vec<String> {
+        review
+            .nodes
+            .iter()
+            .map(|node| node.comment.identity())
+            .collect()
+    }
+
+    #[test]
+    fn groups_threads_under_files_sorted_by_line() {
+        let mut review = faceted_review();
+        review.toggle_file_grouping();
+
+        assert_eq!(
+            visible_identities(&review),
+            vec![
+                "path:src",
+                "path:src/b.rs",
+                "review:2",
+                "review:3",
+                "review:1",
+                "path:README.md",
+                "review:4",
+            ]
+        );
+        assert_eq!(review.nodes[1].kind, ListNodeKind::File);
+        assert_eq!(review.nodes[2].depth, 2);
+
+        review.selected_row = 1;
+        review.toggle_selected_thread_collapsed();
+        assert_eq!(
+            visible_identities(&review),
+            vec!["path:src", "path:src/b.rs", "path:README.md", "review:4"]
+        );
+        assert_eq!(review.threads_under_path("src", true).len(), 2);
+    }
+
+    #[test]
+    fn jumps_between_threads_and_their_diff_lines() {
+        let mut review = faceted_review();
+        review.selected_row = visible_identities(&review)
+            .iter()
+            .position(|identity| identity == "review:1")
+            .expect("thread listed");
+        assert_eq!(review.show_selected_thread_in_diff(), Ok(()));
+        assert_eq!(review.active_tab, ReviewTab::Diff);
+
+        let mut file = diff_file("src/b.rs");
+        file.rows = (18..=22).map(|line| paired_row(line, line)).collect();
+        file.hunk_starts = vec![0];
+        review.set_diff(PullRequestDiffData {
+            files: vec![diff_file("README.md"), file],
+        });
+        assert_eq!(review.apply_pending_diff_jump(), Ok(()));
+        assert_eq!(
+            review.selected_diff_file().map(|file| file.path.as_str()),
+            Some("src/b.rs")
+        );
+        assert_eq!(review.selected_diff_line(), 2);
+        assert!(review.is_diff_content_focused());
+        assert!(review.selected_diff_line_has_thread());
+
+        let input = review.thread_search_input_mut();
+        "path:readme".chars().for_each(|ch| input.push_char(ch));
+        review.refresh_thread_search_results();
+        assert_eq!(review.show_diff_line_thread(), Ok(()));
+        assert_eq!(review.active_tab, ReviewTab::Threads);
+        assert!(review.thread_search_query().is_empty());
+        assert_eq!(
+            review.selected_node().map(|node| node.comment.identity()),
+            Some("review:1".to_owned())
+        );
+
+        review.active_tab = ReviewTab::Diff;
+        review.selected_diff_line = 0;
+        assert_eq!(
+            review.show_diff_line_thread(),
+            Err("no comment thread on this line")
+        );
+    }
+
+    #[test]
+    fn search_facets_filter_whole_threads() {
+        let mut review = faceted_review();
+        let query = |review: &mut ReviewScreenState, text: &str| {
+            let input = review.thread_search_input_mut();
+            while !input.is_empty() {
+                input.backspace();
+            }
+            text.chars().for_each(|ch| input.push_char(ch));
+            review.refresh_thread_search_results();
+            visible_identities(review)
+        };
+
+        assert_eq!(
+            query(&mut review, "author:dev"),
+            vec!["review:2", "review:3"]
+        );
+        assert_eq!(query(&mut review, "path:readme"), vec!["review:4"]);
+        assert_eq!(
+            query(&mut review, "is:involved"),
+            vec!["review:2", "review:3", "review:4"]
+        );
+        // The viewer authored the pull request, so every thread where someone
+        // else spoke last awaits their reply.
+        assert_eq!(
+            query(&mut review, "is:awaiting"),
+            vec!["review:1", "review:4"]
+        );
+        assert!(query(&mut review, "is:outdated").is_empty());
+        assert_eq!(query(&mut review, "is:involved typo"), vec!["review:2"]);
+        assert_eq!(review.thread_facets().labels(), vec!["involves me"]);
+    }
+
+    #[test]
+    fn participant_logins_include_author_and_commenters() {
+        let mut thread = ReviewThread {
+            thread_id: Some("T1".to_owned()),
+            is_resolved: false,
+            comment: review_comment(1, "root", None),
+            replies: Vec::new(),
+        };
+        thread.comment.user = Some(author("Carol"));
+        let mut review = build_review_state_with_comments(vec![PullRequestComment::ReviewThread(
+            Box::new(thread),
+        )]);
+        review.pull.reviewer_logins = vec!["bob".to_owned(), "DEV".to_owned()];
+
+        assert_eq!(review.participant_logins(), vec!["bob", "Carol", "dev"]);
+    }
+
     fn search_pull(
         number: u64,
         author: &str,
@@ -2780,6 +4204,7 @@ mod tests {
             is_draft: false,
             reviewer_logins: Vec::new(),
             review_status: None,
+            comment_count: None,
         }
     }
 
@@ -2804,6 +4229,7 @@ mod tests {
             is_draft,
             reviewer_logins: Vec::new(),
             review_status,
+            comment_count: None,
         }
     }
 }
@@ -0,0 +1,610 @@
+//! Text buffer behind the in-app comment composer: multi-line editing with a
+//! character cursor, a markdown preview toggle, and `@`/`#` completion.
+
+use super::ReviewScreenState;
+use crate::domain::{PullRequestComment, ReviewThread};
+
+const MAX_COMPLETIONS: usize = 6;
+
+/// What a composer buffer is written back to when saved.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ComposerTarget {
+    /// A staged reply for the thread rooted at `root_key`.
+    Reply { root_key: String },
+    /// A pending inline comment on the current diff selection.
+    PendingComment,
+}
+
+/// What Esc did to an open composer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ComposerCancel {
+    /// The composer closed; nothing typed was lost.
+    Closed,
+    /// The composer closed and the reply was kept as the thread's draft.
+    KeptReplyDraft,
+    /// The inline comment is not empty; another Esc discards it.
+    ConfirmDiscard,
+}
+
+/// Candidate values offered by the composer's `@` and `#` completion.
+#[derive(Debug, Clone, Default)]
+pub struct ComposerCompletions {
+    pub mentions: Vec<String>,
+    pub issues: Vec<(u64, String)>,
+}
+
+/// A single completion entry shown under the composer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ComposerSuggestion {
+    pub insert: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone)]
+struct ActiveCompletion {
+    /// Char column of the trigger character on the cursor row.
+    start_col: usize,
+    suggestions: Vec<ComposerSuggestion>,
+    selected: usize,
+}
+
+/// In-app multiline editor for replies and pending inline comments.
+#[derive(Debug, Clone)]
+pub struct ComposerState {
+    target: ComposerTarget,
+    lines: Vec<String>,
+    cursor_row: usize,
+    cursor_col: usize,
+    preview: bool,
+    completions: ComposerCompletions,
+    completion: Option<ActiveCompletion>,
+    /// Set by a first Esc on a non-empty inline comment.
+    discard_armed: bool,
+}
+
+impl ComposerState {
+    pub fn new(target: ComposerTarget, initial: &str, completions: ComposerCompletions) -> Self {
+        let mut lines = initial.lines().map(str::to_owned).collect::<Vec<_>>();
+        if lines.is_empty() || initial.ends_with('\n') {
+            lines.push(String::new());
+        }
+        let cursor_row = lines.len() - 1;
+        let cursor_col = lines[cursor_row].chars().count();
+
+        Self {
+            target,
+            lines,
+            cursor_row,
+            cursor_col,
+            preview: false,
+            completions,
+            completion: None,
+            discard_armed: false,
+        }
+    }
+
+    pub fn target(&self) -> &ComposerTarget {
+        &self.target
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Replaces the buffer, e.g. after a round-trip through the system editor.
+    pub fn set_text(&mut self, text: &str) {
+        let completions = std::mem::take(&mut self.completions);
+        *self = Self {
+            preview: self.preview,
+            ..Self::new(self.target.clone(), text, completions)
+        };
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Cursor position as `(row, char column)`.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_row, self.cursor_col)
+    }
+
+    pub fn is_preview_visible(&self) -> bool {
+        self.preview
+    }
+
+    pub fn toggle_preview(&mut self) {
+        self.preview = !self.preview;
+    }
+
+    /// Records an Esc that would discard the buffer, returning whether the
+    /// previous key was one too.
+    pub fn arm_discard(&mut self) -> bool {
+        std::mem::replace(&mut self.discard_armed, true)
+    }
+
+    pub fn disarm_discard(&mut self) {
+        self.discard_armed = false;
+    }
+
+    pub fn insert_char(&mut self, ch: char) {
+        let byte = self.cursor_byte();
+        self.lines[self.cursor_row].insert(byte, ch);
+        self.cursor_col += 1;
+        self.refresh_completion();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch == '\n' {
+                self.insert_newline();
+            } else {
+                self.insert_char(ch);
+            }
+        }
+        self.completion = None;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let byte = self.cursor_byte();
+        let rest = self.lines[self.cursor_row].split_off(byte);
+        self.cursor_row += 1;
+        self.cursor_col = 0;
+        self.lines.insert(self.cursor_row, rest);
+        self.completion = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+            let byte = self.cursor_byte();
+            self.lines[self.cursor_row].remove(byte);
+        } else if self.cursor_row > 0 {
+            let line = self.lines.remove(self.cursor_row);
+            self.cursor_row -= 1;
+            self.cursor_col = self.lines[self.cursor_row].chars().count();
+            self.lines[self.cursor_row].push_str(&line);
+        }
+        self.refresh_completion();
+    }
+
+    pub fn delete(&mut self) {
+        let len = self.lines[self.cursor_row].chars().count();
+        if self.cursor_col < len {
+            let byte = self.cursor_byte();
+            self.lines[self.cursor_row].remove(byte);
+        } else if self.cursor_row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.cursor_row + 1);
+            self.lines[self.cursor_row].push_str(&next);
+        }
+        self.refresh_completion();
+    }
+
+    pub fn move_left(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+        } else if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            self.cursor_col = self.lines[self.cursor_row].chars().count();
+        }
+        self.completion = None;
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor_col < self.lines[self.cursor_row].chars().count() {
+            self.cursor_col += 1;
+        } else if self.cursor_row + 1 < self.lines.len() {
+            self.cursor_row += 1;
+            self.cursor_col = 0;
+        }
+        self.completion = None;
+    }
+
+    pub fn move_up(&mut self) {
+        if let Some(completion) = self.completion.as_mut() {
+            completion.selected = completion.selected.saturating_sub(1);
+            return;
+        }
+        if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            self.clamp_cursor_col();
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if let Some(completion) = self.completion.as_mut() {
+            let last = completion.suggestions.len().saturating_sub(1);
+            completion.selected = (completion.selected + 1).min(last);
+            return;
+        }
+        if self.cursor_row + 1 < self.lines.len() {
+            self.cursor_row += 1;
+            self.clamp_cursor_col();
+        }
+    }
+
+    pub fn move_line_start(&mut self) {
+        self.cursor_col = 0;
+        self.completion = None;
+    }
+
+    pub fn move_line_end(&mut self) {
+        self.cursor_col = self.lines[self.cursor_row].chars().count();
+        self.completion = None;
+    }
+
+    pub fn suggestions(&self) -> Option<(&[ComposerSuggestion], usize)> {
+        self.completion
+            .as_ref()
+            .map(|completion| (completion.suggestions.as_slice(), completion.selected))
+    }
+
+    pub fn has_completion(&self) -> bool {
+        self.completion.is_some()
+    }
+
+    pub fn dismiss_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// Replaces the word being completed with the selected suggestion.
+    pub fn accept_completion(&mut self) -> bool {
+        let Some(completion) = self.completion.take() else {
+            return false;
+        };
+        let Some(suggestion) = completion.suggestions.get(completion.selected) else {
+            return false;
+        };
+
+        let line = &mut self.lines[self.cursor_row];
+        let start = char_to_byte(line, completion.start_col);
+        let end = char_to_byte(line, self.cursor_col);
+        line.replace_range(start..end, &suggestion.insert);
+        self.cursor_col = completion.start_col + suggestion.insert.chars().count();
+        self.insert_char(' ');
+        self.completion = None;
+        true
+    }
+
+    fn refresh_completion(&mut self) {
+        self.completion = None;
+        let line = &self.lines[self.cursor_row];
+        let before = line.chars().take(self.cursor_col).collect::<Vec<_>>();
+
+        let word_start = before
+            .iter()
+            .rposition(|ch| ch.is_whitespace() || matches!(ch, '(' | '[' | ','))
+            .map_or(0, |index| index + 1);
+        let Some(&trigger) = before.get(word_start) else {
+            return;
+        };
+        let query = before[word_start + 1..]
+            .iter()
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        let suggestions = match trigger {
+            '@' => self
+                .completions
+                .mentions
+                .iter()
+                .filter(|login| login.to_ascii_lowercase().starts_with(&query))
+                .take(MAX_COMPLETIONS)
+                .map(|login| ComposerSuggestion {
+                    insert: format!("@{login}"),
+                    label: format!("@{login}"),
+                })
+                .collect::<Vec<_>>(),
+            '#' => self
+                .completions
+                .issues
+                .iter()
+                .filter(|(number, title)| {
+                    number.to_string().starts_with(&query)
+                        || title.to_ascii_lowercase().contains(&query)
+                })
+                .take(MAX_COMPLETIONS)
+                .map(|(number, title)| ComposerSuggestion {
+                    insert: format!("#{number}"),
+                    label: format!("#{number} {title}"),
+                })
+                .collect::<Vec<_>>(),
+            _ => return,
+        };
+
+        if !suggestions.is_empty() {
+            self.completion = Some(ActiveCompletion {
+                start_col: word_start,
+                suggestions,
+                selected: 0,
+            });
+        }
+    }
+
+    fn clamp_cursor_col(&mut self) {
+        self.cursor_col = self
+            .cursor_col
+            .min(self.lines[self.cursor_row].chars().count());
+    }
+
+    fn cursor_byte(&self) -> usize {
+        char_to_byte(&self.lines[self.cursor_row], self.cursor_col)
+    }
+}
+
+fn char_to_byte(line: &str, col: usize) -> usize {
+    line.char_indices()
+        .nth(col)
+        .map_or(line.len(), |(byte, _)| byte)
+}
+
+impl ReviewScreenState {
+    /// Opens the in-app composer for `target`, seeded with `initial`.
+    pub fn open_composer(
+        &mut self,
+        target: ComposerTarget,
+        initial: &str,
+        issues: Vec<(u64, String)>,
+    ) {
+        let completions = ComposerCompletions {
+            mentions: self.participant_logins(),
+            issues,
+        };
+        self.composer = Some(ComposerState::new(target, initial, completions));
+    }
+
+    /// Closes the composer without losing typed text: a non-empty reply is
+    /// kept as the thread's draft, and a non-empty inline comment is only
+    /// discarded by a second Esc.
+    pub fn cancel_composer(&mut self) -> ComposerCancel {
+        let Some(composer) = self.composer.as_mut() else {
+            return ComposerCancel::Closed;
+        };
+        if composer.text().trim().is_empty() {
+            self.composer = None;
+            return ComposerCancel::Closed;
+        }
+        match composer.target() {
+            ComposerTarget::Reply { .. } => {
+                // Saving a reply only stores its draft and cannot fail.
+                let _ = self.save_composer();
+                ComposerCancel::KeptReplyDraft
+            }
+            ComposerTarget::PendingComment => {
+                if composer.arm_discard() {
+                    self.composer = None;
+                    ComposerCancel::Closed
+                } else {
+                    ComposerCancel::ConfirmDiscard
+                }
+            }
+        }
+    }
+
+    /// Writes the composer buffer back to its target and closes the composer.
+    pub fn save_composer(&mut self) -> Result<(), &'static str> {
+        let Some(composer) = self.composer.as_ref() else {
+            return Ok(());
+        };
+        let text = composer.text();
+
+        match composer.target().clone() {
+            ComposerTarget::Reply { root_key } => {
+                if text.trim().is_empty() {
+                    self.clear_reply_draft(&root_key);
+                } else {
+                    self.set_reply_draft(root_key, text);
+                }
+            }
+            ComposerTarget::PendingComment => {
+                self.upsert_pending_review_comment_from_selection(text)?;
+            }
+        }
+
+        self.composer = None;
+        Ok(())
+    }
+
+    /// Logins of the pull request author, requested reviewers, and commenters.
+    pub fn participant_logins(&self) -> Vec<String> {
+        let mut logins = vec![self.pull.author.clone()];
+        logins.extend(self.pull.reviewer_logins.iter().cloned());
+
+        fn collect_thread(thread: &ReviewThread, logins: &mut Vec<String>) {
+            if let Some(user) = thread.comment.user.as_ref() {
+                logins.push(user.login.clone());
+            }
+            for reply in &thread.replies {
+                collect_thread(reply, logins);
+            }
+        }
+
+        for entry in &self.data.comments {
+            match entry {
+                PullRequestComment::ReviewThread(thread) => collect_thread(thread, &mut logins),
+                PullRequestComment::IssueComment(comment) => {
+                    logins.push(comment.user.login.clone());
+                }
+                PullRequestComment::ReviewSummary(review) => {
+                    if let Some(user) = review.user.as_ref() {
+                        logins.push(user.login.clone());
+                    }
+                }
+            }
+        }
+
+        logins.retain(|login| !login.trim().is_empty());
+        logins.sort_by_key(|login| login.to_ascii_lowercase());
+        logins.dedup_by(|left, right| left.eq_ignore_ascii_case(right));
+        logins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComposerCompletions, ComposerState, ComposerTarget, MAX_COMPLETIONS};
+
+    fn composer(initial: &str) -> ComposerState {
+        ComposerState::new(
+            ComposerTarget::PendingComment,
+            initial,
+            ComposerCompletions::default(),
+        )
+    }
+
+    fn type_text(composer: &mut ComposerState, text: &str) {
+        for ch in text.chars() {
+            composer.insert_char(ch);
+        }
+    }
+
+    #[test]
+    fn edits_multiline_text() {
+        let mut composer = composer("first");
+        composer.insert_newline();
+        type_text(&mut composer, "secnd");
+        composer.move_left();
+        composer.move_left();
+        composer.insert_char('o');
+        composer.move_line_end();
+        composer.backspace();
+
+        assert_eq!(composer.text(), "first\nsecon");
+        assert_eq!(composer.cursor(), (1, 5));
+    }
+
+    #[test]
+    fn cursor_moves_by_char_column_across_lines() {
+        let mut composer = composer("héllo\nab\n");
+        assert_eq!(composer.lines().len(), 3);
+        assert_eq!(composer.cursor(), (2, 0));
+
+        composer.move_left();
+        assert_eq!(composer.cursor(), (1, 2));
+        composer.move_right();
+        assert_eq!(composer.cursor(), (2, 0));
+
+        // Moving up keeps the column, clamped to the shorter line.
+        composer.move_up();
+        composer.move_line_end();
+        composer.move_up();
+        assert_eq!(composer.cursor(), (0, 2));
+        composer.move_line_end();
+        assert_eq!(composer.cursor(), (0, 5));
+        composer.move_down();
+        assert_eq!(composer.cursor(), (1, 2));
+        composer.move_line_start();
+        composer.move_left();
+        assert_eq!(composer.cursor(), (0, 5));
+        composer.move_right();
+        composer.move_right();
+        assert_eq!(composer.cursor(), (1, 1));
+    }
+
+    #[test]
+    fn inserts_and_deletes_multi_byte_and_wide_characters() {
+        let mut composer = composer("");
+        type_text(&mut composer, "a日é🙂b");
+        assert_eq!(composer.cursor(), (0, 5));
+
+        composer.move_left();
+        composer.backspace();
+        assert_eq!(composer.text(), "a日éb");
+        composer.move_line_start();
+        composer.move_right();
+        composer.delete();
+        assert_eq!(composer.text(), "aéb");
+        composer.insert_char('本');
+        assert_eq!(composer.text(), "a本éb");
+        assert_eq!(composer.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn newlines_split_and_join_lines() {
+        let mut composer = composer("ab日cd");
+        composer.move_left();
+        composer.move_left();
+        composer.insert_newline();
+        assert_eq!(composer.lines(), ["ab日", "cd"]);
+        assert_eq!(composer.cursor(), (1, 0));
+
+        // Backspace at a line start joins it onto the previous line.
+        composer.backspace();
+        assert_eq!(composer.text(), "ab日cd");
+        assert_eq!(composer.cursor(), (0, 3));
+
+        // Delete at a line end pulls the next line up.
+        composer.insert_str("\n");
+        composer.move_left();
+        composer.delete();
+        assert_eq!(composer.text(), "ab日cd");
+
+        composer.set_text("one\ntwo\n");
+        assert_eq!(composer.lines(), ["one", "two", ""]);
+        assert_eq!(composer.text(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn completes_mentions_and_issues() {
+        let completions = ComposerCompletions {
+            mentions: vec!["alice".to_owned(), "bob".to_owned()],
+            issues: vec![(12, "Fix parser".to_owned())],
+        };
+        let mut composer = ComposerState::new(ComposerTarget::PendingComment, "", completions);
+
+        type_text(&mut composer, "cc @al");
+        let (suggestions, _) = composer.suggestions().expect("mention suggestions");
+        assert_eq!(suggestions.len(), 1);
+        assert!(composer.accept_completion());
+
+        type_text(&mut composer, "see #pars");
+        assert!(composer.accept_completion());
+
+        assert_eq!(composer.text(), "cc @alice see #12 ");
+        assert!(!composer.accept_completion());
+    }
+
+    #[test]
+    fn caps_completions_and_accepts_the_selected_candidate() {
+        let completions = ComposerCompletions {
+            mentions: (0..MAX_COMPLETIONS + 3)
+                .map(|index| format!("user{index}"))
+                .collect(),
+            issues: Vec::new(),
+        };
+        let mut composer = ComposerState::new(ComposerTarget::PendingComment, "", completions);
+
+        type_text(&mut composer, "(@us");
+        let (suggestions, selected) = composer.suggestions().expect("suggestions");
+        assert_eq!(suggestions.len(), MAX_COMPLETIONS);
+        assert_eq!(selected, 0);
+
+        // Up and down choose a candidate instead of moving the cursor.
+        composer.move_down();
+        composer.move_down();
+        composer.move_up();
+        for _ in 0..MAX_COMPLETIONS + 1 {
+            composer.move_down();
+        }
+        composer.move_up();
+        assert_eq!(
+            composer.suggestions().map(|(_, selected)| selected),
+            Some(MAX_COMPLETIONS - 2)
+        );
+        assert!(composer.accept_completion());
+        assert_eq!(composer.text(), format!("(@user{} ", MAX_COMPLETIONS - 2));
+        assert!(!composer.has_completion());
+
+        // Moving the cursor or dismissing drops the completion.
+        type_text(&mut composer, "@us");
+        assert!(composer.has_completion());
+        composer.move_left();
+        assert!(!composer.has_completion());
+        composer.move_right();
+        composer.backspace();
+        assert!(composer.has_completion());
+        composer.dismiss_completion();
+        assert!(!composer.accept_completion());
+        assert_eq!(composer.cursor(), (0, composer.text().chars().count()));
+    }
+}
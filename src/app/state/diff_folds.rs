@@ -0,0 +1,453 @@
+//! Folding of unchanged diff rows far from any change into "N unchanged lines"
+//! placeholders that expand in place, and the pane lines of each layout.
+
+use super::{DiffLayout, PendingReviewCommentSide, ReviewScreenState};
+use crate::domain::{PullRequestDiffFile, PullRequestDiffRowKind};
+
+/// Rows revealed by one expand-up or expand-down step.
+const EXPAND_STEP: usize = 20;
+/// Shorter unchanged runs stay visible; a placeholder would not save space.
+const MIN_FOLD_ROWS: usize = 2;
+
+/// One line of the diff pane: a file row, or a run of folded rows.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DiffLine {
+    Row(usize),
+    /// One side of a modified row in the unified layout; the removed side
+    /// comes first.
+    Half {
+        row: usize,
+        side: PendingReviewCommentSide,
+    },
+    /// Inclusive range of hidden rows.
+    Fold {
+        start: usize,
+        end: usize,
+    },
+}
+
+impl DiffLine {
+    pub fn first_row(self) -> usize {
+        match self {
+            Self::Row(row) | Self::Half { row, .. } => row,
+            Self::Fold { start, .. } => start,
+        }
+    }
+
+    fn last_row(self) -> usize {
+        match self {
+            Self::Row(row) | Self::Half { row, .. } => row,
+            Self::Fold { end, .. } => end,
+        }
+    }
+}
+
+/// Which part of a fold to reveal.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FoldExpansion {
+    /// The rows just above the change below the fold.
+    Up,
+    /// The rows just below the change above the fold.
+    Down,
+    All,
+}
+
+/// Fold state and pane lines of one diff file.
+#[derive(Debug, Clone, Default)]
+pub(super) struct FileFolds {
+    /// Rows folded at the configured context radius.
+    foldable: Vec<bool>,
+    hidden: Vec<bool>,
+    /// Modified rows, which take a line per side in the unified layout.
+    modified: Vec<bool>,
+    unified: bool,
+    lines: Vec<DiffLine>,
+}
+
+impl FileFolds {
+    /// Folds unchanged runs more than `context` rows away from a change.
+    /// Files without changes are left unfolded.
+    pub(super) fn new(file: &PullRequestDiffFile, context: usize, unified: bool) -> Self {
+        let changed = file
+            .rows
+            .iter()
+            .map(|row| row.kind != PullRequestDiffRowKind::Context)
+            .collect::<Vec<_>>();
+        let mut foldable = vec![false; changed.len()];
+        if changed.contains(&true) {
+            let mut since_change = vec![usize::MAX; changed.len()];
+            let mut last = None;
+            for (index, is_changed) in changed.iter().enumerate() {
+                if *is_changed {
+                    last = Some(index);
+                }
+                if let Some(last) = last {
+                    since_change[index] = index - last;
+                }
+            }
+            let mut next = None;
+            for index in (0..changed.len()).rev() {
+                if changed[index] {
+                    next = Some(index);
+                }
+                let until_change = next.map_or(usize::MAX, |next| next - index);
+                foldable[index] = since_change[index].min(until_change) > context;
+            }
+            drop_short_runs(&mut foldable);
+        }
+
+        let mut folds = Self {
+            hidden: foldable.clone(),
+            foldable,
+            modified: file
+                .rows
+                .iter()
+                .map(|row| row.kind == PullRequestDiffRowKind::Modified)
+                .collect(),
+            unified,
+            lines: Vec::new(),
+        };
+        folds.rebuild_lines();
+        folds
+    }
+
+    pub(super) fn lines(&self) -> &[DiffLine] {
+        &self.lines
+    }
+
+    pub(super) fn set_unified(&mut self, unified: bool) {
+        if self.unified != unified {
+            self.unified = unified;
+            self.rebuild_lines();
+        }
+    }
+
+    pub(super) fn is_hidden(&self, row: usize) -> bool {
+        self.hidden.get(row).copied().unwrap_or(false)
+    }
+
+    /// Index of the pane line showing `side` of `row`. Rows past the end of
+    /// the file map one-to-one past the last line.
+    pub(super) fn line_index(&self, row: usize, side: PendingReviewCommentSide) -> usize {
+        let position = self.lines.partition_point(|line| line.first_row() <= row);
+        let Some(index) = position.checked_sub(1) else {
+            return row;
+        };
+        let line = self.lines[index];
+        if side == PendingReviewCommentSide::Left
+            && matches!(line, DiffLine::Half { row: half_row, .. } if half_row == row)
+            && index > 0
+            && self.lines[index - 1].first_row() == row
+        {
+            return index - 1;
+        }
+        index + row.saturating_sub(line.last_row())
+    }
+
+    /// First row shown on pane line `index`, with the side when the line
+    /// shows only one side of a modified row; the inverse of
+    /// [`Self::line_index`].
+    pub(super) fn row_at_line(&self, index: usize) -> (usize, Option<PendingReviewCommentSide>) {
+        match self.lines.get(index) {
+            Some(DiffLine::Half { row, side }) => (*row, Some(*side)),
+            Some(line) => (line.first_row(), None),
+            None => match self.lines.last() {
+                Some(last) => (last.last_row() + (index - (self.lines.len() - 1)), None),
+                None => (index, None),
+            },
+        }
+    }
+
+    /// Reveals part of the fold holding `row` and returns the row to select:
+    /// the rest of the fold while one remains, so expanding can be repeated.
+    pub(super) fn expand(&mut self, row: usize, expansion: FoldExpansion) -> Option<usize> {
+        let line = self.line_index(row, PendingReviewCommentSide::Left);
+        let Some(DiffLine::Fold { start, end }) = self.lines.get(line).copied() else {
+            return None;
+        };
+        let (reveal_start, reveal_end) = match expansion {
+            FoldExpansion::Up => (end.saturating_sub(EXPAND_STEP - 1).max(start), end),
+            FoldExpansion::Down => (start, (start + EXPAND_STEP - 1).min(end)),
+            FoldExpansion::All => (start, end),
+        };
+        self.hidden[reveal_start..=reveal_end].fill(false);
+        self.rebuild_lines();
+
+        Some(if expansion == FoldExpansion::Down && reveal_end < end {
+            reveal_end + 1
+        } else {
+            start
+        })
+    }
+
+    /// Hides again the foldable run around `row`, returning the fold's first
+    /// row, or `None` when `row` is near a change.
+    pub(super) fn refold(&mut self, row: usize) -> Option<usize> {
+        if !self.foldable.get(row).copied().unwrap_or(false) {
+            return None;
+        }
+        let start = (0..row)
+            .rev()
+            .take_while(|index| self.foldable[*index])
+            .last()
+            .unwrap_or(row);
+        let end = (row..self.foldable.len())
+            .take_while(|index| self.foldable[*index])
+            .last()
+            .unwrap_or(row);
+        self.hidden[start..=end].fill(true);
+        self.rebuild_lines();
+        Some(start)
+    }
+
+    fn rebuild_lines(&mut self) {
+        self.lines.clear();
+        let mut row = 0;
+        while row < self.hidden.len() {
+            if !self.hidden[row] {
+                if self.unified && self.modified[row] {
+                    for side in [
+                        PendingReviewCommentSide::Left,
+                        PendingReviewCommentSide::Right,
+                    ] {
+                        self.lines.push(DiffLine::Half { row, side });
+                    }
+                } else {
+                    self.lines.push(DiffLine::Row(row));
+                }
+                row += 1;
+                continue;
+            }
+            let start = row;
+            while row < self.hidden.len() && self.hidden[row] {
+                row += 1;
+            }
+            self.lines.push(DiffLine::Fold {
+                start,
+                end: row - 1,
+            });
+        }
+    }
+}
+
+fn drop_short_runs(foldable: &mut [bool]) {
+    let mut row = 0;
+    while row < foldable.len() {
+        if !foldable[row] {
+            row += 1;
+            continue;
+        }
+        let start = row;
+        while row < foldable.len() && foldable[row] {
+            row += 1;
+        }
+        if row - start < MIN_FOLD_ROWS {
+            foldable[start..row].fill(false);
+        }
+    }
+}
+
+impl ReviewScreenState {
+    /// Refolds every file for a new context radius, dropping manual expansions.
+    pub fn set_diff_context_lines(&mut self, lines: usize) {
+        if self.diff_context_lines == lines {
+            return;
+        }
+        self.diff_context_lines = lines;
+        if self.diff.is_some() {
+            self.rebuild_diff_folds();
+            self.keep_selected_line_visible();
+        }
+    }
+
+    /// Pane lines of the selected file, with unchanged runs folded.
+    pub fn diff_lines(&self) -> &[DiffLine] {
+        self.diff_folds
+            .get(self.selected_diff_file)
+            .map(FileFolds::lines)
+            .unwrap_or_default()
+    }
+
+    /// Rows hidden in the fold under the cursor, if it is on one.
+    pub fn selected_diff_fold(&self) -> Option<(usize, usize)> {
+        let folds = self.diff_folds.get(self.selected_diff_file)?;
+        match folds
+            .lines()
+            .get(folds.line_index(self.selected_diff_line, PendingReviewCommentSide::Left))?
+        {
+            DiffLine::Fold { start, end } => Some((*start, *end)),
+            DiffLine::Row(_) | DiffLine::Half { .. } => None,
+        }
+    }
+
+    /// Reveals part of the fold under the cursor. Returns `false` when the
+    /// cursor is not on a fold.
+    pub fn expand_selected_diff_fold(&mut self, expansion: FoldExpansion) -> bool {
+        let Some(folds) = self.diff_folds.get_mut(self.selected_diff_file) else {
+            return false;
+        };
+        let Some(row) = folds.expand(self.selected_diff_line, expansion) else {
+            return false;
+        };
+        self.selected_diff_line = row;
+        self.diff_selection_anchor = None;
+        self.keep_selected_line_visible();
+        true
+    }
+
+    /// Expands the fold under the cursor, or folds the unchanged run around it
+    /// again. Returns `false` when neither applies.
+    pub fn toggle_selected_diff_fold(&mut self) -> bool {
+        if self.expand_selected_diff_fold(FoldExpansion::All) {
+            return true;
+        }
+        let Some(row) = self
+            .diff_folds
+            .get_mut(self.selected_diff_file)
+            .and_then(|folds| folds.refold(self.selected_diff_line))
+        else {
+            return false;
+        };
+        self.selected_diff_line = row;
+        self.diff_selection_anchor = None;
+        self.keep_selected_line_visible();
+        true
+    }
+
+    pub(super) fn rebuild_diff_folds(&mut self) {
+        self.diff_folds = self
+            .diff
+            .as_ref()
+            .map(|diff| {
+                diff.files
+                    .iter()
+                    .map(|file| {
+                        FileFolds::new(
+                            file,
+                            self.diff_context_lines,
+                            self.diff_layout == DiffLayout::Unified,
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    /// Pane line showing `side` of `row` in a file, counting each fold as one
+    /// line.
+    pub(super) fn diff_line_index(
+        &self,
+        file_index: usize,
+        row: usize,
+        side: PendingReviewCommentSide,
+    ) -> usize {
+        self.diff_folds
+            .get(file_index)
+            .map_or(row, |folds| folds.line_index(row, side))
+    }
+
+    pub(super) fn diff_row_at_line(
+        &self,
+        file_index: usize,
+        line: usize,
+    ) -> (usize, Option<PendingReviewCommentSide>) {
+        self.diff_folds
+            .get(file_index)
+            .map_or((line, None), |folds| folds.row_at_line(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffLine, FileFolds, FoldExpansion, PendingReviewCommentSide};
+    use crate::domain::{
+        PullRequestDiffFile, PullRequestDiffFileStatus, PullRequestDiffRow, PullRequestDiffRowKind,
+    };
+
+    fn file(changed_rows: &[usize], len: usize) -> PullRequestDiffFile {
+        let rows = (0..len)
+            .map(|index| PullRequestDiffRow {
+                left_line_number: Some(index + 1),
+                right_line_number: Some(index + 1),
+                left_text: String::new(),
+                right_text: String::new(),
+                left_highlights: Vec::new(),
+                right_highlights: Vec::new(),
+                kind: if changed_rows.contains(&index) {
+                    PullRequestDiffRowKind::Modified
+                } else {
+                    PullRequestDiffRowKind::Context
+                },
+            })
+            .collect();
+        PullRequestDiffFile {
+            path: "src/lib.rs".to_owned(),
+            status: PullRequestDiffFileStatus::Modified,
+            rows,
+            hunk_starts: changed_rows.to_vec(),
+        }
+    }
+
+    #[test]
+    fn folds_unchanged_runs_beyond_the_context_radius() {
+        let left = PendingReviewCommentSide::Left;
+        let mut folds = FileFolds::new(&file(&[10, 60], 100), 3, false);
+        assert_eq!(folds.lines()[0], DiffLine::Fold { start: 0, end: 6 });
+        assert_eq!(folds.lines()[4], DiffLine::Row(10));
+        assert_eq!(folds.lines()[8], DiffLine::Fold { start: 14, end: 56 });
+        assert_eq!(folds.line_index(60, left), 12);
+        assert_eq!(folds.row_at_line(12), (60, None));
+        assert_eq!(folds.line_index(30, left), 8);
+        assert_eq!(folds.line_index(105, left), folds.lines().len() + 5);
+
+        assert_eq!(folds.expand(30, FoldExpansion::Down), Some(34));
+        assert_eq!(folds.lines()[8], DiffLine::Row(14));
+        assert_eq!(folds.lines()[28], DiffLine::Fold { start: 34, end: 56 });
+        assert_eq!(folds.expand(34, FoldExpansion::Up), Some(34));
+        assert_eq!(folds.lines()[28], DiffLine::Fold { start: 34, end: 36 });
+        assert_eq!(folds.expand(34, FoldExpansion::All), Some(34));
+        assert!(!folds.is_hidden(35));
+
+        assert_eq!(folds.refold(40), Some(14));
+        assert!(folds.is_hidden(56) && !folds.is_hidden(57));
+        assert_eq!(folds.refold(12), None);
+        assert!(
+            FileFolds::new(&file(&[], 50), 3, false)
+                .lines()
+                .iter()
+                .all(|line| matches!(line, DiffLine::Row(_)))
+        );
+    }
+
+    #[test]
+    fn unified_layout_splits_modified_rows_into_two_lines() {
+        let (left, right) = (
+            PendingReviewCommentSide::Left,
+            PendingReviewCommentSide::Right,
+        );
+        let mut folds = FileFolds::new(&file(&[10, 11], 30), 3, true);
+        assert_eq!(
+            folds.lines()[4],
+            DiffLine::Half {
+                row: 10,
+                side: left
+            }
+        );
+        assert_eq!(
+            folds.lines()[5],
+            DiffLine::Half {
+                row: 10,
+                side: right
+            }
+        );
+        assert_eq!(folds.line_index(11, left), 6);
+        assert_eq!(folds.line_index(11, right), 7);
+        assert_eq!(folds.line_index(12, left), 8);
+        assert_eq!(folds.row_at_line(7), (11, Some(right)));
+        assert_eq!(folds.row_at_line(8), (12, None));
+
+        folds.set_unified(false);
+        assert_eq!(folds.lines()[5], DiffLine::Row(11));
+        assert_eq!(folds.line_index(11, right), 5);
+    }
+}
@@ -0,0 +1,416 @@
+//! Text search across the rows of every diff file: the query syntax and the
+//! matches `n`/`N` step through.
+
+use super::{DiffFocus, DiffLayout, PendingReviewCommentSide, ReviewScreenState, SearchInputState};
+use crate::domain::{PullRequestDiffFile, PullRequestDiffRowKind};
+use regex::{Regex, RegexBuilder};
+
+/// Sides of the diff a search looks at, picked with `is:added`/`is:removed`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum DiffTextSide {
+    #[default]
+    Both,
+    /// New lines of added and modified rows.
+    Added,
+    /// Old lines of removed and modified rows.
+    Removed,
+}
+
+/// One match, in characters of the row text of `side`. Unchanged rows only
+/// report the new side; their old side holds the same text.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DiffTextMatch {
+    pub file_index: usize,
+    pub row: usize,
+    pub side: PendingReviewCommentSide,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct DiffTextQuery {
+    pattern: Regex,
+    side: DiffTextSide,
+}
+
+impl DiffTextQuery {
+    /// Parses a search: text between slashes is a regular expression, other
+    /// text matches literally, and either ignores case unless it has an
+    /// uppercase letter. `is:added`/`is:removed` words are taken out; the rest
+    /// keeps its spacing, trimmed at the ends. Returns `Ok(None)` when nothing
+    /// is left to search.
+    pub(super) fn parse(query: &str) -> Result<Option<Self>, &'static str> {
+        let mut side = DiffTextSide::Both;
+        let mut text = String::new();
+        let mut rest = query;
+        while !rest.is_empty() {
+            let word_start = rest
+                .find(|ch: char| !ch.is_whitespace())
+                .unwrap_or(rest.len());
+            let word_end = rest[word_start..]
+                .find(char::is_whitespace)
+                .map_or(rest.len(), |end| word_start + end);
+            let (spacing, word) = (&rest[..word_start], &rest[word_start..word_end]);
+            match word.to_ascii_lowercase().as_str() {
+                "is:added" => side = DiffTextSide::Added,
+                "is:removed" => side = DiffTextSide::Removed,
+                _ => {
+                    text.push_str(spacing);
+                    text.push_str(word);
+                }
+            }
+            rest = &rest[word_end..];
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+
+        let pattern = match text
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(expression) if !expression.is_empty() => expression.to_owned(),
+            _ => regex::escape(text),
+        };
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(!text.chars().any(char::is_uppercase))
+            .build()
+            .map_err(|_| "invalid regular expression")?;
+        Ok(Some(Self { pattern, side }))
+    }
+
+    pub(super) fn side(&self) -> DiffTextSide {
+        self.side
+    }
+
+    /// Matches in `file`, ordered by row, then old side before new, then
+    /// position.
+    pub(super) fn find_matches(
+        &self,
+        file_index: usize,
+        file: &PullRequestDiffFile,
+    ) -> Vec<DiffTextMatch> {
+        let mut matches = Vec::new();
+        for (row_index, row) in file.rows.iter().enumerate() {
+            let (left, right) = match (row.kind, self.side) {
+                (PullRequestDiffRowKind::Context, DiffTextSide::Both) => (false, true),
+                (PullRequestDiffRowKind::Context, _) => (false, false),
+                (_, DiffTextSide::Both) => (true, true),
+                (_, DiffTextSide::Added) => (false, true),
+                (_, DiffTextSide::Removed) => (true, false),
+            };
+            let sides = [
+                (
+                    left && row.left_line_number.is_some(),
+                    PendingReviewCommentSide::Left,
+                    &row.left_text,
+                ),
+                (
+                    right && row.right_line_number.is_some(),
+                    PendingReviewCommentSide::Right,
+                    &row.right_text,
+                ),
+            ];
+            for (searched, side, text) in sides {
+                if !searched {
+                    continue;
+                }
+                for found in self.pattern.find_iter(text) {
+                    if found.is_empty() {
+                        continue;
+                    }
+                    let start = text[..found.start()].chars().count();
+                    matches.push(DiffTextMatch {
+                        file_index,
+                        row: row_index,
+                        side,
+                        start,
+                        end: start + found.as_str().chars().count(),
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+impl ReviewScreenState {
+    pub fn focus_diff_text_search(&mut self) {
+        self.diff_text_search.focus();
+    }
+
+    pub fn is_diff_text_search_focused(&self) -> bool {
+        self.diff_text_search.is_focused()
+    }
+
+    pub fn diff_text_search_query(&self) -> &str {
+        self.diff_text_search.query()
+    }
+
+    pub fn diff_text_search_input_mut(&mut self) -> &mut SearchInputState {
+        &mut self.diff_text_search
+    }
+
+    /// Reparses the diff text search and finds its matches in every file.
+    pub fn refresh_diff_text_search(&mut self) {
+        self.diff_text_current = None;
+        (self.diff_text_query, self.diff_text_error) =
+            match DiffTextQuery::parse(self.diff_text_search.query()) {
+                Ok(query) => (query, None),
+                Err(error) => (None, Some(error)),
+            };
+        self.diff_text_matches = match (&self.diff_text_query, &self.diff) {
+            (Some(query), Some(diff)) => diff
+                .files
+                .iter()
+                .enumerate()
+                .flat_map(|(file_index, file)| query.find_matches(file_index, file))
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Whether a diff text search is active, so `n`/`N` step through its
+    /// matches rather than hunks.
+    pub fn has_diff_text_search(&self) -> bool {
+        self.diff_text_query.is_some()
+    }
+
+    /// Match count, position and side filter of the diff text search, or why
+    /// the query is invalid.
+    pub fn diff_text_search_status(&self) -> Option<String> {
+        if let Some(error) = self.diff_text_error {
+            return Some(error.to_owned());
+        }
+        let query = self.diff_text_query.as_ref()?;
+        let mut status = match (self.diff_text_matches.len(), self.diff_text_current) {
+            (0, _) => "no matches".to_owned(),
+            (1, _) => "1 match".to_owned(),
+            (count, Some(current)) => format!("{} of {count} matches", current + 1),
+            (count, None) => format!("{count} matches"),
+        };
+        match query.side() {
+            DiffTextSide::Both => {}
+            DiffTextSide::Added => status.push_str(", added lines"),
+            DiffTextSide::Removed => status.push_str(", removed lines"),
+        }
+        Some(status)
+    }
+
+    /// Diff text search matches in one file, ordered by row.
+    pub fn diff_text_matches_for_file(&self, file_index: usize) -> &[DiffTextMatch] {
+        let start = self
+            .diff_text_matches
+            .partition_point(|found| found.file_index < file_index);
+        let end = self
+            .diff_text_matches
+            .partition_point(|found| found.file_index <= file_index);
+        &self.diff_text_matches[start..end]
+    }
+
+    /// Moves to the next line with a diff text search match, across files in
+    /// tree order and wrapping around. Returns `false` when nothing matches.
+    pub fn jump_next_diff_match(&mut self) -> bool {
+        let targets = self.diff_match_targets();
+        let current = self.diff_cursor_match_key();
+        let Some((_, index)) = targets
+            .iter()
+            .find(|(key, _)| Some(*key) > current)
+            .or(targets.first())
+            .copied()
+        else {
+            return false;
+        };
+        self.jump_to_diff_match(index);
+        true
+    }
+
+    pub fn jump_prev_diff_match(&mut self) -> bool {
+        let targets = self.diff_match_targets();
+        let current = self.diff_cursor_match_key();
+        let Some((_, index)) = targets
+            .iter()
+            .rev()
+            .find(|(key, _)| Some(*key) < current)
+            .or(targets.last())
+            .copied()
+        else {
+            return false;
+        };
+        self.jump_to_diff_match(index);
+        true
+    }
+
+    /// First match of each line in navigation order, keyed like
+    /// [`Self::diff_match_key`]. Files filtered out of the tree are skipped.
+    fn diff_match_targets(&self) -> Vec<((usize, usize, bool), usize)> {
+        let files = self.navigable_diff_files();
+        let mut targets = self
+            .diff_text_matches
+            .iter()
+            .enumerate()
+            .filter_map(|(index, found)| {
+                let key = self.diff_match_key(&files, found.file_index, found.row, found.side)?;
+                Some((key, index))
+            })
+            .collect::<Vec<_>>();
+        targets.sort_unstable();
+        targets.dedup_by_key(|(key, _)| *key);
+        targets
+    }
+
+    fn diff_cursor_match_key(&self) -> Option<(usize, usize, bool)> {
+        self.diff_match_key(
+            &self.navigable_diff_files(),
+            self.selected_diff_file,
+            self.selected_diff_line,
+            self.diff_cursor_side,
+        )
+    }
+
+    /// Position of a row side for match navigation: the file's place in the
+    /// tree, the row, and the side where it has a line of its own.
+    fn diff_match_key(
+        &self,
+        files: &[usize],
+        file_index: usize,
+        row: usize,
+        side: PendingReviewCommentSide,
+    ) -> Option<(usize, usize, bool)> {
+        let position = files.iter().position(|index| *index == file_index)?;
+        let split_row = self.diff_layout == DiffLayout::Unified
+            && self
+                .diff
+                .as_ref()
+                .and_then(|diff| diff.files.get(file_index)?.rows.get(row))
+                .is_some_and(|row| row.kind == crate::domain::PullRequestDiffRowKind::Modified);
+        Some((
+            position,
+            row,
+            split_row && side == PendingReviewCommentSide::Right,
+        ))
+    }
+
+    fn jump_to_diff_match(&mut self, index: usize) {
+        let found = self.diff_text_matches[index];
+        self.jump_to_diff_row(found.file_index, found.row, found.side);
+        if found.start < self.diff_column_offset {
+            self.diff_column_offset = 0;
+        }
+        self.diff_focus = DiffFocus::Content;
+        self.diff_text_current = Some(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffTextQuery, DiffTextSide};
+    use crate::{
+        app::state::PendingReviewCommentSide,
+        domain::{
+            PullRequestDiffFile, PullRequestDiffFileStatus, PullRequestDiffRow,
+            PullRequestDiffRowKind,
+        },
+    };
+
+    fn row(kind: PullRequestDiffRowKind, left: &str, right: &str) -> PullRequestDiffRow {
+        PullRequestDiffRow {
+            left_line_number: (!left.is_empty()).then_some(1),
+            right_line_number: (!right.is_empty()).then_some(1),
+            left_text: left.to_owned(),
+            right_text: right.to_owned(),
+            left_highlights: Vec::new(),
+            right_highlights: Vec::new(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn finds_literal_and_regex_matches_on_the_requested_side() {
+        let file = PullRequestDiffFile {
+            path: "src/lib.rs".to_owned(),
+            status: PullRequestDiffFileStatus::Modified,
+            rows: vec![
+                row(
+                    PullRequestDiffRowKind::Context,
+                    "let té = 1;",
+                    "let té = 1;",
+                ),
+                row(PullRequestDiffRowKind::Modified, "old_té()", "new_Té()"),
+                row(PullRequestDiffRowKind::Added, "", "té té"),
+            ],
+            hunk_starts: vec![1],
+        };
+        let spans = |query: &str| {
+            DiffTextQuery::parse(query)
+                .unwrap()
+                .unwrap()
+                .find_matches(0, &file)
+                .into_iter()
+                .map(|found| (found.row, found.side, found.start, found.end))
+                .collect::<Vec<_>>()
+        };
+        let (left, right) = (
+            PendingReviewCommentSide::Left,
+            PendingReviewCommentSide::Right,
+        );
+
+        assert_eq!(
+            spans("té"),
+            vec![
+                (0, right, 4, 6),
+                (1, left, 4, 6),
+                (1, right, 4, 6),
+                (2, right, 0, 2),
+                (2, right, 3, 5),
+            ]
+        );
+        assert_eq!(spans("Té"), vec![(1, right, 4, 6)]);
+        assert_eq!(spans("is:removed té"), vec![(1, left, 4, 6)]);
+        assert_eq!(
+            spans("/^(old|new)_/"),
+            vec![(1, left, 0, 4), (1, right, 0, 4)]
+        );
+        assert_eq!(spans("is:added /t.$/"), vec![(2, right, 3, 5)]);
+
+        let query = DiffTextQuery::parse("IS:ADDED").unwrap();
+        assert!(query.is_none());
+        assert_eq!(
+            DiffTextQuery::parse("is:removed x")
+                .unwrap()
+                .unwrap()
+                .side(),
+            DiffTextSide::Removed
+        );
+        assert!(DiffTextQuery::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn keeps_the_spacing_of_the_searched_text() {
+        let file = PullRequestDiffFile {
+            path: "src/lib.rs".to_owned(),
+            status: PullRequestDiffFileStatus::Modified,
+            rows: vec![
+                row(PullRequestDiffRowKind::Added, "", "a b"),
+                row(PullRequestDiffRowKind::Added, "", "a  b"),
+                row(PullRequestDiffRowKind::Added, "", "    indented"),
+            ],
+            hunk_starts: vec![0],
+        };
+        let rows = |query: &str| {
+            DiffTextQuery::parse(query)
+                .unwrap()
+                .unwrap()
+                .find_matches(0, &file)
+                .into_iter()
+                .map(|found| (found.row, found.start, found.end))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(rows("a  b"), vec![(1, 0, 4)]);
+        assert_eq!(rows("a b"), vec![(0, 0, 3)]);
+        assert_eq!(rows("is:added  a  b "), vec![(1, 0, 4)]);
+        assert_eq!(rows("/^    i/"), vec![(2, 0, 5)]);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{ReviewScreenState, ReviewSubmissionEvent, ReviewTab, SearchInputState};
+use crate::{config::CommentTemplate, search::fuzzy::rank_templates};
+
+/// Where a picked template is inserted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TemplateTarget {
+    /// At the cursor of the open in-app composer.
+    Composer,
+    /// Appended to the staged reply for the thread rooted at `root_key`.
+    Reply { root_key: String },
+    /// Appended to the pending inline comment on the current diff selection.
+    PendingComment,
+    /// As the starting body of a review submission.
+    ReviewBody(ReviewSubmissionEvent),
+}
+
+impl TemplateTarget {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Composer => "composer",
+            Self::Reply { .. } => "reply",
+            Self::PendingComment => "inline comment",
+            Self::ReviewBody(ReviewSubmissionEvent::Comment) => "review comment",
+            Self::ReviewBody(ReviewSubmissionEvent::Approve) => "approval",
+            Self::ReviewBody(ReviewSubmissionEvent::RequestChanges) => "request changes",
+        }
+    }
+}
+
+/// Fuzzy picker over saved comment templates.
+#[derive(Debug, Clone)]
+pub struct TemplatePickerState {
+    templates: Vec<CommentTemplate>,
+    query: SearchInputState,
+    results: Vec<usize>,
+    selected: usize,
+    targets: Vec<TemplateTarget>,
+    target_index: usize,
+}
+
+impl TemplatePickerState {
+    pub fn new(templates: Vec<CommentTemplate>, targets: Vec<TemplateTarget>) -> Self {
+        let mut query = SearchInputState::default();
+        query.focus();
+        let mut picker = Self {
+            templates,
+            query,
+            results: Vec::new(),
+            selected: 0,
+            targets,
+            target_index: 0,
+        };
+        picker.refresh_results();
+        picker
+    }
+
+    pub fn query(&self) -> &str {
+        self.query.query()
+    }
+
+    pub fn push_char(&mut self, ch: char) {
+        self.query.push_char(ch);
+        self.refresh_results();
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.backspace();
+        self.refresh_results();
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.results.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Matching templates in ranked order.
+    pub fn results(&self) -> impl Iterator<Item = &CommentTemplate> {
+        self.results
+            .iter()
+            .filter_map(|index| self.templates.get(*index))
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_template(&self) -> Option<&CommentTemplate> {
+        self.results
+            .get(self.selected)
+            .and_then(|index| self.templates.get(*index))
+    }
+
+    pub fn target(&self) -> Option<&TemplateTarget> {
+        self.targets.get(self.target_index)
+    }
+
+    pub fn has_multiple_targets(&self) -> bool {
+        self.targets.len() > 1
+    }
+
+    pub fn cycle_target(&mut self) {
+        if !self.targets.is_empty() {
+            self.target_index = (self.target_index + 1) % self.targets.len();
+        }
+    }
+
+    fn refresh_results(&mut self) {
+        self.results = rank_templates(self.query.query(), &self.templates)
+            .into_iter()
+            .map(|result| result.index)
+            .collect();
+        self.selected = self.selected.min(self.results.len().saturating_sub(1));
+    }
+}
+
+impl ReviewScreenState {
+    /// Opens the template picker.
+    ///
+    /// Inside the composer templates are inserted at the cursor; otherwise the
+    /// picker offers the selected thread or diff line followed by the three
+    /// review submission bodies.
+    pub fn open_template_picker(&mut self, templates: Vec<CommentTemplate>) {
+        let targets = if self.composer.is_some() {
+            vec![TemplateTarget::Composer]
+        } else {
+            let mut targets = Vec::new();
+            match self.active_tab {
+                ReviewTab::Threads => {
+                    if let Some(context) = self.selected_thread_context() {
+                        targets.push(TemplateTarget::Reply {
+                            root_key: context.root_key,
+                        });
+                    }
+                }
+                ReviewTab::Diff => {
+                    if self.is_diff_content_focused() {
+                        targets.push(TemplateTarget::PendingComment);
+                    }
+                }
+            }
+            targets.extend([
+                TemplateTarget::ReviewBody(ReviewSubmissionEvent::Comment),
+                TemplateTarget::ReviewBody(ReviewSubmissionEvent::Approve),
+                TemplateTarget::ReviewBody(ReviewSubmissionEvent::RequestChanges),
+            ]);
+            targets
+        };
+
+        self.template_picker = Some(TemplatePickerState::new(templates, targets));
+    }
+
+    pub fn close_template_picker(&mut self) {
+        self.template_picker = None;
+    }
+}
@@ -0,0 +1,198 @@
+//! Facet tokens accepted by the comment search, such as `author:octocat` or
+//! `is:outdated`, and the thread-level checks behind them.
+
+use super::ReviewScreenState;
+use crate::domain::{ReviewComment, ReviewThread, review_comment_is_outdated};
+
+/// Facets parsed out of the comment search query. Every set facet must match.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ThreadFacets {
+    /// Lowercase login that wrote at least one comment of the thread.
+    pub author: Option<String>,
+    /// Lowercase fragment of the thread's file path.
+    pub path: Option<String>,
+    pub outdated: bool,
+    /// Unresolved threads I am part of where someone else spoke last.
+    pub awaiting_reply: bool,
+    /// Threads I wrote in or was mentioned in.
+    pub involves_me: bool,
+}
+
+/// Who is looking at the threads, for the facets that depend on it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Viewer<'a> {
+    pub login: Option<&'a str>,
+    pub pull_author: &'a str,
+}
+
+/// Splits a comment search query into its facets and the remaining free text.
+pub(super) fn parse_query(query: &str) -> (ThreadFacets, String) {
+    let mut facets = ThreadFacets::default();
+    let mut text = Vec::new();
+
+    for token in query.split_whitespace() {
+        let lowered = token.to_ascii_lowercase();
+        match lowered.split_once(':') {
+            Some(("author", login)) if !login.is_empty() => {
+                facets.author = Some(login.trim_start_matches('@').to_owned());
+            }
+            Some(("path", path)) if !path.is_empty() => facets.path = Some(path.to_owned()),
+            Some(("is", "outdated")) => facets.outdated = true,
+            Some(("is", "awaiting")) => facets.awaiting_reply = true,
+            Some(("is", "involved")) => facets.involves_me = true,
+            _ => text.push(token),
+        }
+    }
+
+    (facets, text.join(" "))
+}
+
+impl ThreadFacets {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Active facets as shown above the comment list.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        if let Some(author) = &self.author {
+            labels.push(format!("@{author}"));
+        }
+        if let Some(path) = &self.path {
+            labels.push(format!("path {path}"));
+        }
+        if self.outdated {
+            labels.push("outdated".to_owned());
+        }
+        if self.awaiting_reply {
+            labels.push("awaiting my reply".to_owned());
+        }
+        if self.involves_me {
+            labels.push("involves me".to_owned());
+        }
+        labels
+    }
+
+    pub(super) fn matches_thread(&self, thread: &ReviewThread, viewer: Viewer<'_>) -> bool {
+        let mut comments = Vec::new();
+        collect_comments(thread, &mut comments);
+
+        if let Some(author) = &self.author
+            && !comments
+                .iter()
+                .any(|comment| login_of(comment).eq_ignore_ascii_case(author))
+        {
+            return false;
+        }
+        if let Some(path) = &self.path
+            && !thread.comment.path.to_ascii_lowercase().contains(path)
+        {
+            return false;
+        }
+        if self.outdated && !review_comment_is_outdated(&thread.comment) {
+            return false;
+        }
+
+        let Some(login) = viewer.login else {
+            return !(self.awaiting_reply || self.involves_me);
+        };
+        let involved = comments.iter().any(|comment| {
+            login_of(comment).eq_ignore_ascii_case(login) || mentions(&comment.body, login)
+        });
+        if self.involves_me && !involved {
+            return false;
+        }
+        if self.awaiting_reply {
+            let last_author = comments
+                .iter()
+                .max_by_key(|comment| comment.created_at)
+                .map(|comment| login_of(comment));
+            let waiting_on_me = !thread.is_resolved
+                && (involved || viewer.pull_author.eq_ignore_ascii_case(login))
+                && last_author.is_some_and(|author| !author.eq_ignore_ascii_case(login));
+            if !waiting_on_me {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Checks a conversation comment or review summary, which has no file and
+    /// no replies, so only the author and involvement facets can match.
+    pub(super) fn matches_comment(&self, author: &str, body: &str, viewer: Viewer<'_>) -> bool {
+        if self.path.is_some() || self.outdated || self.awaiting_reply {
+            return false;
+        }
+        if let Some(expected) = &self.author
+            && !author.eq_ignore_ascii_case(expected)
+        {
+            return false;
+        }
+        if self.involves_me {
+            return viewer
+                .login
+                .is_some_and(|login| author.eq_ignore_ascii_case(login) || mentions(body, login));
+        }
+        true
+    }
+}
+
+fn collect_comments<'a>(thread: &'a ReviewThread, comments: &mut Vec<&'a ReviewComment>) {
+    comments.push(&thread.comment);
+    for reply in &thread.replies {
+        collect_comments(reply, comments);
+    }
+}
+
+fn login_of(comment: &ReviewComment) -> &str {
+    comment
+        .user
+        .as_ref()
+        .map(|user| user.login.as_str())
+        .unwrap_or("")
+}
+
+/// Whether `body` contains `@login` as a whole mention.
+fn mentions(body: &str, login: &str) -> bool {
+    let body = body.to_ascii_lowercase();
+    let needle = format!("@{}", login.to_ascii_lowercase());
+    body.match_indices(&needle).any(|(start, _)| {
+        let before = body[..start].chars().next_back();
+        let after = body[start + needle.len()..].chars().next();
+        before.is_none_or(|ch| !(ch.is_ascii_alphanumeric() || ch == '-'))
+            && after.is_none_or(|ch| !(ch.is_ascii_alphanumeric() || ch == '-'))
+    })
+}
+
+impl ReviewScreenState {
+    pub fn thread_facets(&self) -> &ThreadFacets {
+        &self.thread_facets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ThreadFacets, mentions, parse_query};
+
+    #[test]
+    fn parses_facet_tokens_and_keeps_free_text() {
+        let (facets, text) =
+            parse_query("author:@Octocat flaky Path:src/app is:outdated is:awaiting");
+
+        assert_eq!(
+            facets,
+            ThreadFacets {
+                author: Some("octocat".to_owned()),
+                path: Some("src/app".to_owned()),
+                outdated: true,
+                awaiting_reply: true,
+                involves_me: false,
+            }
+        );
+        assert_eq!(text, "flaky");
+        assert!(parse_query("is:involved").0.involves_me);
+        assert!(parse_query("author: is:unknown").0.is_empty());
+        assert!(mentions("thanks @octocat!", "OctoCat"));
+        assert!(!mentions("ping @octocat-bot", "octocat"));
+    }
+}
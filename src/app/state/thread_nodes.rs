@@ -1,6 +1,9 @@
 use crate::domain::{CommentRef, ListNode, ListNodeKind, ReviewThread, review_comment_is_outdated};
 use std::collections::{HashMap, HashSet};
 
+/// Key of the pull request description row pinned above the comments.
+pub(super) const DESCRIPTION_NODE_KEY: &str = "description";
+
 fn append_reply_nodes(
     nodes: &mut Vec<ListNode>,
     thread: &ReviewThread,
@@ -18,6 +18,11 @@ fn node_matches_query(node: &ListNode, query: &str) -> bool {
             CommentRef::Review(comment) => contains_ignore_case(comment.path.as_str(), query),
             CommentRef::Issue(_) => false,
             CommentRef::ReviewSummary(_) => false,
+            CommentRef::Description(description) => description
+                .labels
+                .iter()
+                .any(|label| contains_ignore_case(label, query)),
+            CommentRef::Path { path, .. } => contains_ignore_case(path, query),
         }
 }
 
@@ -0,0 +1,259 @@
+use super::ReviewScreenState;
+use super::thread_facets::Viewer;
+use super::thread_nodes::append_thread_nodes;
+use crate::domain::{CommentRef, ListNode, ListNodeKind, PullRequestComment, ReviewThread};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Threads nested under their file paths, mirroring the diff file tree.
+#[derive(Debug, Default)]
+struct ThreadTreeNode<'a> {
+    children: BTreeMap<String, ThreadTreeNode<'a>>,
+    files: BTreeMap<String, Vec<&'a ReviewThread>>,
+}
+
+/// Appends directory and file headings with each file's threads below them,
+/// ordered by path and then by line.
+pub(super) fn append_file_tree_nodes(
+    nodes: &mut Vec<ListNode>,
+    threads_by_key: &mut HashMap<String, ReviewThread>,
+    collapsed: &HashSet<String>,
+    threads: &[&ReviewThread],
+) {
+    let mut root = ThreadTreeNode::default();
+    for thread in threads {
+        insert_thread(&mut root, thread);
+    }
+    append_tree_nodes(&root, "", 0, nodes, threads_by_key, collapsed);
+}
+
+/// Sort key placing threads by line, with threads that lost their line last.
+pub(super) fn thread_position(thread: &ReviewThread) -> (bool, u64, i64) {
+    let comment = &thread.comment;
+    let line = comment
+        .start_line
+        .or(comment.line)
+        .or(comment.original_start_line)
+        .or(comment.original_line);
+    (
+        line.is_none(),
+        line.unwrap_or_default(),
+        comment.created_at.timestamp_millis(),
+    )
+}
+
+pub(super) fn directory_key(path: &str) -> String {
+    format!("dir:{path}")
+}
+
+pub(super) fn file_key(path: &str) -> String {
+    format!("file:{path}")
+}
+
+fn insert_thread<'a>(root: &mut ThreadTreeNode<'a>, thread: &'a ReviewThread) {
+    let path = thread.comment.path.as_str();
+    let parts = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut node = root;
+    for segment in parts.iter().take(parts.len().saturating_sub(1)) {
+        node = node.children.entry((*segment).to_owned()).or_default();
+    }
+    node.files.entry(path.to_owned()).or_default().push(thread);
+}
+
+fn append_tree_nodes(
+    node: &ThreadTreeNode<'_>,
+    parent_path: &str,
+    depth: usize,
+    nodes: &mut Vec<ListNode>,
+    threads_by_key: &mut HashMap<String, ReviewThread>,
+    collapsed: &HashSet<String>,
+) {
+    for (segment, child) in &node.children {
+        let (label, path, compressed) = compress_directory(parent_path, segment, child);
+        let key = directory_key(&path);
+        let is_collapsed = collapsed.contains(&key);
+        nodes.push(heading_node(
+            key,
+            ListNodeKind::Directory,
+            depth,
+            path.clone(),
+            label,
+        ));
+
+        if !is_collapsed {
+            append_tree_nodes(
+                compressed,
+                &path,
+                depth + 1,
+                nodes,
+                threads_by_key,
+                collapsed,
+            );
+        }
+    }
+
+    for (path, threads) in &node.files {
+        let label = match path.rsplit('/').next() {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ => "(no file)".to_owned(),
+        };
+        let key = file_key(path);
+        let is_collapsed = collapsed.contains(&key);
+        nodes.push(heading_node(
+            key,
+            ListNodeKind::File,
+            depth,
+            path.clone(),
+            label,
+        ));
+        if is_collapsed {
+            continue;
+        }
+
+        let mut threads = threads.clone();
+        threads.sort_by_key(|thread| thread_position(thread));
+        for thread in threads {
+            append_thread_nodes(nodes, threads_by_key, collapsed, thread, depth + 1);
+        }
+    }
+}
+
+fn heading_node(
+    key: String,
+    kind: ListNodeKind,
+    depth: usize,
+    path: String,
+    label: String,
+) -> ListNode {
+    ListNode {
+        key,
+        kind,
+        depth,
+        root_key: None,
+        is_resolved: false,
+        is_outdated: false,
+        comment: CommentRef::Path { path, label },
+    }
+}
+
+fn compress_directory<'a, 'b>(
+    parent_path: &str,
+    initial_segment: &str,
+    initial_node: &'b ThreadTreeNode<'a>,
+) -> (String, String, &'b ThreadTreeNode<'a>) {
+    let mut label = initial_segment.to_owned();
+    let mut path = if parent_path.is_empty() {
+        initial_segment.to_owned()
+    } else {
+        format!("{parent_path}/{initial_segment}")
+    };
+    let mut node = initial_node;
+
+    while node.files.is_empty() && node.children.len() == 1 {
+        let Some((segment, next)) = node.children.iter().next() else {
+            break;
+        };
+        label.push('/');
+        label.push_str(segment);
+        path.push('/');
+        path.push_str(segment);
+        node = next;
+    }
+
+    (label, path, node)
+}
+
+impl ReviewScreenState {
+    /// Nests inline threads under their files, sorted by path and line, and
+    /// lists conversation comments and reviews after them.
+    pub(super) fn append_file_grouped_nodes(&mut self) {
+        let facets = &self.thread_facets;
+        let viewer = Viewer {
+            login: self.viewer_login.as_deref(),
+            pull_author: &self.pull.author,
+        };
+        let threads: Vec<&ReviewThread> = self
+            .data
+            .comments
+            .iter()
+            .filter_map(|entry| match entry {
+                PullRequestComment::ReviewThread(thread) => Some(&**thread),
+                _ => None,
+            })
+            .filter(|thread| !(self.hide_resolved && thread.is_resolved))
+            .filter(|thread| facets.matches_thread(thread, viewer))
+            .collect();
+        append_file_tree_nodes(
+            &mut self.thread_nodes_cache,
+            &mut self.threads_by_key,
+            &self.collapsed,
+            &threads,
+        );
+
+        for entry in &self.data.comments {
+            let (key, comment) = match entry {
+                PullRequestComment::ReviewThread(_) => continue,
+                PullRequestComment::IssueComment(comment) => (
+                    format!("issue:{}", comment.id),
+                    CommentRef::Issue((**comment).clone()),
+                ),
+                PullRequestComment::ReviewSummary(review) => (
+                    format!("review-issue:{}", review.id),
+                    CommentRef::ReviewSummary((**review).clone()),
+                ),
+            };
+            if !facets.matches_comment(comment.author(), comment.body(), viewer) {
+                continue;
+            }
+            self.thread_nodes_cache.push(ListNode {
+                key,
+                kind: ListNodeKind::Issue,
+                depth: 0,
+                root_key: None,
+                is_resolved: false,
+                is_outdated: false,
+                comment,
+            });
+        }
+    }
+
+    /// Switches between listing comments in conversation order and nesting
+    /// threads under their files.
+    pub fn toggle_file_grouping(&mut self) {
+        self.group_by_file = !self.group_by_file;
+        self.rebuild_nodes();
+    }
+
+    /// Inline threads under a file heading, or under every file below a
+    /// directory heading, in tree order.
+    pub fn threads_under_path(&self, path: &str, is_directory: bool) -> Vec<&ReviewThread> {
+        let prefix = format!("{path}/");
+        let mut threads: Vec<&ReviewThread> = self
+            .data
+            .comments
+            .iter()
+            .filter_map(|entry| match entry {
+                PullRequestComment::ReviewThread(thread) => Some(&**thread),
+                _ => None,
+            })
+            .filter(|thread| {
+                let file = thread.comment.path.as_str();
+                if is_directory {
+                    file.starts_with(&prefix)
+                } else {
+                    file == path
+                }
+            })
+            .collect();
+        threads.sort_by(|a, b| {
+            a.comment
+                .path
+                .cmp(&b.comment.path)
+                .then_with(|| thread_position(a).cmp(&thread_position(b)))
+        });
+        threads
+    }
+}
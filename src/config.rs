@@ -1,36 +1,223 @@
 //! User configuration loading from `~/.critic/config.toml`.
 
-use crate::ui::theme::{ThemeMode, ThemePalette};
+use crate::{
+    app::{
+        files::critic_dir,
+        keymap::{Action, KeyContext, Keymap, parse_sequence},
+        state::{SearchScope, SearchSort, SearchStatusFilter},
+    },
+    github::{client::ClientOptions, diff::DiffEngine},
+    ui::theme::{ThemeMode, ThemePalette},
+};
 use anyhow::{Context, Result, anyhow};
 use dark_light::Mode;
+use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     env, fs,
     path::{Path, PathBuf},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
-const CONFIG_DIR: &str = ".critic";
 const CONFIG_FILE: &str = "config.toml";
+const TEMPLATES_DIR: &str = "templates";
+const THEMES_DIR: &str = "themes";
+const SYNTAXES_DIR: &str = "syntaxes";
+const MIN_REFRESH_INTERVAL_SECS: u64 = 15;
+const DEFAULT_DIFF_CONTEXT_LINES: usize = 3;
+const DEFAULT_DIFF_UNIFIED_BELOW: u16 = 100;
 
-const DEFAULT_CONFIG_HEADER: &str = r#"# critic configuration
+const DEFAULT_CONFIG_HEADER: &str = r##"# critic configuration
 # Set `theme.mode` to one of: "auto", "dark", "light".
-"#;
+# Set `theme.name` to load `~/.critic/themes/<name>.toml`, `theme.syntax` to pick a
+# code highlighting theme, and override palette colors under `[theme.dark]` and
+# `[theme.light]` with hex (`"#1e1e2e"`), ANSI names (`"light_blue"`), or 0-255 indices.
+# Changes to this file are applied while critic is running.
+# Extra `.sublime-syntax` files are read from `~/.critic/syntaxes/` and `.tmTheme`
+# files from `~/.critic/themes/`. Map file globs to syntaxes under `[syntax.globs]`,
+# e.g. `"*.sol" = "Solidity"`.
+# Per-repository overrides go in `[repos."owner/name"]` tables (`theme`, `diff`,
+# `search`, `templates`); a `.critic.toml` at the repository root is read too.
+# Set `editor.composer` to "system" ($VISUAL/$EDITOR) or "builtin" (in-app composer).
+# Add saved replies with `[[templates]]` tables (`name`, `body`) or as files in
+# `~/.critic/templates/`.
+# Under `[github]`, set `host` (and optionally `api_url`) for GitHub Enterprise and
+# `token_command` to print a token; `GH_TOKEN`/`GITHUB_TOKEN` (or, for Enterprise,
+# `GH_ENTERPRISE_TOKEN`/`GITHUB_ENTERPRISE_TOKEN`) take precedence, and
+# `gh auth token --hostname <host>` is the fallback. Read at startup only.
+# Rebind keys under `[keymap.search]`, `[keymap.review]`, `[keymap.composer]`, and
+# `[keymap.template_picker]`, e.g.
+# `refresh = ["R", "g r"]` or `quit = []`. Chords accept `C-`, `M-`, and `S-`.
+# `refresh.interval_secs` turns on re-fetching the open pull request and the search
+# list in the background at that interval (minimum 15); unset or 0 leaves it off.
+"##;
 
 /// Application configuration loaded from disk.
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub theme_preference: ThemePreference,
+    /// Named theme file loaded from `~/.critic/themes/`.
+    pub theme_name: Option<String>,
+    pub theme_file: ThemeOverrides,
+    pub theme_overrides: ThemeOverrides,
+    pub composer: ComposerPreference,
+    pub templates: Vec<CommentTemplate>,
+    pub keymap: Keymap,
+    pub syntax: SyntaxConfig,
+    pub diff: DiffConfig,
+    pub search: SearchDefaults,
+    pub github: ClientOptions,
+    pub refresh: RefreshConfig,
+    /// Overrides keyed by lowercase `owner/name`.
+    pub repos: BTreeMap<String, RepoOverrides>,
+    /// Problems that were skipped rather than failing the load, such as
+    /// unreadable template files.
+    pub warnings: Vec<String>,
+}
+
+/// How pull request diffs are loaded and shown.
+#[derive(Debug, Clone)]
+pub struct DiffConfig {
+    pub engine: DiffEngine,
+    /// Globs for changed files that are left out of the Diff tab.
+    pub ignored_paths: Vec<String>,
+    /// Unchanged lines kept around each change; longer unchanged runs fold.
+    pub context_lines: usize,
+    /// Pane width in columns below which the diff is shown unified rather
+    /// than side by side; 0 keeps it side by side.
+    pub unified_below: u16,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            engine: DiffEngine::default(),
+            ignored_paths: Vec::new(),
+            context_lines: DEFAULT_DIFF_CONTEXT_LINES,
+            unified_below: DEFAULT_DIFF_UNIFIED_BELOW,
+        }
+    }
+}
+
+/// Background polling of the open pull request and the search list.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct RefreshConfig {
+    /// `None` when polling is turned off, which is the default.
+    pub interval: Option<Duration>,
+}
+
+/// Initial filters for the pull request search screen.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SearchDefaults {
+    pub scope: SearchScope,
+    pub status: SearchStatusFilter,
+    pub sort: SearchSort,
+}
+
+impl Default for SearchDefaults {
+    fn default() -> Self {
+        Self {
+            scope: SearchScope::All,
+            status: SearchStatusFilter::All,
+            sort: SearchSort::UpdatedAt,
+        }
+    }
+}
+
+/// Settings from `[repos."owner/name"]` or a repository's `.critic.toml`.
+#[derive(Debug, Clone, Default)]
+pub struct RepoOverrides {
+    theme: ThemeSection,
+    diff: DiffSection,
+    scope: Option<SearchScope>,
+    status: Option<SearchStatusFilter>,
+    sort: Option<SearchSort>,
+    templates: Vec<CommentTemplate>,
+}
+
+/// Parsed `[theme]` table; every field is optional so it can layer.
+#[derive(Debug, Clone, Default)]
+struct ThemeSection {
+    mode: Option<ThemePreference>,
+    name: Option<String>,
+    overrides: ThemeOverrides,
+}
+
+/// Parsed `[diff]` table; every field is optional so it can layer.
+#[derive(Debug, Clone, Default)]
+struct DiffSection {
+    engine: Option<DiffEngine>,
+    ignored_paths: Option<Vec<String>>,
+    context_lines: Option<usize>,
+    unified_below: Option<u16>,
+}
+
+/// Custom syntect assets and file-to-syntax mappings.
+#[derive(Debug, Clone, Default)]
+pub struct SyntaxConfig {
+    /// `(glob, syntax name)` pairs such as `("*.sol", "Solidity")`.
+    pub globs: Vec<(String, String)>,
+    pub syntaxes_dir: Option<PathBuf>,
+    pub themes_dir: Option<PathBuf>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             theme_preference: ThemePreference::Auto,
+            theme_name: None,
+            theme_file: ThemeOverrides::default(),
+            theme_overrides: ThemeOverrides::default(),
+            composer: ComposerPreference::System,
+            templates: Vec::new(),
+            keymap: Keymap::default(),
+            syntax: SyntaxConfig::default(),
+            diff: DiffConfig::default(),
+            search: SearchDefaults::default(),
+            github: ClientOptions::default(),
+            refresh: RefreshConfig::default(),
+            repos: BTreeMap::new(),
+            warnings: Vec::new(),
         }
     }
 }
 
+/// Palette colors and syntax theme layered over the built-in theme for a mode.
+#[derive(Debug, Clone, Default)]
+pub struct ThemeOverrides {
+    pub syntax: Option<String>,
+    dark: Vec<(String, Color)>,
+    light: Vec<(String, Color)>,
+}
+
+impl ThemeOverrides {
+    fn extend(&mut self, other: &ThemeOverrides) {
+        if other.syntax.is_some() {
+            self.syntax.clone_from(&other.syntax);
+        }
+        self.dark.extend(other.dark.iter().cloned());
+        self.light.extend(other.light.iter().cloned());
+    }
+
+    fn apply(&self, mode: ThemeMode, palette: &mut ThemePalette) {
+        let colors = match mode {
+            ThemeMode::Dark => &self.dark,
+            ThemeMode::Light => &self.light,
+        };
+        for (field, color) in colors {
+            palette.set_color(field, *color);
+        }
+    }
+}
+
+/// A saved reply that can be inserted into comments and review bodies.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommentTemplate {
+    pub name: String,
+    pub body: String,
+}
+
 /// Preferred theme mode from config.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ThemePreference {
@@ -39,11 +226,22 @@ pub enum ThemePreference {
     Light,
 }
 
+/// Where replies and inline comments are written.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ComposerPreference {
+    /// Suspend the TUI and open `$VISUAL`/`$EDITOR`.
+    System,
+    /// Use the in-app multiline composer.
+    Builtin,
+}
+
 /// Runtime theme selection after resolving `auto` mode.
 #[derive(Debug, Clone)]
 pub struct RuntimeThemeConfig {
     pub palette: ThemePalette,
     pub mode: ThemeMode,
+    /// Syntect theme for code highlighting; `None` uses the ocean theme for `mode`.
+    pub syntax_theme: Option<String>,
 }
 
 /// Terminal theme sample from runtime detection.
@@ -67,23 +265,109 @@ impl AppConfig {
 
     /// Builds a runtime theme for a specific mode.
     pub fn resolve_runtime_theme_for_mode(&self, mode: ThemeMode) -> RuntimeThemeConfig {
-        match mode {
-            ThemeMode::Dark => RuntimeThemeConfig {
-                palette: ThemePalette::default(),
-                mode,
-            },
-            ThemeMode::Light => RuntimeThemeConfig {
-                palette: ThemePalette::light_default(),
-                mode,
-            },
+        let mut palette = match mode {
+            ThemeMode::Dark => ThemePalette::default(),
+            ThemeMode::Light => ThemePalette::light_default(),
+        };
+        self.theme_file.apply(mode, &mut palette);
+        self.theme_overrides.apply(mode, &mut palette);
+
+        RuntimeThemeConfig {
+            palette,
+            mode,
+            syntax_theme: self
+                .theme_overrides
+                .syntax
+                .clone()
+                .or_else(|| self.theme_file.syntax.clone()),
         }
     }
 
+    /// Returns this config with overrides for `repository` (`owner/name`)
+    /// applied: the repository's own `.critic.toml` first, then the user's
+    /// `[repos."owner/name"]` table.
+    pub fn for_repository(
+        &self,
+        repository: &str,
+        repo_file: Option<&RepoOverrides>,
+    ) -> Result<AppConfig> {
+        let mut config = self.clone();
+        let configured = self.repos.get(&repository.to_ascii_lowercase());
+        for overrides in repo_file.into_iter().chain(configured) {
+            config.apply_repo_overrides(overrides)?;
+        }
+        Ok(config)
+    }
+
+    fn apply_repo_overrides(&mut self, overrides: &RepoOverrides) -> Result<()> {
+        if let Some(mode) = overrides.theme.mode {
+            self.theme_preference = mode;
+        }
+        if let Some(name) = &overrides.theme.name {
+            self.theme_name = Some(name.clone());
+            self.theme_file = match &self.syntax.themes_dir {
+                Some(dir) => load_theme_file(&dir.join(format!("{name}.toml")))?,
+                None => ThemeOverrides::default(),
+            };
+        }
+        self.theme_overrides.extend(&overrides.theme.overrides);
+
+        if let Some(engine) = overrides.diff.engine {
+            self.diff.engine = engine;
+        }
+        if let Some(paths) = &overrides.diff.ignored_paths {
+            self.diff.ignored_paths.clone_from(paths);
+        }
+        if let Some(lines) = overrides.diff.context_lines {
+            self.diff.context_lines = lines;
+        }
+        if let Some(width) = overrides.diff.unified_below {
+            self.diff.unified_below = width;
+        }
+        if let Some(scope) = overrides.scope {
+            self.search.scope = scope;
+        }
+        if let Some(status) = overrides.status {
+            self.search.status = status;
+        }
+        if let Some(sort) = overrides.sort {
+            self.search.sort = sort;
+        }
+        self.templates
+            .splice(0..0, overrides.templates.iter().cloned());
+        Ok(())
+    }
+
     fn to_persisted_config(&self) -> PersistedConfig {
         PersistedConfig {
             theme: PersistedThemeConfig {
                 mode: theme_preference_to_string(self.theme_preference).to_owned(),
             },
+            editor: PersistedEditorConfig {
+                composer: composer_preference_to_string(self.composer).to_owned(),
+            },
+            diff: PersistedDiffConfig {
+                engine: diff_engine_to_string(self.diff.engine).to_owned(),
+            },
+            search: PersistedSearchConfig {
+                scope: search_scope_to_string(self.search.scope).to_owned(),
+                status: search_status_to_string(self.search.status).to_owned(),
+                sort: search_sort_to_string(self.search.sort).to_owned(),
+            },
+            refresh: PersistedRefreshConfig {
+                interval_secs: self
+                    .refresh
+                    .interval
+                    .map_or(0, |interval| interval.as_secs()),
+            },
+            templates: self
+                .templates
+                .iter()
+                .map(|template| PersistedTemplate {
+                    name: template.name.clone(),
+                    body: template.body.clone(),
+                })
+                .collect(),
         }
     }
 }
@@ -101,25 +385,456 @@ pub fn load_or_create() -> Result<AppConfig> {
     let content = fs::read_to_string(&path)
         .with_context(|| format!("failed to read config file at {}", path.display()))?;
 
-    parse_app_config(&content)
-        .with_context(|| format!("failed to parse TOML in {}", path.display()))
+    let mut config = parse_app_config(&content)
+        .with_context(|| format!("failed to parse TOML in {}", path.display()))?;
+
+    if let Some(theme_path) = theme_file_path(&path, &config) {
+        config.theme_file = load_theme_file(&theme_path)?;
+    }
+
+    if let Some(dir) = path.parent() {
+        config.syntax.syntaxes_dir = Some(dir.join(SYNTAXES_DIR));
+        config.syntax.themes_dir = Some(dir.join(THEMES_DIR));
+
+        let templates_dir = dir.join(TEMPLATES_DIR);
+        let (templates, skipped) = load_template_files(&templates_dir).with_context(|| {
+            format!("failed to load templates from {}", templates_dir.display())
+        })?;
+        config.templates.extend(templates);
+        config.warnings.extend(skipped);
+    }
+    Ok(config)
+}
+
+/// Watches the config file and the active theme file for edits.
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    watched: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let mut watcher = Self {
+            watched: Vec::new(),
+        };
+        watcher.rewatch(config)?;
+        Ok(watcher)
+    }
+
+    /// Re-reads the watched paths after `config` has been reloaded.
+    pub fn rewatch(&mut self, config: &AppConfig) -> Result<()> {
+        let path = config_path()?;
+        let theme_path = theme_file_path(&path, config);
+        self.watched = std::iter::once(path)
+            .chain(theme_path)
+            .map(|path| {
+                let modified = modified_at(&path);
+                (path, modified)
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Returns `true` if any watched file changed since the last call.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last_modified) in &mut self.watched {
+            let modified = modified_at(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn theme_file_path(config_path: &Path, config: &AppConfig) -> Option<PathBuf> {
+    let name = config.theme_name.as_ref()?;
+    Some(
+        config_path
+            .parent()?
+            .join(THEMES_DIR)
+            .join(format!("{name}.toml")),
+    )
+}
+
+fn load_theme_file(path: &Path) -> Result<ThemeOverrides> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("failed to read theme file {}", path.display()))?;
+    parse_theme_file(&content)
+        .with_context(|| format!("failed to parse theme file {}", path.display()))
+}
+
+/// Parses a repository's `.critic.toml`.
+pub fn parse_repo_config_file(content: &str) -> Result<RepoOverrides> {
+    let raw: RawRepoConfig = toml::from_str(content).context("failed to parse TOML")?;
+    parse_repo_overrides("", raw)
+}
+
+/// Loads one template per file in `dir`, named after the file stem.
+///
+/// Files that cannot be read are skipped and described in the returned
+/// warnings, so one bad file does not stop the config from loading.
+fn load_template_files(dir: &Path) -> Result<(Vec<CommentTemplate>, Vec<String>)> {
+    if !dir.is_dir() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let mut templates = Vec::new();
+    let mut skipped = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(body) => templates.push(CommentTemplate {
+                name: name.to_owned(),
+                body: body.trim_end().to_owned(),
+            }),
+            Err(err) => skipped.push(format!("skipped template {}: {err}", path.display())),
+        }
+    }
+    Ok((templates, skipped))
 }
 
 fn parse_app_config(content: &str) -> Result<AppConfig> {
     let raw: RawConfig = toml::from_str(content).context("failed to parse config TOML")?;
-    let theme_preference = match raw.theme.mode {
-        Some(mode) => parse_theme_preference(mode.trim())
-            .with_context(|| format!("invalid value for `theme.mode`: {mode}"))?,
-        None => ThemePreference::Auto,
+    let theme = parse_theme_section("theme", raw.theme)?;
+
+    let composer = match raw.editor.composer {
+        Some(composer) => parse_composer_preference(composer.trim())
+            .with_context(|| format!("invalid value for `editor.composer`: {composer}"))?,
+        None => ComposerPreference::System,
+    };
+
+    let templates = parse_templates("templates", raw.templates)?;
+    let diff = parse_diff_section("diff", raw.diff)?;
+    let (scope, status, sort) = parse_search_section("search", raw.search)?;
+    let mut repos = BTreeMap::new();
+    for (repository, overrides) in raw.repos {
+        let key = repository.trim().to_ascii_lowercase();
+        if key.split('/').filter(|part| !part.is_empty()).count() != 2 {
+            return Err(anyhow!("`repos.{repository}` must be named `owner/name`"));
+        }
+        let overrides = parse_repo_overrides(&format!("repos.\"{repository}\""), overrides)?;
+        if repos.insert(key, overrides).is_some() {
+            return Err(anyhow!("`repos.{repository}` is configured more than once"));
+        }
+    }
+
+    let mut keymap = Keymap::default();
+    for (context, bindings) in [
+        (KeyContext::Search, raw.keymap.search),
+        (KeyContext::Review, raw.keymap.review),
+        (KeyContext::Composer, raw.keymap.composer),
+        (KeyContext::TemplatePicker, raw.keymap.template_picker),
+    ] {
+        for (name, binding) in bindings {
+            let field = format!("keymap.{}.{name}", context.name());
+            let action =
+                Action::from_name(&name).ok_or_else(|| anyhow!("unknown action `{field}`"))?;
+            let sequences = binding
+                .into_vec()
+                .iter()
+                .map(|raw| parse_sequence(raw))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("invalid value for `{field}`"))?;
+            keymap.rebind(context, action, sequences);
+        }
+    }
+    let globs = raw
+        .syntax
+        .globs
+        .into_iter()
+        .map(|(glob, name)| {
+            let (glob, name) = (glob.trim().to_owned(), name.trim().to_owned());
+            if glob.is_empty() || name.is_empty() {
+                return Err(anyhow!("`syntax.globs` entries must not be empty"));
+            }
+            Ok((glob, name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let conflicts = keymap.conflicts();
+    if !conflicts.is_empty() {
+        return Err(anyhow!(
+            "conflicting key bindings:\n  {}",
+            conflicts.join("\n  ")
+        ));
+    }
+
+    let defaults = SearchDefaults::default();
+    Ok(AppConfig {
+        theme_preference: theme.mode.unwrap_or(ThemePreference::Auto),
+        theme_name: theme.name,
+        theme_file: ThemeOverrides::default(),
+        theme_overrides: theme.overrides,
+        composer,
+        templates,
+        keymap,
+        syntax: SyntaxConfig {
+            globs,
+            ..SyntaxConfig::default()
+        },
+        diff: DiffConfig {
+            engine: diff.engine.unwrap_or_default(),
+            ignored_paths: diff.ignored_paths.unwrap_or_default(),
+            context_lines: diff.context_lines.unwrap_or(DEFAULT_DIFF_CONTEXT_LINES),
+            unified_below: diff.unified_below.unwrap_or(DEFAULT_DIFF_UNIFIED_BELOW),
+        },
+        search: SearchDefaults {
+            scope: scope.unwrap_or(defaults.scope),
+            status: status.unwrap_or(defaults.status),
+            sort: sort.unwrap_or(defaults.sort),
+        },
+        github: parse_github_section(raw.github)?,
+        refresh: parse_refresh_section(raw.refresh)?,
+        repos,
+        warnings: Vec::new(),
+    })
+}
+
+fn parse_github_section(raw: RawGitHubConfig) -> Result<ClientOptions> {
+    let non_empty = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+    let host = non_empty(raw.host);
+    if let Some(host) = host.as_deref()
+        && (host.contains("://") || host.contains('/'))
+    {
+        return Err(anyhow!(
+            "invalid value for `github.host`: {host} (expected a hostname like github.example.com)"
+        ));
+    }
+    let api_url = non_empty(raw.api_url);
+    // The token is sent with every request, so plain HTTP is never allowed.
+    if let Some(url) = api_url.as_deref()
+        && !url.starts_with("https://")
+    {
+        return Err(anyhow!(
+            "invalid value for `github.api_url`: {url} (expected an https URL)"
+        ));
+    }
+
+    Ok(ClientOptions {
+        host,
+        api_url,
+        token_command: non_empty(raw.token_command),
+    })
+}
+
+fn parse_refresh_section(raw: RawRefreshConfig) -> Result<RefreshConfig> {
+    let interval = match raw.interval_secs {
+        None => return Ok(RefreshConfig::default()),
+        Some(0) => None,
+        Some(secs) if secs < MIN_REFRESH_INTERVAL_SECS => {
+            return Err(anyhow!(
+                "`refresh.interval_secs` must be 0 (off) or at least {MIN_REFRESH_INTERVAL_SECS}"
+            ));
+        }
+        Some(secs) => Some(Duration::from_secs(secs)),
+    };
+    Ok(RefreshConfig { interval })
+}
+
+fn parse_repo_overrides(section: &str, raw: RawRepoConfig) -> Result<RepoOverrides> {
+    let key = |field: &str| {
+        if section.is_empty() {
+            field.to_owned()
+        } else {
+            format!("{section}.{field}")
+        }
+    };
+    let diff = parse_diff_section(&key("diff"), raw.diff)?;
+    let (scope, status, sort) = parse_search_section(&key("search"), raw.search)?;
+    Ok(RepoOverrides {
+        theme: parse_theme_section(&key("theme"), raw.theme)?,
+        diff,
+        scope,
+        status,
+        sort,
+        templates: parse_templates(&key("templates"), raw.templates)?,
+    })
+}
+
+fn parse_theme_section(section: &str, raw: RawThemeConfig) -> Result<ThemeSection> {
+    let mode = raw
+        .mode
+        .map(|mode| {
+            parse_theme_preference(mode.trim())
+                .with_context(|| format!("invalid value for `{section}.mode`: {mode}"))
+        })
+        .transpose()?;
+    let name = raw
+        .name
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty());
+    if let Some(name) = name.as_deref()
+        && name.contains(['/', '\\'])
+    {
+        return Err(anyhow!("invalid value for `{section}.name`: {name}"));
+    }
+
+    Ok(ThemeSection {
+        mode,
+        name,
+        overrides: parse_theme_overrides(section, raw.syntax, raw.dark, raw.light)?,
+    })
+}
+
+fn parse_templates(section: &str, raw: Vec<RawTemplate>) -> Result<Vec<CommentTemplate>> {
+    raw.into_iter()
+        .enumerate()
+        .map(|(index, template)| {
+            let name = template.name.trim();
+            if name.is_empty() {
+                return Err(anyhow!("`{section}[{index}].name` must not be empty"));
+            }
+            Ok(CommentTemplate {
+                name: name.to_owned(),
+                body: template.body,
+            })
+        })
+        .collect()
+}
+
+fn parse_diff_section(section: &str, raw: RawDiffConfig) -> Result<DiffSection> {
+    let engine = raw
+        .engine
+        .map(|engine| match engine.trim().to_ascii_lowercase().as_str() {
+            "difftastic" | "difft" => Ok(DiffEngine::Difftastic),
+            "line" => Ok(DiffEngine::Line),
+            _ => Err(anyhow!(
+                "invalid value for `{section}.engine`: {engine} (expected difftastic or line)"
+            )),
+        })
+        .transpose()?;
+    let ignore = raw.ignore.map(|globs| {
+        globs
+            .into_iter()
+            .map(|glob| glob.trim().to_owned())
+            .filter(|glob| !glob.is_empty())
+            .collect()
+    });
+    Ok(DiffSection {
+        engine,
+        ignored_paths: ignore,
+        context_lines: raw.context,
+        unified_below: raw.unified_below,
+    })
+}
+
+type SearchOverrides = (
+    Option<SearchScope>,
+    Option<SearchStatusFilter>,
+    Option<SearchSort>,
+);
+
+fn parse_search_section(section: &str, raw: RawSearchConfig) -> Result<SearchOverrides> {
+    let invalid = |field: &str, value: &str, expected: &str| {
+        anyhow!("invalid value for `{section}.{field}`: {value} (expected one of: {expected})")
+    };
+    let scope = raw
+        .scope
+        .map(|value| match value.trim().to_ascii_lowercase().as_str() {
+            "all" => Ok(SearchScope::All),
+            "author" => Ok(SearchScope::Author),
+            "reviewer" => Ok(SearchScope::Reviewer),
+            _ => Err(invalid("scope", &value, "all, author, reviewer")),
+        })
+        .transpose()?;
+    let status = raw
+        .status
+        .map(|value| match value.trim().to_ascii_lowercase().as_str() {
+            "all" => Ok(SearchStatusFilter::All),
+            "draft" => Ok(SearchStatusFilter::Draft),
+            "ready" => Ok(SearchStatusFilter::Ready),
+            "approved" => Ok(SearchStatusFilter::Approved),
+            "rejected" => Ok(SearchStatusFilter::Rejected),
+            _ => Err(invalid(
+                "status",
+                &value,
+                "all, draft, ready, approved, rejected",
+            )),
+        })
+        .transpose()?;
+    let sort = raw
+        .sort
+        .map(|value| match value.trim().to_ascii_lowercase().as_str() {
+            "updated" => Ok(SearchSort::UpdatedAt),
+            "created" => Ok(SearchSort::CreatedAt),
+            _ => Err(invalid("sort", &value, "updated, created")),
+        })
+        .transpose()?;
+    Ok((scope, status, sort))
+}
+
+/// Parses a theme file from `~/.critic/themes/`.
+fn parse_theme_file(content: &str) -> Result<ThemeOverrides> {
+    let raw: RawThemeFile = toml::from_str(content).context("failed to parse theme TOML")?;
+    parse_theme_overrides("", raw.syntax, raw.dark, raw.light)
+}
+
+fn parse_theme_overrides(
+    section: &str,
+    syntax: Option<String>,
+    dark: BTreeMap<String, String>,
+    light: BTreeMap<String, String>,
+) -> Result<ThemeOverrides> {
+    let prefix = if section.is_empty() {
+        String::new()
+    } else {
+        format!("{section}.")
+    };
+    let parse_colors = |mode: &str, colors: BTreeMap<String, String>| {
+        let mut scratch = ThemePalette::default();
+        colors
+            .into_iter()
+            .map(|(field, raw)| {
+                let key = format!("{prefix}{mode}.{field}");
+                let color = parse_color(&raw)
+                    .with_context(|| format!("invalid value for `{key}`: {raw}"))?;
+                if !scratch.set_color(&field, color) {
+                    return Err(anyhow!("unknown palette color `{key}`"));
+                }
+                Ok((field, color))
+            })
+            .collect::<Result<Vec<_>>>()
     };
 
-    Ok(AppConfig { theme_preference })
+    Ok(ThemeOverrides {
+        syntax: syntax
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty()),
+        dark: parse_colors("dark", dark)?,
+        light: parse_colors("light", light)?,
+    })
+}
+
+fn parse_color(raw: &str) -> Result<Color> {
+    raw.trim()
+        .parse::<Color>()
+        .map_err(|_| anyhow!("expected a hex color (#rrggbb), an ANSI color name, or 0-255"))
 }
 
 fn config_path() -> Result<PathBuf> {
-    let home =
-        env::var_os("HOME").ok_or_else(|| anyhow!("HOME environment variable is not set"))?;
-    Ok(PathBuf::from(home).join(CONFIG_DIR).join(CONFIG_FILE))
+    Ok(critic_dir()?.join(CONFIG_FILE))
 }
 
 fn ensure_default_config(path: &Path) -> Result<()> {
@@ -143,6 +858,68 @@ fn ensure_default_config(path: &Path) -> Result<()> {
 #[serde(deny_unknown_fields)]
 struct RawConfig {
     theme: RawThemeConfig,
+    editor: RawEditorConfig,
+    templates: Vec<RawTemplate>,
+    keymap: RawKeymapConfig,
+    syntax: RawSyntaxConfig,
+    diff: RawDiffConfig,
+    search: RawSearchConfig,
+    github: RawGitHubConfig,
+    refresh: RawRefreshConfig,
+    repos: BTreeMap<String, RawRepoConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawRefreshConfig {
+    interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawGitHubConfig {
+    host: Option<String>,
+    api_url: Option<String>,
+    token_command: Option<String>,
+}
+
+/// Per-repository table; also the schema of a repository's `.critic.toml`.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawRepoConfig {
+    theme: RawThemeConfig,
+    diff: RawDiffConfig,
+    search: RawSearchConfig,
+    templates: Vec<RawTemplate>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawDiffConfig {
+    engine: Option<String>,
+    ignore: Option<Vec<String>>,
+    context: Option<usize>,
+    unified_below: Option<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawSearchConfig {
+    scope: Option<String>,
+    status: Option<String>,
+    sort: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawSyntaxConfig {
+    globs: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -150,11 +927,88 @@ struct RawConfig {
 #[serde(deny_unknown_fields)]
 struct RawThemeConfig {
     mode: Option<String>,
+    name: Option<String>,
+    syntax: Option<String>,
+    dark: BTreeMap<String, String>,
+    light: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawThemeFile {
+    syntax: Option<String>,
+    dark: BTreeMap<String, String>,
+    light: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawEditorConfig {
+    composer: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+struct RawKeymapConfig {
+    search: BTreeMap<String, RawKeyBinding>,
+    review: BTreeMap<String, RawKeyBinding>,
+    composer: BTreeMap<String, RawKeyBinding>,
+    template_picker: BTreeMap<String, RawKeyBinding>,
+}
+
+/// Either a single key sequence or a list of alternatives.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum RawKeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl RawKeyBinding {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(sequence) => vec![sequence],
+            Self::Many(sequences) => sequences,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTemplate {
+    name: String,
+    body: String,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 struct PersistedConfig {
     theme: PersistedThemeConfig,
+    editor: PersistedEditorConfig,
+    diff: PersistedDiffConfig,
+    search: PersistedSearchConfig,
+    refresh: PersistedRefreshConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    templates: Vec<PersistedTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PersistedDiffConfig {
+    engine: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PersistedSearchConfig {
+    scope: String,
+    status: String,
+    sort: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PersistedRefreshConfig {
+    interval_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
@@ -172,6 +1026,25 @@ fn parse_theme_preference(raw: &str) -> Result<ThemePreference> {
     }
 }
 
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PersistedEditorConfig {
+    composer: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct PersistedTemplate {
+    name: String,
+    body: String,
+}
+
+fn parse_composer_preference(raw: &str) -> Result<ComposerPreference> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "system" | "external" => Ok(ComposerPreference::System),
+        "builtin" | "built_in" | "built-in" => Ok(ComposerPreference::Builtin),
+        _ => Err(anyhow!("expected one of: system, builtin")),
+    }
+}
+
 fn build_default_config_toml() -> Result<String> {
     let default = AppConfig::default().to_persisted_config();
     let serialized =
@@ -187,6 +1060,45 @@ fn theme_preference_to_string(value: ThemePreference) -> &'static str {
     }
 }
 
+fn diff_engine_to_string(value: DiffEngine) -> &'static str {
+    match value {
+        DiffEngine::Difftastic => "difftastic",
+        DiffEngine::Line => "line",
+    }
+}
+
+fn search_scope_to_string(value: SearchScope) -> &'static str {
+    match value {
+        SearchScope::All => "all",
+        SearchScope::Author => "author",
+        SearchScope::Reviewer => "reviewer",
+    }
+}
+
+fn search_status_to_string(value: SearchStatusFilter) -> &'static str {
+    match value {
+        SearchStatusFilter::All => "all",
+        SearchStatusFilter::Draft => "draft",
+        SearchStatusFilter::Ready => "ready",
+        SearchStatusFilter::Approved => "approved",
+        SearchStatusFilter::Rejected => "rejected",
+    }
+}
+
+fn search_sort_to_string(value: SearchSort) -> &'static str {
+    match value {
+        SearchSort::UpdatedAt => "updated",
+        SearchSort::CreatedAt => "created",
+    }
+}
+
+fn composer_preference_to_string(value: ComposerPreference) -> &'static str {
+    match value {
+        ComposerPreference::System => "system",
+        ComposerPreference::Builtin => "builtin",
+    }
+}
+
 /// Detects terminal background mode using runtime probes with env fallbacks.
 pub fn detect_terminal_theme_mode() -> Option<ThemeMode> {
     detect_terminal_theme_sample().map(|sample| sample.mode)
@@ -352,12 +1264,21 @@ fn parse_theme_mode_hint(value: &str) -> Option<ThemeMode> {
 #[cfg(test)]
 mod tests {
     use super::{
-        AppConfig, ThemePreference, ansi_256_to_rgb, build_default_config_toml,
-        detect_from_colorfgbg_sample, parse_app_config, parse_theme_mode_hint,
-        parse_theme_preference, theme_mode_from_rgb,
+        AppConfig, ComposerPreference, ThemePreference, ansi_256_to_rgb, build_default_config_toml,
+        detect_from_colorfgbg_sample, load_template_files, parse_app_config,
+        parse_repo_config_file, parse_theme_file, parse_theme_mode_hint, parse_theme_preference,
+        theme_mode_from_rgb,
     };
-    use crate::ui::theme::ThemeMode;
-    use std::env;
+    use crate::{
+        app::{
+            keymap::{Action, KeyContext},
+            state::{SearchScope, SearchSort, SearchStatusFilter},
+        },
+        github::diff::DiffEngine,
+        ui::theme::{ThemeMode, ThemePalette},
+    };
+    use ratatui::style::Color;
+    use std::{env, fs, time::Duration};
 
     #[test]
     fn parse_theme_preference_accepts_expected_values() {
@@ -422,6 +1343,287 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_builtin_composer_preference() {
+        let config = parse_app_config(
+            r#"
+[editor]
+composer = "builtin"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.composer, ComposerPreference::Builtin);
+        assert!(parse_app_config("[editor]\ncomposer = \"emacs\"\n").is_err());
+    }
+
+    #[test]
+    fn parses_comment_templates() {
+        let config = parse_app_config(
+            r#"
+[[templates]]
+name = "nit"
+body = "nit: "
+
+[[templates]]
+name = "needs test"
+body = """
+Could you add a test covering this?
+"""
+"#,
+        )
+        .unwrap();
+
+        let names = config
+            .templates
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["nit", "needs test"]);
+        assert!(parse_app_config("[[templates]]\nname = \" \"\nbody = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn skips_unreadable_template_files() {
+        let dir = env::temp_dir().join(format!("critic-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("nit.md"), "nit: \n").unwrap();
+        fs::write(dir.join("binary.md"), [0xff, 0xfe]).unwrap();
+
+        let (templates, skipped) = load_template_files(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "nit");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("binary.md"));
+    }
+
+    #[test]
+    fn parses_keymap_overrides_and_rejects_conflicts() {
+        let config = parse_app_config(
+            r#"
+[keymap.review]
+refresh = ["R", "g r"]
+quit = []
+
+[keymap.composer]
+save_draft = "M-s"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .keymap
+                .labels(KeyContext::Composer, Action::SaveDraft),
+            vec!["M-s"]
+        );
+        assert_eq!(
+            config.keymap.labels(KeyContext::Review, Action::Refresh),
+            vec!["R", "g r"]
+        );
+        assert!(
+            config
+                .keymap
+                .labels(KeyContext::Review, Action::Quit)
+                .is_empty()
+        );
+
+        assert!(
+            parse_app_config(
+                "[keymap.review]
+fly = \"F\"\n"
+            )
+            .is_err()
+        );
+        assert!(
+            parse_app_config(
+                "[keymap.review]
+edit = \"C-hyper\"\n"
+            )
+            .is_err()
+        );
+        let conflict = parse_app_config(
+            "[keymap.search]
+refresh = \"q\"\n",
+        )
+        .unwrap_err();
+        assert!(format!("{conflict:#}").contains("conflicting key bindings"));
+    }
+
+    #[test]
+    fn layers_theme_file_and_palette_overrides() {
+        let mut config = parse_app_config(
+            r##"
+[theme]
+name = "mocha"
+syntax = "Solarized (dark)"
+
+[theme.dark]
+border = "#112233"
+diff_add = "light_green"
+"##,
+        )
+        .unwrap();
+        config.theme_file = parse_theme_file(
+            r##"
+syntax = "base16-mocha.dark"
+
+[dark]
+border = "red"
+title = "42"
+
+[light]
+text = "#000000"
+"##,
+        )
+        .unwrap();
+
+        let dark = config.resolve_runtime_theme_for_mode(ThemeMode::Dark);
+        assert_eq!(dark.palette.border, Color::Rgb(0x11, 0x22, 0x33));
+        assert_eq!(dark.palette.title, Color::Indexed(42));
+        assert_eq!(dark.palette.diff_add, Color::LightGreen);
+        assert_eq!(dark.syntax_theme.as_deref(), Some("Solarized (dark)"));
+
+        let light = config.resolve_runtime_theme_for_mode(ThemeMode::Light);
+        assert_eq!(light.palette.text, Color::Rgb(0, 0, 0));
+        assert_eq!(light.palette.border, ThemePalette::light_default().border);
+
+        assert!(parse_app_config("[theme.dark]\nborder = \"nope\"\n").is_err());
+        assert!(parse_app_config("[theme.dark]\nborders = \"red\"\n").is_err());
+        assert!(parse_app_config("[theme]\nname = \"../x\"\n").is_err());
+    }
+
+    #[test]
+    fn parses_syntax_globs() {
+        let config = parse_app_config(
+            r#"
+[syntax.globs]
+"*.sol" = "Solidity"
+"**/BUILD" = "Python"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.syntax.globs,
+            vec![
+                ("**/BUILD".to_owned(), "Python".to_owned()),
+                ("*.sol".to_owned(), "Solidity".to_owned()),
+            ]
+        );
+        assert!(parse_app_config("[syntax.globs]\n\"*.x\" = \" \"\n").is_err());
+    }
+
+    #[test]
+    fn layers_repository_overrides() {
+        let config = parse_app_config(
+            r#"
+[diff]
+ignore = ["*.lock"]
+
+[search]
+scope = "reviewer"
+
+[[templates]]
+name = "global"
+body = "g"
+
+[repos."Acme/Widgets".diff]
+engine = "line"
+
+[repos."Acme/Widgets".search]
+sort = "created"
+
+[repos."Acme/Widgets".theme]
+mode = "light"
+"#,
+        )
+        .unwrap();
+        let repo_file = parse_repo_config_file(
+            r#"
+[diff]
+ignore = ["vendor/**"]
+context = 8
+unified_below = 0
+
+[search]
+scope = "author"
+status = "ready"
+
+[[templates]]
+name = "repo"
+body = "r"
+"#,
+        )
+        .unwrap();
+
+        let effective = config
+            .for_repository("acme/widgets", Some(&repo_file))
+            .unwrap();
+        assert_eq!(effective.diff.engine, DiffEngine::Line);
+        assert_eq!(effective.diff.ignored_paths, vec!["vendor/**".to_owned()]);
+        assert_eq!(effective.diff.context_lines, 8);
+        assert_eq!(effective.diff.unified_below, 0);
+        assert_eq!(effective.search.scope, SearchScope::Author);
+        assert_eq!(effective.search.status, SearchStatusFilter::Ready);
+        assert_eq!(effective.search.sort, SearchSort::CreatedAt);
+        assert_eq!(effective.theme_preference, ThemePreference::Light);
+        let names = effective
+            .templates
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["repo", "global"]);
+
+        let other = config.for_repository("acme/gadgets", None).unwrap();
+        assert_eq!(other.diff.engine, DiffEngine::Difftastic);
+        assert_eq!(other.diff.context_lines, 3);
+        assert_eq!(other.diff.unified_below, 100);
+        assert_eq!(other.search.scope, SearchScope::Reviewer);
+
+        assert!(parse_app_config("[repos.widgets.diff]\nengine = \"line\"\n").is_err());
+        assert!(parse_app_config("[repos.\"a/b\".diff]\n[repos.\"A/B\".diff]\n").is_err());
+        assert!(parse_repo_config_file("[diff]\nengine = \"patience\"\n").is_err());
+        assert!(parse_repo_config_file("[keymap.review]\nquit = \"Q\"\n").is_err());
+    }
+
+    #[test]
+    fn parses_github_host_and_token_command() {
+        let config = parse_app_config(
+            r#"
+[github]
+host = "ghe.example.com"
+token_command = "pass show ghe"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.github.host.as_deref(), Some("ghe.example.com"));
+        assert_eq!(config.github.api_url, None);
+        assert_eq!(
+            config.github.token_command.as_deref(),
+            Some("pass show ghe")
+        );
+        assert!(parse_app_config("[github]\nhost = \"https://ghe.example.com\"\n").is_err());
+        assert!(parse_app_config("[github]\napi_url = \"ghe.example.com/api\"\n").is_err());
+        assert!(parse_app_config("[github]\napi_url = \"http://ghe.example.com/api\"\n").is_err());
+    }
+
+    #[test]
+    fn parses_refresh_interval() {
+        let config = parse_app_config("").unwrap();
+        assert_eq!(config.refresh.interval, None);
+
+        let config = parse_app_config("[refresh]\ninterval_secs = 120\n").unwrap();
+        assert_eq!(config.refresh.interval, Some(Duration::from_secs(120)));
+
+        let config = parse_app_config("[refresh]\ninterval_secs = 0\n").unwrap();
+        assert_eq!(config.refresh.interval, None);
+
+        assert!(parse_app_config("[refresh]\ninterval_secs = 5\n").is_err());
+    }
+
     #[test]
     fn rejects_legacy_color_fields() {
         let legacy = r#"
@@ -1,10 +1,11 @@
 //! Domain models shared across GitHub, search, and UI layers.
 
 use octocrab::models::{issues, pulls};
-use std::fmt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt};
 
 /// A lightweight pull request summary shown on the search screen.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequestSummary {
     pub owner: String,
     pub repo: String,
@@ -21,6 +22,9 @@ pub struct PullRequestSummary {
     pub is_draft: bool,
     pub reviewer_logins: Vec<String>,
     pub review_status: Option<PullRequestReviewStatus>,
+    /// GitHub's `totalCommentsCount`, when the list metadata query provided it.
+    #[serde(default)]
+    pub comment_count: Option<u64>,
 }
 
 impl PullRequestSummary {
@@ -40,7 +44,7 @@ impl PullRequestSummary {
 }
 
 /// Aggregate review state shown on the search list.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PullRequestReviewStatus {
     Approved,
     ChangesRequested,
@@ -56,7 +60,7 @@ pub type IssueComment = issues::Comment;
 pub type PullReviewSummary = pulls::Review;
 
 /// A hierarchical review thread rooted at a top-level review comment.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewThread {
     pub thread_id: Option<String>,
     pub is_resolved: bool,
@@ -64,8 +68,39 @@ pub struct ReviewThread {
     pub replies: Vec<ReviewThread>,
 }
 
+impl ReviewThread {
+    /// Adds the [`CommentRef::identity`] of this comment and all replies.
+    pub fn collect_identities(&self, identities: &mut HashSet<String>) {
+        identities.insert(format!("review:{}", self.comment.id));
+        for reply in &self.replies {
+            reply.collect_identities(identities);
+        }
+    }
+
+    /// Number of comments in the thread, replies included.
+    pub fn comment_count(&self) -> u64 {
+        1 + self.replies.iter().map(Self::comment_count).sum::<u64>()
+    }
+
+    fn collect_created_after(
+        &self,
+        since_unix_ms: i64,
+        viewer: Option<&str>,
+        identities: &mut HashSet<String>,
+    ) {
+        let author = self.comment.user.as_ref().map(|user| user.login.as_str());
+        if self.comment.created_at.timestamp_millis() > since_unix_ms && !is_viewer(author, viewer)
+        {
+            identities.insert(format!("review:{}", self.comment.id));
+        }
+        for reply in &self.replies {
+            reply.collect_created_after(since_unix_ms, viewer, identities);
+        }
+    }
+}
+
 /// A merged comment entry shown in the left pane.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PullRequestComment {
     ReviewThread(Box<ReviewThread>),
     IssueComment(Box<IssueComment>),
@@ -73,7 +108,7 @@ pub enum PullRequestComment {
 }
 
 /// All review data required for the review screen.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequestData {
     pub head_ref: String,
     pub base_ref: String,
@@ -81,6 +116,63 @@ pub struct PullRequestData {
     pub base_sha: String,
     pub changed_files: Vec<String>,
     pub comments: Vec<PullRequestComment>,
+    /// Missing from snapshots saved before descriptions were loaded.
+    #[serde(default)]
+    pub description: Option<PullRequestDescription>,
+}
+
+/// The pull request's own description and metadata.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PullRequestDescription {
+    pub author: String,
+    /// RFC 3339 creation time.
+    pub created_at: String,
+    pub body: String,
+    pub html_url: String,
+    pub labels: Vec<String>,
+    pub assignees: Vec<String>,
+    pub milestone: Option<String>,
+    pub reviewers: Vec<PullRequestReviewer>,
+    /// Issues that merging the pull request will close.
+    pub linked_issues: Vec<LinkedIssue>,
+}
+
+/// A requested reviewer or someone who has reviewed, with their latest verdict.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PullRequestReviewer {
+    /// User login, or `org/team` for team review requests.
+    pub login: String,
+    pub state: ReviewerState,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ReviewerState {
+    Requested,
+    Commented,
+    Approved,
+    ChangesRequested,
+    Dismissed,
+}
+
+impl ReviewerState {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Requested => "requested",
+            Self::Commented => "commented",
+            Self::Approved => "approved",
+            Self::ChangesRequested => "changes requested",
+            Self::Dismissed => "dismissed",
+        }
+    }
+}
+
+/// An issue closed by the pull request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedIssue {
+    /// `#123`, or `owner/repo#123` for issues in other repositories.
+    pub reference: String,
+    pub title: String,
+    pub is_open: bool,
 }
 
 impl PullRequestData {
@@ -99,16 +191,89 @@ impl PullRequestData {
 
         (resolved, total)
     }
+
+    /// [`CommentRef::identity`] of every comment, reply, and review summary.
+    pub fn comment_identities(&self) -> HashSet<String> {
+        let mut identities = HashSet::new();
+        for entry in &self.comments {
+            match entry {
+                PullRequestComment::ReviewThread(thread) => {
+                    thread.collect_identities(&mut identities);
+                }
+                PullRequestComment::IssueComment(comment) => {
+                    identities.insert(format!("issue:{}", comment.id));
+                }
+                PullRequestComment::ReviewSummary(review) => {
+                    identities.insert(format!("summary:{}", review.id));
+                }
+            }
+        }
+        identities
+    }
+
+    /// Conversation comments plus inline review comments, which is what
+    /// GitHub's `totalCommentsCount` counts; review summaries are left out.
+    pub fn comment_count(&self) -> u64 {
+        self.comments
+            .iter()
+            .map(|entry| match entry {
+                PullRequestComment::ReviewThread(thread) => thread.comment_count(),
+                PullRequestComment::IssueComment(_) => 1,
+                PullRequestComment::ReviewSummary(_) => 0,
+            })
+            .sum()
+    }
+
+    /// [`CommentRef::identity`] of comments created after `since_unix_ms`,
+    /// leaving out the viewer's own.
+    pub fn comments_created_after(
+        &self,
+        since_unix_ms: i64,
+        viewer: Option<&str>,
+    ) -> HashSet<String> {
+        let mut identities = HashSet::new();
+        for entry in &self.comments {
+            match entry {
+                PullRequestComment::ReviewThread(thread) => {
+                    thread.collect_created_after(since_unix_ms, viewer, &mut identities);
+                }
+                PullRequestComment::IssueComment(comment) => {
+                    if comment.created_at.timestamp_millis() > since_unix_ms
+                        && !is_viewer(Some(comment.user.login.as_str()), viewer)
+                    {
+                        identities.insert(format!("issue:{}", comment.id));
+                    }
+                }
+                PullRequestComment::ReviewSummary(review) => {
+                    let author = review.user.as_ref().map(|user| user.login.as_str());
+                    if review
+                        .submitted_at
+                        .is_some_and(|submitted| submitted.timestamp_millis() > since_unix_ms)
+                        && !is_viewer(author, viewer)
+                    {
+                        identities.insert(format!("summary:{}", review.id));
+                    }
+                }
+            }
+        }
+        identities
+    }
+}
+
+fn is_viewer(author: Option<&str>, viewer: Option<&str>) -> bool {
+    author
+        .zip(viewer)
+        .is_some_and(|(author, viewer)| author.eq_ignore_ascii_case(viewer))
 }
 
 /// A rendered pull request diff payload for the diff tab.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PullRequestDiffData {
     pub files: Vec<PullRequestDiffFile>,
 }
 
 /// A single changed file in the pull request diff.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequestDiffFile {
     pub path: String,
     pub status: PullRequestDiffFileStatus,
@@ -117,7 +282,7 @@ pub struct PullRequestDiffFile {
 }
 
 /// File-level status in the pull request diff.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PullRequestDiffFileStatus {
     Modified,
     Added,
@@ -125,7 +290,7 @@ pub enum PullRequestDiffFileStatus {
 }
 
 /// A single aligned diff row.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequestDiffRow {
     pub left_line_number: Option<usize>,
     pub right_line_number: Option<usize>,
@@ -137,7 +302,7 @@ pub struct PullRequestDiffRow {
 }
 
 /// Diff row styling category.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PullRequestDiffRowKind {
     Context,
     Added,
@@ -149,7 +314,7 @@ pub enum PullRequestDiffRowKind {
 ///
 /// `end` is exclusive for normal spans. When `end == FULL_LINE_END`, the
 /// range represents a full-line highlight.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PullRequestDiffHighlightRange {
     pub start: usize,
     pub end: usize,
@@ -187,6 +352,11 @@ pub enum ListNodeKind {
     Reply,
     Issue,
     Review,
+    Description,
+    /// A directory heading when threads are grouped by file.
+    Directory,
+    /// A file heading when threads are grouped by file.
+    File,
 }
 
 /// A flattened left-pane row for navigation/rendering.
@@ -207,6 +377,12 @@ pub enum CommentRef {
     Review(ReviewComment),
     Issue(IssueComment),
     ReviewSummary(PullReviewSummary),
+    Description(Box<PullRequestDescription>),
+    /// Heading for a directory or file; `label` is the part shown in the tree.
+    Path {
+        path: String,
+        label: String,
+    },
 }
 
 impl CommentRef {
@@ -223,6 +399,19 @@ impl CommentRef {
                 .as_ref()
                 .map(|user| user.login.as_str())
                 .unwrap_or("unknown"),
+            Self::Description(description) => description.author.as_str(),
+            Self::Path { .. } => "",
+        }
+    }
+
+    /// Stable key for the underlying comment, independent of how it is listed.
+    pub fn identity(&self) -> String {
+        match self {
+            Self::Review(comment) => format!("review:{}", comment.id),
+            Self::Issue(comment) => format!("issue:{}", comment.id),
+            Self::ReviewSummary(review) => format!("summary:{}", review.id),
+            Self::Description(_) => "description".to_owned(),
+            Self::Path { path, .. } => format!("path:{path}"),
         }
     }
 
@@ -231,6 +420,18 @@ impl CommentRef {
             Self::Review(comment) => comment.body.as_str(),
             Self::Issue(comment) => comment.body.as_deref().unwrap_or(""),
             Self::ReviewSummary(review) => review.body.as_deref().unwrap_or(""),
+            Self::Description(description) => description.body.as_str(),
+            Self::Path { .. } => "",
+        }
+    }
+
+    pub fn html_url(&self) -> &str {
+        match self {
+            Self::Review(comment) => comment.html_url.as_str(),
+            Self::Issue(comment) => comment.html_url.as_str(),
+            Self::ReviewSummary(review) => review.html_url.as_str(),
+            Self::Description(description) => description.html_url.as_str(),
+            Self::Path { .. } => "",
         }
     }
 }
@@ -249,6 +450,9 @@ impl fmt::Display for ListNodeKind {
             Self::Reply => write!(f, "reply"),
             Self::Issue => write!(f, "issue"),
             Self::Review => write!(f, "review"),
+            Self::Description => write!(f, "description"),
+            Self::Directory => write!(f, "directory"),
+            Self::File => write!(f, "file"),
         }
     }
 }
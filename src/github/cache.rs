@@ -0,0 +1,483 @@
+//! Persistent HTTP response cache with ETag / Last-Modified revalidation.
+//!
+//! GET responses carrying a validator are stored under `~/.critic/cache/http`
+//! and revalidated with conditional requests; GitHub answers unchanged
+//! resources with `304 Not Modified`, which does not count against the rate
+//! limit. GraphQL queries have no validators, so their latest responses are
+//! only replayed by a cache-only client.
+
+use crate::{
+    app::files::{critic_dir, write_atomically},
+    github::graphql::{GraphQlOperation, graphql_operation},
+};
+use bytes::Bytes;
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    header::{
+        ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        LAST_MODIFIED, TRANSFER_ENCODING,
+    },
+    request::Parts,
+};
+use http_body_util::{BodyExt, Full};
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+use tokio::fs;
+use tower::{Layer, Service, ServiceExt};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const CACHE_DIR: &str = "cache";
+const HTTP_CACHE_DIR: &str = "http";
+/// Entries not refreshed for this long are removed at startup.
+const MAX_ENTRY_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Size the cache is trimmed back to at startup, dropping the least recently
+/// written entries first.
+const MAX_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Whether requests may reach the network.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CacheMode {
+    /// Send requests, revalidating cached GET responses with their validators.
+    Revalidate,
+    /// Answer from the cache only; anything not cached fails with `504`.
+    CacheOnly,
+}
+
+/// Disk-backed store of response bodies keyed by request.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    root: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    /// Full request key, guarding against file name hash collisions.
+    request: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl ResponseCache {
+    /// Opens the cache under `~/.critic/cache/http`; without `HOME` nothing is cached.
+    pub fn new() -> Self {
+        let root = critic_dir()
+            .ok()
+            .map(|dir| dir.join(CACHE_DIR).join(HTTP_CACHE_DIR));
+        Self { root }
+    }
+
+    /// Removes entries that have not been written for [`MAX_ENTRY_AGE`], then
+    /// the oldest remaining ones while the cache is over [`MAX_CACHE_BYTES`].
+    pub async fn prune(&self) {
+        self.prune_to(MAX_CACHE_BYTES).await;
+    }
+
+    async fn prune_to(&self, max_bytes: u64) {
+        let Some(root) = self.root.as_ref() else {
+            return;
+        };
+        let Ok(mut entries) = fs::read_dir(root).await else {
+            return;
+        };
+        let mut kept = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let expired = SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age > MAX_ENTRY_AGE);
+            if expired {
+                let _ = fs::remove_file(entry.path()).await;
+            } else {
+                kept.push((modified, metadata.len(), entry.path()));
+            }
+        }
+
+        let mut total: u64 = kept.iter().map(|(_, len, _)| len).sum();
+        kept.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in kept {
+            if total <= max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).await.is_ok() {
+                total -= len;
+            }
+        }
+    }
+
+    async fn load(&self, key: &str) -> Option<CachedResponse> {
+        let path = self.root.as_ref()?.join(entry_file_name(key));
+        let raw = fs::read(&path).await.ok()?;
+        let entry: CachedResponse = serde_json::from_slice(&raw).ok()?;
+        (entry.request == key).then_some(entry)
+    }
+
+    /// Best-effort write; a failed write only costs a future full download.
+    async fn store(&self, entry: &CachedResponse) {
+        let Some(root) = self.root.as_ref() else {
+            return;
+        };
+        let Ok(raw) = serde_json::to_vec(entry) else {
+            return;
+        };
+        if fs::create_dir_all(root).await.is_err() {
+            return;
+        }
+        let path = root.join(entry_file_name(&entry.request));
+        let _ = write_atomically(&path, &raw).await;
+    }
+}
+
+/// Layer that serves and revalidates responses through a [`ResponseCache`].
+#[derive(Debug, Clone)]
+pub struct HttpCacheLayer {
+    cache: ResponseCache,
+    mode: CacheMode,
+}
+
+impl HttpCacheLayer {
+    pub fn new(cache: ResponseCache, mode: CacheMode) -> Self {
+        Self { cache, mode }
+    }
+}
+
+impl<S> Layer<S> for HttpCacheLayer {
+    type Service = HttpCache<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpCache {
+            inner,
+            cache: self.cache.clone(),
+            mode: self.mode,
+        }
+    }
+}
+
+/// Service produced by [`HttpCacheLayer`].
+#[derive(Debug, Clone)]
+pub struct HttpCache<S> {
+    inner: S,
+    cache: ResponseCache,
+    mode: CacheMode,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for HttpCache<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError>,
+    ReqBody: http_body::Body + From<Bytes> + Send + 'static,
+    ReqBody::Data: Send,
+    ReqBody::Error: Into<BoxError>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.mode == CacheMode::CacheOnly {
+            return Poll::Ready(Ok(()));
+        }
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let cache = self.cache.clone();
+        let mode = self.mode;
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+            let key = cache_key(&parts, &body);
+            let cached = match key.as_deref() {
+                Some(key) => cache.load(key).await,
+                None => None,
+            };
+
+            if mode == CacheMode::CacheOnly {
+                return Ok(cached
+                    .map(|entry| cached_response(entry, None))
+                    .unwrap_or_else(offline_response));
+            }
+
+            if parts.method == Method::GET
+                && let Some(entry) = cached.as_ref()
+            {
+                add_validators(&mut parts.headers, entry);
+            }
+
+            let mut request = Request::new(ReqBody::from(body));
+            *request.method_mut() = parts.method;
+            *request.uri_mut() = parts.uri;
+            *request.version_mut() = parts.version;
+            *request.headers_mut() = parts.headers;
+            *request.extensions_mut() = parts.extensions;
+
+            let response = inner.oneshot(request).await.map_err(Into::into)?;
+            let (parts, body) = response.into_parts();
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+
+            if parts.status == StatusCode::NOT_MODIFIED
+                && let Some(entry) = cached
+            {
+                return Ok(cached_response(entry, Some(&parts.headers)));
+            }
+
+            if parts.status == StatusCode::OK
+                && let Some(key) = key
+                && let Some(entry) = cacheable_entry(key, &parts.headers, &body)
+            {
+                cache.store(&entry).await;
+            }
+
+            Ok(Response::from_parts(parts, Full::new(body)))
+        })
+    }
+}
+
+/// Key for requests whose responses may be cached: GETs, and GraphQL queries
+/// (which are POSTs) keyed by their body.
+fn cache_key(parts: &Parts, body: &Bytes) -> Option<String> {
+    let accept = parts
+        .headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    match parts.method {
+        Method::GET => Some(format!("GET {} {accept}", parts.uri)),
+        Method::POST if graphql_operation(parts, body) == Some(GraphQlOperation::Query) => {
+            Some(format!("POST {} {accept} {:016x}", parts.uri, fnv1a(body)))
+        }
+        _ => None,
+    }
+}
+
+/// Builds the entry to store, or `None` for responses that cannot be revalidated
+/// or replayed.
+fn cacheable_entry(request: String, headers: &HeaderMap, body: &Bytes) -> Option<CachedResponse> {
+    let body = String::from_utf8(body.to_vec()).ok()?;
+    let is_graphql = request.starts_with("POST ");
+    if is_graphql {
+        // Partial GraphQL results are not worth replaying.
+        if body.contains("\"errors\":") {
+            return None;
+        }
+    } else if !headers.contains_key(ETAG) && !headers.contains_key(LAST_MODIFIED) {
+        return None;
+    }
+
+    let headers = headers
+        .iter()
+        .filter(|(name, _)| is_replayed_header(name))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect();
+    Some(CachedResponse {
+        request,
+        headers,
+        body,
+    })
+}
+
+fn add_validators(headers: &mut HeaderMap, entry: &CachedResponse) {
+    for (name, value) in &entry.headers {
+        let conditional = if name.eq_ignore_ascii_case(ETAG.as_str()) {
+            IF_NONE_MATCH
+        } else if name.eq_ignore_ascii_case(LAST_MODIFIED.as_str()) {
+            IF_MODIFIED_SINCE
+        } else {
+            continue;
+        };
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(conditional, value);
+        }
+    }
+}
+
+/// Rebuilds a `200` from a cached entry, taking fresher headers (rate limits,
+/// validators) from the `304` that confirmed it.
+fn cached_response(
+    entry: CachedResponse,
+    revalidated: Option<&HeaderMap>,
+) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(entry.body)));
+    let headers = response.headers_mut();
+    for (name, value) in entry.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    if let Some(fresh) = revalidated {
+        for (name, value) in fresh {
+            if is_replayed_header(name) {
+                headers.insert(name, value.clone());
+            }
+        }
+    }
+    response
+}
+
+fn offline_response() -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from_static(
+        br#"{"message":"not available offline"}"#,
+    )));
+    *response.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// Framing headers describe the original transfer, not the replayed body.
+fn is_replayed_header(name: &HeaderName) -> bool {
+    name != CONTENT_LENGTH && name != TRANSFER_ENCODING
+}
+
+fn entry_file_name(key: &str) -> String {
+    format!("{:016x}.json", fnv1a(key.as_bytes()))
+}
+
+/// Stable 64-bit FNV-1a hash used for cache file names.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CachedResponse, ResponseCache, add_validators, cache_key, cacheable_entry, cached_response,
+    };
+    use bytes::Bytes;
+    use http::{HeaderMap, HeaderValue, Method, Request, header};
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    fn parts(method: Method, uri: &str) -> http::request::Parts {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::ACCEPT, "application/vnd.github.v3+json")
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    #[test]
+    fn caches_gets_and_graphql_queries_only() {
+        let empty = Bytes::new();
+        assert!(cache_key(&parts(Method::GET, "https://api.github.com/user"), &empty).is_some());
+        assert!(
+            cache_key(
+                &parts(
+                    Method::POST,
+                    "https://api.github.com/repos/a/b/pulls/1/reviews"
+                ),
+                &empty
+            )
+            .is_none()
+        );
+
+        let graphql = parts(Method::POST, "https://api.github.com/graphql");
+        let first = cache_key(&graphql, &Bytes::from_static(b"{\"query\":\"query A\"}"));
+        let second = cache_key(&graphql, &Bytes::from_static(b"{\"query\":\"query B\"}"));
+        assert!(first.is_some());
+        assert_ne!(first, second);
+        assert!(cache_key(&graphql, &Bytes::from_static(b"{\"query\":\"mutation M\"}")).is_none());
+    }
+
+    #[test]
+    fn revalidates_with_stored_validators() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("W/\"abc\""));
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("2"));
+        headers.insert(
+            "link",
+            HeaderValue::from_static("<https://next>; rel=\"next\""),
+        );
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("10"));
+        let entry = cacheable_entry("GET /user".to_owned(), &headers, &Bytes::from_static(b"[]"))
+            .expect("responses with an etag are cached");
+        assert!(
+            entry
+                .headers
+                .iter()
+                .all(|(name, _)| name != "content-length")
+        );
+
+        let mut request = HeaderMap::new();
+        add_validators(&mut request, &entry);
+        assert_eq!(request[header::IF_NONE_MATCH], "W/\"abc\"");
+
+        let mut fresh = HeaderMap::new();
+        fresh.insert("x-ratelimit-remaining", HeaderValue::from_static("9"));
+        let response = cached_response(entry, Some(&fresh));
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "9");
+        assert_eq!(response.headers()["link"], "<https://next>; rel=\"next\"");
+    }
+
+    #[test]
+    fn skips_unvalidated_and_failed_responses() {
+        let body = Bytes::from_static(b"{\"data\":null,\"errors\":[]}");
+        assert!(cacheable_entry("GET /user".to_owned(), &HeaderMap::new(), &body).is_none());
+        assert!(cacheable_entry("POST /graphql".to_owned(), &HeaderMap::new(), &body).is_none());
+
+        let entry: Option<CachedResponse> = cacheable_entry(
+            "POST /graphql".to_owned(),
+            &HeaderMap::new(),
+            &Bytes::from_static(b"{\"data\":{}}"),
+        );
+        assert!(entry.is_some());
+    }
+
+    #[tokio::test]
+    async fn prune_drops_the_oldest_entries_over_the_size_limit() {
+        let root = std::env::temp_dir().join(format!("critic-cache-prune-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old", 30), ("middle", 20), ("new", 10)] {
+            let path = root.join(format!("{name}.json"));
+            fs::write(&path, [0u8; 100]).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        let cache = ResponseCache {
+            root: Some(root.clone()),
+        };
+        cache.prune_to(250).await;
+        assert!(!root.join("old.json").exists());
+        assert!(root.join("middle.json").exists());
+        assert!(root.join("new.json").exists());
+
+        cache.prune_to(100).await;
+        assert!(!root.join("middle.json").exists());
+        assert!(root.join("new.json").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
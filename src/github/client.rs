@@ -1,12 +1,41 @@
 //! Authenticated Octocrab client bootstrap.
 
+use crate::github::{
+    cache::{CacheMode, HttpCacheLayer, ResponseCache},
+    ratelimit::{RateLimitLayer, RateLimitTracker},
+};
+use http::{
+    HeaderValue, Uri,
+    header::{AUTHORIZATION, USER_AGENT},
+};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use octocrab::{
+    AuthState, OctocrabBuilder,
+    service::middleware::{base_uri::BaseUriLayer, extra_headers::ExtraHeadersLayer},
+};
 use secrecy::{ExposeSecret, SecretString};
+use std::{
+    env,
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 use thiserror::Error;
 use tokio::process::Command;
+use tower_http::follow_redirect::FollowRedirectLayer;
 
 /// Result type for GitHub client bootstrap.
 pub type Result<T> = std::result::Result<T, GitHubClientError>;
 
+/// Hostname of the public GitHub instance.
+pub const GITHUB_COM: &str = "github.com";
+
 /// Errors returned while loading a token and creating an Octocrab client.
 #[derive(Debug, Error)]
 pub enum GitHubClientError {
@@ -14,27 +43,343 @@ pub enum GitHubClientError {
     GhNotAvailable(std::io::Error),
     #[error("`gh auth token` failed with status {status}: {stderr}")]
     GhAuthFailed { status: i32, stderr: String },
-    #[error("`gh auth token` returned an empty token")]
-    InvalidToken,
-    #[error("failed to initialize octocrab client: {0}")]
-    Octocrab(octocrab::Error),
+    #[error("failed to run token command `{command}`: {source}")]
+    TokenCommandUnavailable {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("token command `{command}` failed with status {status}: {stderr}")]
+    TokenCommandFailed {
+        command: String,
+        status: i32,
+        stderr: String,
+    },
+    #[error("`{0}` returned an empty token")]
+    InvalidToken(String),
+    #[error("invalid GitHub API URL `{0}`")]
+    InvalidApiUrl(String),
+    #[error("token contains characters that are not valid in an HTTP header")]
+    InvalidTokenCharacters,
+    #[error("failed to load TLS root certificates: {0}")]
+    Tls(std::io::Error),
+}
+
+/// Where the client connects and how it finds a token.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClientOptions {
+    /// GitHub host, e.g. `github.example.com`. Falls back to `GH_HOST`, then github.com.
+    pub host: Option<String>,
+    /// REST API base URL; defaults to `https://<host>/api/v3` for Enterprise hosts.
+    pub api_url: Option<String>,
+    /// Shell command that prints a token, tried after the token environment variables.
+    pub token_command: Option<String>,
+}
+
+/// Resolved GitHub host along with its API endpoints.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GitHubHost {
+    name: String,
+    api_url: String,
+}
+
+impl GitHubHost {
+    pub fn new(host: Option<&str>, api_url: Option<&str>) -> Self {
+        let name = host
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .unwrap_or(GITHUB_COM)
+            .trim_start_matches("https://")
+            .trim_end_matches('/')
+            .to_ascii_lowercase();
+        let api_url = match api_url.map(str::trim).filter(|url| !url.is_empty()) {
+            Some(url) => url.trim_end_matches('/').to_owned(),
+            None if name == GITHUB_COM => "https://api.github.com".to_owned(),
+            None => format!("https://{name}/api/v3"),
+        };
+        Self { name, api_url }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_github_com(&self) -> bool {
+        self.name == GITHUB_COM
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Base URL that `/graphql` is appended to. Enterprise serves GraphQL from
+    /// `/api/graphql` rather than under the `/api/v3` REST prefix.
+    pub fn graphql_base_url(&self) -> &str {
+        self.api_url
+            .strip_suffix("/v3")
+            .unwrap_or(self.api_url.as_str())
+    }
+
+    /// Base URL for web pages on this host.
+    pub fn web_url(&self) -> String {
+        format!("https://{}", self.name)
+    }
+
+    /// HTTPS clone URL for `owner/repo`.
+    pub fn clone_url(&self, owner: &str, repo: &str) -> String {
+        format!("{}/{owner}/{repo}.git", self.web_url())
+    }
 }
 
-/// Builds an authenticated Octocrab client from `gh auth token`.
-pub async fn create_client() -> Result<octocrab::Octocrab> {
-    let token = gh_auth_token().await?;
+/// Octocrab client bound to one GitHub host.
+///
+/// Dereferences to the REST client; [`GitHubClient::graphql`] routes queries
+/// to the host's GraphQL endpoint. Both go through the on-disk response cache.
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    live: Arc<Endpoints>,
+    cached: Arc<Endpoints>,
+    cache_only: bool,
+    host: GitHubHost,
+    token: SecretString,
+    rate_limits: RateLimitTracker,
+    /// Set once the host rejected `fullDatabaseId`; shared by clones.
+    legacy_database_ids: Arc<AtomicBool>,
+}
 
-    octocrab::OctocrabBuilder::new()
-        .personal_token(token.expose_secret().to_owned())
-        .build()
-        .map_err(GitHubClientError::Octocrab)
+/// REST and GraphQL clients sharing one cache mode.
+#[derive(Debug, Clone)]
+struct Endpoints {
+    rest: octocrab::Octocrab,
+    graphql: octocrab::Octocrab,
 }
 
-/// Returns the active GitHub token from the `gh` CLI.
-pub async fn gh_auth_token() -> Result<SecretString> {
+impl Deref for GitHubClient {
+    type Target = octocrab::Octocrab;
+
+    fn deref(&self) -> &Self::Target {
+        &self.endpoints().rest
+    }
+}
+
+impl GitHubClient {
+    pub fn host(&self) -> &GitHubHost {
+        &self.host
+    }
+
+    /// Sends a GraphQL query to the host's GraphQL endpoint.
+    pub async fn graphql<R: octocrab::FromResponse>(
+        &self,
+        payload: &(impl serde::Serialize + ?Sized),
+    ) -> octocrab::Result<R> {
+        self.endpoints().graphql.graphql(payload).await
+    }
+
+    /// A client that answers from the response cache without touching the
+    /// network; uncached requests fail with status 504.
+    pub fn cache_only(&self) -> Self {
+        Self {
+            cache_only: true,
+            ..self.clone()
+        }
+    }
+
+    pub fn token(&self) -> &SecretString {
+        &self.token
+    }
+
+    /// Quota reported by the most recent responses.
+    pub fn rate_limits(&self) -> &RateLimitTracker {
+        &self.rate_limits
+    }
+
+    /// Whether the host lacks `fullDatabaseId`, as older GitHub Enterprise
+    /// Server releases do, so queries select `databaseId` instead.
+    pub fn uses_legacy_database_ids(&self) -> bool {
+        self.legacy_database_ids.load(Ordering::Relaxed)
+    }
+
+    /// Records that the host rejected `fullDatabaseId`.
+    pub fn set_legacy_database_ids(&self) {
+        self.legacy_database_ids.store(true, Ordering::Relaxed);
+    }
+
+    fn endpoints(&self) -> &Endpoints {
+        if self.cache_only {
+            &self.cached
+        } else {
+            &self.live
+        }
+    }
+}
+
+/// The configured host, falling back to `GH_HOST` and then github.com.
+pub fn resolve_host(options: &ClientOptions) -> GitHubHost {
+    let env_host = env::var("GH_HOST").ok();
+    GitHubHost::new(
+        options.host.as_deref().or(env_host.as_deref()),
+        options.api_url.as_deref(),
+    )
+}
+
+/// Builds an authenticated client for the configured host.
+pub async fn create_client(options: &ClientOptions) -> Result<GitHubClient> {
+    let host = resolve_host(options);
+    let token = resolve_token(&host, options.token_command.as_deref()).await?;
+
+    let cache = ResponseCache::new();
+    tokio::spawn({
+        let cache = cache.clone();
+        async move { cache.prune().await }
+    });
+
+    let connector = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .map_err(GitHubClientError::Tls)?
+        .https_only()
+        .enable_http1()
+        .build();
+    let rate_limits = RateLimitTracker::default();
+    let endpoints = |mode| -> Result<Endpoints> {
+        let stack = Stack {
+            connector: &connector,
+            rate_limits: &rate_limits,
+            cache: HttpCacheLayer::new(cache.clone(), mode),
+        };
+        let rest = build_octocrab(&token, host.api_url(), &stack)?;
+        let graphql = if host.graphql_base_url() == host.api_url() {
+            rest.clone()
+        } else {
+            build_octocrab(&token, host.graphql_base_url(), &stack)?
+        };
+        Ok(Endpoints { rest, graphql })
+    };
+    let live = Arc::new(endpoints(CacheMode::Revalidate)?);
+    let cached = Arc::new(endpoints(CacheMode::CacheOnly)?);
+
+    Ok(GitHubClient {
+        live,
+        cached,
+        cache_only: false,
+        host,
+        token,
+        rate_limits,
+        legacy_database_ids: Arc::default(),
+    })
+}
+
+/// Shared pieces of every Octocrab middleware stack.
+struct Stack<'a> {
+    connector: &'a HttpsConnector<HttpConnector>,
+    rate_limits: &'a RateLimitTracker,
+    cache: HttpCacheLayer,
+}
+
+/// Builds an Octocrab client whose requests pass through [`HttpCacheLayer`]
+/// and [`RateLimitLayer`].
+fn build_octocrab(
+    token: &SecretString,
+    base_url: &str,
+    stack: &Stack<'_>,
+) -> Result<octocrab::Octocrab> {
+    let base_uri: Uri = base_url
+        .parse()
+        .map_err(|_| GitHubClientError::InvalidApiUrl(base_url.to_owned()))?;
+    let mut authorization = HeaderValue::from_str(&format!("Bearer {}", token.expose_secret()))
+        .map_err(|_| GitHubClientError::InvalidTokenCharacters)?;
+    authorization.set_sensitive(true);
+
+    let http = Client::builder(TokioExecutor::new()).build(stack.connector.clone());
+
+    let client = OctocrabBuilder::new_empty()
+        .with_service(http)
+        .with_layer(&RateLimitLayer::new(stack.rate_limits.clone()))
+        .with_layer(&FollowRedirectLayer::new())
+        .with_layer(&stack.cache)
+        .with_layer(&BaseUriLayer::new(base_uri))
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(vec![
+            (USER_AGENT, HeaderValue::from_static("critic")),
+            (AUTHORIZATION, authorization),
+        ])))
+        .with_auth(AuthState::None)
+        .build();
+    Ok(match client {
+        Ok(client) => client,
+        Err(never) => match never {},
+    })
+}
+
+/// Environment variables checked for a token, in order.
+///
+/// github.com tokens are never sent to other hosts: an Enterprise host only
+/// reads the Enterprise variables.
+fn token_env_vars(host: &GitHubHost) -> &'static [&'static str] {
+    if host.is_github_com() {
+        &["GH_TOKEN", "GITHUB_TOKEN"]
+    } else {
+        &["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"]
+    }
+}
+
+/// Returns the first non-empty token among `host`'s environment variables.
+fn token_from_env(host: &GitHubHost, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    token_env_vars(host)
+        .iter()
+        .filter_map(|name| var(name))
+        .map(|token| token.trim().to_owned())
+        .find(|token| !token.is_empty())
+}
+
+/// Finds a token from the environment, then `token_command`, then `gh auth token`.
+async fn resolve_token(host: &GitHubHost, token_command: Option<&str>) -> Result<SecretString> {
+    if let Some(token) = token_from_env(host, |name| env::var(name).ok()) {
+        return Ok(SecretString::from(token));
+    }
+
+    match token_command
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+    {
+        Some(command) => run_token_command(command).await,
+        None => gh_auth_token(host).await,
+    }
+}
+
+async fn run_token_command(command: &str) -> Result<SecretString> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let output = shell.arg(command).output().await.map_err(|source| {
+        GitHubClientError::TokenCommandUnavailable {
+            command: command.to_owned(),
+            source,
+        }
+    })?;
+
+    if !output.status.success() {
+        return Err(GitHubClientError::TokenCommandFailed {
+            command: command.to_owned(),
+            status: output.status.code().unwrap_or(-1),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+
+    parse_token(&output.stdout, command)
+}
+
+/// Returns the `gh` CLI token for `host`.
+async fn gh_auth_token(host: &GitHubHost) -> Result<SecretString> {
     let output = Command::new("gh")
         .arg("auth")
         .arg("token")
+        .arg("--hostname")
+        .arg(host.name())
         .output()
         .await
         .map_err(GitHubClientError::GhNotAvailable)?;
@@ -47,10 +392,71 @@ pub async fn gh_auth_token() -> Result<SecretString> {
         });
     }
 
-    let token = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    parse_token(&output.stdout, "gh auth token")
+}
+
+fn parse_token(stdout: &[u8], source: &str) -> Result<SecretString> {
+    let token = String::from_utf8_lossy(stdout).trim().to_owned();
     if token.is_empty() {
-        return Err(GitHubClientError::InvalidToken);
+        return Err(GitHubClientError::InvalidToken(source.to_owned()));
     }
 
     Ok(SecretString::from(token))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GitHubHost, token_from_env};
+
+    #[test]
+    fn derives_enterprise_endpoints_from_host() {
+        let public = GitHubHost::new(None, None);
+        assert!(public.is_github_com());
+        assert_eq!(public.api_url(), "https://api.github.com");
+        assert_eq!(public.graphql_base_url(), "https://api.github.com");
+        assert_eq!(
+            public.clone_url("acme", "widgets"),
+            "https://github.com/acme/widgets.git"
+        );
+
+        let enterprise = GitHubHost::new(Some("GHE.example.com/"), None);
+        assert_eq!(enterprise.name(), "ghe.example.com");
+        assert_eq!(enterprise.api_url(), "https://ghe.example.com/api/v3");
+        assert_eq!(enterprise.graphql_base_url(), "https://ghe.example.com/api");
+        assert_eq!(
+            enterprise.clone_url("acme", "widgets"),
+            "https://ghe.example.com/acme/widgets.git"
+        );
+
+        let custom = GitHubHost::new(Some("ghe.example.com"), Some("https://api.ghe.test/"));
+        assert_eq!(custom.api_url(), "https://api.ghe.test");
+        assert_eq!(custom.graphql_base_url(), "https://api.ghe.test");
+    }
+
+    #[test]
+    fn github_com_tokens_are_not_used_for_enterprise_hosts() {
+        let public = GitHubHost::new(None, None);
+        let enterprise = GitHubHost::new(Some("ghe.example.com"), None);
+        let github_token = |name: &str| (name == "GH_TOKEN").then(|| "public".to_owned());
+
+        assert_eq!(
+            token_from_env(&public, github_token).as_deref(),
+            Some("public")
+        );
+        assert_eq!(token_from_env(&enterprise, github_token), None);
+
+        let enterprise_token = |name: &str| match name {
+            "GH_TOKEN" => Some("public".to_owned()),
+            "GITHUB_ENTERPRISE_TOKEN" => Some(" enterprise ".to_owned()),
+            _ => None,
+        };
+        assert_eq!(
+            token_from_env(&enterprise, enterprise_token).as_deref(),
+            Some("enterprise")
+        );
+        assert_eq!(
+            token_from_env(&public, enterprise_token).as_deref(),
+            Some("public")
+        );
+    }
+}
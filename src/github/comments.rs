@@ -2,21 +2,25 @@
 
 use crate::{
     domain::{
-        IssueComment, PullRequestComment, PullRequestData, PullRequestSummary, PullReviewSummary,
-        ReviewComment, ReviewThread,
+        IssueComment, LinkedIssue, PullRequestComment, PullRequestData, PullRequestDescription,
+        PullRequestReviewer, PullRequestSummary, PullReviewSummary, ReviewComment, ReviewThread,
+        ReviewerState,
+    },
+    github::{
+        client::{GitHubClient, GitHubHost},
+        errors::{format_octocrab_error, is_unreachable_error},
     },
-    github::errors::format_octocrab_error,
 };
 use octocrab::models::{CommentId, pulls};
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
 use thiserror::Error;
 
 /// Result type for pull request comment loading.
 pub type Result<T> = std::result::Result<T, PullRequestCommentsError>;
 
 /// Inline review comment payload staged before review submission.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubmitReviewComment {
     pub path: String,
     pub body: String,
@@ -43,8 +47,13 @@ pub struct SubmitPullRequestReviewRequest<'a> {
 pub enum PullRequestCommentsError {
     #[error("GitHub API request failed: {0}")]
     Octocrab(String),
+    #[error("GitHub is unreachable: {0}")]
+    Unreachable(String),
     #[error("graphql response error: {0}")]
     GraphQlResponseError(String),
+    /// The schema has no `field`, e.g. one added after the host's release.
+    #[error("graphql response error: {message}")]
+    UndefinedField { field: String, message: String },
     #[error(
         "pull request was updated since last refresh (loaded {loaded_head_sha}, current {current_head_sha}); refresh before submitting"
     )]
@@ -58,9 +67,20 @@ pub enum PullRequestCommentsError {
     UnexpectedReviewState { event: String, state: String },
 }
 
+impl PullRequestCommentsError {
+    /// Whether the request can be retried once connectivity returns.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, Self::Unreachable(_))
+    }
+}
+
 impl From<octocrab::Error> for PullRequestCommentsError {
     fn from(error: octocrab::Error) -> Self {
-        Self::Octocrab(format_octocrab_error(error))
+        if is_unreachable_error(&error) {
+            Self::Unreachable(format_octocrab_error(error))
+        } else {
+            Self::Octocrab(format_octocrab_error(error))
+        }
     }
 }
 
@@ -73,36 +93,196 @@ struct GraphQlPageInfo {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GraphQlCommentNode {
+struct GraphQlConnection<T> {
+    nodes: Vec<T>,
+    page_info: GraphQlPageInfo,
+}
+
+/// Any `Actor`: users and bots, but also mannequins, organizations and kinds
+/// added later, so every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct GraphQlActor {
+    #[serde(rename = "__typename")]
+    typename: String,
+    login: String,
+    avatar_url: String,
+    url: String,
     database_id: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GraphQlReviewComments {
-    nodes: Vec<GraphQlCommentNode>,
-    page_info: GraphQlPageInfo,
+struct GraphQlDatabaseRef {
+    #[serde(
+        default,
+        alias = "databaseId",
+        deserialize_with = "deserialize_big_int"
+    )]
+    full_database_id: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlCommitRef {
+    oid: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GraphQlReviewThreadNode {
+struct GraphQlReviewComment {
+    id: String,
+    #[serde(
+        default,
+        alias = "databaseId",
+        deserialize_with = "deserialize_big_int"
+    )]
+    full_database_id: Option<u64>,
+    body: String,
+    path: String,
+    diff_hunk: String,
+    url: String,
+    created_at: String,
+    updated_at: String,
+    author_association: Option<String>,
+    line: Option<u64>,
+    original_line: Option<u64>,
+    start_line: Option<u64>,
+    original_start_line: Option<u64>,
+    commit: Option<GraphQlCommitRef>,
+    original_commit: Option<GraphQlCommitRef>,
+    reply_to: Option<GraphQlDatabaseRef>,
+    pull_request_review: Option<GraphQlDatabaseRef>,
+    author: Option<GraphQlActor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlReviewThread {
     id: String,
     is_resolved: bool,
-    comments: GraphQlReviewComments,
+    diff_side: Option<String>,
+    start_diff_side: Option<String>,
+    comments: GraphQlConnection<GraphQlReviewComment>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GraphQlReviewThreads {
-    nodes: Vec<GraphQlReviewThreadNode>,
-    page_info: GraphQlPageInfo,
+struct GraphQlIssueComment {
+    id: String,
+    #[serde(
+        default,
+        alias = "databaseId",
+        deserialize_with = "deserialize_big_int"
+    )]
+    full_database_id: Option<u64>,
+    body: String,
+    url: String,
+    created_at: String,
+    updated_at: Option<String>,
+    author_association: Option<String>,
+    author: Option<GraphQlActor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlReview {
+    id: String,
+    #[serde(
+        default,
+        alias = "databaseId",
+        deserialize_with = "deserialize_big_int"
+    )]
+    full_database_id: Option<u64>,
+    body: String,
+    state: String,
+    url: String,
+    submitted_at: Option<String>,
+    author_association: Option<String>,
+    commit: Option<GraphQlCommitRef>,
+    author: Option<GraphQlActor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlChangedFile {
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlNodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlNamed {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlLogin {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlMilestone {
+    title: String,
+}
+
+/// A requested user (`login`) or team (`combinedSlug`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRequestedReviewer {
+    login: Option<String>,
+    combined_slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlReviewRequest {
+    requested_reviewer: Option<GraphQlRequestedReviewer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRepositoryName {
+    name_with_owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlClosingIssue {
+    number: u64,
+    title: String,
+    state: String,
+    repository: GraphQlRepositoryName,
+}
+
+/// Description fields, only present on the first page of the query.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlPullRequestDetails {
+    body: Option<String>,
+    url: Option<String>,
+    created_at: Option<String>,
+    author: Option<GraphQlActor>,
+    milestone: Option<GraphQlMilestone>,
+    labels: Option<GraphQlNodes<GraphQlNamed>>,
+    assignees: Option<GraphQlNodes<GraphQlLogin>>,
+    review_requests: Option<GraphQlNodes<GraphQlReviewRequest>>,
+    closing_issues_references: Option<GraphQlNodes<GraphQlClosingIssue>>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GraphQlPullRequest {
-    review_threads: GraphQlReviewThreads,
+    head_ref_name: String,
+    base_ref_name: String,
+    head_ref_oid: String,
+    base_ref_oid: String,
+    files: Option<GraphQlConnection<GraphQlChangedFile>>,
+    review_threads: Option<GraphQlConnection<GraphQlReviewThread>>,
+    comments: Option<GraphQlConnection<GraphQlIssueComment>>,
+    reviews: Option<GraphQlConnection<GraphQlReview>>,
+    #[serde(flatten)]
+    details: GraphQlPullRequestDetails,
 }
 
 #[derive(Debug, Deserialize)]
@@ -112,44 +292,161 @@ struct GraphQlRepository {
 }
 
 #[derive(Debug, Deserialize)]
-struct GraphQlData {
+struct GraphQlPullRequestData {
     repository: Option<GraphQlRepository>,
 }
 
 #[derive(Debug, Deserialize)]
-struct GraphQlResponse {
-    data: Option<GraphQlData>,
+struct GraphQlThreadComments {
+    comments: Option<GraphQlConnection<GraphQlReviewComment>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlThreadCommentsData {
+    node: Option<GraphQlThreadComments>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
     errors: Option<Vec<GraphQlError>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GraphQlError {
     message: String,
+    #[serde(default)]
+    extensions: GraphQlErrorExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct GraphQlErrorExtensions {
+    code: Option<String>,
+    field_name: Option<String>,
+}
+
+/// Fields shared by the pull request and thread comment queries.
+const GRAPHQL_FRAGMENTS: &str = r#"
+fragment ActorFields on Actor {
+  __typename
+  login
+  avatarUrl
+  url
+  ... on User { databaseId }
+  ... on Bot { databaseId }
+  ... on Mannequin { databaseId }
+  ... on Organization { databaseId }
 }
 
-const REVIEW_THREADS_RESOLUTION_QUERY: &str = r#"
-query PullRequestReviewThreadResolution($owner: String!, $repo: String!, $pullNumber: Int!, $after: String) {
+fragment ReviewCommentFields on PullRequestReviewComment {
+  id
+  fullDatabaseId
+  body
+  path
+  diffHunk
+  url
+  createdAt
+  updatedAt
+  authorAssociation
+  line
+  originalLine
+  startLine
+  originalStartLine
+  commit { oid }
+  originalCommit { oid }
+  replyTo { fullDatabaseId }
+  pullRequestReview { fullDatabaseId }
+  author { ...ActorFields }
+}
+"#;
+
+/// Loads refs plus one page of every connection still flagged with `$with*`.
+const PULL_REQUEST_DATA_QUERY: &str = r#"
+query PullRequestData(
+  $owner: String!
+  $repo: String!
+  $pullNumber: Int!
+  $withFiles: Boolean!
+  $filesAfter: String
+  $withThreads: Boolean!
+  $threadsAfter: String
+  $withComments: Boolean!
+  $commentsAfter: String
+  $withReviews: Boolean!
+  $reviewsAfter: String
+  $withDescription: Boolean!
+) {
   repository(owner: $owner, name: $repo) {
     pullRequest(number: $pullNumber) {
-      reviewThreads(first: 100, after: $after) {
-        pageInfo {
-          hasNextPage
-          endCursor
+      headRefName
+      baseRefName
+      headRefOid
+      baseRefOid
+      ... @include(if: $withDescription) {
+        body
+        url
+        createdAt
+        author { ...ActorFields }
+        milestone { title }
+        labels(first: 50) { nodes { name } }
+        assignees(first: 50) { nodes { login } }
+        reviewRequests(first: 50) {
+          nodes {
+            requestedReviewer {
+              ... on Actor { login }
+              ... on Team { combinedSlug }
+            }
+          }
+        }
+        closingIssuesReferences(first: 25) {
+          nodes { number title state repository { nameWithOwner } }
         }
+      }
+      files(first: 100, after: $filesAfter) @include(if: $withFiles) {
+        pageInfo { hasNextPage endCursor }
+        nodes { path }
+      }
+      reviewThreads(first: 50, after: $threadsAfter) @include(if: $withThreads) {
+        pageInfo { hasNextPage endCursor }
         nodes {
           id
           isResolved
-          comments(first: 100) {
-            pageInfo {
-              hasNextPage
-              endCursor
-            }
-            nodes {
-              databaseId
-            }
+          diffSide
+          startDiffSide
+          comments(first: 50) {
+            pageInfo { hasNextPage endCursor }
+            nodes { ...ReviewCommentFields }
           }
         }
       }
+      comments(first: 100, after: $commentsAfter) @include(if: $withComments) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          id
+          fullDatabaseId
+          body
+          url
+          createdAt
+          updatedAt
+          authorAssociation
+          author { ...ActorFields }
+        }
+      }
+      reviews(first: 100, after: $reviewsAfter) @include(if: $withReviews) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          id
+          fullDatabaseId
+          body
+          state
+          url
+          submittedAt
+          authorAssociation
+          commit { oid }
+          author { ...ActorFields }
+        }
+      }
     }
   }
 }
@@ -160,19 +457,33 @@ query PullRequestReviewThreadComments($threadId: ID!, $after: String) {
   node(id: $threadId) {
     ... on PullRequestReviewThread {
       comments(first: 100, after: $after) {
-        pageInfo {
-          hasNextPage
-          endCursor
-        }
-        nodes {
-          databaseId
-        }
+        pageInfo { hasNextPage endCursor }
+        nodes { ...ReviewCommentFields }
       }
     }
   }
 }
 "#;
 
+/// Pagination state for one connection of [`PULL_REQUEST_DATA_QUERY`].
+#[derive(Debug, Default)]
+struct PageCursor {
+    after: Option<String>,
+    done: bool,
+}
+
+impl PageCursor {
+    /// Records the page just received; a missing connection ends pagination.
+    fn advance(&mut self, page_info: Option<GraphQlPageInfo>) {
+        match page_info {
+            Some(page_info) if page_info.has_next_page && page_info.end_cursor.is_some() => {
+                self.after = page_info.end_cursor;
+            }
+            _ => self.done = true,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct BuildNode {
     comment: ReviewComment,
@@ -180,67 +491,275 @@ struct BuildNode {
 }
 
 /// Fetches all comment data for a pull request and returns merged review/issue entries.
+///
+/// Everything comes from one GraphQL query that is re-issued with per-connection
+/// cursors until files, review threads, issue comments and reviews are exhausted.
+/// The description is only requested with the first page.
 pub async fn fetch_pull_request_data(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     pull: &PullRequestSummary,
 ) -> Result<PullRequestData> {
-    let (changed_files_set, review_threads, issue_comments, review_summaries, pull_state) = tokio::try_join!(
-        pull_request_file_paths(client, &pull.owner, &pull.repo, pull.number),
-        list_review_comment_threads(client, &pull.owner, &pull.repo, pull.number),
-        list_issue_comments(client, &pull.owner, &pull.repo, pull.number),
-        list_pull_review_summary_comments(client, &pull.owner, &pull.repo, pull.number),
-        async {
-            client
-                .pulls(&pull.owner, &pull.repo)
-                .get(pull.number)
-                .await
-                .map_err(PullRequestCommentsError::from)
-        },
-    )?;
+    let host = client.host();
+    let mut files = PageCursor::default();
+    let mut threads = PageCursor::default();
+    let mut comments = PageCursor::default();
+    let mut reviews = PageCursor::default();
+
+    let mut refs = None;
+    let mut description: Option<PullRequestDescription> = None;
+    let mut reviewed: Vec<PullRequestReviewer> = Vec::new();
+    let mut changed_files = Vec::new();
+    let mut merged: Vec<(i64, PullRequestComment)> = Vec::new();
+
+    while !(files.done && threads.done && comments.done && reviews.done) {
+        let data: GraphQlPullRequestData = graphql_query_with_ids(
+            client,
+            &format!("{PULL_REQUEST_DATA_QUERY}{GRAPHQL_FRAGMENTS}"),
+            serde_json::json!({
+                "owner": pull.owner,
+                "repo": pull.repo,
+                "pullNumber": pull.number,
+                "withFiles": !files.done,
+                "filesAfter": files.after,
+                "withThreads": !threads.done,
+                "threadsAfter": threads.after,
+                "withComments": !comments.done,
+                "commentsAfter": comments.after,
+                "withReviews": !reviews.done,
+                "reviewsAfter": reviews.after,
+                "withDescription": description.is_none(),
+            }),
+        )
+        .await?;
+        let Some(pull_request) = data
+            .repository
+            .and_then(|repository| repository.pull_request)
+        else {
+            return Err(PullRequestCommentsError::GraphQlResponseError(
+                "missing pull request data in GraphQL response".to_owned(),
+            ));
+        };
 
-    let mut changed_files: Vec<String> = changed_files_set.into_iter().collect();
-    changed_files.sort();
+        refs.get_or_insert((
+            pull_request.head_ref_name,
+            pull_request.base_ref_name,
+            pull_request.head_ref_oid,
+            pull_request.base_ref_oid,
+        ));
+        if description.is_none() {
+            description = Some(description_from_node(pull_request.details, pull));
+        }
 
-    let mut merged: Vec<(i64, PullRequestComment)> = review_threads
-        .into_iter()
-        .map(|thread| {
-            (
-                thread.comment.created_at.timestamp_millis(),
-                PullRequestComment::ReviewThread(Box::new(thread)),
-            )
-        })
-        .chain(issue_comments.into_iter().map(|comment| {
-            (
-                comment.created_at.timestamp_millis(),
-                PullRequestComment::IssueComment(Box::new(comment)),
-            )
-        }))
-        .chain(review_summaries.into_iter().map(|review| {
-            (
-                review
-                    .submitted_at
-                    .map(|value| value.timestamp_millis())
-                    .unwrap_or_default(),
-                PullRequestComment::ReviewSummary(Box::new(review)),
-            )
-        }))
-        .collect();
+        if !files.done {
+            let page = pull_request.files.map(|connection| {
+                changed_files.extend(connection.nodes.into_iter().map(|file| file.path));
+                connection.page_info
+            });
+            files.advance(page);
+        }
+
+        if !threads.done {
+            let mut page = None;
+            if let Some(connection) = pull_request.review_threads {
+                for mut thread in connection.nodes {
+                    if thread.comments.page_info.has_next_page {
+                        let rest = fetch_remaining_thread_comments(
+                            client,
+                            &thread.id,
+                            thread.comments.page_info.end_cursor.take(),
+                        )
+                        .await?;
+                        thread.comments.nodes.extend(rest);
+                    }
+                    for thread in review_threads_from_node(thread, host)? {
+                        merged.push((
+                            thread.comment.created_at.timestamp_millis(),
+                            PullRequestComment::ReviewThread(Box::new(thread)),
+                        ));
+                    }
+                }
+                page = Some(connection.page_info);
+            }
+            threads.advance(page);
+        }
+
+        if !comments.done {
+            let mut page = None;
+            if let Some(connection) = pull_request.comments {
+                for comment in connection.nodes {
+                    let comment = issue_comment_from_node(comment, host)?;
+                    merged.push((
+                        comment.created_at.timestamp_millis(),
+                        PullRequestComment::IssueComment(Box::new(comment)),
+                    ));
+                }
+                page = Some(connection.page_info);
+            }
+            comments.advance(page);
+        }
+
+        if !reviews.done {
+            let mut page = None;
+            if let Some(connection) = pull_request.reviews {
+                for review in connection.nodes {
+                    if let Some(author) = review.author.as_ref()
+                        && !author.login.is_empty()
+                    {
+                        record_review_state(&mut reviewed, &author.login, &review.state);
+                    }
+                    if review.body.trim().is_empty() {
+                        continue;
+                    }
+                    let review = review_from_node(review, host)?;
+                    merged.push((
+                        review
+                            .submitted_at
+                            .map(|value| value.timestamp_millis())
+                            .unwrap_or_default(),
+                        PullRequestComment::ReviewSummary(Box::new(review)),
+                    ));
+                }
+                page = Some(connection.page_info);
+            }
+            reviews.advance(page);
+        }
+    }
 
+    changed_files.sort();
+    changed_files.dedup();
     merged.sort_by_key(|entry| entry.0);
+    if let Some(description) = description.as_mut() {
+        let requested = std::mem::take(&mut description.reviewers);
+        description.reviewers = merge_reviewers(reviewed, requested, &description.author);
+    }
 
+    let (head_ref, base_ref, head_sha, base_sha) = refs.unwrap_or_default();
     Ok(PullRequestData {
-        head_ref: pull_state.head.ref_field,
-        base_ref: pull_state.base.ref_field,
-        head_sha: pull_state.head.sha,
-        base_sha: pull_state.base.sha,
+        head_ref,
+        base_ref,
+        head_sha,
+        base_sha,
         changed_files,
         comments: merged.into_iter().map(|(_, entry)| entry).collect(),
+        description,
     })
 }
 
+/// Builds the description from the first page; `reviewers` holds only the
+/// pending review requests until the reviews have all been read.
+fn description_from_node(
+    details: GraphQlPullRequestDetails,
+    pull: &PullRequestSummary,
+) -> PullRequestDescription {
+    let repository = format!("{}/{}", pull.owner, pull.repo);
+    PullRequestDescription {
+        author: details
+            .author
+            .map(|author| author.login)
+            .filter(|login| !login.is_empty())
+            .unwrap_or_else(|| "ghost".to_owned()),
+        created_at: details.created_at.unwrap_or_default(),
+        body: details.body.unwrap_or_default(),
+        html_url: details
+            .url
+            .or_else(|| pull.html_url.clone())
+            .unwrap_or_default(),
+        labels: optional_nodes(details.labels)
+            .into_iter()
+            .map(|label| label.name)
+            .collect(),
+        assignees: optional_nodes(details.assignees)
+            .into_iter()
+            .map(|assignee| assignee.login)
+            .collect(),
+        milestone: details.milestone.map(|milestone| milestone.title),
+        reviewers: optional_nodes(details.review_requests)
+            .into_iter()
+            .filter_map(|request| {
+                let reviewer = request.requested_reviewer?;
+                Some(PullRequestReviewer {
+                    login: reviewer.login.or(reviewer.combined_slug)?,
+                    state: ReviewerState::Requested,
+                })
+            })
+            .collect(),
+        linked_issues: optional_nodes(details.closing_issues_references)
+            .into_iter()
+            .map(|issue| LinkedIssue {
+                reference: if issue
+                    .repository
+                    .name_with_owner
+                    .eq_ignore_ascii_case(&repository)
+                {
+                    format!("#{}", issue.number)
+                } else {
+                    format!("{}#{}", issue.repository.name_with_owner, issue.number)
+                },
+                title: issue.title,
+                is_open: issue.state == "OPEN",
+            })
+            .collect(),
+    }
+}
+
+fn optional_nodes<T>(connection: Option<GraphQlNodes<T>>) -> Vec<T> {
+    connection.map_or_else(Vec::new, |connection| connection.nodes)
+}
+
+/// Folds a submitted review into its author's latest verdict. A plain comment
+/// does not replace an earlier approval or change request.
+fn record_review_state(reviewers: &mut Vec<PullRequestReviewer>, login: &str, state: &str) {
+    let state = match state {
+        "APPROVED" => ReviewerState::Approved,
+        "CHANGES_REQUESTED" => ReviewerState::ChangesRequested,
+        "COMMENTED" => ReviewerState::Commented,
+        "DISMISSED" => ReviewerState::Dismissed,
+        _ => return,
+    };
+    match reviewers
+        .iter_mut()
+        .find(|reviewer| reviewer.login == login)
+    {
+        Some(reviewer) => {
+            let keeps_verdict = state == ReviewerState::Commented
+                && matches!(
+                    reviewer.state,
+                    ReviewerState::Approved | ReviewerState::ChangesRequested
+                );
+            if !keeps_verdict {
+                reviewer.state = state;
+            }
+        }
+        None => reviewers.push(PullRequestReviewer {
+            login: login.to_owned(),
+            state,
+        }),
+    }
+}
+
+/// Lists everyone who reviewed, then pending requests. A pending request wins
+/// over an earlier review since the author has asked for another look.
+fn merge_reviewers(
+    reviewed: Vec<PullRequestReviewer>,
+    requested: Vec<PullRequestReviewer>,
+    author: &str,
+) -> Vec<PullRequestReviewer> {
+    let mut merged: Vec<PullRequestReviewer> = reviewed
+        .into_iter()
+        .filter(|reviewer| {
+            reviewer.login != author
+                && !requested
+                    .iter()
+                    .any(|request| request.login == reviewer.login)
+        })
+        .collect();
+    merged.extend(requested);
+    merged
+}
+
 /// Replies to an existing review comment.
 pub async fn reply_to_review_comment(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     owner: &str,
     repo: &str,
     pull_number: u64,
@@ -257,7 +776,7 @@ pub async fn reply_to_review_comment(
 
 /// Resolves or unresolves a review thread by GraphQL thread id.
 pub async fn set_review_thread_resolved(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     thread_id: &str,
     resolved: bool,
 ) -> Result<()> {
@@ -304,7 +823,7 @@ mutation UnresolveReviewThread($threadId: ID!) {
 
 /// Submits a pull request review with `COMMENT`, `APPROVE`, or `REQUEST_CHANGES`.
 pub async fn submit_pull_request_review(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     request: SubmitPullRequestReviewRequest<'_>,
 ) -> Result<()> {
     let SubmitPullRequestReviewRequest {
@@ -397,74 +916,6 @@ impl From<&SubmitReviewComment> for SubmitReviewCommentRequest {
     }
 }
 
-async fn list_review_comment_threads(
-    client: &octocrab::Octocrab,
-    owner: &str,
-    repo: &str,
-    pull_number: u64,
-) -> Result<Vec<ReviewThread>> {
-    let first_page = client
-        .pulls(owner, repo)
-        .list_comments(Some(pull_number))
-        .per_page(100)
-        .send()
-        .await?;
-    let mapped = client.all_pages(first_page).await?;
-
-    let resolved_by_comment_id =
-        review_thread_resolution_map(client, owner, repo, pull_number).await?;
-
-    let mut threads = build_review_threads(mapped);
-    for thread in &mut threads {
-        apply_thread_resolution(thread, &resolved_by_comment_id);
-        if thread.thread_id.is_none() && thread.replies.is_empty() {
-            set_thread_resolved_without_id(thread);
-        }
-    }
-
-    Ok(threads)
-}
-
-async fn list_issue_comments(
-    client: &octocrab::Octocrab,
-    owner: &str,
-    repo: &str,
-    pull_number: u64,
-) -> Result<Vec<IssueComment>> {
-    let first_page = client
-        .issues(owner, repo)
-        .list_comments(pull_number)
-        .per_page(100)
-        .send()
-        .await?;
-    client.all_pages(first_page).await.map_err(Into::into)
-}
-
-async fn list_pull_review_summary_comments(
-    client: &octocrab::Octocrab,
-    owner: &str,
-    repo: &str,
-    pull_number: u64,
-) -> Result<Vec<PullReviewSummary>> {
-    let first_page = client
-        .pulls(owner, repo)
-        .list_reviews(pull_number)
-        .per_page(100)
-        .send()
-        .await?;
-    let reviews = client.all_pages(first_page).await?;
-
-    Ok(reviews
-        .into_iter()
-        .filter(|review| {
-            review
-                .body
-                .as_deref()
-                .is_some_and(|body| !body.trim().is_empty())
-        })
-        .collect())
-}
-
 fn build_review_threads(comments: Vec<ReviewComment>) -> Vec<ReviewThread> {
     let mut nodes: HashMap<u64, BuildNode> = HashMap::with_capacity(comments.len());
     let mut parent_links: Vec<(u64, Option<u64>)> = Vec::with_capacity(comments.len());
@@ -518,211 +969,338 @@ fn materialize_thread(id: u64, nodes: &mut HashMap<u64, BuildNode>) -> Option<Re
     })
 }
 
-fn apply_thread_resolution(
-    thread: &mut ReviewThread,
-    resolved_by_comment_id: &HashMap<u64, (bool, String)>,
-) {
-    if let Some((is_resolved, thread_id)) = find_thread_resolution(thread, resolved_by_comment_id) {
-        set_thread_resolution(thread, is_resolved, &thread_id);
+fn set_thread_resolution(thread: &mut ReviewThread, is_resolved: bool, thread_id: &str) {
+    thread.is_resolved = is_resolved;
+    thread.thread_id = Some(thread_id.to_owned());
+    for reply in &mut thread.replies {
+        set_thread_resolution(reply, is_resolved, thread_id);
     }
 }
 
-fn find_thread_resolution(
-    thread: &ReviewThread,
-    resolved_by_comment_id: &HashMap<u64, (bool, String)>,
-) -> Option<(bool, String)> {
-    if let Some((is_resolved, thread_id)) =
-        resolved_by_comment_id.get(&thread.comment.id.into_inner())
-    {
-        return Some((*is_resolved, thread_id.clone()));
-    }
-
-    for reply in &thread.replies {
-        if let Some(found) = find_thread_resolution(reply, resolved_by_comment_id) {
-            return Some(found);
+/// Runs a query selecting `fullDatabaseId`, falling back to the older 32-bit
+/// `databaseId` on hosts that do not have the field.
+async fn graphql_query_with_ids<T: DeserializeOwned>(
+    client: &GitHubClient,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<T> {
+    if !client.uses_legacy_database_ids() {
+        match graphql_query(client, query, variables.clone()).await {
+            Err(PullRequestCommentsError::UndefinedField { field, .. })
+                if field == "fullDatabaseId" =>
+            {
+                client.set_legacy_database_ids();
+            }
+            result => return result,
         }
     }
+    graphql_query(client, &legacy_id_query(query), variables).await
+}
 
-    None
+fn legacy_id_query(query: &str) -> String {
+    query.replace("fullDatabaseId", "databaseId")
 }
 
-fn set_thread_resolution(thread: &mut ReviewThread, is_resolved: bool, thread_id: &str) {
-    thread.is_resolved = is_resolved;
-    thread.thread_id = Some(thread_id.to_owned());
-    for reply in &mut thread.replies {
-        set_thread_resolution(reply, is_resolved, thread_id);
+/// Sends a GraphQL request and returns its `data`, surfacing any reported errors.
+async fn graphql_query<T: DeserializeOwned>(
+    client: &GitHubClient,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<T> {
+    let response: GraphQlResponse<T> = client
+        .graphql(&serde_json::json!({
+            "query": query,
+            "variables": variables,
+        }))
+        .await?;
+
+    if let Some(errors) = response.errors
+        && !errors.is_empty()
+    {
+        return Err(graphql_errors(errors));
     }
+
+    response.data.ok_or_else(|| {
+        PullRequestCommentsError::GraphQlResponseError(
+            "missing data in GraphQL response".to_owned(),
+        )
+    })
 }
 
-fn set_thread_resolved_without_id(thread: &mut ReviewThread) {
-    thread.is_resolved = true;
-    for reply in &mut thread.replies {
-        set_thread_resolved_without_id(reply);
+/// Folds reported errors into one, keeping an undefined field apart so
+/// callers can retry without it.
+fn graphql_errors(errors: Vec<GraphQlError>) -> PullRequestCommentsError {
+    let undefined_field = errors.iter().find_map(|error| {
+        (error.extensions.code.as_deref() == Some("undefinedField"))
+            .then(|| error.extensions.field_name.clone())
+            .flatten()
+    });
+    let message = errors
+        .into_iter()
+        .map(|error| error.message)
+        .collect::<Vec<_>>()
+        .join("; ");
+    match undefined_field {
+        Some(field) => PullRequestCommentsError::UndefinedField { field, message },
+        None => PullRequestCommentsError::GraphQlResponseError(message),
     }
 }
 
-async fn review_thread_resolution_map(
-    client: &octocrab::Octocrab,
-    owner: &str,
-    repo: &str,
-    pull_number: u64,
-) -> Result<HashMap<u64, (bool, String)>> {
-    let mut after: Option<String> = None;
-    let mut resolved_by_comment_id = HashMap::new();
-
-    loop {
-        let response: GraphQlResponse = client
-            .graphql(&serde_json::json!({
-                "query": REVIEW_THREADS_RESOLUTION_QUERY,
-                "variables": {
-                    "owner": owner,
-                    "repo": repo,
-                    "pullNumber": pull_number,
-                    "after": after,
-                }
-            }))
-            .await?;
-
-        if let Some(errors) = response.errors
-            && !errors.is_empty()
-        {
-            let message = errors
-                .into_iter()
-                .map(|error| error.message)
-                .collect::<Vec<_>>()
-                .join("; ");
-            return Err(PullRequestCommentsError::GraphQlResponseError(message));
-        }
+/// Loads the comments of a thread that did not fit in the first nested page.
+async fn fetch_remaining_thread_comments(
+    client: &GitHubClient,
+    thread_id: &str,
+    mut after: Option<String>,
+) -> Result<Vec<GraphQlReviewComment>> {
+    let mut comments = Vec::new();
 
-        let Some(review_threads) = response
-            .data
-            .and_then(|data| data.repository)
-            .and_then(|repository| repository.pull_request)
-            .map(|pull_request| pull_request.review_threads)
-        else {
+    while let Some(cursor) = after {
+        let data: GraphQlThreadCommentsData = graphql_query_with_ids(
+            client,
+            &format!("{REVIEW_THREAD_COMMENTS_QUERY}{GRAPHQL_FRAGMENTS}"),
+            serde_json::json!({
+                "threadId": thread_id,
+                "after": cursor,
+            }),
+        )
+        .await?;
+        let Some(connection) = data.node.and_then(|node| node.comments) else {
             return Err(PullRequestCommentsError::GraphQlResponseError(
-                "missing review thread data in GraphQL response".to_owned(),
+                "missing review thread comments data in GraphQL response".to_owned(),
             ));
         };
 
-        for thread in &review_threads.nodes {
-            let mut comment_ids = thread
-                .comments
-                .nodes
-                .iter()
-                .filter_map(|comment| comment.database_id)
-                .collect::<Vec<_>>();
-
-            if thread.comments.page_info.has_next_page {
-                comment_ids.extend(
-                    fetch_remaining_thread_comment_ids(
-                        client,
-                        &thread.id,
-                        thread.comments.page_info.end_cursor.clone(),
-                    )
-                    .await?,
-                );
-            }
+        comments.extend(connection.nodes);
+        after = connection
+            .page_info
+            .end_cursor
+            .filter(|_| connection.page_info.has_next_page);
+    }
 
-            for comment_id in comment_ids {
-                resolved_by_comment_id.insert(comment_id, (thread.is_resolved, thread.id.clone()));
-            }
-        }
+    Ok(comments)
+}
 
-        if !review_threads.page_info.has_next_page {
-            break;
-        }
+/// Converts a GraphQL review thread into reply trees carrying its id and resolution.
+///
+/// A thread normally yields one tree; replies whose parent was deleted become
+/// extra roots that still belong to the same thread.
+fn review_threads_from_node(
+    thread: GraphQlReviewThread,
+    host: &GitHubHost,
+) -> Result<Vec<ReviewThread>> {
+    let GraphQlReviewThread {
+        id,
+        is_resolved,
+        diff_side,
+        start_diff_side,
+        comments,
+    } = thread;
 
-        after = review_threads.page_info.end_cursor;
+    let comments = comments
+        .nodes
+        .into_iter()
+        .map(|comment| {
+            review_comment_from_node(
+                comment,
+                diff_side.as_deref(),
+                start_diff_side.as_deref(),
+                host,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut threads = build_review_threads(comments);
+    for thread in &mut threads {
+        set_thread_resolution(thread, is_resolved, &id);
     }
 
-    Ok(resolved_by_comment_id)
+    Ok(threads)
 }
 
-async fn fetch_remaining_thread_comment_ids(
-    client: &octocrab::Octocrab,
-    thread_id: &str,
-    mut after: Option<String>,
-) -> Result<Vec<u64>> {
-    let mut comment_ids = Vec::new();
+fn review_comment_from_node(
+    comment: GraphQlReviewComment,
+    side: Option<&str>,
+    start_side: Option<&str>,
+    host: &GitHubHost,
+) -> Result<ReviewComment> {
+    let commit_id = comment.commit.map(|commit| commit.oid).unwrap_or_default();
+    let original_commit_id = comment
+        .original_commit
+        .map(|commit| commit.oid)
+        .unwrap_or_else(|| commit_id.clone());
+
+    from_graphql_node(
+        "review comment",
+        serde_json::json!({
+            "url": comment.url,
+            "id": comment.full_database_id,
+            "node_id": comment.id,
+            "pull_request_review_id": comment
+                .pull_request_review
+                .and_then(|review| review.full_database_id),
+            "diff_hunk": comment.diff_hunk,
+            "path": comment.path,
+            "position": null,
+            "original_position": null,
+            "commit_id": commit_id,
+            "original_commit_id": original_commit_id,
+            "in_reply_to_id": comment.reply_to.and_then(|parent| parent.full_database_id),
+            "user": comment.author.as_ref().map(|author| author_value(author, host)),
+            "body": comment.body,
+            "created_at": comment.created_at,
+            "updated_at": comment.updated_at,
+            "html_url": comment.url,
+            "author_association": comment.author_association,
+            "_links": {},
+            "start_line": comment.start_line,
+            "original_start_line": comment.original_start_line,
+            "start_side": comment.start_line.and(start_side),
+            "line": comment.line,
+            "original_line": comment.original_line,
+            "side": side,
+        }),
+    )
+}
 
-    while let Some(cursor) = after {
-        let response: serde_json::Value = client
-            .graphql(&serde_json::json!({
-                "query": REVIEW_THREAD_COMMENTS_QUERY,
-                "variables": {
-                    "threadId": thread_id,
-                    "after": cursor,
-                }
-            }))
-            .await?;
-
-        if let Some(errors) = response.get("errors").and_then(|value| value.as_array())
-            && !errors.is_empty()
-        {
-            let message = errors
-                .iter()
-                .filter_map(|value| value.get("message").and_then(|message| message.as_str()))
-                .collect::<Vec<_>>()
-                .join("; ");
-            return Err(PullRequestCommentsError::GraphQlResponseError(message));
-        }
+fn issue_comment_from_node(
+    comment: GraphQlIssueComment,
+    host: &GitHubHost,
+) -> Result<IssueComment> {
+    let user = match comment.author.as_ref() {
+        Some(author) => author_value(author, host),
+        None => ghost_author_value(host),
+    };
 
-        let comments = response
-            .get("data")
-            .and_then(|value| value.get("node"))
-            .and_then(|value| value.get("comments"))
-            .ok_or_else(|| {
-                PullRequestCommentsError::GraphQlResponseError(
-                    "missing review thread comments data in GraphQL response".to_owned(),
-                )
-            })?;
-
-        if let Some(nodes) = comments.get("nodes").and_then(|value| value.as_array()) {
-            comment_ids.extend(
-                nodes
-                    .iter()
-                    .filter_map(|node| node.get("databaseId").and_then(|id| id.as_u64())),
-            );
-        }
+    from_graphql_node(
+        "issue comment",
+        serde_json::json!({
+            "id": comment.full_database_id,
+            "node_id": comment.id,
+            "url": comment.url,
+            "html_url": comment.url,
+            "body": comment.body,
+            "author_association": comment.author_association,
+            "user": user,
+            "created_at": comment.created_at,
+            "updated_at": comment.updated_at,
+        }),
+    )
+}
 
-        let page_info = comments.get("pageInfo").ok_or_else(|| {
-            PullRequestCommentsError::GraphQlResponseError(
-                "missing review thread comments page info in GraphQL response".to_owned(),
-            )
-        })?;
-        let has_next_page = page_info
-            .get("hasNextPage")
-            .and_then(|value| value.as_bool())
-            .unwrap_or(false);
-        if has_next_page {
-            after = page_info
-                .get("endCursor")
-                .and_then(|value| value.as_str())
-                .map(str::to_owned);
-        } else {
-            break;
-        }
+fn review_from_node(review: GraphQlReview, host: &GitHubHost) -> Result<PullReviewSummary> {
+    from_graphql_node(
+        "review",
+        serde_json::json!({
+            "id": review.full_database_id,
+            "node_id": review.id,
+            "html_url": review.url,
+            "user": review.author.as_ref().map(|author| author_value(author, host)),
+            "body": review.body,
+            "commit_id": review.commit.map(|commit| commit.oid),
+            "state": review.state,
+            "submitted_at": review.submitted_at,
+            "author_association": review.author_association,
+        }),
+    )
+}
+
+/// Builds the REST user shape from a GraphQL actor.
+///
+/// The API URLs are not queried, so they all point at the actor's profile.
+/// Actors without a login or an absolute profile URL are shown as `ghost`
+/// rather than failing the load; a missing avatar falls back to the profile.
+fn author_value(author: &GraphQlActor, host: &GitHubHost) -> serde_json::Value {
+    if author.login.is_empty() || !is_absolute_url(&author.url) {
+        return ghost_author_value(host);
     }
+    let avatar_url = if is_absolute_url(&author.avatar_url) {
+        &author.avatar_url
+    } else {
+        &author.url
+    };
+    let kind = if author.typename.is_empty() {
+        "User"
+    } else {
+        &author.typename
+    };
 
-    Ok(comment_ids)
+    serde_json::json!({
+        "login": author.login,
+        "id": author.database_id.unwrap_or_default(),
+        "node_id": "",
+        "avatar_url": avatar_url,
+        "gravatar_id": "",
+        "url": author.url,
+        "html_url": author.url,
+        "followers_url": author.url,
+        "following_url": author.url,
+        "gists_url": author.url,
+        "starred_url": author.url,
+        "subscriptions_url": author.url,
+        "organizations_url": author.url,
+        "repos_url": author.url,
+        "events_url": author.url,
+        "received_events_url": author.url,
+        "type": kind,
+        "site_admin": false,
+    })
 }
 
-async fn pull_request_file_paths(
-    client: &octocrab::Octocrab,
-    owner: &str,
-    repo: &str,
-    pull_number: u64,
-) -> Result<HashSet<String>> {
-    let first_page = client.pulls(owner, repo).list_files(pull_number).await?;
-    let files = client.all_pages(first_page).await?;
+/// Stand-in for comments whose author account was deleted.
+fn ghost_author_value(host: &GitHubHost) -> serde_json::Value {
+    let url = format!("{}/ghost", host.web_url());
+    author_value(
+        &GraphQlActor {
+            typename: "User".to_owned(),
+            login: "ghost".to_owned(),
+            avatar_url: url.clone(),
+            url,
+            database_id: None,
+        },
+        host,
+    )
+}
+
+fn is_absolute_url(value: &str) -> bool {
+    value.starts_with("https://") || value.starts_with("http://")
+}
 
-    Ok(files.into_iter().map(|file| file.filename).collect())
+fn from_graphql_node<T: DeserializeOwned>(kind: &str, value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|error| {
+        PullRequestCommentsError::GraphQlResponseError(format!("unexpected {kind} shape: {error}"))
+    })
+}
+
+/// Accepts GraphQL `BigInt` ids, which are encoded as strings.
+fn deserialize_big_int<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BigInt {
+        Number(u64),
+        Text(String),
+    }
+
+    match Option::<BigInt>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(BigInt::Number(value)) => Ok(Some(value)),
+        Some(BigInt::Text(text)) => text.parse().map(Some).map_err(serde::de::Error::custom),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ReviewComment, build_review_threads};
+    use super::{
+        GRAPHQL_FRAGMENTS, GraphQlError, PULL_REQUEST_DATA_QUERY, PullRequestCommentsError,
+        ReviewComment, build_review_threads, graphql_errors, legacy_id_query, merge_reviewers,
+        record_review_state, review_threads_from_node,
+    };
+    use crate::{
+        domain::{PullRequestReviewer, ReviewerState},
+        github::client::GitHubHost,
+    };
     use serde_json::json;
 
     fn review_comment(id: u64, in_reply_to_id: Option<u64>) -> ReviewComment {
@@ -768,4 +1346,224 @@ mod tests {
         assert_eq!(threads.len(), 1);
         assert_eq!(threads[0].comment.id.into_inner(), 5);
     }
+
+    fn thread_node(comments: Vec<serde_json::Value>) -> super::GraphQlReviewThread {
+        serde_json::from_value(json!({
+            "id": "PRRT_1",
+            "isResolved": true,
+            "diffSide": "RIGHT",
+            "startDiffSide": null,
+            "comments": {
+                "pageInfo": { "hasNextPage": false, "endCursor": null },
+                "nodes": comments
+            }
+        }))
+        .expect("valid GraphQL review thread fixture")
+    }
+
+    fn comment_node(
+        id: &str,
+        reply_to: Option<&str>,
+        author: serde_json::Value,
+    ) -> serde_json::Value {
+        json!({
+            "id": format!("PRRC_{id}"),
+            "fullDatabaseId": id,
+            "body": format!("comment {id}"),
+            "path": "src/lib.rs",
+            "diffHunk": "@@ -1,1 +1,2 @@",
+            "url": format!("https://github.com/acme/widgets/pull/1#discussion_r{id}"),
+            "createdAt": "2026-02-01T00:00:00Z",
+            "updatedAt": "2026-02-01T00:00:00Z",
+            "authorAssociation": "MEMBER",
+            "line": 2,
+            "originalLine": 2,
+            "startLine": null,
+            "originalStartLine": null,
+            "commit": { "oid": "deadbeef" },
+            "originalCommit": null,
+            "replyTo": reply_to.map(|parent| json!({ "fullDatabaseId": parent })),
+            "pullRequestReview": { "fullDatabaseId": "77" },
+            "author": author
+        })
+    }
+
+    #[test]
+    fn maps_graphql_thread_with_id_and_resolution() {
+        let author = json!({
+            "__typename": "User",
+            "login": "octocat",
+            "avatarUrl": "https://avatars.example.invalid/u/1",
+            "url": "https://github.com/octocat",
+            "databaseId": 1
+        });
+        let thread = thread_node(vec![
+            comment_node("3000000001", None, author.clone()),
+            comment_node("3000000002", Some("3000000001"), author),
+        ]);
+
+        let threads = review_threads_from_node(thread, &GitHubHost::new(None, None))
+            .expect("thread maps to domain models");
+
+        assert_eq!(threads.len(), 1);
+        let root = &threads[0];
+        assert_eq!(root.thread_id.as_deref(), Some("PRRT_1"));
+        assert!(root.is_resolved);
+        assert_eq!(root.comment.id.into_inner(), 3_000_000_001);
+        assert_eq!(root.comment.side.as_deref(), Some("RIGHT"));
+        assert_eq!(root.comment.original_commit_id, "deadbeef");
+        assert_eq!(
+            root.comment
+                .pull_request_review_id
+                .map(|id| id.into_inner()),
+            Some(77)
+        );
+        assert_eq!(
+            root.comment.user.as_ref().map(|user| user.login.as_str()),
+            Some("octocat")
+        );
+        assert_eq!(root.replies.len(), 1);
+        assert_eq!(root.replies[0].thread_id.as_deref(), Some("PRRT_1"));
+        assert!(root.replies[0].is_resolved);
+    }
+
+    #[test]
+    fn legacy_hosts_get_database_ids_instead() {
+        let query = legacy_id_query(&format!("{PULL_REQUEST_DATA_QUERY}{GRAPHQL_FRAGMENTS}"));
+        assert!(!query.contains("fullDatabaseId"));
+        assert!(query.contains("replyTo { databaseId }"));
+
+        let author = json!({ "__typename": "User", "login": "octocat", "databaseId": 1 });
+        let legacy = comment_node("9", None, author)
+            .to_string()
+            .replace("\"fullDatabaseId\":\"9\"", "\"databaseId\":9")
+            .replace("\"fullDatabaseId\":\"77\"", "\"databaseId\":77");
+        assert!(!legacy.contains("fullDatabaseId"));
+        let thread = thread_node(vec![serde_json::from_str(&legacy).expect("valid JSON")]);
+        let threads = review_threads_from_node(thread, &GitHubHost::new(None, None))
+            .expect("thread maps to domain models");
+
+        assert_eq!(threads[0].comment.id.into_inner(), 9);
+        assert_eq!(
+            threads[0]
+                .comment
+                .pull_request_review_id
+                .map(|id| id.into_inner()),
+            Some(77)
+        );
+    }
+
+    #[test]
+    fn only_undefined_field_errors_name_the_field() {
+        let errors: Vec<GraphQlError> = serde_json::from_value(json!([{
+            "message": "Field 'fullDatabaseId' doesn't exist on type 'IssueComment'",
+            "extensions": {
+                "code": "undefinedField",
+                "typeName": "IssueComment",
+                "fieldName": "fullDatabaseId"
+            }
+        }]))
+        .expect("valid GraphQL errors");
+        assert!(matches!(
+            graphql_errors(errors),
+            PullRequestCommentsError::UndefinedField { field, .. } if field == "fullDatabaseId"
+        ));
+
+        let errors: Vec<GraphQlError> = serde_json::from_value(json!([{
+            "message": "Could not resolve fullDatabaseId for comment",
+            "type": "INTERNAL"
+        }]))
+        .expect("valid GraphQL errors");
+        assert!(matches!(
+            graphql_errors(errors),
+            PullRequestCommentsError::GraphQlResponseError(_)
+        ));
+    }
+
+    #[test]
+    fn unusual_actor_kinds_do_not_fail_the_load() {
+        let thread = thread_node(vec![
+            comment_node(
+                "1",
+                None,
+                json!({
+                    "__typename": "Mannequin",
+                    "login": "imported-user",
+                    "url": "https://github.com/imported-user",
+                    "databaseId": 5
+                }),
+            ),
+            comment_node(
+                "2",
+                Some("1"),
+                json!({
+                    "__typename": "Organization",
+                    "login": "acme",
+                    "avatarUrl": "https://avatars.example.invalid/o/1",
+                    "url": "https://github.com/acme"
+                }),
+            ),
+            comment_node("3", Some("2"), json!({ "__typename": "FutureActor" })),
+        ]);
+
+        let threads = review_threads_from_node(thread, &GitHubHost::new(None, None))
+            .expect("unknown actors map to domain models");
+
+        let root = &threads[0];
+        let mannequin = root.comment.user.as_ref().expect("mannequin author");
+        assert_eq!(mannequin.login, "imported-user");
+        assert_eq!(mannequin.r#type, "Mannequin");
+        assert_eq!(
+            mannequin.avatar_url.as_str(),
+            "https://github.com/imported-user"
+        );
+        let organization = &root.replies[0];
+        assert_eq!(
+            organization
+                .comment
+                .user
+                .as_ref()
+                .map(|user| user.login.as_str()),
+            Some("acme")
+        );
+        assert_eq!(
+            organization.replies[0]
+                .comment
+                .user
+                .as_ref()
+                .map(|user| user.login.as_str()),
+            Some("ghost")
+        );
+    }
+
+    #[test]
+    fn folds_review_states_into_reviewer_list() {
+        let mut reviewed = Vec::new();
+        record_review_state(&mut reviewed, "alice", "CHANGES_REQUESTED");
+        record_review_state(&mut reviewed, "alice", "COMMENTED");
+        record_review_state(&mut reviewed, "bob", "COMMENTED");
+        record_review_state(&mut reviewed, "bob", "APPROVED");
+        record_review_state(&mut reviewed, "author", "COMMENTED");
+        record_review_state(&mut reviewed, "carol", "APPROVED");
+        record_review_state(&mut reviewed, "dave", "PENDING");
+
+        let requested = vec![PullRequestReviewer {
+            login: "carol".to_owned(),
+            state: ReviewerState::Requested,
+        }];
+        let reviewers = merge_reviewers(reviewed, requested, "author");
+        let summary: Vec<(&str, ReviewerState)> = reviewers
+            .iter()
+            .map(|reviewer| (reviewer.login.as_str(), reviewer.state))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("alice", ReviewerState::ChangesRequested),
+                ("bob", ReviewerState::Approved),
+                ("carol", ReviewerState::Requested),
+            ]
+        );
+    }
 }
@@ -6,7 +6,7 @@ use crate::{
         PullRequestDiffHighlightRange, PullRequestDiffRow, PullRequestDiffRowKind,
         PullRequestSummary,
     },
-    github::client::gh_auth_token,
+    github::client::GitHubClient,
 };
 use secrecy::ExposeSecret;
 use serde::Deserialize;
@@ -15,7 +15,7 @@ use std::{
     env,
     path::{Component, Path, PathBuf},
     process::Stdio,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use thiserror::Error;
 use tokio::{fs, process::Command};
@@ -62,44 +62,59 @@ pub enum PullRequestDiffError {
     },
 }
 
+/// Time the in-process line diff may spend searching for a minimal
+/// alignment of one file.
+const LINE_DIFF_DEADLINE: Duration = Duration::from_millis(500);
+
+/// Strategy used to align base and head file contents.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum DiffEngine {
+    /// Structural diff via `difft --display json`.
+    #[default]
+    Difftastic,
+    /// Line-based diff computed in-process, without external tools.
+    Line,
+}
+
 /// Loads aligned per-file diff data for a pull request.
 pub async fn fetch_pull_request_diff_data(
+    client: &GitHubClient,
     pull: &PullRequestSummary,
     changed_files: &[String],
+    engine: DiffEngine,
 ) -> Result<PullRequestDiffData> {
     if changed_files.is_empty() {
         return Ok(PullRequestDiffData { files: Vec::new() });
     }
 
-    let git_auth = build_git_auth_header().await;
-    let repo_dir = ensure_repo_available(pull, git_auth.as_deref()).await?;
-    fetch_required_commits(
-        &repo_dir,
-        &pull.base_sha,
-        &pull.head_sha,
-        git_auth.as_deref(),
-    )
-    .await?;
+    let git_auth = build_git_auth_config(client);
+    let repo_dir = ensure_repo_available(client, pull, Some(&git_auth)).await?;
+    fetch_required_commits(&repo_dir, &pull.base_sha, &pull.head_sha, Some(&git_auth)).await?;
 
-    let (workspace_root, base_root, head_root) = create_workspace(pull).await?;
+    let workspace = match engine {
+        DiffEngine::Difftastic => Some(create_workspace(pull).await?),
+        DiffEngine::Line => None,
+    };
     let mut source_by_path = HashMap::<String, SourcePair>::new();
 
     for raw_path in changed_files {
         let normalized = normalize_changed_path(raw_path)?;
 
         let base_source =
-            git_show_file(&repo_dir, &pull.base_sha, raw_path, git_auth.as_deref()).await?;
+            git_show_file(&repo_dir, &pull.base_sha, raw_path, Some(&git_auth)).await?;
         let head_source =
-            git_show_file(&repo_dir, &pull.head_sha, raw_path, git_auth.as_deref()).await?;
+            git_show_file(&repo_dir, &pull.head_sha, raw_path, Some(&git_auth)).await?;
         if base_source.is_none() && head_source.is_none() {
             continue;
         }
 
-        if let Some(base) = &base_source {
-            write_snapshot_file(&base_root, &normalized, base).await?;
-        }
-        if let Some(head) = &head_source {
-            write_snapshot_file(&head_root, &normalized, head).await?;
+        if let Some((_, base_root, head_root)) = &workspace {
+            if let Some(base) = &base_source {
+                write_snapshot_file(base_root, &normalized, base).await?;
+            }
+            if let Some(head) = &head_source {
+                write_snapshot_file(head_root, &normalized, head).await?;
+            }
         }
 
         source_by_path.insert(
@@ -111,14 +126,20 @@ pub async fn fetch_pull_request_diff_data(
         );
     }
 
-    let parsed = run_difft_json(&base_root, &head_root).await;
-    let _ = fs::remove_dir_all(&workspace_root).await;
-    let difft_files = parsed?;
-
-    let mut parsed_by_path = HashMap::<String, RawDifftFile>::with_capacity(difft_files.len());
-    for file in difft_files {
-        parsed_by_path.insert(normalize_path_for_lookup(&file.path), file);
-    }
+    let mut parsed_by_path = match workspace {
+        Some((workspace_root, base_root, head_root)) => {
+            let parsed = run_difft_json(&base_root, &head_root).await;
+            let _ = fs::remove_dir_all(&workspace_root).await;
+            parsed?
+                .into_iter()
+                .map(|file| (normalize_path_for_lookup(&file.path), file))
+                .collect::<HashMap<_, _>>()
+        }
+        None => source_by_path
+            .iter()
+            .map(|(path, source)| (path.clone(), line_diff_file(path, source)))
+            .collect(),
+    };
 
     let mut files = Vec::new();
     for raw_path in changed_files {
@@ -176,6 +197,7 @@ struct RawDifftChange {
 }
 
 async fn ensure_repo_available(
+    client: &GitHubClient,
     pull: &PullRequestSummary,
     git_auth: Option<&str>,
 ) -> Result<PathBuf> {
@@ -193,7 +215,7 @@ async fn ensure_repo_available(
         })?;
     }
 
-    let remote = format!("https://github.com/{}/{}.git", pull.owner, pull.repo);
+    let remote = client.host().clone_url(&pull.owner, &pull.repo);
     run_git(
         &[
             "clone",
@@ -383,6 +405,128 @@ async fn run_difft_json(base_root: &Path, head_root: &Path) -> Result<Vec<RawDif
     })
 }
 
+/// Reads `path` at `HEAD` from the cached clone of `owner/repo`, if one exists.
+pub async fn read_cached_repository_file(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    path: &str,
+) -> Result<Option<String>> {
+    let repo_dir = runtime_root()?.join("repos").join(owner).join(repo);
+    if !repo_dir.exists() {
+        return Ok(None);
+    }
+
+    let git_auth = build_git_auth_config(client);
+    git_show_file(&repo_dir, "HEAD", path, Some(&git_auth)).await
+}
+
+/// Builds difft-shaped output from a longest-common-subsequence line diff.
+fn line_diff_file(path: &str, source: &SourcePair) -> RawDifftFile {
+    let left_lines = split_preserving_trailing_newline(&source.base);
+    let right_lines = split_preserving_trailing_newline(&source.head);
+    let status = if source.base.is_empty() {
+        "created"
+    } else if source.head.is_empty() {
+        "deleted"
+    } else {
+        "changed"
+    };
+
+    let mut aligned_lines = Vec::new();
+    let mut chunks = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut flush = |removed: &mut Vec<usize>,
+                     added: &mut Vec<usize>,
+                     aligned_lines: &mut Vec<[Option<usize>; 2]>| {
+        if removed.is_empty() && added.is_empty() {
+            return;
+        }
+        let len = removed.len().max(added.len());
+        let mut chunk = Vec::with_capacity(len);
+        for index in 0..len {
+            let left = removed.get(index).copied();
+            let right = added.get(index).copied();
+            aligned_lines.push([left, right]);
+            chunk.push(RawDifftChunkLine {
+                lhs: left.map(|line_number| RawDifftSide {
+                    line_number,
+                    changes: Vec::new(),
+                }),
+                rhs: right.map(|line_number| RawDifftSide {
+                    line_number,
+                    changes: Vec::new(),
+                }),
+            });
+        }
+        chunks.push(chunk);
+        removed.clear();
+        added.clear();
+    };
+
+    // An empty file would otherwise align its lone empty line with itself,
+    // which `trim_terminal_empty_row` drops; show it as the fallback does.
+    let ops = if source.base.is_empty() && source.head.is_empty() {
+        vec![LineOp::Add(0)]
+    } else {
+        line_diff_ops(&left_lines, &right_lines)
+    };
+    for op in ops {
+        match op {
+            LineOp::Equal(left, right) => {
+                flush(&mut removed, &mut added, &mut aligned_lines);
+                aligned_lines.push([Some(left), Some(right)]);
+            }
+            LineOp::Remove(left) => removed.push(left),
+            LineOp::Add(right) => added.push(right),
+        }
+    }
+    flush(&mut removed, &mut added, &mut aligned_lines);
+
+    RawDifftFile {
+        path: path.to_owned(),
+        status: status.to_owned(),
+        aligned_lines,
+        chunks,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LineOp {
+    Equal(usize, usize),
+    Remove(usize),
+    Add(usize),
+}
+
+/// Aligns lines with Myers' diff. Past [`LINE_DIFF_DEADLINE`] the remaining
+/// regions are paired without searching further, so huge rewrites still
+/// finish promptly with a valid, if not minimal, alignment.
+fn line_diff_ops(left: &[String], right: &[String]) -> Vec<LineOp> {
+    let deadline = Instant::now() + LINE_DIFF_DEADLINE;
+    let mut ops = Vec::new();
+    for op in similar::capture_diff_slices_deadline(
+        similar::Algorithm::Myers,
+        left,
+        right,
+        Some(deadline),
+    ) {
+        let (tag, left_range, right_range) = op.as_tag_tuple();
+        match tag {
+            similar::DiffTag::Equal => ops.extend(
+                left_range
+                    .zip(right_range)
+                    .map(|(left, right)| LineOp::Equal(left, right)),
+            ),
+            similar::DiffTag::Delete | similar::DiffTag::Insert | similar::DiffTag::Replace => {
+                ops.extend(left_range.map(LineOp::Remove));
+                ops.extend(right_range.map(LineOp::Add));
+            }
+        }
+    }
+    ops
+}
+
 fn build_diff_file(
     path: &str,
     source: &SourcePair,
@@ -757,20 +901,22 @@ fn git_command(git_auth: Option<&str>) -> Command {
         .env("GCM_INTERACTIVE", "never")
         .stdin(Stdio::null());
 
-    if let Some(extra_header) = git_auth {
-        command.arg("-c").arg(format!(
-            "http.https://github.com/.extraheader={extra_header}"
-        ));
+    if let Some(auth_config) = git_auth {
+        command.arg("-c").arg(auth_config);
     }
 
     command
 }
 
-async fn build_git_auth_header() -> Option<String> {
-    let token = gh_auth_token().await.ok()?;
-    let credential = format!("x-access-token:{}", token.expose_secret());
+/// Builds the `http.<url>.extraheader` git config that authenticates against
+/// the client's host.
+fn build_git_auth_config(client: &GitHubClient) -> String {
+    let credential = format!("x-access-token:{}", client.token().expose_secret());
     let encoded = base64_encode(credential.as_bytes());
-    Some(format!("AUTHORIZATION: basic {encoded}"))
+    format!(
+        "http.{}/.extraheader=AUTHORIZATION: basic {encoded}",
+        client.host().web_url()
+    )
 }
 
 fn base64_encode(input: &[u8]) -> String {
@@ -799,6 +945,65 @@ fn base64_encode(input: &[u8]) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn line_engine_pairs_replaced_lines_and_marks_hunks() {
+        let source = SourcePair {
+            base: "a\nb\nc\nd\n".to_owned(),
+            head: "a\nB\nc\nnew\nd\n".to_owned(),
+        };
+        let parsed = line_diff_file("f.txt", &source);
+        assert_eq!(parsed.status, "changed");
+        assert_eq!(
+            parsed.aligned_lines,
+            vec![
+                [Some(0), Some(0)],
+                [Some(1), Some(1)],
+                [Some(2), Some(2)],
+                [None, Some(3)],
+                [Some(3), Some(4)],
+                [Some(4), Some(5)],
+            ]
+        );
+        assert_eq!(parsed.chunks.len(), 2);
+
+        let file = build_diff_file("f.txt", &source, Some(parsed));
+        assert_eq!(file.hunk_starts, vec![1, 3]);
+        assert_eq!(file.rows[1].kind, PullRequestDiffRowKind::Modified);
+        assert_eq!(file.rows[3].kind, PullRequestDiffRowKind::Added);
+    }
+
+    #[test]
+    fn line_engine_aligns_large_files_without_collapsing_them() {
+        let base = (0..3000)
+            .map(|line| format!("line {line}\n"))
+            .collect::<String>();
+        let head = base
+            .replacen("line 0\n", "first\n", 1)
+            .replacen("line 1500\n", "middle\n", 1)
+            .replacen("line 2999\n", "last\n", 1);
+        let parsed = line_diff_file("big.txt", &SourcePair { base, head });
+
+        // The trailing newline leaves an empty last line.
+        assert_eq!(parsed.aligned_lines.len(), 3001);
+        assert_eq!(parsed.chunks.len(), 3);
+    }
+
+    #[test]
+    fn line_engine_keeps_a_row_for_empty_files() {
+        let source = SourcePair {
+            base: String::new(),
+            head: String::new(),
+        };
+        let parsed = line_diff_file("pkg/__init__.py", &source);
+        assert_eq!(parsed.status, "created");
+
+        let file = build_diff_file("pkg/__init__.py", &source, Some(parsed));
+        assert_eq!(file.status, PullRequestDiffFileStatus::Added);
+        assert_eq!(file.rows.len(), 1);
+        assert_eq!(file.rows[0].right_line_number, Some(1));
+        assert_eq!(file.hunk_starts, vec![0]);
+    }
+
     #[test]
     fn normalize_changed_path_rejects_parent_traversal() {
         let result = normalize_changed_path("../etc/passwd");
@@ -10,7 +10,38 @@ pub fn format_octocrab_error(error: octocrab::Error) -> String {
     }
 }
 
+/// Whether the request failed while connecting (DNS, TCP or TLS), before any
+/// of it reached GitHub, so sending it again later cannot apply it twice.
+///
+/// Failures after the request was written, including gateway errors, are
+/// ambiguous and are reported instead.
+pub fn is_unreachable_error(error: &octocrab::Error) -> bool {
+    let mut current: Option<&(dyn StdError + 'static)> = Some(error);
+    while let Some(err) = current {
+        if err
+            .downcast_ref::<hyper_util::client::legacy::Error>()
+            .is_some_and(hyper_util::client::legacy::Error::is_connect)
+        {
+            return true;
+        }
+        current = err.source();
+    }
+    false
+}
+
 fn format_github_error(error: octocrab::GitHubError) -> String {
+    if is_rate_limit_error(&error) {
+        let kind = if error.message.to_ascii_lowercase().contains("secondary") {
+            "secondary rate limit"
+        } else {
+            "rate limit"
+        };
+        return format!(
+            "GitHub {kind} exceeded (status {}); wait a moment and refresh",
+            error.status_code.as_u16()
+        );
+    }
+
     let mut message = format!(
         "status {}: {}",
         error.status_code.as_u16(),
@@ -35,6 +66,11 @@ fn format_github_error(error: octocrab::GitHubError) -> String {
     message
 }
 
+fn is_rate_limit_error(error: &octocrab::GitHubError) -> bool {
+    let status = error.status_code.as_u16();
+    (status == 403 || status == 429) && error.message.to_ascii_lowercase().contains("rate limit")
+}
+
 fn format_github_detail(value: &serde_json::Value) -> String {
     if let Some(message) = value.get("message").and_then(serde_json::Value::as_str) {
         return message.to_owned();
@@ -0,0 +1,218 @@
+//! Classifies GraphQL requests for the HTTP middleware.
+//!
+//! GraphQL queries are POSTs, so the cache and the retry layer both need to
+//! tell them apart from mutations, which must never be replayed.
+
+use http::request::Parts;
+use serde::Deserialize;
+
+/// Kind of operation a GraphQL request carries.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GraphQlOperation {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+#[derive(Deserialize)]
+struct GraphQlBody {
+    query: String,
+}
+
+/// Returns the operation type of a request to a `/graphql` endpoint, or `None`
+/// for other requests and bodies that are not a GraphQL document.
+///
+/// A document defining several operations counts as a mutation if any of
+/// them is one, so it is never replayed by mistake.
+pub fn graphql_operation(parts: &Parts, body: &[u8]) -> Option<GraphQlOperation> {
+    if !parts.uri.path().ends_with("/graphql") {
+        return None;
+    }
+    let body: GraphQlBody = serde_json::from_slice(body).ok()?;
+    document_operation(&body.query)
+}
+
+/// Reads the keyword of every top-level definition, skipping fragments.
+fn document_operation(document: &str) -> Option<GraphQlOperation> {
+    let mut tokens = Tokens {
+        rest: document.as_bytes(),
+    };
+    let mut first = None;
+    loop {
+        let operation = match tokens.next_token()? {
+            // A bare selection set is shorthand for a query.
+            Token::Open => GraphQlOperation::Query,
+            Token::Name(b"query") => GraphQlOperation::Query,
+            Token::Name(b"mutation") => return Some(GraphQlOperation::Mutation),
+            Token::Name(b"subscription") => GraphQlOperation::Subscription,
+            Token::Name(b"fragment") => {
+                tokens.skip_definition()?;
+                continue;
+            }
+            Token::End => return first,
+            Token::Name(_) | Token::Other => return None,
+        };
+        first.get_or_insert(operation);
+        tokens.skip_definition()?;
+    }
+}
+
+enum Token<'a> {
+    Name(&'a [u8]),
+    Open,
+    Other,
+    End,
+}
+
+/// Just enough of a GraphQL lexer to walk top-level definitions.
+struct Tokens<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Tokens<'a> {
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_ignored();
+        let Some(&first) = self.rest.first() else {
+            return Some(Token::End);
+        };
+        if first == b'{' {
+            return Some(Token::Open);
+        }
+        if first.is_ascii_alphabetic() || first == b'_' {
+            let len = self
+                .rest
+                .iter()
+                .position(|byte| !(byte.is_ascii_alphanumeric() || *byte == b'_'))
+                .unwrap_or(self.rest.len());
+            let (name, rest) = self.rest.split_at(len);
+            self.rest = rest;
+            return Some(Token::Name(name));
+        }
+        Some(Token::Other)
+    }
+
+    /// Skips whitespace, commas and `#` comments.
+    fn skip_ignored(&mut self) {
+        loop {
+            match self.rest.first() {
+                Some(byte) if byte.is_ascii_whitespace() || *byte == b',' => {
+                    self.rest = &self.rest[1..];
+                }
+                Some(b'#') => {
+                    let end = self
+                        .rest
+                        .iter()
+                        .position(|byte| *byte == b'\n')
+                        .unwrap_or(self.rest.len());
+                    self.rest = &self.rest[end..];
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skips to the end of the definition's selection set, or of the document
+    /// when the definition has none.
+    fn skip_definition(&mut self) -> Option<()> {
+        match self.rest.iter().position(|byte| *byte == b'{') {
+            Some(start) => {
+                self.rest = &self.rest[start..];
+                self.skip_block()
+            }
+            None => {
+                self.rest = &[];
+                Some(())
+            }
+        }
+    }
+
+    /// Skips a `{ ... }` block starting at the cursor, including nested blocks
+    /// and braces inside string literals.
+    fn skip_block(&mut self) -> Option<()> {
+        let mut depth = 0usize;
+        let mut index = 0;
+        while let Some(&byte) = self.rest.get(index) {
+            match byte {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth = depth.checked_sub(1)?;
+                    if depth == 0 {
+                        self.rest = &self.rest[index + 1..];
+                        return Some(());
+                    }
+                }
+                b'"' => {
+                    index += 1;
+                    while *self.rest.get(index)? != b'"' {
+                        if self.rest[index] == b'\\' {
+                            index += 1;
+                        }
+                        index += 1;
+                    }
+                }
+                b'#' => {
+                    while self.rest.get(index).is_some_and(|byte| *byte != b'\n') {
+                        index += 1;
+                    }
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphQlOperation, graphql_operation};
+    use http::{Method, Request};
+
+    fn operation(uri: &str, query: &str) -> Option<GraphQlOperation> {
+        let parts = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0;
+        let body = serde_json::to_vec(&serde_json::json!({ "query": query })).unwrap();
+        graphql_operation(&parts, &body)
+    }
+
+    #[test]
+    fn reads_the_operation_keyword_of_graphql_documents() {
+        let graphql = "https://api.github.com/graphql";
+        assert_eq!(
+            operation(graphql, "query Q { mutationCount }"),
+            Some(GraphQlOperation::Query)
+        );
+        assert_eq!(
+            operation(graphql, "{ viewer { login } }"),
+            Some(GraphQlOperation::Query)
+        );
+        assert_eq!(
+            operation(
+                graphql,
+                "# comment\n  mutation M { resolve(body: \"{\") { id } }"
+            ),
+            Some(GraphQlOperation::Mutation)
+        );
+        assert_eq!(
+            operation(
+                graphql,
+                "fragment F on User { login }\nquery Q { viewer { ...F } }"
+            ),
+            Some(GraphQlOperation::Query)
+        );
+        assert_eq!(
+            operation(graphql, "query Q { a }\nmutation M { b }"),
+            Some(GraphQlOperation::Mutation)
+        );
+        assert_eq!(operation(graphql, "not graphql {"), None);
+        assert_eq!(
+            operation("https://api.github.com/repos/a/b/pulls", "query Q { a }"),
+            None
+        );
+    }
+}
@@ -1,7 +1,10 @@
 //! GitHub integration modules.
 
+pub mod cache;
 pub mod client;
 pub mod comments;
 pub mod diff;
 pub mod errors;
+pub mod graphql;
 pub mod pulls;
+pub mod ratelimit;
@@ -2,7 +2,7 @@
 
 use crate::{
     domain::{PullRequestReviewStatus, PullRequestSummary},
-    github::errors::format_octocrab_error,
+    github::{client::GitHubClient, errors::format_octocrab_error},
 };
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
@@ -102,6 +102,8 @@ struct GraphQlPullNode {
     number: u64,
     review_decision: Option<GraphQlReviewDecision>,
     #[serde(default)]
+    total_comments_count: Option<u64>,
+    #[serde(default)]
     review_requests: GraphQlReviewRequestConnection,
 }
 
@@ -170,6 +172,7 @@ query OpenPullReviewDecisions($owner: String!, $repo: String!, $after: String) {
       nodes {
         number
         reviewDecision
+        totalCommentsCount
         reviewRequests(first: 100) {
           nodes {
             requestedReviewer {
@@ -190,6 +193,7 @@ query OpenPullReviewDecisions($owner: String!, $repo: String!, $after: String) {
 struct PullReviewMetadata {
     review_status: Option<PullRequestReviewStatus>,
     reviewer_logins: Vec<String>,
+    comment_count: Option<u64>,
 }
 
 /// Resolves repository context from explicit args, or `gh repo view` when omitted.
@@ -232,7 +236,7 @@ async fn resolve_repository_from_gh() -> Result<RepositoryRef> {
 
 /// Fetches open pull requests for the target repository.
 pub async fn fetch_open_pull_requests(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     repository: &RepositoryRef,
 ) -> Result<Vec<PullRequestSummary>> {
     use octocrab::params::State;
@@ -246,7 +250,13 @@ pub async fn fetch_open_pull_requests(
         .await?;
 
     let mut pulls = client.all_pages(first_page).await?;
-    let review_metadata = fetch_review_metadata(client, repository).await;
+    // Review decisions are a nice-to-have; keep the remaining GraphQL quota
+    // for loading review threads.
+    let review_metadata = if client.rate_limits().is_low("graphql") {
+        HashMap::new()
+    } else {
+        fetch_review_metadata(client, repository).await
+    };
 
     pulls.sort_by(|a, b| {
         let a_ts = a
@@ -277,15 +287,41 @@ pub async fn fetch_open_pull_requests(
     Ok(mapped)
 }
 
+/// Fetches the most recently updated open issues and pull requests (GitHub
+/// lists both as issues) for `#` completion, as number and title.
+pub async fn fetch_open_issue_titles(
+    client: &GitHubClient,
+    repository: &RepositoryRef,
+) -> Result<Vec<(u64, String)>> {
+    use octocrab::params::{Direction, State, issues::Sort};
+
+    // One page is plenty for completion and keeps the cost to one request.
+    let page = client
+        .issues(&repository.owner, &repository.repo)
+        .list()
+        .state(State::Open)
+        .sort(Sort::Updated)
+        .direction(Direction::Descending)
+        .per_page(100)
+        .send()
+        .await?;
+
+    Ok(page
+        .items
+        .into_iter()
+        .map(|issue| (issue.number, issue.title))
+        .collect())
+}
+
 /// Fetches the authenticated GitHub login for the current token.
-pub async fn fetch_viewer_login(client: &octocrab::Octocrab) -> Result<String> {
+pub async fn fetch_viewer_login(client: &GitHubClient) -> Result<String> {
     let user = client.current().user().await?;
     Ok(user.login)
 }
 
 /// Fetches a single pull request summary by number.
 pub async fn fetch_pull_request_summary(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     repository: &RepositoryRef,
     pull_number: u64,
 ) -> Result<PullRequestSummary> {
@@ -301,7 +337,7 @@ pub async fn fetch_pull_request_summary(
 }
 
 async fn fetch_review_metadata(
-    client: &octocrab::Octocrab,
+    client: &GitHubClient,
     repository: &RepositoryRef,
 ) -> HashMap<u64, PullReviewMetadata> {
     let mut after: Option<String> = None;
@@ -373,6 +409,7 @@ async fn fetch_review_metadata(
                 PullReviewMetadata {
                     review_status,
                     reviewer_logins: reviewer_logins.into_iter().collect(),
+                    comment_count: pull.total_comments_count,
                 },
             );
         }
@@ -422,5 +459,6 @@ fn map_pull_request(
         is_draft: pull.draft.unwrap_or(false),
         reviewer_logins: review_metadata.reviewer_logins,
         review_status: review_metadata.review_status,
+        comment_count: review_metadata.comment_count,
     }
 }
@@ -0,0 +1,369 @@
+//! Rate-limit tracking and retry/backoff middleware shared by every GitHub request.
+
+use crate::github::graphql::{GraphQlOperation, graphql_operation};
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode, request::Parts};
+use http_body_util::BodyExt;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tower::{Layer, Service, ServiceExt};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Retries after the first attempt.
+const MAX_RETRIES: u32 = 3;
+/// First backoff delay for server errors; doubled on each retry.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest a request is held across all of its retries; waits that would go
+/// past it are skipped and the last response is returned so the limit is
+/// reported instead of freezing the caller.
+const MAX_TOTAL_WAIT: Duration = Duration::from_secs(20);
+/// GitHub asks clients to wait at least a minute after a secondary rate limit.
+const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+/// Remaining requests below which optional calls are skipped.
+const LOW_QUOTA_FLOOR: u32 = 50;
+
+/// Quota reported for one rate-limit resource (`core`, `graphql`, ...).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp (seconds) at which the quota resets.
+    pub reset_at: u64,
+}
+
+impl RateLimitStatus {
+    /// Whether fewer than 5% (and at least 50) requests remain.
+    pub fn is_low(&self) -> bool {
+        self.remaining < LOW_QUOTA_FLOOR.max(self.limit / 20)
+    }
+
+    pub fn resets_in(&self) -> Duration {
+        Duration::from_secs(self.reset_at.saturating_sub(unix_now()))
+    }
+}
+
+/// Latest rate-limit headers per resource, shared by every clone of the client.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitTracker {
+    resources: Arc<Mutex<BTreeMap<String, RateLimitStatus>>>,
+}
+
+impl RateLimitTracker {
+    pub fn get(&self, resource: &str) -> Option<RateLimitStatus> {
+        self.resources.lock().ok()?.get(resource).copied()
+    }
+
+    /// The resource with the smallest share of its quota left.
+    pub fn most_constrained(&self) -> Option<(String, RateLimitStatus)> {
+        let resources = self.resources.lock().ok()?;
+        resources
+            .iter()
+            .min_by_key(|(_, status)| {
+                u64::from(status.remaining) * 1_000 / u64::from(status.limit.max(1))
+            })
+            .map(|(resource, status)| (resource.clone(), *status))
+    }
+
+    /// Whether `resource` is known to be close to its limit.
+    pub fn is_low(&self, resource: &str) -> bool {
+        self.get(resource)
+            .is_some_and(|status| status.is_low() && !status.resets_in().is_zero())
+    }
+
+    fn record(&self, headers: &HeaderMap) {
+        let Some(status) = parse_status(headers) else {
+            return;
+        };
+        let resource = header_str(headers, "x-ratelimit-resource").unwrap_or("core");
+        if let Ok(mut resources) = self.resources.lock() {
+            resources.insert(resource.to_owned(), status);
+        }
+    }
+}
+
+/// Layer that records rate-limit headers and retries transient failures.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    tracker: RateLimitTracker,
+}
+
+impl RateLimitLayer {
+    pub fn new(tracker: RateLimitTracker) -> Self {
+        Self { tracker }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            tracker: self.tracker.clone(),
+        }
+    }
+}
+
+/// Service produced by [`RateLimitLayer`].
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    tracker: RateLimitTracker,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RateLimit<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError>,
+    ReqBody: http_body::Body + From<Bytes> + Send + 'static,
+    ReqBody::Data: Send,
+    ReqBody::Error: Into<BoxError>,
+    ResBody: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // Keep the service that was polled ready for the first attempt.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let tracker = self.tracker.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+            let replayable = is_replayable(&parts, &body);
+
+            let mut attempt = 0;
+            let mut waited = Duration::ZERO;
+            loop {
+                let request = rebuild_request(&parts, body.clone());
+                let result = inner
+                    .ready()
+                    .await
+                    .map_err(Into::into)?
+                    .call(request)
+                    .await
+                    .map_err(Into::into);
+
+                let delay = match &result {
+                    Ok(response) => {
+                        tracker.record(response.headers());
+                        retry_delay(response.status(), response.headers(), attempt, replayable)
+                    }
+                    Err(_) => replayable.then(|| backoff(attempt)),
+                };
+                match delay {
+                    Some(delay) if attempt < MAX_RETRIES && waited + delay <= MAX_TOTAL_WAIT => {
+                        tokio::time::sleep(delay).await;
+                        waited += delay;
+                        attempt += 1;
+                    }
+                    _ => return result,
+                }
+            }
+        })
+    }
+}
+
+/// Whether repeating the request after an ambiguous failure cannot apply it twice.
+/// GraphQL queries are POSTs, so those are replayed unless they carry a mutation.
+fn is_replayable(parts: &Parts, body: &Bytes) -> bool {
+    match parts.method {
+        Method::GET | Method::HEAD => true,
+        Method::POST => graphql_operation(parts, body) == Some(GraphQlOperation::Query),
+        _ => false,
+    }
+}
+
+fn rebuild_request<B: From<Bytes>>(parts: &Parts, body: Bytes) -> Request<B> {
+    let mut request = Request::new(B::from(body));
+    *request.method_mut() = parts.method.clone();
+    *request.uri_mut() = parts.uri.clone();
+    *request.version_mut() = parts.version;
+    *request.headers_mut() = parts.headers.clone();
+    *request.extensions_mut() = parts.extensions.clone();
+    request
+}
+
+/// How long to wait before retrying, or `None` when the response should be returned.
+fn retry_delay(
+    status: StatusCode,
+    headers: &HeaderMap,
+    attempt: u32,
+    replayable: bool,
+) -> Option<Duration> {
+    if is_rate_limited(status, headers) {
+        // Rejected requests were not applied, so any method can be retried.
+        let delay = if let Some(seconds) = header_u64(headers, "retry-after") {
+            Duration::from_secs(seconds)
+        } else if header_u64(headers, "x-ratelimit-remaining") == Some(0) {
+            let reset_at = header_u64(headers, "x-ratelimit-reset").unwrap_or_default();
+            Duration::from_secs(reset_at.saturating_sub(unix_now()) + 1)
+        } else {
+            SECONDARY_LIMIT_WAIT
+        };
+        return (delay <= MAX_TOTAL_WAIT).then_some(delay);
+    }
+
+    let transient = matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    );
+    (transient && replayable).then(|| backoff(attempt))
+}
+
+fn is_rate_limited(status: StatusCode, headers: &HeaderMap) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (headers.contains_key("retry-after")
+                || header_u64(headers, "x-ratelimit-remaining") == Some(0)))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_DELAY.saturating_mul(1 << attempt.min(6))
+}
+
+fn parse_status(headers: &HeaderMap) -> Option<RateLimitStatus> {
+    Some(RateLimitStatus {
+        limit: u32::try_from(header_u64(headers, "x-ratelimit-limit")?).ok()?,
+        remaining: u32::try_from(header_u64(headers, "x-ratelimit-remaining")?).ok()?,
+        reset_at: header_u64(headers, "x-ratelimit-reset")?,
+    })
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    header_str(headers, name)?.parse().ok()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimitTracker, is_replayable, retry_delay, unix_now};
+    use bytes::Bytes;
+    use http::{HeaderMap, HeaderValue, Method, Request, StatusCode};
+    use std::time::Duration;
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn retries_server_errors_only_when_replayable() {
+        let empty = HeaderMap::new();
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, &empty, 1, true),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(retry_delay(StatusCode::BAD_GATEWAY, &empty, 0, false), None);
+        assert_eq!(retry_delay(StatusCode::NOT_FOUND, &empty, 0, true), None);
+
+        let secondary = headers(&[("retry-after", "3".to_owned())]);
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &secondary, 0, false),
+            Some(Duration::from_secs(3))
+        );
+
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining", "0".to_owned()),
+            ("x-ratelimit-reset", (unix_now() + 3_600).to_string()),
+        ]);
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &exhausted, 0, true),
+            None
+        );
+
+        // Secondary limits without `retry-after` ask for a minute; report them.
+        assert_eq!(
+            retry_delay(StatusCode::TOO_MANY_REQUESTS, &empty, 0, true),
+            None
+        );
+    }
+
+    #[test]
+    fn graphql_mutations_are_not_replayed() {
+        let parts = |method: Method, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let graphql = parts(Method::POST, "https://api.github.com/graphql");
+        assert!(is_replayable(
+            &graphql,
+            &Bytes::from_static(br#"{"query":"query Q { viewer { login } }"}"#)
+        ));
+        assert!(!is_replayable(
+            &graphql,
+            &Bytes::from_static(br#"{"query":"mutation M { x }"}"#)
+        ));
+        assert!(!is_replayable(
+            &parts(
+                Method::POST,
+                "https://api.github.com/repos/a/b/pulls/1/reviews"
+            ),
+            &Bytes::new()
+        ));
+        assert!(is_replayable(
+            &parts(Method::GET, "https://api.github.com/user"),
+            &Bytes::new()
+        ));
+    }
+
+    #[test]
+    fn tracks_the_most_constrained_resource() {
+        let tracker = RateLimitTracker::default();
+        let reset = (unix_now() + 600).to_string();
+        tracker.record(&headers(&[
+            ("x-ratelimit-limit", "5000".to_owned()),
+            ("x-ratelimit-remaining", "4000".to_owned()),
+            ("x-ratelimit-reset", reset.clone()),
+            ("x-ratelimit-resource", "core".to_owned()),
+        ]));
+        tracker.record(&headers(&[
+            ("x-ratelimit-limit", "5000".to_owned()),
+            ("x-ratelimit-remaining", "120".to_owned()),
+            ("x-ratelimit-reset", reset),
+            ("x-ratelimit-resource", "graphql".to_owned()),
+        ]));
+
+        let (resource, status) = tracker.most_constrained().unwrap();
+        assert_eq!(resource, "graphql");
+        assert_eq!(status.remaining, 120);
+        assert!(tracker.is_low("graphql"));
+        assert!(!tracker.is_low("core"));
+    }
+}
@@ -32,6 +32,11 @@ struct Cli {
     /// Pull request number to open directly on startup.
     #[arg(long)]
     pull: Option<u64>,
+
+    /// Review pull requests saved by earlier sessions without contacting GitHub.
+    /// Replies, resolutions and reviews are queued until the next online run.
+    #[arg(long)]
+    offline: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -78,6 +83,7 @@ async fn main() -> anyhow::Result<()> {
         owner: cli.owner,
         repo: cli.repo,
         pull: cli.pull,
+        offline: cli.offline,
         initial_theme_mode: runtime_theme.mode,
         initial_terminal_background: terminal_background,
         theme_config: config,
@@ -0,0 +1,254 @@
+//! Recognition of the references GitHub links automatically in comment text:
+//! bare URLs, `@mentions`, `#123`, `owner/repo#45` and commit SHAs.
+
+/// A run of comment text, either plain or a recognized reference.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) enum Segment<'a> {
+    Text(&'a str),
+    Reference { text: &'a str, reference: Reference },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) enum Reference {
+    Url(String),
+    /// A user login, or `org/team` for team mentions.
+    Mention(String),
+    Issue {
+        /// `owner/repo` for cross-repository references.
+        repository: Option<(String, String)>,
+        number: u64,
+    },
+    Commit(String),
+}
+
+const MAX_LOGIN_LENGTH: usize = 39;
+const SHORT_SHA_LENGTH: usize = 7;
+const FULL_SHA_LENGTH: usize = 40;
+
+/// Splits `text` into plain runs and references.
+pub(super) fn split(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut plain_start = 0;
+    let mut index = 0;
+    let mut previous: Option<char> = None;
+
+    while let Some(ch) = text[index..].chars().next() {
+        if previous.is_none_or(starts_reference)
+            && let Some((length, reference)) = match_reference(&text[index..])
+        {
+            if plain_start < index {
+                segments.push(Segment::Text(&text[plain_start..index]));
+            }
+            let end = index + length;
+            segments.push(Segment::Reference {
+                text: &text[index..end],
+                reference,
+            });
+            previous = text[..end].chars().next_back();
+            index = end;
+            plain_start = end;
+            continue;
+        }
+        previous = Some(ch);
+        index += ch.len_utf8();
+    }
+
+    if plain_start < text.len() {
+        segments.push(Segment::Text(&text[plain_start..]));
+    }
+    segments
+}
+
+/// Text shown for a reference; full commit SHAs are shortened like on GitHub.
+pub(super) fn display_text<'a>(text: &'a str, reference: &Reference) -> &'a str {
+    match reference {
+        Reference::Commit(_) if text.len() == FULL_SHA_LENGTH => &text[..SHORT_SHA_LENGTH],
+        _ => text,
+    }
+}
+
+/// Whether a reference may begin right after `previous`.
+fn starts_reference(previous: char) -> bool {
+    !(previous.is_alphanumeric() || matches!(previous, '_' | '-' | '.' | '/' | '@' | '#'))
+}
+
+fn match_reference(rest: &str) -> Option<(usize, Reference)> {
+    if rest.starts_with("https://") || rest.starts_with("http://") {
+        let length = url_length(rest);
+        return Some((length, Reference::Url(rest[..length].to_owned())));
+    }
+    if let Some(after) = rest.strip_prefix('@') {
+        let length = mention_length(after)?;
+        return Some((length + 1, Reference::Mention(after[..length].to_owned())));
+    }
+    if let Some(after) = rest.strip_prefix('#') {
+        let (length, number) = issue_number(after)?;
+        return Some((
+            length + 1,
+            Reference::Issue {
+                repository: None,
+                number,
+            },
+        ));
+    }
+    if let Some(reference) = match_cross_repository_issue(rest) {
+        return Some(reference);
+    }
+    match_commit(rest)
+}
+
+/// Length of a bare URL, leaving off trailing punctuation and a closing
+/// parenthesis that belongs to the surrounding prose.
+fn url_length(rest: &str) -> usize {
+    let mut url = rest
+        .find(|ch: char| ch.is_whitespace() || matches!(ch, '<' | '>' | '"'))
+        .map_or(rest, |end| &rest[..end]);
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*', '_']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(inner) if inner.matches('(').count() < trimmed.matches(')').count() => inner,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url.len();
+        }
+        url = trimmed;
+    }
+}
+
+fn mention_length(after: &str) -> Option<usize> {
+    let login = leading(after, |ch| ch.is_ascii_alphanumeric() || ch == '-');
+    if login == 0
+        || login > MAX_LOGIN_LENGTH
+        || after.starts_with('-')
+        || after[..login].ends_with('-')
+    {
+        return None;
+    }
+    let mut length = login;
+    if let Some(team) = after[login..].strip_prefix('/') {
+        let team_length = leading(team, |ch| {
+            ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_')
+        });
+        if team_length > 0 {
+            length += 1 + team_length;
+        }
+    }
+    ends_word(&after[length..]).then_some(length)
+}
+
+fn issue_number(after: &str) -> Option<(usize, u64)> {
+    let digits = leading(after, |ch| ch.is_ascii_digit());
+    if digits == 0 || !ends_word(&after[digits..]) {
+        return None;
+    }
+    let number = after[..digits].parse().ok().filter(|number| *number > 0)?;
+    Some((digits, number))
+}
+
+fn match_cross_repository_issue(rest: &str) -> Option<(usize, Reference)> {
+    let owner = leading(rest, |ch| ch.is_ascii_alphanumeric() || ch == '-');
+    if owner == 0 {
+        return None;
+    }
+    let after_owner = rest[owner..].strip_prefix('/')?;
+    let repo = leading(after_owner, |ch| {
+        ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')
+    });
+    if repo == 0 {
+        return None;
+    }
+    let after_repo = after_owner[repo..].strip_prefix('#')?;
+    let (digits, number) = issue_number(after_repo)?;
+    Some((
+        owner + 1 + repo + 1 + digits,
+        Reference::Issue {
+            repository: Some((rest[..owner].to_owned(), after_owner[..repo].to_owned())),
+            number,
+        },
+    ))
+}
+
+/// Matches 7–40 lowercase hex digits containing both a digit and a letter, so
+/// plain numbers and words like `decade` are left alone.
+fn match_commit(rest: &str) -> Option<(usize, Reference)> {
+    let length = leading(rest, |ch| matches!(ch, '0'..='9' | 'a'..='f'));
+    let sha = &rest[..length];
+    let plausible = (SHORT_SHA_LENGTH..=FULL_SHA_LENGTH).contains(&length)
+        && ends_word(&rest[length..])
+        && sha.contains(|ch: char| ch.is_ascii_digit())
+        && sha.contains(|ch: char| ch.is_ascii_lowercase());
+    plausible.then(|| (length, Reference::Commit(sha.to_owned())))
+}
+
+/// Byte length of the ASCII prefix of `text` matching `accept`.
+fn leading(text: &str, accept: impl Fn(char) -> bool) -> usize {
+    text.find(|ch: char| !accept(ch)).unwrap_or(text.len())
+}
+
+fn ends_word(rest: &str) -> bool {
+    rest.chars()
+        .next()
+        .is_none_or(|ch| !(ch.is_alphanumeric() || ch == '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reference, Segment, split};
+
+    fn references(text: &str) -> Vec<(&str, Reference)> {
+        split(text)
+            .into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Reference { text, reference } => Some((text, reference)),
+                Segment::Text(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recognizes_github_references() {
+        let found = references(
+            "cc @octo-cat and @acme/reviewers: fixed in #12 and acme/widgets#45 \
+             by 1a2b3c4d (see https://example.com/docs).",
+        );
+
+        assert_eq!(
+            found,
+            vec![
+                ("@octo-cat", Reference::Mention("octo-cat".to_owned())),
+                (
+                    "@acme/reviewers",
+                    Reference::Mention("acme/reviewers".to_owned())
+                ),
+                (
+                    "#12",
+                    Reference::Issue {
+                        repository: None,
+                        number: 12
+                    }
+                ),
+                (
+                    "acme/widgets#45",
+                    Reference::Issue {
+                        repository: Some(("acme".to_owned(), "widgets".to_owned())),
+                        number: 45
+                    }
+                ),
+                ("1a2b3c4d", Reference::Commit("1a2b3c4d".to_owned())),
+                (
+                    "https://example.com/docs",
+                    Reference::Url("https://example.com/docs".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lookalikes_inside_words() {
+        assert!(
+            references("mail me@example.com about C#1 or build 20240101 and facade").is_empty()
+        );
+        assert_eq!(split("plain text"), vec![Segment::Text("plain text")]);
+    }
+}
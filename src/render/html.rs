@@ -0,0 +1,187 @@
+//! Minimal scanner for the HTML that GitHub comments commonly embed.
+//!
+//! Only the tags that change the preview layout are recognized; everything
+//! else is dropped and the text between tags is kept.
+
+/// A piece of embedded HTML relevant to the preview.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) enum HtmlToken {
+    /// `<details>`; `open` when the section starts expanded.
+    DetailsStart {
+        open: bool,
+    },
+    DetailsEnd,
+    Summary(String),
+    LineBreak,
+    Image {
+        alt: String,
+        src: String,
+    },
+    Text(String),
+}
+
+/// Splits an HTML fragment into [`HtmlToken`]s.
+pub(super) fn scan(html: &str) -> Vec<HtmlToken> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut tokens, rest);
+            break;
+        };
+        push_text(&mut tokens, &rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            push_text(&mut tokens, rest);
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|ch: char| ch.is_whitespace() || ch == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let closing = tag.starts_with('/');
+        match (name.as_str(), closing) {
+            ("details", false) => tokens.push(HtmlToken::DetailsStart {
+                open: attribute(tag, "open").is_some(),
+            }),
+            ("details", true) => tokens.push(HtmlToken::DetailsEnd),
+            ("summary", false) => {
+                let (summary, after) = match find_ascii_case_insensitive(rest, "</summary>") {
+                    Some(close) => (&rest[..close], &rest[close + "</summary>".len()..]),
+                    None => (rest, ""),
+                };
+                tokens.push(HtmlToken::Summary(collapse_whitespace(&strip_tags(
+                    summary,
+                ))));
+                rest = after;
+            }
+            ("br" | "p" | "div", _) => tokens.push(HtmlToken::LineBreak),
+            ("img", false) => tokens.push(HtmlToken::Image {
+                alt: attribute(tag, "alt").unwrap_or_default(),
+                src: attribute(tag, "src").unwrap_or_default(),
+            }),
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+fn push_text(tokens: &mut Vec<HtmlToken>, text: &str) {
+    let text = collapse_whitespace(text);
+    if !text.is_empty() {
+        tokens.push(HtmlToken::Text(text));
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Returns an attribute's value (empty for bare attributes like `open`).
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag.split_once(char::is_whitespace)?.1;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest
+            .find(|ch: char| ch == '=' || ch.is_whitespace() || ch == '/')
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = if let Some(after_equals) = rest.strip_prefix('=') {
+            let after_equals = after_equals.trim_start();
+            let (value, after) = match after_equals.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_equals[1..];
+                    let close = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_equals
+                        .find(char::is_whitespace)
+                        .unwrap_or(after_equals.len());
+                    (&after_equals[..end], &after_equals[end..])
+                }
+            };
+            rest = after;
+            value
+        } else {
+            if key.is_empty() {
+                rest = rest.get(1..).unwrap_or("");
+                continue;
+            }
+            ""
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value.to_owned());
+        }
+    }
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HtmlToken, scan};
+
+    #[test]
+    fn scans_details_summary_and_images() {
+        let tokens = scan(
+            "<details open>\n<summary><b>Coverage</b> report</summary>\n<!-- bot -->\
+             <img src=\"https://example.com/a.png\" alt='chart' /><br>done</details>",
+        );
+
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlToken::DetailsStart { open: true },
+                HtmlToken::Summary("Coverage report".to_owned()),
+                HtmlToken::Image {
+                    alt: "chart".to_owned(),
+                    src: "https://example.com/a.png".to_owned(),
+                },
+                HtmlToken::LineBreak,
+                HtmlToken::Text("done".to_owned()),
+                HtmlToken::DetailsEnd,
+            ]
+        );
+        assert_eq!(
+            scan("<details>"),
+            vec![HtmlToken::DetailsStart { open: false }]
+        );
+    }
+}
@@ -1,16 +1,26 @@
 //! Markdown rendering for the right pane preview.
 
 use crate::{
-    domain::PullRequestDiffFile,
-    render::syntax::SyntaxHighlighter,
+    config::SyntaxConfig,
+    domain::{PullRequestDiffFile, PullRequestSummary},
+    render::{
+        autolink::{self, Reference, Segment},
+        html::{self, HtmlToken},
+        syntax::SyntaxHighlighter,
+        table::{Cell, layout_table},
+    },
     ui::{theme, theme::ThemeMode},
 };
-use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use anyhow::{Result, bail};
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd,
+};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
 };
 use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
 
 type SyntaxColorLine = Vec<Option<Color>>;
 
@@ -31,6 +41,9 @@ struct CodeBlockState {
 pub struct MarkdownRenderer {
     syntax: SyntaxHighlighter,
     diff_cache: HashMap<DiffCacheKey, DiffFileHighlights>,
+    link_context: Option<LinkContext>,
+    /// Links found by renders since the last [`MarkdownRenderer::take_links`].
+    links: Vec<PreviewLink>,
 }
 
 impl MarkdownRenderer {
@@ -38,6 +51,8 @@ impl MarkdownRenderer {
         Self {
             syntax: SyntaxHighlighter::new(),
             diff_cache: HashMap::new(),
+            link_context: None,
+            links: Vec::new(),
         }
     }
 
@@ -45,12 +60,37 @@ impl MarkdownRenderer {
         self.diff_cache.clear();
     }
 
-    pub fn set_ocean_theme(&mut self, mode: ThemeMode) -> bool {
-        let changed = self.syntax.set_ocean_theme(mode);
-        if changed {
+    /// Loads custom syntaxes and themes and applies file glob mappings.
+    pub fn configure_syntax(&mut self, config: &SyntaxConfig) -> Result<()> {
+        self.diff_cache.clear();
+        self.syntax
+            .load_custom_assets(config.syntaxes_dir.as_deref(), config.themes_dir.as_deref())?;
+        let unknown = self.syntax.set_path_syntaxes(config.globs.clone());
+        if !unknown.is_empty() {
+            bail!("unknown syntax in `syntax.globs`: {}", unknown.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Switches code highlighting to `preferred`, or the ocean theme for `mode`.
+    /// Returns `false` if `preferred` is not a known syntect theme.
+    pub fn set_syntax_theme(&mut self, preferred: Option<&str>, mode: ThemeMode) -> bool {
+        let previous = self.syntax.current_theme_name().to_owned();
+        let found = self.syntax.set_preferred_theme(preferred, mode);
+        if self.syntax.current_theme_name() != previous {
             self.diff_cache.clear();
         }
-        changed
+        found
+    }
+
+    /// Sets the repository that `#123`, mentions and commit SHAs resolve against.
+    pub fn set_link_context(&mut self, context: Option<LinkContext>) {
+        self.link_context = context;
+    }
+
+    /// Returns the links found by renders since the previous call, in order.
+    pub fn take_links(&mut self) -> Vec<PreviewLink> {
+        std::mem::take(&mut self.links)
     }
 
     fn current_syntax_theme_name(&self) -> &str {
@@ -84,13 +124,23 @@ impl MarkdownRenderer {
     }
 
     /// Renders markdown into styled ratatui lines.
-    pub fn render(&mut self, text: &str) -> Vec<Line<'static>> {
+    pub fn render(&mut self, text: &str, options: MarkdownOptions) -> Vec<Line<'static>> {
         let parser = Parser::new_ext(text, Options::all());
 
-        let mut lines: Vec<Vec<Span<'static>>> = vec![Vec::new()];
+        let mut out = LineWriter::default();
         let mut style_stack: Vec<Style> = vec![theme::text()];
         let mut list_stack: Vec<ListState> = Vec::new();
         let mut in_code_block: Option<CodeBlockState> = None;
+        let mut table: Option<TableState> = None;
+        let mut details = DetailsState::default();
+        let mut image_targets: Vec<String> = Vec::new();
+        let mut link_depth = 0usize;
+        let mut links = LinkCollector {
+            context: self.link_context.as_ref(),
+            found: Vec::new(),
+            offset: self.links.len(),
+            hint_total: options.link_hints,
+        };
 
         for event in parser {
             if let Some(code) = in_code_block.as_mut() {
@@ -103,9 +153,9 @@ impl MarkdownRenderer {
                             for span in &mut line.spans {
                                 span.style.bg = None;
                             }
-                            lines.push(line.spans);
+                            out.push_line(line.spans);
                         }
-                        lines.push(Vec::new());
+                        out.blank_line();
                         in_code_block = None;
                     }
                     _ => {}
@@ -113,14 +163,43 @@ impl MarkdownRenderer {
                 continue;
             }
 
+            match &event {
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    for token in html::scan(html) {
+                        render_html_token(&mut out, &mut details, &mut links, token, options);
+                    }
+                    continue;
+                }
+                Event::Start(Tag::HtmlBlock) | Event::End(TagEnd::HtmlBlock) => continue,
+                _ => {}
+            }
+            // A `<details>` without `<summary>` gets a generic title.
+            if details.awaiting_summary.is_some() {
+                details.open_section(&mut out, "Details", options);
+            }
+            if details.hidden > 0 {
+                continue;
+            }
+
             match event {
                 Event::Start(tag) => match tag {
                     Tag::Paragraph => {}
                     Tag::Heading { .. } => {
-                        push_nonempty_newline(&mut lines);
+                        out.push_nonempty_newline();
                         let base = *style_stack.last().unwrap_or(&Style::default());
                         style_stack.push(base.add_modifier(Modifier::BOLD));
                     }
+                    Tag::BlockQuote(kind) => {
+                        out.push_nonempty_newline();
+                        out.quote_depth += 1;
+                        if let Some(kind) = kind {
+                            out.push_span(Span::styled(
+                                alert_title(kind),
+                                theme::info().add_modifier(Modifier::BOLD),
+                            ));
+                            out.push_nonempty_newline();
+                        }
+                    }
                     Tag::Strong => {
                         let base = *style_stack.last().unwrap_or(&Style::default());
                         style_stack.push(base.add_modifier(Modifier::BOLD));
@@ -142,7 +221,7 @@ impl MarkdownRenderer {
                         });
                     }
                     Tag::Item => {
-                        push_nonempty_newline(&mut lines);
+                        out.push_nonempty_newline();
                         let depth = list_stack.len().saturating_sub(1);
                         let indent = "  ".repeat(depth);
                         let marker = match list_stack.last_mut() {
@@ -153,10 +232,10 @@ impl MarkdownRenderer {
                             }
                             _ => format!("{indent}- "),
                         };
-                        push_span(&mut lines, Span::styled(marker, theme::dim()));
+                        out.push_span(Span::styled(marker, theme::dim()));
                     }
                     Tag::CodeBlock(kind) => {
-                        push_nonempty_newline(&mut lines);
+                        out.push_nonempty_newline();
                         let language = match kind {
                             CodeBlockKind::Fenced(lang) => lang.to_string(),
                             CodeBlockKind::Indented => "text".to_owned(),
@@ -166,68 +245,563 @@ impl MarkdownRenderer {
                             content: String::new(),
                         });
                     }
-                    Tag::Link { .. } => {
+                    Tag::Link {
+                        link_type,
+                        dest_url,
+                        ..
+                    } => {
+                        if link_type == LinkType::Email {
+                            links.record(&mut out, &format!("mailto:{dest_url}"));
+                        } else {
+                            links.record(&mut out, &dest_url);
+                        }
+                        link_depth += 1;
                         let base = *style_stack.last().unwrap_or(&Style::default());
                         style_stack.push(
                             base.add_modifier(Modifier::UNDERLINED)
                                 .fg(theme::link_color()),
                         );
                     }
+                    Tag::Image { dest_url, .. } => {
+                        links.record(&mut out, &dest_url);
+                        out.push_span(Span::styled("[image: ", theme::dim()));
+                        let base = *style_stack.last().unwrap_or(&Style::default());
+                        style_stack.push(base.add_modifier(Modifier::ITALIC));
+                        image_targets.push(dest_url.to_string());
+                    }
+                    Tag::Table(alignments) => {
+                        out.push_nonempty_newline();
+                        table = Some(TableState {
+                            alignments,
+                            rows: Vec::new(),
+                        });
+                    }
+                    Tag::TableHead => {
+                        let base = *style_stack.last().unwrap_or(&Style::default());
+                        style_stack.push(base.add_modifier(Modifier::BOLD));
+                        if let Some(table) = table.as_mut() {
+                            table.rows.push(Vec::new());
+                        }
+                    }
+                    Tag::TableRow => {
+                        if let Some(table) = table.as_mut() {
+                            table.rows.push(Vec::new());
+                        }
+                    }
+                    Tag::TableCell => {
+                        out.cell = Some(Vec::new());
+                    }
+                    Tag::FootnoteDefinition(label) => {
+                        out.push_nonempty_newline();
+                        out.push_span(Span::styled(format!("[^{label}]: "), theme::dim()));
+                    }
                     _ => {}
                 },
                 Event::End(tag_end) => match tag_end {
                     TagEnd::Paragraph => {
-                        push_nonempty_newline(&mut lines);
-                        lines.push(Vec::new());
+                        out.push_nonempty_newline();
+                        out.blank_line();
                     }
                     TagEnd::Heading(_) => {
                         pop_style_if_possible(&mut style_stack);
-                        push_nonempty_newline(&mut lines);
-                        lines.push(Vec::new());
+                        out.push_nonempty_newline();
+                        out.blank_line();
+                    }
+                    TagEnd::BlockQuote(_) => {
+                        out.trim_trailing_blank_lines();
+                        out.quote_depth = out.quote_depth.saturating_sub(1);
+                        out.push_nonempty_newline();
+                        out.blank_line();
                     }
-                    TagEnd::Strong | TagEnd::Emphasis | TagEnd::Strikethrough | TagEnd::Link => {
+                    TagEnd::Strong | TagEnd::Emphasis | TagEnd::Strikethrough => {
                         pop_style_if_possible(&mut style_stack);
                     }
+                    TagEnd::Link => {
+                        link_depth = link_depth.saturating_sub(1);
+                        pop_style_if_possible(&mut style_stack);
+                    }
+                    TagEnd::Image => {
+                        pop_style_if_possible(&mut style_stack);
+                        let target = image_targets.pop().unwrap_or_default();
+                        out.push_span(Span::styled("]", theme::dim()));
+                        if !target.is_empty() {
+                            out.push_span(Span::styled(
+                                format!(" {target}"),
+                                Style::default().fg(theme::link_color()),
+                            ));
+                        }
+                    }
                     TagEnd::Item => {
-                        push_nonempty_newline(&mut lines);
+                        out.push_nonempty_newline();
                     }
                     TagEnd::List(_) => {
                         list_stack.pop();
-                        push_nonempty_newline(&mut lines);
+                        out.push_nonempty_newline();
+                    }
+                    TagEnd::TableHead => {
+                        pop_style_if_possible(&mut style_stack);
+                    }
+                    TagEnd::TableCell => {
+                        let cell = out.cell.take().unwrap_or_default();
+                        if let Some(row) = table.as_mut().and_then(|table| table.rows.last_mut()) {
+                            row.push(cell);
+                        }
+                    }
+                    TagEnd::Table => {
+                        if let Some(table) = table.take() {
+                            let width = usize::from(options.width)
+                                .saturating_sub(out.quote_depth * QUOTE_GUTTER.width());
+                            for line in layout_table(&table.rows, &table.alignments, width) {
+                                out.push_line(line);
+                            }
+                        }
+                        out.blank_line();
+                    }
+                    TagEnd::FootnoteDefinition => {
+                        out.push_nonempty_newline();
                     }
                     TagEnd::CodeBlock => {}
                     _ => {}
                 },
                 Event::Text(content) => {
                     let style = *style_stack.last().unwrap_or(&Style::default());
-                    push_span(&mut lines, Span::styled(content.to_string(), style));
+                    if link_depth > 0 {
+                        out.push_span(Span::styled(content.to_string(), style));
+                    } else {
+                        links.push_text(&mut out, &content, style);
+                    }
                 }
                 Event::Code(content) => {
-                    push_span(
-                        &mut lines,
-                        Span::styled(format!("`{content}`"), theme::inline_code()),
-                    );
+                    out.push_span(Span::styled(format!("`{content}`"), theme::inline_code()));
+                }
+                Event::TaskListMarker(checked) => {
+                    let (marker, style) = if checked {
+                        ("[x] ", theme::info())
+                    } else {
+                        ("[ ] ", theme::dim())
+                    };
+                    out.push_span(Span::styled(marker, style));
+                }
+                Event::FootnoteReference(label) => {
+                    out.push_span(Span::styled(
+                        format!("[^{label}]"),
+                        Style::default().fg(theme::link_color()),
+                    ));
                 }
                 Event::SoftBreak | Event::HardBreak => {
-                    push_nonempty_newline(&mut lines);
+                    out.push_nonempty_newline();
                 }
                 Event::Rule => {
-                    push_nonempty_newline(&mut lines);
-                    push_span(
-                        &mut lines,
-                        Span::styled("────────────────────────────────────────", theme::dim()),
-                    );
-                    push_nonempty_newline(&mut lines);
+                    out.push_nonempty_newline();
+                    out.push_span(Span::styled(
+                        "────────────────────────────────────────",
+                        theme::dim(),
+                    ));
+                    out.push_nonempty_newline();
                 }
                 _ => {}
             }
         }
 
-        while lines.last().is_some_and(|line| line.is_empty()) && lines.len() > 1 {
-            lines.pop();
+        let found = links.found;
+        self.links.extend(found);
+        out.finish()
+    }
+}
+
+/// Layout settings for [`MarkdownRenderer::render`].
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownOptions {
+    /// Columns available to the rendered text; tables are fitted to it.
+    pub width: u16,
+    /// Show the contents of `<details>` sections that are collapsed by default.
+    pub expand_details: bool,
+    /// Prefix each link with its hint label. Holds the number of links in the
+    /// whole preview so every label has the same length.
+    pub link_hints: Option<usize>,
+}
+
+impl MarkdownOptions {
+    pub fn new(width: u16) -> Self {
+        Self {
+            width,
+            expand_details: false,
+            link_hints: None,
+        }
+    }
+
+    /// Narrows the width for text that will be indented by `columns`.
+    pub fn indented(self, columns: usize) -> Self {
+        Self {
+            width: self
+                .width
+                .saturating_sub(u16::try_from(columns).unwrap_or(u16::MAX)),
+            ..self
+        }
+    }
+}
+
+/// Repository the rendered comments belong to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LinkContext {
+    /// Base URL for web pages, e.g. `https://github.com`.
+    pub web_url: String,
+    pub owner: String,
+    pub repo: String,
+}
+
+impl LinkContext {
+    /// Context for comments on `pull`, taking the host from its web URL.
+    pub fn for_pull(pull: &PullRequestSummary) -> Self {
+        let path = format!("/{}/{}/pull/", pull.owner, pull.repo);
+        let web_url = pull
+            .html_url
+            .as_deref()
+            .and_then(|url| url.find(&path).map(|index| url[..index].to_owned()))
+            .unwrap_or_else(|| "https://github.com".to_owned());
+        Self {
+            web_url,
+            owner: pull.owner.clone(),
+            repo: pull.repo.clone(),
+        }
+    }
+
+    fn repository_url(&self) -> String {
+        format!("{}/{}/{}", self.web_url, self.owner, self.repo)
+    }
+
+    /// Number of the issue or pull request in this repository that `url` points to.
+    pub fn number_in(&self, url: &str) -> Option<u64> {
+        let prefix = self.repository_url();
+        url.get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(&prefix))?;
+        let rest = &url[prefix.len()..];
+        let rest = rest
+            .strip_prefix("/pull/")
+            .or_else(|| rest.strip_prefix("/issues/"))?;
+        let digits = rest
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(rest.len());
+        rest[..digits].parse().ok()
+    }
+
+    fn is_same_repository(&self, owner: &str, repo: &str) -> bool {
+        self.owner.eq_ignore_ascii_case(owner) && self.repo.eq_ignore_ascii_case(repo)
+    }
+
+    fn resolve(&self, reference: &Reference) -> PreviewLink {
+        let (url, number) = match reference {
+            Reference::Url(url) => (url.clone(), self.number_in(url)),
+            Reference::Mention(name) => match name.split_once('/') {
+                Some((org, team)) => (format!("{}/orgs/{org}/teams/{team}", self.web_url), None),
+                None => (format!("{}/{name}", self.web_url), None),
+            },
+            Reference::Issue {
+                repository: Some((owner, repo)),
+                number,
+            } if !self.is_same_repository(owner, repo) => (
+                format!("{}/{owner}/{repo}/issues/{number}", self.web_url),
+                None,
+            ),
+            Reference::Issue { number, .. } => (
+                format!("{}/issues/{number}", self.repository_url()),
+                Some(*number),
+            ),
+            Reference::Commit(sha) => (format!("{}/commit/{sha}", self.repository_url()), None),
+        };
+        PreviewLink { url, number }
+    }
+}
+
+/// A link found while rendering.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PreviewLink {
+    pub url: String,
+    /// Issue or pull request number when the link points into the current repository.
+    pub number: Option<u64>,
+}
+
+/// Keys used for link hint labels, home row first.
+const HINT_ALPHABET: &[u8] = b"asdfghjklqwertyuiopzxcvbnm";
+
+/// Label for the `index`th of `total` links. Labels share one length so none is
+/// a prefix of another.
+pub fn link_hint_label(index: usize, total: usize) -> String {
+    let base = HINT_ALPHABET.len();
+    let mut length = 1;
+    let mut capacity = base;
+    while capacity < total {
+        length += 1;
+        capacity = capacity.saturating_mul(base);
+    }
+
+    let mut label = vec![' '; length];
+    let mut rest = index;
+    for slot in label.iter_mut().rev() {
+        *slot = char::from(HINT_ALPHABET[rest % base]);
+        rest /= base;
+    }
+    label.into_iter().collect()
+}
+
+/// Records link targets during a render and, in link-hint mode, writes each
+/// link's label in front of it.
+struct LinkCollector<'a> {
+    context: Option<&'a LinkContext>,
+    found: Vec<PreviewLink>,
+    /// Links recorded by earlier renders; labels continue after them.
+    offset: usize,
+    hint_total: Option<usize>,
+}
+
+impl LinkCollector<'_> {
+    fn record(&mut self, out: &mut LineWriter, url: &str) {
+        let url = url.trim();
+        if url.is_empty() || url.starts_with('#') {
+            return;
+        }
+        let number = self.context.and_then(|context| context.number_in(url));
+        self.push(
+            out,
+            PreviewLink {
+                url: url.to_owned(),
+                number,
+            },
+        );
+    }
+
+    fn push(&mut self, out: &mut LineWriter, link: PreviewLink) {
+        if let Some(total) = self.hint_total {
+            let label = link_hint_label(self.offset + self.found.len(), total);
+            out.push_span(Span::styled(format!("[{label}]"), theme::selected()));
+        }
+        self.found.push(link);
+    }
+
+    /// Writes plain text, styling and recording the references in it.
+    fn push_text(&mut self, out: &mut LineWriter, text: &str, style: Style) {
+        for segment in autolink::split(text) {
+            match segment {
+                Segment::Text(text) => out.push_span(Span::styled(text.to_owned(), style)),
+                Segment::Reference { text, reference } => {
+                    let target = match (&reference, self.context) {
+                        (_, Some(context)) => Some(context.resolve(&reference)),
+                        (Reference::Url(url), None) => Some(PreviewLink {
+                            url: url.clone(),
+                            number: None,
+                        }),
+                        _ => None,
+                    };
+                    if let Some(link) = target {
+                        self.push(out, link);
+                    }
+                    let reference_style = match reference {
+                        Reference::Mention(_) => style.patch(theme::author()),
+                        _ => style.fg(theme::link_color()),
+                    };
+                    out.push_span(Span::styled(
+                        autolink::display_text(text, &reference).to_owned(),
+                        reference_style,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+const QUOTE_GUTTER: &str = "▎ ";
+
+/// Accumulates rendered lines, remembering the blockquote depth of each so the
+/// gutter can be drawn once the whole document is laid out.
+#[derive(Default)]
+struct LineWriter {
+    lines: Vec<(usize, Vec<Span<'static>>)>,
+    quote_depth: usize,
+    /// Spans of the table cell being read, which are laid out separately.
+    cell: Option<Cell>,
+}
+
+impl LineWriter {
+    fn push_span(&mut self, span: Span<'static>) {
+        if let Some(cell) = self.cell.as_mut() {
+            cell.push(span);
+            return;
+        }
+        match self.lines.last_mut() {
+            Some((depth, line)) => {
+                if line.is_empty() {
+                    *depth = self.quote_depth;
+                }
+                line.push(span);
+            }
+            None => self.lines.push((self.quote_depth, vec![span])),
+        }
+    }
+
+    /// Appends a complete line and starts a new one after it.
+    fn push_line(&mut self, spans: Vec<Span<'static>>) {
+        self.push_nonempty_newline();
+        for span in spans {
+            self.push_span(span);
+        }
+        self.lines.push((self.quote_depth, Vec::new()));
+    }
+
+    fn push_nonempty_newline(&mut self) {
+        if self.cell.is_some() {
+            self.push_span(Span::raw(" "));
+            return;
+        }
+        if self.lines.last().is_none_or(|(_, line)| line.is_empty()) {
+            if let Some((depth, _)) = self.lines.last_mut() {
+                *depth = self.quote_depth;
+            }
+            return;
+        }
+        self.lines.push((self.quote_depth, Vec::new()));
+    }
+
+    /// Ends the current line and leaves an empty separator line after it.
+    fn blank_line(&mut self) {
+        self.push_nonempty_newline();
+        self.lines.push((self.quote_depth, Vec::new()));
+    }
+
+    fn trim_trailing_blank_lines(&mut self) {
+        while self.lines.last().is_some_and(|(_, line)| line.is_empty()) {
+            self.lines.pop();
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        self.trim_trailing_blank_lines();
+        if self.lines.is_empty() {
+            return vec![Line::default()];
+        }
+
+        self.lines
+            .into_iter()
+            .map(|(depth, spans)| {
+                if depth == 0 {
+                    return Line::from(spans);
+                }
+                let mut line = vec![Span::styled(QUOTE_GUTTER.repeat(depth), theme::dim())];
+                line.extend(spans);
+                Line::from(line)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TableState {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Cell>>,
+}
+
+/// Tracks `<details>` sections while rendering.
+#[derive(Debug, Clone, Default)]
+struct DetailsState {
+    /// Set between `<details>` and its `<summary>`; `true` for `<details open>`.
+    awaiting_summary: Option<bool>,
+    /// Nesting depth inside a collapsed section; its content is skipped.
+    hidden: usize,
+}
+
+impl DetailsState {
+    fn open_section(&mut self, out: &mut LineWriter, summary: &str, options: MarkdownOptions) {
+        let open = self.awaiting_summary.take().unwrap_or(false) || options.expand_details;
+        out.push_nonempty_newline();
+        let marker = if open { "▾ " } else { "▸ " };
+        out.push_span(Span::styled(marker, theme::dim()));
+        out.push_span(Span::styled(
+            summary.to_owned(),
+            theme::text().add_modifier(Modifier::BOLD),
+        ));
+        out.push_nonempty_newline();
+        if !open {
+            self.hidden = 1;
+        }
+    }
+}
+
+fn render_html_token(
+    out: &mut LineWriter,
+    details: &mut DetailsState,
+    links: &mut LinkCollector<'_>,
+    token: HtmlToken,
+    options: MarkdownOptions,
+) {
+    if details.hidden > 0 {
+        match token {
+            HtmlToken::DetailsStart { .. } => details.hidden += 1,
+            HtmlToken::DetailsEnd => details.hidden -= 1,
+            _ => {}
         }
+        return;
+    }
 
-        lines.into_iter().map(Line::from).collect()
+    match token {
+        HtmlToken::DetailsStart { open } => {
+            if details.awaiting_summary.is_some() {
+                details.open_section(out, "Details", options);
+                if details.hidden > 0 {
+                    details.hidden += 1;
+                    return;
+                }
+            }
+            details.awaiting_summary = Some(open);
+        }
+        HtmlToken::Summary(summary) => {
+            let summary = if summary.is_empty() {
+                "Details"
+            } else {
+                summary.as_str()
+            };
+            details.open_section(out, summary, options);
+        }
+        HtmlToken::DetailsEnd => {
+            if details.awaiting_summary.is_some() {
+                details.open_section(out, "Details", options);
+                details.hidden = 0;
+            }
+            out.push_nonempty_newline();
+        }
+        HtmlToken::LineBreak => out.push_nonempty_newline(),
+        HtmlToken::Image { alt, src } => {
+            links.record(out, &src);
+            out.push_span(Span::styled("[image: ", theme::dim()));
+            out.push_span(Span::styled(
+                alt,
+                theme::text().add_modifier(Modifier::ITALIC),
+            ));
+            out.push_span(Span::styled("]", theme::dim()));
+            if !src.is_empty() {
+                out.push_span(Span::styled(
+                    format!(" {src}"),
+                    Style::default().fg(theme::link_color()),
+                ));
+            }
+        }
+        HtmlToken::Text(text) => {
+            if details.awaiting_summary.is_some() {
+                details.open_section(out, "Details", options);
+                if details.hidden > 0 {
+                    return;
+                }
+            }
+            out.push_span(Span::styled(text, theme::text()));
+        }
+    }
+}
+
+fn alert_title(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "Note",
+        BlockQuoteKind::Tip => "Tip",
+        BlockQuoteKind::Important => "Important",
+        BlockQuoteKind::Warning => "Warning",
+        BlockQuoteKind::Caution => "Caution",
     }
 }
 
@@ -284,21 +858,110 @@ fn build_diff_side_source(file: &PullRequestDiffFile, side: DiffSide) -> String
     source
 }
 
-fn push_span(lines: &mut Vec<Vec<Span<'static>>>, span: Span<'static>) {
-    if let Some(line) = lines.last_mut() {
-        line.push(span);
+fn pop_style_if_possible(style_stack: &mut Vec<Style>) {
+    if style_stack.len() > 1 {
+        style_stack.pop();
     }
 }
 
-fn push_nonempty_newline(lines: &mut Vec<Vec<Span<'static>>>) {
-    if lines.last().is_some_and(|line| line.is_empty()) {
-        return;
+#[cfg(test)]
+mod tests {
+    use super::{LinkContext, MarkdownOptions, MarkdownRenderer, PreviewLink, link_hint_label};
+    use ratatui::text::Line;
+
+    fn render_text(markdown: &str, options: MarkdownOptions) -> Vec<String> {
+        MarkdownRenderer::default()
+            .render(markdown, options)
+            .iter()
+            .map(Line::to_string)
+            .collect()
     }
-    lines.push(Vec::new());
-}
 
-fn pop_style_if_possible(style_stack: &mut Vec<Style>) {
-    if style_stack.len() > 1 {
-        style_stack.pop();
+    #[test]
+    fn renders_quotes_with_gutter_and_task_list_checkboxes() {
+        let lines = render_text(
+            "> quoted\n\n- [x] done\n- [ ] todo",
+            MarkdownOptions::new(80),
+        );
+
+        assert_eq!(lines, vec!["▎ quoted", "", "- [x] done", "- [ ] todo"]);
+    }
+
+    #[test]
+    fn collapses_details_unless_expanded() {
+        let body = "<details>\n<summary>Logs</summary>\n\nsecret output\n\n</details>\n\nafter";
+
+        let collapsed = render_text(body, MarkdownOptions::new(80));
+        assert_eq!(collapsed, vec!["▸ Logs", "after"]);
+
+        let expanded = render_text(
+            body,
+            MarkdownOptions {
+                expand_details: true,
+                ..MarkdownOptions::new(80)
+            },
+        );
+        assert_eq!(expanded, vec!["▾ Logs", "secret output", "", "after"]);
+    }
+
+    #[test]
+    fn renders_image_placeholder_with_url() {
+        let lines = render_text(
+            "![build status](https://example.com/badge.svg)",
+            MarkdownOptions::new(80),
+        );
+
+        assert_eq!(
+            lines,
+            vec!["[image: build status] https://example.com/badge.svg"]
+        );
+    }
+
+    #[test]
+    fn collects_autolinks_and_labels_them_in_hint_mode() {
+        let mut renderer = MarkdownRenderer::default();
+        renderer.set_link_context(Some(LinkContext {
+            web_url: "https://github.com".to_owned(),
+            owner: "acme".to_owned(),
+            repo: "widgets".to_owned(),
+        }));
+
+        let lines = renderer.render(
+            "@octocat fixed #12 in [the docs](https://github.com/acme/widgets/pull/7)",
+            MarkdownOptions {
+                link_hints: Some(3),
+                ..MarkdownOptions::new(80)
+            },
+        );
+        assert_eq!(
+            lines[0].to_string(),
+            "[a]@octocat fixed [s]#12 in [d]the docs"
+        );
+        assert_eq!(
+            renderer.take_links(),
+            vec![
+                PreviewLink {
+                    url: "https://github.com/octocat".to_owned(),
+                    number: None,
+                },
+                PreviewLink {
+                    url: "https://github.com/acme/widgets/issues/12".to_owned(),
+                    number: Some(12),
+                },
+                PreviewLink {
+                    url: "https://github.com/acme/widgets/pull/7".to_owned(),
+                    number: Some(7),
+                },
+            ]
+        );
+        assert!(renderer.take_links().is_empty());
+    }
+
+    #[test]
+    fn hint_labels_share_a_length() {
+        assert_eq!(link_hint_label(0, 3), "a");
+        assert_eq!(link_hint_label(2, 3), "d");
+        assert_eq!(link_hint_label(0, 30), "aa");
+        assert_eq!(link_hint_label(27, 30), "ss");
     }
 }
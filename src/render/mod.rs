@@ -1,5 +1,8 @@
 //! Rich text renderers for the review pane.
 
+mod autolink;
+mod html;
 pub mod markdown;
 pub mod syntax;
+mod table;
 pub mod thread;
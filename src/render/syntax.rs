@@ -1,10 +1,19 @@
 //! Fenced code highlighting via `tui-syntax-highlight` + `syntect`.
 
-use crate::ui::{theme, theme::ThemeMode};
+use crate::{
+    search::glob::glob_matches,
+    ui::{theme, theme::ThemeMode},
+};
+use anyhow::{Context, Result};
 use ratatui::{
     style::Color,
     text::{Line, Span},
 };
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use syntect::{
     highlighting::ThemeSet,
     parsing::{SyntaxReference, SyntaxSet},
@@ -22,8 +31,17 @@ pub struct SyntaxHighlighter {
     theme_names: Vec<String>,
     active_theme_index: usize,
     highlighter: Highlighter,
+    /// `(glob, syntax name)` pairs checked before extension lookup, most specific first.
+    path_syntaxes: Vec<(String, String)>,
+    /// Fingerprint of the custom asset directories last loaded, so config
+    /// reloads that leave them untouched skip rebuilding the sets.
+    custom_assets: Option<AssetsFingerprint>,
 }
 
+/// Every file under the custom asset directories with its size and
+/// modification time, preceded by the directory itself.
+type AssetsFingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
 impl Default for SyntaxHighlighter {
     fn default() -> Self {
         Self::new()
@@ -47,9 +65,70 @@ impl SyntaxHighlighter {
             theme_names,
             active_theme_index,
             highlighter,
+            path_syntaxes: Vec::new(),
+            custom_assets: None,
         }
     }
 
+    /// Reloads the bundled syntaxes and themes, adding any `.sublime-syntax`
+    /// files from `syntaxes_dir` and `.tmTheme` files from `themes_dir`.
+    ///
+    /// Nothing is rebuilt when the directories hold the same files as the
+    /// last successful load.
+    pub fn load_custom_assets(
+        &mut self,
+        syntaxes_dir: Option<&Path>,
+        themes_dir: Option<&Path>,
+    ) -> Result<()> {
+        let fingerprint = assets_fingerprint(&[syntaxes_dir, themes_dir]);
+        if self.custom_assets.as_ref() == Some(&fingerprint) {
+            return Ok(());
+        }
+
+        let mut syntax_set = SyntaxSet::load_defaults_newlines();
+        if let Some(dir) = syntaxes_dir.filter(|dir| dir.is_dir()) {
+            let mut builder = syntax_set.into_builder();
+            builder
+                .add_from_folder(dir, true)
+                .with_context(|| format!("failed to load syntaxes from {}", dir.display()))?;
+            syntax_set = builder.build();
+        }
+
+        let mut theme_set = ThemeSet::load_defaults();
+        if let Some(dir) = themes_dir.filter(|dir| dir.is_dir()) {
+            theme_set
+                .add_from_folder(dir)
+                .with_context(|| format!("failed to load themes from {}", dir.display()))?;
+        }
+        let mut theme_names: Vec<String> = theme_set.themes.keys().cloned().collect();
+        theme_names.sort();
+
+        let active_theme = self.current_theme_name().to_owned();
+        self.active_theme_index = theme_names
+            .iter()
+            .position(|name| *name == active_theme)
+            .unwrap_or_else(|| select_theme_index(&theme_names));
+        self.syntax_set = syntax_set;
+        self.theme_set = theme_set;
+        self.theme_names = theme_names;
+        self.rebuild_highlighter();
+        self.custom_assets = Some(fingerprint);
+        Ok(())
+    }
+
+    /// Maps file globs to syntax names for diff highlighting. Returns the
+    /// syntax names that do not match any loaded syntax.
+    pub fn set_path_syntaxes(&mut self, mut mappings: Vec<(String, String)>) -> Vec<String> {
+        mappings.sort_by_key(|(glob, _)| std::cmp::Reverse(glob.len()));
+        let unknown = mappings
+            .iter()
+            .filter(|(_, name)| find_syntax_named(&self.syntax_set, name).is_none())
+            .map(|(_, name)| name.clone())
+            .collect();
+        self.path_syntaxes = mappings;
+        unknown
+    }
+
     /// Highlights a fenced code block using a best-effort language lookup.
     pub fn highlight(&self, lang: &str, source: &str) -> Vec<Line<'static>> {
         let syntax = resolve_syntax(&self.syntax_set, lang);
@@ -62,7 +141,12 @@ impl SyntaxHighlighter {
         path: &str,
         source: &str,
     ) -> Vec<Vec<Option<Color>>> {
-        let syntax = resolve_syntax_for_path(&self.syntax_set, path);
+        let syntax = self
+            .path_syntaxes
+            .iter()
+            .find(|(glob, _)| glob_matches(glob, path))
+            .and_then(|(_, name)| find_syntax_named(&self.syntax_set, name))
+            .unwrap_or_else(|| resolve_syntax_for_path(&self.syntax_set, path));
         match self.highlighter.highlight_lines(
             LinesWithEndings::from(source),
             syntax,
@@ -117,6 +201,18 @@ impl SyntaxHighlighter {
         self.set_theme(name)
     }
 
+    /// Activates `preferred` when it names a known theme, otherwise the ocean
+    /// theme for `mode`. Returns `false` if `preferred` was not found.
+    pub fn set_preferred_theme(&mut self, preferred: Option<&str>, mode: ThemeMode) -> bool {
+        if let Some(name) = preferred
+            && self.set_theme(name)
+        {
+            return true;
+        }
+        self.set_ocean_theme(mode);
+        preferred.is_none()
+    }
+
     fn rebuild_highlighter(&mut self) {
         self.highlighter =
             build_highlighter(&self.theme_set, &self.theme_names, self.active_theme_index);
@@ -182,6 +278,16 @@ fn resolve_syntax_for_path<'a>(syntax_set: &'a SyntaxSet, path: &str) -> &'a Syn
         .unwrap_or_else(|| syntax_set.find_syntax_plain_text())
 }
 
+fn find_syntax_named<'a>(syntax_set: &'a SyntaxSet, name: &str) -> Option<&'a SyntaxReference> {
+    syntax_set.find_syntax_by_name(name).or_else(|| {
+        syntax_set
+            .syntaxes()
+            .iter()
+            .find(|syntax| syntax.name.eq_ignore_ascii_case(name))
+            .or_else(|| syntax_set.find_syntax_by_token(name))
+    })
+}
+
 fn normalize_lang(lang: &str) -> String {
     match lang.trim().to_lowercase().as_str() {
         "rs" => "rust".to_owned(),
@@ -199,10 +305,38 @@ fn plain_code_lines(source: &str) -> Vec<Line<'static>> {
         .collect()
 }
 
+fn assets_fingerprint(dirs: &[Option<&Path>]) -> AssetsFingerprint {
+    let mut fingerprint = Vec::new();
+    for dir in dirs.iter().flatten() {
+        fingerprint.push((dir.to_path_buf(), 0, None));
+        let start = fingerprint.len();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    pending.push(path);
+                } else {
+                    fingerprint.push((path, metadata.len(), metadata.modified().ok()));
+                }
+            }
+        }
+        fingerprint[start..].sort();
+    }
+    fingerprint
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SyntaxHighlighter;
+    use super::{SyntaxHighlighter, assets_fingerprint};
     use crate::ui::theme::ThemeMode;
+    use std::fs;
 
     #[test]
     fn includes_ocean_light_theme() {
@@ -225,4 +359,59 @@ mod tests {
         assert!(highlighter.set_ocean_theme(ThemeMode::Dark));
         assert_eq!(highlighter.current_theme_name(), "base16-ocean.dark");
     }
+
+    #[test]
+    fn preferred_theme_falls_back_to_ocean() {
+        let mut highlighter = SyntaxHighlighter::new();
+        assert!(highlighter.set_preferred_theme(Some("Solarized (dark)"), ThemeMode::Light));
+        assert_eq!(highlighter.current_theme_name(), "Solarized (dark)");
+        assert!(!highlighter.set_preferred_theme(Some("no-such-theme"), ThemeMode::Light));
+        assert_eq!(highlighter.current_theme_name(), "base16-ocean.light");
+    }
+
+    #[test]
+    fn loads_custom_syntaxes_and_maps_globs() {
+        let dir = std::env::temp_dir().join(format!("critic-syntax-test-{}", std::process::id()));
+        let syntaxes = dir.join("syntaxes");
+        fs::create_dir_all(&syntaxes).unwrap();
+        fs::write(
+            syntaxes.join("widget.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Widget\nfile_extensions: [widget]\nscope: source.widget\ncontexts:\n  main:\n    - match: '\\b(widget)\\b'\n      scope: keyword.control.widget\n",
+        )
+        .unwrap();
+
+        let mut highlighter = SyntaxHighlighter::new();
+        highlighter
+            .load_custom_assets(Some(&syntaxes), Some(&dir.join("themes")))
+            .unwrap();
+        assert_eq!(highlighter.current_theme_name(), "base16-ocean.dark");
+        assert!(
+            highlighter
+                .set_path_syntaxes(vec![("*.wdg".to_owned(), "widget".to_owned())])
+                .is_empty()
+        );
+        assert_eq!(
+            highlighter.set_path_syntaxes(vec![("*.x".to_owned(), "Nope".to_owned())]),
+            vec!["Nope".to_owned()]
+        );
+
+        let loaded = highlighter.custom_assets.clone();
+        assert_eq!(
+            loaded,
+            Some(assets_fingerprint(&[
+                Some(&syntaxes),
+                Some(&dir.join("themes"))
+            ]))
+        );
+        fs::write(syntaxes.join("notes.txt"), "not a syntax").unwrap();
+        assert_ne!(
+            loaded,
+            Some(assets_fingerprint(&[
+                Some(&syntaxes),
+                Some(&dir.join("themes"))
+            ]))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
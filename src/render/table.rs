@@ -0,0 +1,206 @@
+//! Column layout for GitHub-flavored markdown tables.
+
+use crate::ui::theme;
+use pulldown_cmark::Alignment;
+use ratatui::text::Span;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Styled contents of one table cell.
+pub(super) type Cell = Vec<Span<'static>>;
+
+const COLUMN_SEPARATOR: &str = " │ ";
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// Lays out `rows` (the first is the header) in columns no wider than `width`
+/// in total, wrapping cell text when the natural column widths do not fit.
+pub(super) fn layout_table(
+    rows: &[Vec<Cell>],
+    alignments: &[Alignment],
+    width: usize,
+) -> Vec<Vec<Span<'static>>> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return Vec::new();
+    }
+
+    let mut widths = vec![1usize; columns];
+    for row in rows {
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(cell_width(cell));
+        }
+    }
+    let separators = COLUMN_SEPARATOR.width() * (columns - 1);
+    fit_column_widths(&mut widths, width.saturating_sub(separators));
+
+    let mut lines = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let wrapped: Vec<Vec<Cell>> = widths
+            .iter()
+            .enumerate()
+            .map(|(column, column_width)| {
+                row.get(column)
+                    .map_or_else(|| vec![Vec::new()], |cell| wrap_cell(cell, *column_width))
+            })
+            .collect();
+        let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+
+        for line_index in 0..height {
+            let mut line = Vec::new();
+            for (column, cell_lines) in wrapped.iter().enumerate() {
+                if column > 0 {
+                    line.push(Span::styled(COLUMN_SEPARATOR, theme::dim()));
+                }
+                let cell_line = cell_lines.get(line_index).cloned().unwrap_or_default();
+                let alignment = alignments.get(column).copied().unwrap_or(Alignment::None);
+                line.extend(pad_cell_line(cell_line, widths[column], alignment));
+            }
+            lines.push(line);
+        }
+
+        if index == 0 {
+            let rule = widths
+                .iter()
+                .map(|width| "─".repeat(*width))
+                .collect::<Vec<_>>()
+                .join("─┼─");
+            lines.push(vec![Span::styled(rule, theme::dim())]);
+        }
+    }
+
+    lines
+}
+
+/// Shrinks the widest columns until the table fits, never below
+/// [`MIN_COLUMN_WIDTH`] (or a column's natural width if that is smaller).
+fn fit_column_widths(widths: &mut [usize], available: usize) {
+    while widths.iter().sum::<usize>() > available {
+        let Some((widest, _)) = widths
+            .iter()
+            .enumerate()
+            .filter(|(_, width)| **width > MIN_COLUMN_WIDTH)
+            .max_by_key(|(index, width)| (**width, std::cmp::Reverse(*index)))
+        else {
+            return;
+        };
+        widths[widest] -= 1;
+    }
+}
+
+fn cell_width(cell: &Cell) -> usize {
+    cell.iter().map(|span| span.content.width()).sum()
+}
+
+/// Word-wraps styled cell text to `width` columns, splitting words that are
+/// longer than a whole line.
+fn wrap_cell(cell: &Cell, width: usize) -> Vec<Cell> {
+    let mut lines: Vec<Cell> = vec![Vec::new()];
+    let mut line_width = 0usize;
+
+    for span in cell {
+        for word in span.content.split_inclusive(' ') {
+            let word_width = word.trim_end().width();
+            if line_width > 0 && line_width + word_width > width {
+                lines.push(Vec::new());
+                line_width = 0;
+            }
+
+            let mut piece = String::new();
+            for ch in word.chars() {
+                let ch_width = ch.width().unwrap_or(0);
+                if line_width == 0 && piece.is_empty() && ch == ' ' {
+                    continue;
+                }
+                if line_width + ch_width > width && ch != ' ' {
+                    push_piece(&mut lines, &mut piece, span);
+                    lines.push(Vec::new());
+                    line_width = 0;
+                }
+                piece.push(ch);
+                line_width += ch_width;
+            }
+            push_piece(&mut lines, &mut piece, span);
+        }
+    }
+
+    lines
+}
+
+fn push_piece(lines: &mut [Cell], piece: &mut String, span: &Span<'static>) {
+    if piece.is_empty() {
+        return;
+    }
+    if let Some(line) = lines.last_mut() {
+        line.push(Span::styled(std::mem::take(piece), span.style));
+    }
+}
+
+fn pad_cell_line(mut line: Cell, width: usize, alignment: Alignment) -> Cell {
+    if let Some(last) = line.last_mut() {
+        let trimmed = last.content.trim_end().to_owned();
+        last.content = trimmed.into();
+    }
+    let padding = width.saturating_sub(cell_width(&line));
+    let (left, right) = match alignment {
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        Alignment::Left | Alignment::None => (0, padding),
+    };
+
+    let mut padded = Vec::with_capacity(line.len() + 2);
+    if left > 0 {
+        padded.push(Span::raw(" ".repeat(left)));
+    }
+    padded.extend(line);
+    if right > 0 {
+        padded.push(Span::raw(" ".repeat(right)));
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::layout_table;
+    use pulldown_cmark::Alignment;
+    use ratatui::text::Span;
+
+    fn cell(text: &str) -> Vec<Span<'static>> {
+        vec![Span::raw(text.to_owned())]
+    }
+
+    fn line_text(line: &[Span<'static>]) -> String {
+        line.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn aligns_columns_and_draws_header_rule() {
+        let rows = vec![
+            vec![cell("Name"), cell("Count")],
+            vec![cell("alpha"), cell("7")],
+        ];
+        let lines = layout_table(&rows, &[Alignment::None, Alignment::Right], 80);
+        let text: Vec<String> = lines.iter().map(|line| line_text(line)).collect();
+
+        assert_eq!(
+            text,
+            vec!["Name  │ Count", "──────┼──────", "alpha │     7"]
+        );
+    }
+
+    #[test]
+    fn wraps_cells_to_fit_width() {
+        let rows = vec![
+            vec![cell("Key"), cell("Description")],
+            vec![cell("a"), cell("a fairly long description")],
+        ];
+        let lines = layout_table(&rows, &[], 20);
+
+        assert!(lines.len() > 3, "long cell should wrap onto extra lines");
+        for line in &lines {
+            assert!(
+                unicode_width::UnicodeWidthStr::width(line_text(line).as_str()) <= 20,
+                "line exceeds width: {:?}",
+                line_text(line)
+            );
+        }
+    }
+}
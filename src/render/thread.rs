@@ -2,17 +2,22 @@
 
 use crate::{
     domain::{
-        CommentRef, IssueComment, ListNode, ListNodeKind, PullReviewSummary, ReviewComment,
-        ReviewThread, review_comment_is_outdated,
+        CommentRef, IssueComment, ListNode, ListNodeKind, PullRequestDescription,
+        PullRequestSummary, PullReviewSummary, ReviewComment, ReviewThread, ReviewerState,
+        review_comment_is_outdated,
     },
-    render::markdown::MarkdownRenderer,
+    render::markdown::{MarkdownOptions, MarkdownRenderer},
     ui::theme,
 };
-use ratatui::text::{Line, Span};
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
 
 /// Renders the preview panel for a selected review thread node.
 pub fn render_thread_preview(
     markdown: &mut MarkdownRenderer,
+    options: MarkdownOptions,
     selected_node: &ListNode,
     root_thread: &ReviewThread,
     reply_draft: Option<&str>,
@@ -42,7 +47,7 @@ pub fn render_thread_preview(
     ]));
     out.push(Line::default());
 
-    render_thread_comment(markdown, &mut out, root_thread, 0);
+    render_thread_comment(markdown, options, &mut out, root_thread, 0);
     out.push(horizontal_rule());
     out.push(Line::from(vec![Span::styled(
         "Pending Reply",
@@ -56,7 +61,7 @@ pub fn render_thread_preview(
             theme::dim(),
         )]));
     } else {
-        let rendered = markdown.render(reply);
+        let rendered = markdown.render(reply, options.indented(2));
         out.extend(prefix_lines(rendered, "  "));
         out.push(Line::from(vec![Span::styled(
             "  [e] edit  [s] send  [x] clear",
@@ -70,6 +75,7 @@ pub fn render_thread_preview(
 /// Renders the preview panel for a selected issue comment.
 pub fn render_issue_preview(
     markdown: &mut MarkdownRenderer,
+    options: MarkdownOptions,
     issue: &IssueComment,
 ) -> Vec<Line<'static>> {
     let mut out = Vec::new();
@@ -88,7 +94,7 @@ pub fn render_issue_preview(
     )]));
     out.push(Line::default());
 
-    let rendered = markdown.render(issue.body.as_deref().unwrap_or(""));
+    let rendered = markdown.render(issue.body.as_deref().unwrap_or(""), options.indented(2));
     out.extend(prefix_lines(rendered, "  "));
 
     out
@@ -96,6 +102,7 @@ pub fn render_issue_preview(
 
 fn render_thread_comment(
     markdown: &mut MarkdownRenderer,
+    options: MarkdownOptions,
     out: &mut Vec<Line<'static>>,
     thread: &ReviewThread,
     depth: usize,
@@ -122,12 +129,15 @@ fn render_thread_comment(
         ),
     ]));
 
-    let rendered = markdown.render(thread.comment.body.as_str());
+    let rendered = markdown.render(
+        thread.comment.body.as_str(),
+        options.indented(indent.len() + 2),
+    );
     out.extend(prefix_lines(rendered, &format!("{indent}  ")));
     out.push(Line::default());
 
     for reply in &thread.replies {
-        render_thread_comment(markdown, out, reply, depth + 1);
+        render_thread_comment(markdown, options, out, reply, depth + 1);
     }
 }
 
@@ -204,9 +214,63 @@ fn comment_location(comment: &ReviewComment) -> String {
     }
 }
 
+/// Renders the threads below a directory or file heading of the by-file grouping.
+pub fn render_path_preview(path: &str, threads: &[&ReviewThread]) -> Vec<Line<'static>> {
+    let open = threads.iter().filter(|thread| !thread.is_resolved).count();
+    let mut out = vec![
+        Line::from(vec![Span::styled(
+            if path.is_empty() { "(no file)" } else { path }.to_owned(),
+            theme::section_title(),
+        )]),
+        Line::from(vec![Span::styled(
+            format!("{} thread(s), {open} open", threads.len()),
+            theme::dim(),
+        )]),
+        Line::default(),
+    ];
+
+    for thread in threads {
+        let (status, status_style) = if thread.is_resolved {
+            ("resolved", theme::resolved_thread())
+        } else {
+            ("open", theme::open_thread())
+        };
+        let author = thread
+            .comment
+            .user
+            .as_ref()
+            .map(|user| user.login.as_str())
+            .unwrap_or("unknown");
+        let mut heading = vec![
+            Span::styled(comment_location(&thread.comment), theme::title()),
+            Span::styled(format!("  @{author}  "), theme::dim()),
+            Span::styled(format!("[{status}]"), status_style),
+        ];
+        if review_comment_is_outdated(&thread.comment) {
+            heading.push(Span::styled(" [outdated]", theme::open_thread()));
+        }
+        out.push(Line::from(heading));
+
+        let first_line = thread.comment.body.lines().next().unwrap_or("").trim();
+        let mut excerpt: String = first_line
+            .chars()
+            .take(PATH_PREVIEW_EXCERPT_CHARS)
+            .collect();
+        if first_line.chars().count() > PATH_PREVIEW_EXCERPT_CHARS {
+            excerpt.push('…');
+        }
+        out.push(Line::from(vec![Span::raw(format!("  {excerpt}"))]));
+    }
+
+    out
+}
+
+const PATH_PREVIEW_EXCERPT_CHARS: usize = 72;
+
 /// Renders pull-request review summary content from `/pulls/{pull}/reviews`.
 pub fn render_review_summary_preview(
     markdown: &mut MarkdownRenderer,
+    options: MarkdownOptions,
     review: &PullReviewSummary,
 ) -> Vec<Line<'static>> {
     let mut out = Vec::new();
@@ -232,12 +296,141 @@ pub fn render_review_summary_preview(
     )]));
     out.push(Line::default());
 
-    let rendered = markdown.render(review.body.as_deref().unwrap_or(""));
+    let rendered = markdown.render(review.body.as_deref().unwrap_or(""), options.indented(2));
     out.extend(prefix_lines(rendered, "  "));
 
     out
 }
 
+/// Renders the pull request description with its labels, people and linked issues.
+pub fn render_description_preview(
+    markdown: &mut MarkdownRenderer,
+    options: MarkdownOptions,
+    pull: &PullRequestSummary,
+    description: &PullRequestDescription,
+) -> Vec<Line<'static>> {
+    let mut out = Vec::new();
+
+    out.push(Line::from(vec![
+        Span::styled(format!("#{}", pull.number), theme::section_title()),
+        Span::raw(" "),
+        Span::styled(pull.title.clone(), theme::strong_text()),
+    ]));
+    out.push(Line::from(vec![
+        Span::styled(format!("@{}", description.author), theme::author()),
+        Span::raw("  "),
+        Span::styled(short_date(&description.created_at), theme::dim()),
+        Span::styled(
+            format!("  {} ← {}", pull.base_ref, pull.head_ref),
+            theme::dim(),
+        ),
+    ]));
+    out.push(Line::default());
+
+    if !description.labels.is_empty() {
+        out.push(metadata_line(
+            "Labels",
+            vec![Span::styled(description.labels.join(", "), theme::issue())],
+        ));
+    }
+    if !description.assignees.is_empty() {
+        let assignees = description
+            .assignees
+            .iter()
+            .map(|login| format!("@{login}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push(metadata_line(
+            "Assignees",
+            vec![Span::styled(assignees, theme::author())],
+        ));
+    }
+    if let Some(milestone) = &description.milestone {
+        out.push(metadata_line(
+            "Milestone",
+            vec![Span::styled(milestone.clone(), theme::text())],
+        ));
+    }
+    if !description.reviewers.is_empty() {
+        let mut spans = Vec::new();
+        for (index, reviewer) in description.reviewers.iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::styled(" · ", theme::dim()));
+            }
+            spans.push(Span::styled(
+                format!("@{} ", reviewer.login),
+                theme::author(),
+            ));
+            spans.push(Span::styled(
+                reviewer.state.label(),
+                reviewer_state_style(reviewer.state),
+            ));
+        }
+        out.push(metadata_line("Reviewers", spans));
+    }
+    if !description.linked_issues.is_empty() {
+        out.push(metadata_line("Closes", Vec::new()));
+        // Rendered as markdown so the references can be followed.
+        let issues = description
+            .linked_issues
+            .iter()
+            .map(|issue| {
+                let state = if issue.is_open { "open" } else { "closed" };
+                format!(
+                    "- {} {} ({state})",
+                    issue.reference,
+                    escape_markdown(&issue.title)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rendered = markdown.render(&issues, options.indented(2));
+        out.extend(prefix_lines(rendered, "  "));
+    }
+    out.push(horizontal_rule());
+
+    if description.body.trim().is_empty() {
+        out.push(Line::from(vec![Span::styled(
+            "  No description provided.",
+            theme::dim(),
+        )]));
+    } else {
+        let rendered = markdown.render(&description.body, options.indented(2));
+        out.extend(prefix_lines(rendered, "  "));
+    }
+
+    out
+}
+
+fn metadata_line(label: &str, value: Vec<Span<'static>>) -> Line<'static> {
+    let mut spans = vec![Span::styled(format!("{label:<10} "), theme::dim())];
+    spans.extend(value);
+    Line::from(spans)
+}
+
+fn reviewer_state_style(state: ReviewerState) -> Style {
+    match state {
+        ReviewerState::Approved => theme::resolved_thread(),
+        ReviewerState::ChangesRequested => theme::error(),
+        ReviewerState::Commented => theme::text(),
+        ReviewerState::Requested | ReviewerState::Dismissed => theme::dim(),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
 fn prefix_lines(lines: Vec<Line<'static>>, prefix: &str) -> Vec<Line<'static>> {
     lines
         .into_iter()
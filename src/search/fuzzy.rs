@@ -1,6 +1,6 @@
-//! Fuzzy matching helpers for pull request search.
+//! Fuzzy matching helpers for pull request search and pickers.
 
-use crate::domain::PullRequestSummary;
+use crate::{config::CommentTemplate, domain::PullRequestSummary};
 use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 
 /// A ranked fuzzy search result.
@@ -41,10 +41,49 @@ pub fn rank_pull_requests(query: &str, pulls: &[PullRequestSummary]) -> Vec<Fuzz
     results
 }
 
+/// Ranks comment templates by name, falling back to body text.
+///
+/// An empty query keeps the configured order.
+pub fn rank_templates(query: &str, templates: &[CommentTemplate]) -> Vec<FuzzyResult> {
+    let trimmed = query.trim();
+
+    if trimmed.is_empty() {
+        return (0..templates.len())
+            .map(|index| FuzzyResult { index, score: 0 })
+            .collect();
+    }
+
+    let matcher = SkimMatcherV2::default().smart_case();
+
+    let mut results: Vec<(bool, FuzzyResult)> = templates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, template)| {
+            matcher
+                .fuzzy_match(&template.name, trimmed)
+                .map(|score| (true, score))
+                .or_else(|| {
+                    matcher
+                        .fuzzy_match(&template.body, trimmed)
+                        .map(|score| (false, score))
+                })
+                .map(|(is_name_match, score)| (is_name_match, FuzzyResult { index, score }))
+        })
+        .collect();
+
+    // Name matches always outrank matches found only in the body.
+    results
+        .sort_by_key(|(is_name_match, result)| std::cmp::Reverse((*is_name_match, result.score)));
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::rank_pull_requests;
-    use crate::domain::{PullRequestReviewStatus, PullRequestSummary};
+    use super::{rank_pull_requests, rank_templates};
+    use crate::{
+        config::CommentTemplate,
+        domain::{PullRequestReviewStatus, PullRequestSummary},
+    };
 
     fn pull(number: u64, title: &str, author: &str) -> PullRequestSummary {
         PullRequestSummary {
@@ -63,6 +102,7 @@ mod tests {
             is_draft: false,
             reviewer_logins: Vec::new(),
             review_status: Some(PullRequestReviewStatus::Approved),
+            comment_count: None,
         }
     }
 
@@ -84,4 +124,46 @@ mod tests {
         assert_eq!(ranked.len(), 1);
         assert_eq!(ranked[0].index, 0);
     }
+
+    #[test]
+    fn templates_rank_by_name_then_body() {
+        let templates = vec![
+            CommentTemplate {
+                name: "nit".to_owned(),
+                body: "nit: ".to_owned(),
+            },
+            CommentTemplate {
+                name: "needs test".to_owned(),
+                body: "Please add a test.".to_owned(),
+            },
+            CommentTemplate {
+                name: "checklist".to_owned(),
+                body: "- [ ] tests pass".to_owned(),
+            },
+        ];
+
+        assert_eq!(rank_templates("", &templates).len(), 3);
+
+        let ranked = rank_templates("test", &templates);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].index, 1);
+        assert_eq!(ranked[1].index, 2);
+
+        // A weak name match still beats an exact body match.
+        let templates = vec![
+            CommentTemplate {
+                name: "body".to_owned(),
+                body: "close, see the linked issue".to_owned(),
+            },
+            CommentTemplate {
+                name: "cleanup: one step".to_owned(),
+                body: "Follow-up is fine.".to_owned(),
+            },
+        ];
+        let ranked = rank_templates("close", &templates);
+        assert_eq!(
+            ranked.iter().map(|result| result.index).collect::<Vec<_>>(),
+            vec![1, 0]
+        );
+    }
 }
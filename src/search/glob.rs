@@ -0,0 +1,55 @@
+//! Minimal path globbing for config-provided file patterns.
+
+/// Matches `path` against a glob supporting `*`, `?`, and `**`. Patterns
+/// without a `/` are matched against the file name only.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let target = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let target = target.chars().collect::<Vec<_>>();
+    glob_matches_chars(&pattern, &target)
+}
+
+fn glob_matches_chars(pattern: &[char], target: &[char]) -> bool {
+    match pattern {
+        [] => target.is_empty(),
+        ['*', '*', '/', rest @ ..] => (0..=target.len()).any(|index| {
+            (index == 0 || target[index - 1] == '/') && glob_matches_chars(rest, &target[index..])
+        }),
+        ['*', '*', rest @ ..] => {
+            (0..=target.len()).any(|index| glob_matches_chars(rest, &target[index..]))
+        }
+        ['*', rest @ ..] => {
+            let segment_end = target
+                .iter()
+                .position(|ch| *ch == '/')
+                .unwrap_or(target.len());
+            (0..=segment_end).any(|index| glob_matches_chars(rest, &target[index..]))
+        }
+        ['?', rest @ ..] => {
+            target.first().is_some_and(|ch| *ch != '/') && glob_matches_chars(rest, &target[1..])
+        }
+        [ch, rest @ ..] => target.first() == Some(ch) && glob_matches_chars(rest, &target[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_matches;
+
+    #[test]
+    fn glob_matching_handles_names_and_paths() {
+        assert!(glob_matches("*.sol", "contracts/Token.sol"));
+        assert!(glob_matches("Tiltfile", "deploy/Tiltfile"));
+        assert!(glob_matches("**/BUILD", "BUILD"));
+        assert!(glob_matches("**/BUILD", "a/b/BUILD"));
+        assert!(glob_matches("src/*.rs", "src/main.rs"));
+        assert!(!glob_matches("src/*.rs", "src/app/mod.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/app/mod.rs"));
+        assert!(glob_matches("file?.txt", "file1.txt"));
+        assert!(!glob_matches("*.sol", "Token.solx"));
+    }
+}
@@ -1,3 +1,4 @@
 //! Search and ranking modules.
 
 pub mod fuzzy;
+pub mod glob;
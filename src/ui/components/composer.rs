@@ -0,0 +1,210 @@
+//! Comment composer overlay: the editor pane with its cursor, an optional
+//! markdown preview, and the `@`/`#` completion list.
+
+use crate::{
+    app::state::{ComposerState, ComposerSuggestion, ComposerTarget},
+    render::markdown::{MarkdownOptions, MarkdownRenderer},
+    ui::theme,
+};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Position, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const MIN_SPLIT_WIDTH: u16 = 100;
+
+/// Draws the in-app comment composer as an overlay on top of `area`.
+pub fn render(
+    frame: &mut Frame<'_>,
+    area: Rect,
+    composer: &ComposerState,
+    markdown: &mut MarkdownRenderer,
+) {
+    let popup = centered(area, 80, 70);
+    frame.render_widget(Clear, popup);
+
+    let title = match composer.target() {
+        ComposerTarget::Reply { .. } => " Reply ",
+        ComposerTarget::PendingComment => " Inline Comment ",
+    };
+
+    let (editor_area, preview_area) = if !composer.is_preview_visible() {
+        (popup, None)
+    } else if popup.width >= MIN_SPLIT_WIDTH {
+        let panes = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(popup);
+        (panes[0], Some(panes[1]))
+    } else {
+        let panes =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).split(popup);
+        (panes[0], Some(panes[1]))
+    };
+
+    render_editor(frame, editor_area, title, composer);
+
+    if let Some(preview_area) = preview_area {
+        let block = Block::default()
+            .title(Span::styled(" Preview ", theme::title()))
+            .borders(Borders::ALL)
+            .border_style(theme::border());
+        let text = composer.text();
+        let lines = if text.trim().is_empty() {
+            vec![Line::styled("Nothing to preview.", theme::dim())]
+        } else {
+            // Show everything being written, including collapsed sections.
+            let options = MarkdownOptions {
+                expand_details: true,
+                ..MarkdownOptions::new(block.inner(preview_area).width)
+            };
+            markdown.render(&text, options)
+        };
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            preview_area,
+        );
+    }
+}
+
+fn render_editor(frame: &mut Frame<'_>, area: Rect, title: &str, composer: &ComposerState) {
+    let block = Block::default()
+        .title(Span::styled(title, theme::info()))
+        .borders(Borders::ALL)
+        .border_style(theme::open_thread());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+
+    let (cursor_row, cursor_col) = composer.cursor();
+    let height = usize::from(inner.height);
+    let width = usize::from(inner.width);
+    let row_offset = cursor_row.saturating_sub(height.saturating_sub(1));
+    // Columns are display cells, so wide characters such as CJK take two.
+    let cursor_cells = composer.lines()[cursor_row]
+        .chars()
+        .take(cursor_col)
+        .map(|ch| ch.width().unwrap_or(0))
+        .sum::<usize>();
+    let col_offset = cursor_cells.saturating_sub(width.saturating_sub(1));
+
+    let lines = composer
+        .lines()
+        .iter()
+        .skip(row_offset)
+        .take(height)
+        .map(|line| Line::styled(visible_cells(line, col_offset, width), theme::text()))
+        .collect::<Vec<_>>();
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    let cursor_x = inner.x + (cursor_cells - col_offset) as u16;
+    let cursor_y = inner.y + (cursor_row - row_offset) as u16;
+    frame.set_cursor_position(Position::new(cursor_x, cursor_y));
+
+    if let Some((suggestions, selected)) = composer.suggestions() {
+        render_suggestions(frame, inner, cursor_x, cursor_y, suggestions, selected);
+    }
+}
+
+/// The characters of `line` that fit entirely within the `width` display cells
+/// starting at cell `offset`.
+fn visible_cells(line: &str, offset: usize, width: usize) -> String {
+    let mut visible = String::new();
+    let mut start = 0;
+    for ch in line.chars() {
+        let end = start + ch.width().unwrap_or(0);
+        if end > offset + width {
+            break;
+        }
+        if start >= offset {
+            visible.push(ch);
+        }
+        start = end;
+    }
+    visible
+}
+
+fn render_suggestions(
+    frame: &mut Frame<'_>,
+    editor: Rect,
+    cursor_x: u16,
+    cursor_y: u16,
+    suggestions: &[ComposerSuggestion],
+    selected: usize,
+) {
+    let label_width = suggestions
+        .iter()
+        .map(|suggestion| suggestion.label.width())
+        .max()
+        .unwrap_or(0) as u16;
+    let width = (label_width + 4).min(editor.width);
+    let height = (suggestions.len() as u16 + 2).min(editor.height);
+    if width < 4 || height < 3 {
+        return;
+    }
+
+    let below = cursor_y + 1;
+    let y = if below + height <= editor.y + editor.height {
+        below
+    } else {
+        cursor_y.saturating_sub(height).max(editor.y)
+    };
+    let x = cursor_x.min(editor.x + editor.width - width);
+    let area = Rect::new(x, y, width, height);
+
+    let lines = suggestions
+        .iter()
+        .enumerate()
+        .map(|(index, suggestion)| {
+            let style = if index == selected {
+                theme::selected()
+            } else {
+                theme::text()
+            };
+            Line::styled(format!(" {}", suggestion.label), style)
+        })
+        .collect::<Vec<_>>();
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme::border()),
+        ),
+        area,
+    );
+}
+
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .split(area);
+    Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .split(vertical[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::visible_cells;
+
+    #[test]
+    fn visible_cells_counts_wide_characters_as_two_columns() {
+        assert_eq!(visible_cells("日本語abc", 0, 5), "日本");
+        assert_eq!(visible_cells("日本語abc", 2, 5), "本語a");
+        // A wide character straddling the left edge is dropped, not split.
+        assert_eq!(visible_cells("日本語abc", 1, 4), "本");
+    }
+}
@@ -1,4 +1,4 @@
-//! Footer component used for keybinding hints.
+//! Footer component used for keybinding hints and transient notifications.
 
 use crate::ui::theme;
 use ratatui::{
@@ -8,21 +8,27 @@ use ratatui::{
     widgets::Paragraph,
 };
 
-/// Returns the footer height required to render all hint tokens for the given terminal width.
-pub fn required_height(screen_width: u16, hints: &str) -> u16 {
+/// Returns the footer height required to render all hint tokens for the given terminal width,
+/// plus one line for a notification.
+pub fn required_height(screen_width: u16, hints: &str, notification: Option<&str>) -> u16 {
     let width = usize::from(screen_width.max(1));
-    wrap_hint_tokens(hints, width).len().max(1) as u16
+    let hint_lines = wrap_hint_tokens(hints, width).len().max(1) as u16;
+    hint_lines + u16::from(notification.is_some())
 }
 
-/// Renders keybinding hints in a plain bottom bar.
-pub fn render(frame: &mut Frame<'_>, area: Rect, hints: &str) {
+/// Renders keybinding hints in a plain bottom bar, below the notification if any.
+pub fn render(frame: &mut Frame<'_>, area: Rect, hints: &str, notification: Option<&str>) {
     let width = usize::from(area.width.max(1));
     let lines = wrap_hint_tokens(hints, width);
-    let text: Vec<Line<'static>> = if lines.is_empty() {
-        vec![Line::from(" ")]
+    let mut text: Vec<Line<'static>> = notification
+        .map(|message| Line::from(Span::styled(message.to_owned(), theme::info())))
+        .into_iter()
+        .collect();
+    if lines.is_empty() {
+        text.push(Line::from(" "));
     } else {
-        lines.iter().map(|line| styled_hint_line(line)).collect()
-    };
+        text.extend(lines.iter().map(|line| styled_hint_line(line)));
+    }
 
     frame.render_widget(Paragraph::new(text).alignment(Alignment::Center), area);
 }
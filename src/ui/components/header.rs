@@ -18,6 +18,17 @@ pub struct HeaderModel {
     pub operation: Option<String>,
     pub error: Option<String>,
     pub review_progress: Option<ReviewProgress>,
+    pub quota: Option<QuotaStatus>,
+}
+
+/// Remaining GitHub API quota for the most constrained rate-limit resource.
+#[derive(Debug, Clone)]
+pub struct QuotaStatus {
+    pub resource: String,
+    pub remaining: u32,
+    pub limit: u32,
+    pub resets_in_minutes: u64,
+    pub is_low: bool,
 }
 
 /// Active tab indicator displayed in the header for review route.
@@ -55,6 +66,7 @@ pub fn render(frame: &mut Frame<'_>, area: Rect, model: &HeaderModel) {
         top_left_spans.push(Span::styled(format!("  {operation}"), theme::info()));
     }
     let top_left = Line::from(top_left_spans);
+    let quota = model.quota.as_ref();
 
     if let Some(progress) = model.review_progress {
         let right_width = inner.width.min(44);
@@ -67,7 +79,7 @@ pub fn render(frame: &mut Frame<'_>, area: Rect, model: &HeaderModel) {
         ])
         .split(columns[1]);
 
-        frame.render_widget(Paragraph::new(top_left), columns[0]);
+        render_left(frame, columns[0], top_left, quota);
         frame.render_widget(
             Paragraph::new(Line::from(thread_ratio_text(progress))).alignment(Alignment::Right),
             right_sections[0],
@@ -80,15 +92,45 @@ pub fn render(frame: &mut Frame<'_>, area: Rect, model: &HeaderModel) {
         let columns =
             Layout::horizontal([Constraint::Min(1), Constraint::Length(viewer_width)]).split(inner);
 
-        frame.render_widget(Paragraph::new(top_left), columns[0]);
+        render_left(frame, columns[0], top_left, quota);
         frame.render_widget(
             Paragraph::new(Line::from(Span::styled(viewer_label, theme::dim())))
                 .alignment(Alignment::Right),
             columns[1],
         );
     } else {
-        frame.render_widget(Paragraph::new(top_left), inner);
+        render_left(frame, inner, top_left, quota);
+    }
+}
+
+/// Renders the title line, with the API quota right-aligned when known.
+fn render_left(frame: &mut Frame<'_>, area: Rect, title: Line<'_>, quota: Option<&QuotaStatus>) {
+    let Some(quota) = quota else {
+        frame.render_widget(Paragraph::new(title), area);
+        return;
+    };
+
+    let label = quota_text(quota);
+    let style = if quota.is_low {
+        theme::error()
+    } else {
+        theme::dim()
+    };
+    let width = (label.chars().count() as u16 + 2).min(area.width / 2);
+    let columns = Layout::horizontal([Constraint::Min(1), Constraint::Length(width)]).split(area);
+    frame.render_widget(Paragraph::new(title), columns[0]);
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(label, style))).alignment(Alignment::Right),
+        columns[1],
+    );
+}
+
+fn quota_text(quota: &QuotaStatus) -> String {
+    let mut text = format!("{} {}/{}", quota.resource, quota.remaining, quota.limit);
+    if quota.is_low {
+        text.push_str(&format!(" (resets in {}m)", quota.resets_in_minutes.max(1)));
     }
+    text
 }
 
 fn review_tabs_spans(tabs: HeaderTabs) -> [Span<'static>; 5] {
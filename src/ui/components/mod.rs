@@ -1,6 +1,8 @@
 //! Reusable UI components.
 
+pub mod composer;
 pub mod footer;
 pub mod header;
 pub mod search_box;
 pub mod shared;
+pub mod template_picker;
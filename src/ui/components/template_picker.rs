@@ -0,0 +1,74 @@
+use crate::{app::state::TemplatePickerState, ui::theme};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+const POPUP_WIDTH: u16 = 72;
+const POPUP_HEIGHT: u16 = 16;
+
+/// Draws the saved-reply picker as an overlay centered in `area`.
+pub fn render(frame: &mut Frame<'_>, area: Rect, picker: &TemplatePickerState) {
+    let width = POPUP_WIDTH.min(area.width);
+    let height = POPUP_HEIGHT.min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, popup);
+
+    let mut title = vec![Span::styled(" Templates ", theme::title())];
+    if let Some(target) = picker.target() {
+        title.push(Span::styled(
+            format!("→ {} ", target.label()),
+            theme::info(),
+        ));
+    }
+    let block = Block::default()
+        .title(Line::from(title))
+        .borders(Borders::ALL)
+        .border_style(theme::open_thread());
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+
+    let sections = Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).split(inner);
+    let query = Line::from(vec![
+        Span::styled("> ", theme::info()),
+        Span::styled(format!("{}|", picker.query()), theme::text()),
+    ]);
+    frame.render_widget(Paragraph::new(query), sections[0]);
+
+    let items = picker
+        .results()
+        .map(|template| {
+            let first_line = template.body.lines().next().unwrap_or_default().to_owned();
+            ListItem::new(Line::from(vec![
+                Span::styled(template.name.clone(), theme::strong_text()),
+                Span::raw("  "),
+                Span::styled(first_line, theme::dim()),
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    if items.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Line::styled("No templates match.", theme::dim())),
+            sections[1],
+        );
+        return;
+    }
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(picker.selected_index()));
+    frame.render_stateful_widget(
+        List::new(items)
+            .highlight_style(theme::selected())
+            .highlight_symbol("▸ "),
+        sections[1],
+        &mut list_state,
+    );
+}
@@ -1,120 +1,273 @@
 //! Footer hint composition for each route and interaction mode.
 
 use crate::{
-    app::state::{AppState, ReviewScreenState, ReviewTab},
-    domain::{CommentRef, ListNodeKind, Route},
+    app::{
+        keymap::{Action, KeyContext, Keymap},
+        state::{AppState, DiffLayout, ReviewScreenState, ReviewTab},
+    },
+    domain::{ListNodeKind, Route},
 };
 
-pub fn build(state: &AppState) -> String {
+pub fn build(state: &AppState, keymap: &Keymap) -> String {
     match state.route {
-        Route::Search => search_hints(state),
-        Route::Review => review_hints(state),
+        Route::Search => search_hints(state, keymap),
+        Route::Review => review_hints(state, keymap),
     }
 }
 
-fn search_hints(state: &AppState) -> String {
+/// Collects `[keys] description` hints, skipping actions that have been unbound.
+struct Hints<'a> {
+    keymap: &'a Keymap,
+    context: KeyContext,
+    parts: Vec<String>,
+}
+
+impl<'a> Hints<'a> {
+    fn new(keymap: &'a Keymap, context: KeyContext) -> Self {
+        Self {
+            keymap,
+            context,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Pushes a hint for `actions`. Grouped actions show only their primary key
+    /// so hints like `[n/N]` stay compact.
+    fn push(&mut self, actions: &[Action], description: &str) {
+        let keys = if let [action] = actions {
+            self.keymap.labels(self.context, *action)
+        } else {
+            actions
+                .iter()
+                .filter_map(|action| self.keymap.labels(self.context, *action).into_iter().next())
+                .collect()
+        };
+        if !keys.is_empty() {
+            self.parts
+                .push(format!("[{}] {description}", keys.join("/")));
+        }
+    }
+
+    fn push_literal(&mut self, hint: &str) {
+        self.parts.push(hint.to_owned());
+    }
+
+    fn finish(self) -> String {
+        self.parts.join("  ")
+    }
+}
+
+const SUBMIT_ACTIONS: &[Action] = &[
+    Action::SubmitComment,
+    Action::SubmitApprove,
+    Action::SubmitRequestChanges,
+];
+
+fn search_hints(state: &AppState, keymap: &Keymap) -> String {
     if state.is_search_focused() {
-        "[type] edit query  [backspace] delete  [enter/esc] unfocus".to_owned()
-    } else {
-        "[j/k/up/down] navigate  [enter] open PR  [W] open web  [s] focus search  [u] toggle scope  [i] toggle status  [o] toggle sort  [R] refresh  [q] quit".to_owned()
+        return "[type] edit query  [backspace] delete  [enter/esc] unfocus".to_owned();
     }
+
+    let mut hints = Hints::new(keymap, KeyContext::Search);
+    hints.push(&[Action::MoveDown, Action::MoveUp], "navigate");
+    hints.push(&[Action::Open], "open PR");
+    hints.push(&[Action::OpenInBrowser], "open web");
+    hints.push(&[Action::FocusSearch], "focus search");
+    hints.push(&[Action::ToggleScope], "toggle scope");
+    hints.push(&[Action::ToggleStatus], "toggle status");
+    hints.push(&[Action::ToggleSort], "toggle sort");
+    hints.push(&[Action::Refresh], "refresh");
+    hints.push(&[Action::Quit], "quit");
+    hints.finish()
 }
 
-fn review_hints(state: &AppState) -> String {
+fn review_hints(state: &AppState, keymap: &Keymap) -> String {
     let Some(review) = state.review.as_ref() else {
-        return fallback_review_hints(state);
+        return fallback_review_hints(state, keymap);
     };
+    if let Some(picker) = review.template_picker.as_ref() {
+        let mut hints = Hints::new(keymap, KeyContext::TemplatePicker);
+        hints.push_literal("[type] filter");
+        hints.push(&[Action::MoveUp, Action::MoveDown], "choose");
+        hints.push(&[Action::Accept], "insert");
+        if picker.has_multiple_targets() {
+            hints.push(&[Action::CycleTarget], "change target");
+        }
+        hints.push(&[Action::Back], "cancel");
+        return hints.finish();
+    }
+    if review.composer.is_some() {
+        return composer_hints(review, keymap);
+    }
 
     match review.active_tab() {
-        ReviewTab::Diff => review_diff_hints(review),
-        ReviewTab::Threads => review_thread_hints(review),
+        ReviewTab::Diff => review_diff_hints(review, keymap),
+        ReviewTab::Threads => review_thread_hints(review, keymap),
     }
 }
 
-fn review_diff_hints(review: &ReviewScreenState) -> String {
+fn review_diff_hints(review: &ReviewScreenState, keymap: &Keymap) -> String {
     if review.is_diff_search_focused() {
         return "[type] edit file filter  [backspace] delete  [enter/esc] unfocus".to_owned();
     }
+    if review.is_diff_text_search_focused() {
+        return "[type] search diff text  [backspace] delete  [enter] find next  [esc] unfocus"
+            .to_owned();
+    }
 
     let is_visual_mode = review.has_diff_selection_anchor();
-    let mut parts = Vec::new();
+    let mut hints = Hints::new(keymap, KeyContext::Review);
 
     if !is_visual_mode {
-        parts.push("[S-tab] show threads".to_owned());
+        hints.push(&[Action::NextTab], "show threads");
         if review.is_diff_content_focused() {
-            parts.push("[tab] focus files".to_owned());
+            hints.push(&[Action::ToggleFocus], "focus files");
         } else {
-            parts.push("[tab] focus diff".to_owned());
+            hints.push(&[Action::ToggleFocus], "focus diff");
         }
     }
 
-    parts.push("[j/k/up/down] navigate".to_owned());
+    hints.push(&[Action::MoveDown, Action::MoveUp], "navigate");
     if !is_visual_mode {
-        parts.push("[n/N] next/prev hunk".to_owned());
+        if review.has_diff_text_search() {
+            hints.push(&[Action::NextMatch, Action::PrevMatch], "next/prev match");
+            hints.push(&[Action::NextHunk, Action::PrevHunk], "next/prev hunk");
+        } else {
+            hints.push(&[Action::NextMatch, Action::PrevMatch], "next/prev hunk");
+        }
+        hints.push(&[Action::SearchComments], "search diff");
+        hints.push(
+            &[Action::ToggleLayout],
+            match review.diff_layout() {
+                DiffLayout::Split => "unified",
+                DiffLayout::Unified => "side by side",
+            },
+        );
         if review.pending_review_comment_count() > 0 {
-            parts.push("[p/P] pending next/prev".to_owned());
+            hints.push(
+                &[Action::NextPending, Action::PrevPending],
+                "pending next/prev",
+            );
         }
     }
     if review.is_diff_content_focused() {
-        parts.push("[C-d/C-u] scroll paragraph".to_owned());
+        hints.push(&[Action::ScrollDown, Action::ScrollUp], "scroll paragraph");
+        if !review.diff_wrap {
+            hints.push(
+                &[Action::ScrollLeft, Action::ScrollRight],
+                "scroll sideways",
+            );
+        }
+        hints.push(
+            &[Action::ToggleWrap],
+            if review.diff_wrap {
+                "clip lines"
+            } else {
+                "wrap lines"
+            },
+        );
     }
 
     if review.is_diff_content_focused() {
-        parts.push("[v] range".to_owned());
+        hints.push(&[Action::VisualRange], "range");
         if review.selected_diff_range().is_some() {
-            parts.push("[esc] cancel visual".to_owned());
-            parts.push("[e] leave comment".to_owned());
+            hints.push_literal("[esc] cancel visual");
+            hints.push(&[Action::Edit], "leave comment");
         } else if review.selected_pending_review_comment().is_some() {
-            parts.push("[e/x] edit or delete pending".to_owned());
+            hints.push(&[Action::Edit, Action::Clear], "edit or delete pending");
         } else {
-            parts.push("[e] leave comment".to_owned());
+            hints.push(&[Action::Edit], "leave comment");
+        }
+        if review.selected_diff_fold().is_some() {
+            hints.push(&[Action::ExpandUp, Action::ExpandDown], "expand up/down");
+            hints.push(&[Action::ToggleCollapse], "expand all");
+        }
+        if review.selected_diff_line_has_thread() {
+            hints.push(&[Action::ShowThread], "show thread");
         }
     } else {
-        parts.push("[s] search files".to_owned());
-        parts.push("[o/z] collapse".to_owned());
+        hints.push(&[Action::SendOrSearch], "search files");
+        hints.push(&[Action::ToggleCollapse], "collapse");
     }
 
     if review.pending_review_comment_count() > 0 && !is_visual_mode {
-        parts.push("[C/A/X] submit review".to_owned());
+        hints.push(SUBMIT_ACTIONS, "submit review");
     }
     if !is_visual_mode {
-        parts.push("[b] back".to_owned());
-        parts.push("[R] refresh".to_owned());
+        hints.push(&[Action::Templates], "templates");
+        hints.push(&[Action::RestoreDraft], "restore draft");
+        if review.has_draft_conflicts() {
+            hints.push(&[Action::SwapDraftConflicts], "swap conflicting drafts");
+        }
+        hints.push(&[Action::Back], "back");
+        hints.push(&[Action::Refresh], "refresh");
     }
-    parts.push("[q] quit".to_owned());
+    hints.push(&[Action::Quit], "quit");
 
-    parts.join("  ")
+    hints.finish()
 }
 
-fn review_thread_hints(review: &ReviewScreenState) -> String {
+fn review_thread_hints(review: &ReviewScreenState, keymap: &Keymap) -> String {
     if review.is_thread_search_focused() {
-        return "[type] edit comment filter  [backspace] delete  [enter/esc] unfocus".to_owned();
+        return "[type] edit comment filter (author: path: is:outdated is:awaiting is:involved)  [backspace] delete  [enter/esc] unfocus".to_owned();
+    }
+    if review.is_picking_link() {
+        return "[type] label to open link  [esc] cancel".to_owned();
     }
 
-    let mut parts = vec![
-        "[S-tab] show diff".to_owned(),
-        "[j/k/up/down] navigate".to_owned(),
-        "[C-d/C-u] scroll paragraph".to_owned(),
-    ];
+    let mut hints = Hints::new(keymap, KeyContext::Review);
+    hints.push(&[Action::NextTab], "show diff");
+    hints.push(&[Action::MoveDown, Action::MoveUp], "navigate");
+    hints.push(&[Action::ScrollDown, Action::ScrollUp], "scroll paragraph");
+    if review.has_unread() {
+        hints.push(
+            &[Action::NextUnread, Action::PrevUnread],
+            "unread next/prev",
+        );
+    }
 
     if review.data.review_thread_totals().1 > 0 {
         let resolved_hint = if review.hide_resolved {
-            "[f] show resolved"
+            "show resolved"
+        } else {
+            "hide resolved"
+        };
+        hints.push(&[Action::ToggleResolvedFilter], resolved_hint);
+        let grouping_hint = if review.group_by_file {
+            "group by conversation"
         } else {
-            "[f] hide resolved"
+            "group by file"
         };
-        parts.push(resolved_hint.to_owned());
+        hints.push(&[Action::ToggleGrouping], grouping_hint);
     }
 
     if let Some(node) = review.selected_node() {
         let collapsible_review_group =
             node.kind == ListNodeKind::Review && node.key.starts_with("review-group:");
-        if node.kind == ListNodeKind::Thread || collapsible_review_group {
-            parts.push("[o/z] collapse".to_owned());
+        let collapsible = matches!(
+            node.kind,
+            ListNodeKind::Thread | ListNodeKind::Directory | ListNodeKind::File
+        );
+        if collapsible || collapsible_review_group {
+            hints.push(&[Action::ToggleCollapse], "collapse");
         }
-        if has_openable_comment_url(&node.comment) {
-            parts.push("[W] open web".to_owned());
+        if node.root_key.is_some() && !node.is_outdated {
+            hints.push(&[Action::ShowInDiff], "show in diff");
         }
+        if !node.comment.html_url().trim().is_empty() {
+            hints.push(&[Action::OpenInBrowser], "open web");
+        }
+        if node.comment.body().contains("<details") {
+            let details_hint = if review.expand_details {
+                "collapse details"
+            } else {
+                "expand details"
+            };
+            hints.push(&[Action::ToggleDetails], details_hint);
+        }
+    }
+    if !review.preview_links.is_empty() {
+        hints.push(&[Action::FollowLink], "follow link");
     }
 
     let has_sendable_reply = review
@@ -124,59 +277,80 @@ fn review_thread_hints(review: &ReviewScreenState) -> String {
     if let Some(context) = review.selected_thread_context() {
         if context.thread_id.is_some() {
             let thread_action = if context.is_resolved {
-                "[t] unresolve"
+                "unresolve"
             } else {
-                "[t] resolve"
+                "resolve"
             };
-            parts.push(thread_action.to_owned());
+            hints.push(&[Action::ToggleThreadResolved], thread_action);
         }
 
         if has_sendable_reply {
-            parts.push("[e/s/x] reply".to_owned());
+            hints.push(
+                &[Action::Edit, Action::SendOrSearch, Action::Clear],
+                "reply",
+            );
         } else {
-            parts.push("[e] edit reply".to_owned());
+            hints.push(&[Action::Edit], "edit reply");
         }
     }
 
     if has_sendable_reply {
-        parts.push("[/] search comments".to_owned());
+        hints.push(&[Action::SearchComments], "search comments");
     } else {
-        parts.push("[s] search comments".to_owned());
+        hints.push(&[Action::SendOrSearch], "search comments");
     }
 
     if review.pending_review_comment_count() > 0 {
-        parts.push("[C/A/X] submit review (+pending)".to_owned());
+        hints.push(SUBMIT_ACTIONS, "submit review (+pending)");
     } else {
-        parts.push("[C/A/X] review submit".to_owned());
+        hints.push(SUBMIT_ACTIONS, "review submit");
+    }
+    hints.push(&[Action::Templates], "templates");
+    hints.push(&[Action::RestoreDraft], "restore draft");
+    if review.has_draft_conflicts() {
+        hints.push(&[Action::SwapDraftConflicts], "swap conflicting drafts");
     }
-    parts.push("[b] back".to_owned());
-    parts.push("[R] refresh".to_owned());
-    parts.push("[q] quit".to_owned());
+    hints.push(&[Action::Back], "back");
+    hints.push(&[Action::Refresh], "refresh");
+    hints.push(&[Action::Quit], "quit");
 
-    parts.join("  ")
+    hints.finish()
 }
 
-fn fallback_review_hints(state: &AppState) -> String {
-    let mut parts = vec!["[S-tab] show diff".to_owned()];
+fn composer_hints(review: &ReviewScreenState, keymap: &Keymap) -> String {
+    let has_completion = review
+        .composer
+        .as_ref()
+        .is_some_and(|composer| composer.has_completion());
+    if has_completion {
+        return "[up/down] choose  [tab/enter] complete  [esc] dismiss".to_owned();
+    }
+
+    let mut hints = Hints::new(keymap, KeyContext::Composer);
+    hints.push_literal("[type] edit");
+    hints.push_literal("[@/#] complete");
+    hints.push(&[Action::Templates], "template");
+    hints.push(&[Action::TogglePreview], "preview");
+    hints.push(&[Action::SystemEditor], "system editor");
+    hints.push(&[Action::SaveDraft], "save draft");
+    hints.push_literal("[esc] cancel");
+    hints.finish()
+}
+
+fn fallback_review_hints(state: &AppState, keymap: &Keymap) -> String {
+    let mut hints = Hints::new(keymap, KeyContext::Review);
+    hints.push(&[Action::NextTab], "show diff");
     if state
         .review
         .as_ref()
         .is_some_and(|review| review.pending_review_comment_count() > 0)
     {
-        parts.push("[C/A/X] submit review (+pending)".to_owned());
+        hints.push(SUBMIT_ACTIONS, "submit review (+pending)");
     } else {
-        parts.push("[C/A/X] review submit".to_owned());
-    }
-    parts.push("[b] back".to_owned());
-    parts.push("[R] refresh".to_owned());
-    parts.push("[q] quit".to_owned());
-    parts.join("  ")
-}
-
-fn has_openable_comment_url(comment: &CommentRef) -> bool {
-    match comment {
-        CommentRef::Review(comment) => !comment.html_url.trim().is_empty(),
-        CommentRef::Issue(comment) => !comment.html_url.as_str().trim().is_empty(),
-        CommentRef::ReviewSummary(review) => !review.html_url.as_str().trim().is_empty(),
+        hints.push(SUBMIT_ACTIONS, "review submit");
     }
+    hints.push(&[Action::Back], "back");
+    hints.push(&[Action::Refresh], "refresh");
+    hints.push(&[Action::Quit], "quit");
+    hints.finish()
 }
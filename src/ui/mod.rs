@@ -1,12 +1,16 @@
 //! Top-level UI composition.
 
 use crate::{
-    app::state::{AppState, ReviewTab},
+    app::{
+        keymap::Keymap,
+        state::{AppState, ReviewTab},
+    },
     domain::Route,
     render::markdown::MarkdownRenderer,
     ui::components::{
-        footer,
-        header::{self, HeaderModel, HeaderTabs, ReviewProgress},
+        composer, footer,
+        header::{self, HeaderModel, HeaderTabs, QuotaStatus, ReviewProgress},
+        template_picker,
     },
 };
 use ratatui::{
@@ -20,13 +24,26 @@ pub mod screens;
 pub mod theme;
 
 /// Draws the active screen.
-pub fn render(frame: &mut Frame<'_>, state: &mut AppState, markdown: &mut MarkdownRenderer) {
-    let hints = hints::build(state);
+pub fn render(
+    frame: &mut Frame<'_>,
+    state: &mut AppState,
+    keymap: &Keymap,
+    markdown: &mut MarkdownRenderer,
+) {
+    let hints = hints::build(state, keymap);
+    let notification = state
+        .notification
+        .as_ref()
+        .map(|notification| notification.message.clone());
 
     let root = Layout::vertical([
         Constraint::Length(3),
         Constraint::Min(8),
-        Constraint::Length(footer::required_height(frame.area().width, &hints)),
+        Constraint::Length(footer::required_height(
+            frame.area().width,
+            &hints,
+            notification.as_deref(),
+        )),
     ])
     .split(frame.area());
 
@@ -50,6 +67,10 @@ pub fn render(frame: &mut Frame<'_>, state: &mut AppState, markdown: &mut Markdo
             },
         ),
     };
+    let context_label = match connectivity_label(state) {
+        Some(status) => format!("{context_label}  {status}"),
+        None => context_label,
+    };
     let review_tabs = if state.route == Route::Review {
         state.review.as_ref().map(|review| HeaderTabs {
             selected: match review.active_tab() {
@@ -61,6 +82,17 @@ pub fn render(frame: &mut Frame<'_>, state: &mut AppState, markdown: &mut Markdo
         None
     };
 
+    let quota = state
+        .rate_limit
+        .as_ref()
+        .map(|(resource, status)| QuotaStatus {
+            resource: resource.clone(),
+            remaining: status.remaining,
+            limit: status.limit,
+            resets_in_minutes: status.resets_in().as_secs().div_ceil(60),
+            is_low: status.is_low(),
+        });
+
     header::render(
         frame,
         root[0],
@@ -72,6 +104,7 @@ pub fn render(frame: &mut Frame<'_>, state: &mut AppState, markdown: &mut Markdo
             operation: state.operation_display(),
             error: state.error_message.clone(),
             review_progress,
+            quota,
         },
     );
 
@@ -80,11 +113,27 @@ pub fn render(frame: &mut Frame<'_>, state: &mut AppState, markdown: &mut Markdo
         Route::Review => {
             if let Some(review) = state.review.as_mut() {
                 screens::review::render(frame, root[1], review, markdown);
+                if let Some(composer) = review.composer.as_ref() {
+                    composer::render(frame, root[1], composer, markdown);
+                }
+                if let Some(picker) = review.template_picker.as_ref() {
+                    template_picker::render(frame, root[1], picker);
+                }
             } else {
                 screens::search::render(frame, root[1], state);
             }
         }
     }
 
-    footer::render(frame, root[2], &hints);
+    footer::render(frame, root[2], &hints, notification.as_deref());
+}
+
+/// Offline mode and queued mutation count, shown next to the context label.
+fn connectivity_label(state: &AppState) -> Option<String> {
+    match (state.offline, state.queued_mutations) {
+        (false, 0) => None,
+        (true, 0) => Some("[offline]".to_owned()),
+        (true, queued) => Some(format!("[offline · {queued} queued]")),
+        (false, queued) => Some(format!("[{queued} queued]")),
+    }
 }
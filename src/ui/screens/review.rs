@@ -4,8 +4,11 @@ use crate::{
     app::state::{PendingReviewCommentDraft, ReviewScreenState, ReviewTab},
     domain::{CommentRef, ListNodeKind, PullRequestDiffFileStatus},
     render::{
-        markdown::MarkdownRenderer,
-        thread::{render_issue_preview, render_review_summary_preview, render_thread_preview},
+        markdown::{LinkContext, MarkdownOptions, MarkdownRenderer},
+        thread::{
+            render_description_preview, render_issue_preview, render_path_preview,
+            render_review_summary_preview, render_thread_preview,
+        },
     },
     ui::{
         components::{search_box, shared::short_preview},
@@ -39,7 +42,7 @@ pub fn render(
 fn render_threads_tab(
     frame: &mut Frame<'_>,
     area: Rect,
-    review: &ReviewScreenState,
+    review: &mut ReviewScreenState,
     markdown: &mut MarkdownRenderer,
 ) {
     let panes =
@@ -76,7 +79,7 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
     }
 
     let comments_area = *sections.last().unwrap_or(&area);
-    let comments_title = if review.thread_search_query().trim().is_empty() {
+    let mut comments_title = if review.thread_search_query().trim().is_empty() {
         format!(" Comments ({}) ", review.nodes.len())
     } else {
         format!(
@@ -85,6 +88,13 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
             review.thread_node_count()
         )
     };
+    if review.group_by_file {
+        comments_title.push_str("· by file ");
+    }
+    let facets = review.thread_facets().labels();
+    if !facets.is_empty() {
+        comments_title.push_str(&format!("· {} ", facets.join(", ")));
+    }
     let block = Block::default()
         .title(Span::styled(comments_title, theme::title()))
         .borders(Borders::ALL)
@@ -126,7 +136,7 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
             .map(|node| {
                 let indent = "  ".repeat(node.depth);
 
-                match node.kind {
+                let mut line = match node.kind {
                     ListNodeKind::Thread => {
                         let preview = short_preview(node.comment.body(), 58);
                         let icon = if review.is_collapsed(&node.key) {
@@ -140,7 +150,7 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                         } else {
                             theme::open_thread()
                         };
-                        ListItem::new(Line::from(vec![
+                        Line::from(vec![
                             Span::styled(
                                 format!("{indent}{icon} @{} ", node.comment.author()),
                                 theme::title(),
@@ -153,19 +163,19 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                             Span::raw(preview),
                             Span::raw("  "),
                             Span::styled(format!("[{status}]"), status_style),
-                        ]))
+                        ])
                     }
                     ListNodeKind::Reply => {
                         let preview = short_preview(node.comment.body(), 64);
-                        ListItem::new(Line::from(vec![
+                        Line::from(vec![
                             Span::styled(
                                 format!("{indent}↳ @{} ", node.comment.author()),
                                 theme::dim(),
                             ),
                             Span::styled(preview, theme::dim()),
-                        ]))
+                        ])
                     }
-                    ListNodeKind::Issue => ListItem::new(Line::from(vec![
+                    ListNodeKind::Issue => Line::from(vec![
                         Span::styled(
                             format!("{indent}• @{} ", node.comment.author()),
                             theme::issue(),
@@ -173,7 +183,36 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                         Span::raw(short_preview(node.comment.body(), 58)),
                         Span::raw("  "),
                         Span::styled("issue", theme::issue()),
-                    ])),
+                    ]),
+                    ListNodeKind::Description => Line::from(vec![
+                        Span::styled(
+                            format!("{indent}≡ @{} ", node.comment.author()),
+                            theme::title(),
+                        ),
+                        Span::raw(short_preview(&review.pull.title, 52)),
+                        Span::raw("  "),
+                        Span::styled("description", theme::info()),
+                    ]),
+                    ListNodeKind::Directory | ListNodeKind::File => {
+                        let icon = if review.is_collapsed(&node.key) {
+                            "▸"
+                        } else {
+                            "▾"
+                        };
+                        let label = match &node.comment {
+                            CommentRef::Path { label, .. } => label.as_str(),
+                            _ => "",
+                        };
+                        let suffix = if node.kind == ListNodeKind::Directory {
+                            "/"
+                        } else {
+                            ""
+                        };
+                        Line::from(vec![Span::styled(
+                            format!("{indent}{icon} {label}{suffix}"),
+                            theme::title(),
+                        )])
+                    }
                     ListNodeKind::Review => {
                         if node.key.starts_with("review-group:") {
                             let icon = if review.is_collapsed(&node.key) {
@@ -188,7 +227,7 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                                 theme::open_thread()
                             };
 
-                            ListItem::new(Line::from(vec![
+                            Line::from(vec![
                                 Span::styled(
                                     format!("{indent}{icon} @{} ", node.comment.author()),
                                     theme::title(),
@@ -196,9 +235,9 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                                 Span::raw(short_preview(node.comment.body(), 56)),
                                 Span::raw("  "),
                                 Span::styled(format!("[{status}]"), status_style),
-                            ]))
+                            ])
                         } else {
-                            ListItem::new(Line::from(vec![
+                            Line::from(vec![
                                 Span::styled(
                                     format!("{indent}• @{} ", node.comment.author()),
                                     theme::issue(),
@@ -206,10 +245,14 @@ fn render_left_pane(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenStat
                                 Span::raw(short_preview(node.comment.body(), 58)),
                                 Span::raw("  "),
                                 Span::styled("review", theme::issue()),
-                            ]))
+                            ])
                         }
                     }
+                };
+                if review.is_new_node(node) {
+                    line.spans.push(Span::styled("  ● new", theme::info()));
                 }
+                ListItem::new(line)
             })
             .collect()
     };
@@ -273,28 +316,62 @@ fn render_pending_review_sidebar(frame: &mut Frame<'_>, area: Rect, review: &Rev
 fn render_right_pane(
     frame: &mut Frame<'_>,
     area: Rect,
-    review: &ReviewScreenState,
+    review: &mut ReviewScreenState,
     markdown: &mut MarkdownRenderer,
 ) {
+    let title = if review.is_picking_link() {
+        " Thread Preview · pick a link "
+    } else {
+        " Thread Preview "
+    };
     let block = Block::default()
-        .title(Span::styled(" Thread Preview ", theme::title()))
+        .title(Span::styled(title, theme::title()))
         .borders(Borders::ALL)
         .border_style(theme::border());
 
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let (text_area, scrollbar_area) = if inner.width > 1 {
+        let sections = Layout::horizontal([Constraint::Min(1), Constraint::Length(1)]).split(inner);
+        (sections[0], Some(sections[1]))
+    } else {
+        (inner, None)
+    };
+
+    let options = MarkdownOptions {
+        width: text_area.width,
+        expand_details: review.expand_details,
+        link_hints: review.link_hint_total(),
+    };
+    markdown.set_link_context(Some(LinkContext::for_pull(&review.pull)));
+    markdown.take_links();
     let lines = if let Some(node) = review.selected_node() {
         match (&node.kind, &node.comment) {
             (ListNodeKind::Issue, CommentRef::Issue(issue)) => {
-                render_issue_preview(markdown, issue)
+                render_issue_preview(markdown, options, issue)
             }
             (ListNodeKind::Issue, CommentRef::ReviewSummary(review)) => {
-                render_review_summary_preview(markdown, review)
+                render_review_summary_preview(markdown, options, review)
             }
             (ListNodeKind::Review, CommentRef::ReviewSummary(review)) => {
-                render_review_summary_preview(markdown, review)
+                render_review_summary_preview(markdown, options, review)
             }
+            (ListNodeKind::Description, CommentRef::Description(description)) => {
+                render_description_preview(markdown, options, &review.pull, description)
+            }
+            (kind, CommentRef::Path { path, .. }) => render_path_preview(
+                path,
+                &review.threads_under_path(path, *kind == ListNodeKind::Directory),
+            ),
             (_, CommentRef::Review(_)) => {
                 if let Some(root) = review.selected_root_thread() {
-                    render_thread_preview(markdown, node, root, review.selected_reply_draft())
+                    render_thread_preview(
+                        markdown,
+                        options,
+                        node,
+                        root,
+                        review.selected_reply_draft(),
+                    )
                 } else {
                     vec![Line::from(vec![Span::styled(
                         "Thread not found for selected row.",
@@ -307,15 +384,7 @@ fn render_right_pane(
     } else {
         vec![Line::from("Select a row to preview comment details.")]
     };
-
-    let inner = block.inner(area);
-    frame.render_widget(block, area);
-    let (text_area, scrollbar_area) = if inner.width > 1 {
-        let sections = Layout::horizontal([Constraint::Min(1), Constraint::Length(1)]).split(inner);
-        (sections[0], Some(sections[1]))
-    } else {
-        (inner, None)
-    };
+    review.preview_links = markdown.take_links();
 
     let viewport_height = usize::from(text_area.height);
     let content_height = wrapped_content_height(&lines, text_area.width);
@@ -538,9 +607,17 @@ fn render_diff_content(
         .title(Span::styled(title, theme::title()))
         .borders(Borders::ALL)
         .border_style(border_style);
-    let inner = block.inner(area);
+    let mut inner = block.inner(area);
     frame.render_widget(block, area);
 
+    let show_text_search =
+        review.is_diff_text_search_focused() || !review.diff_text_search_query().is_empty();
+    if show_text_search && inner.height > 4 {
+        let sections = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).split(inner);
+        render_diff_text_search(frame, sections[0], review);
+        inner = sections[1];
+    }
+
     review.sync_pending_review_preview_target();
     let hovered_pending_comment = review.selected_pending_review_comment().cloned();
     let (diff_area, pending_preview_area) = if hovered_pending_comment.is_some() && inner.height > 3
@@ -560,28 +637,50 @@ fn render_diff_content(
         (diff_area, None)
     };
     review.set_diff_viewport_height(text_area.height.max(1));
+    review.set_diff_viewport_width(text_area.width);
 
     let viewport_height = usize::from(text_area.height.max(1));
     let (lines, content_height, scroll) = if let Some(file) = review.selected_diff_file() {
         let (left, right) = markdown.diff_file_highlights(file);
-        let content_height = file.rows.len().max(1);
-        let max_scroll = content_height.saturating_sub(viewport_height);
-        let scroll = usize::from(review.diff_scroll).min(max_scroll);
+        let diff_lines = review.diff_lines();
+        let content_height = diff_lines.len().max(1);
+        let scroll = if review.diff_wrap {
+            review_diff::wrapped_scroll(
+                file,
+                diff_lines,
+                review.diff_layout(),
+                text_area.width,
+                usize::from(review.diff_scroll),
+                review.selected_diff_pane_line(),
+                viewport_height,
+            )
+        } else {
+            let max_scroll = content_height.saturating_sub(viewport_height);
+            usize::from(review.diff_scroll).min(max_scroll)
+        };
         let pending = review
             .pending_review_comments_for_file(file)
             .into_iter()
             .cloned()
             .collect::<Vec<_>>();
+        let thread_rows = review.thread_rows_for_file(file);
         let lines = review_diff::render_rows(DiffRowsRenderContext {
             file,
+            lines: diff_lines,
+            layout: review.diff_layout(),
             width: text_area.width,
+            wrap: review.diff_wrap,
+            column_offset: review.diff_column_offset,
             left_syntax: left,
             right_syntax: right,
             row_offset: scroll,
             row_limit: viewport_height,
             selected_line: review.selected_diff_line(),
+            selected_side: review.diff_cursor_side(),
             selected_range: review.selected_diff_range(),
             pending_comments: &pending,
+            thread_rows: &thread_rows,
+            search_matches: review.diff_text_matches_for_file(review.selected_diff_file),
         });
         (lines, content_height, scroll)
     } else if let Some(error) = &review.diff_error {
@@ -597,6 +696,11 @@ fn render_diff_content(
         )])];
         (lines, 1usize, 0usize)
     };
+    if review.diff_wrap {
+        // Keep the scroll that fits the wrapped selection so moving back up
+        // does not jump.
+        review.diff_scroll = u16::try_from(scroll).unwrap_or(u16::MAX);
+    }
 
     let paragraph = Paragraph::new(lines);
     frame.render_widget(paragraph, text_area);
@@ -621,6 +725,39 @@ fn render_diff_content(
     }
 }
 
+fn render_diff_text_search(frame: &mut Frame<'_>, area: Rect, review: &ReviewScreenState) {
+    let focused = review.is_diff_text_search_focused();
+    let mut title = vec![Span::styled(
+        " Diff Search ",
+        if focused {
+            theme::info()
+        } else {
+            theme::title()
+        },
+    )];
+    if let Some(status) = review.diff_text_search_status() {
+        let style = if review.has_diff_text_search() {
+            theme::dim()
+        } else {
+            theme::error()
+        };
+        title.push(Span::styled(format!("· {status} "), style));
+    }
+    search_box::render(
+        frame,
+        area,
+        search_box::SearchBoxProps {
+            title: " Diff Search ",
+            title_line: Some(Line::from(title)),
+            query: review.diff_text_search_query(),
+            focused,
+            focused_placeholder: "(text, /regex/, is:added or is:removed)",
+            unfocused_placeholder: "(press [/] to search the diff)",
+            focused_right_hint: None,
+        },
+    );
+}
+
 fn render_pending_comment_preview(
     frame: &mut Frame<'_>,
     area: Rect,
@@ -652,7 +789,7 @@ fn render_pending_comment_preview(
         (inner, None)
     };
 
-    let mut lines = markdown.render(&comment.body);
+    let mut lines = markdown.render(&comment.body, MarkdownOptions::new(text_area.width));
     if lines.is_empty() {
         lines.push(Line::from(vec![Span::styled("(empty)", theme::dim())]));
     }
@@ -1,7 +1,9 @@
 //! Diff row renderer shared by the review diff pane.
 
 use crate::{
-    app::state::{PendingReviewCommentDraft, PendingReviewCommentSide},
+    app::state::{
+        DiffLayout, DiffLine, DiffTextMatch, PendingReviewCommentDraft, PendingReviewCommentSide,
+    },
     domain::{
         PullRequestDiffFile, PullRequestDiffHighlightRange, PullRequestDiffRow,
         PullRequestDiffRowKind,
@@ -12,92 +14,363 @@ use ratatui::{
     style::{Color, Style},
     text::{Line, Span},
 };
+use std::collections::HashSet;
+
+const MARKER_WIDTH: usize = 2;
+/// Width of the line number column of each side.
+const LINE_NUMBER_WIDTH: usize = 6;
+const SEPARATOR: &str = " │ ";
+/// Width of the `+`/`-` column of the unified layout.
+const SIGN_WIDTH: usize = 2;
 
 pub(crate) struct DiffRowsRenderContext<'a> {
     pub file: &'a PullRequestDiffFile,
+    /// Pane lines of `file`, with unchanged runs folded.
+    pub lines: &'a [DiffLine],
+    pub layout: DiffLayout,
     pub width: u16,
+    /// Wrap long lines onto extra screen lines instead of clipping them.
+    pub wrap: bool,
+    /// Characters skipped at the start of each side while not wrapping.
+    pub column_offset: usize,
     pub left_syntax: &'a [Vec<Option<Color>>],
     pub right_syntax: &'a [Vec<Option<Color>>],
+    /// First pane line to render.
     pub row_offset: usize,
+    /// Screen lines to render.
     pub row_limit: usize,
     pub selected_line: usize,
+    /// Side the cursor is on when the selected row is split into two lines.
+    pub selected_side: PendingReviewCommentSide,
     pub selected_range: Option<(usize, usize)>,
     pub pending_comments: &'a [PendingReviewCommentDraft],
+    /// Rows covered by an existing comment thread.
+    pub thread_rows: &'a HashSet<usize>,
+    /// Diff text search matches in `file`, ordered by row.
+    pub search_matches: &'a [DiffTextMatch],
+}
+
+/// Widths of the diff side columns for a pane width.
+#[derive(Debug, Clone, Copy)]
+struct DiffColumns {
+    left: usize,
+    right: usize,
+    unified: usize,
+}
+
+impl DiffColumns {
+    fn new(width: usize) -> Self {
+        let available = width.saturating_sub(MARKER_WIDTH + SEPARATOR.len());
+        let left = available / 2;
+        Self {
+            left,
+            right: available.saturating_sub(left),
+            unified: width.saturating_sub(MARKER_WIDTH + SIGN_WIDTH),
+        }
+    }
+
+    /// Sides drawn for a row, with their widths.
+    fn sides(
+        self,
+        layout: DiffLayout,
+        row: &PullRequestDiffRow,
+        half: Option<PendingReviewCommentSide>,
+    ) -> Vec<(DiffSide, usize)> {
+        match layout {
+            DiffLayout::Split => vec![(DiffSide::Left, self.left), (DiffSide::Right, self.right)],
+            DiffLayout::Unified => vec![(unified_side(row, half), self.unified)],
+        }
+    }
+}
+
+/// Side a unified line shows: the removed side of removed rows and of the
+/// first half of modified rows, the new side otherwise.
+fn unified_side(row: &PullRequestDiffRow, half: Option<PendingReviewCommentSide>) -> DiffSide {
+    match (half, row.kind) {
+        (Some(PendingReviewCommentSide::Left), _) | (None, PullRequestDiffRowKind::Removed) => {
+            DiffSide::Left
+        }
+        _ => DiffSide::Right,
+    }
+}
+
+/// Screen lines one pane line wraps onto; the longer side of a row sets the
+/// height of both.
+fn wrapped_line_height(
+    file: &PullRequestDiffFile,
+    line: DiffLine,
+    layout: DiffLayout,
+    width: u16,
+) -> usize {
+    let (row_index, half) = match line {
+        DiffLine::Row(row) => (row, None),
+        DiffLine::Half { row, side } => (row, Some(side)),
+        DiffLine::Fold { .. } => return 1,
+    };
+    let Some(row) = file.rows.get(row_index) else {
+        return 1;
+    };
+    DiffColumns::new(usize::from(width.max(1)))
+        .sides(layout, row, half)
+        .into_iter()
+        .map(|(side, width)| wrapped_part_count(side_text(row, side), width))
+        .max()
+        .unwrap_or(1)
+}
+
+/// First pane line to render so that the selected line is fully on screen
+/// when rows wrap onto several screen lines.
+pub(crate) fn wrapped_scroll(
+    file: &PullRequestDiffFile,
+    lines: &[DiffLine],
+    layout: DiffLayout,
+    width: u16,
+    scroll: usize,
+    selected: usize,
+    viewport_height: usize,
+) -> usize {
+    if lines.is_empty() {
+        return scroll;
+    }
+    let selected = selected.min(lines.len() - 1);
+    let mut scroll = scroll.min(selected);
+    let height = |index: usize| wrapped_line_height(file, lines[index], layout, width);
+    let mut used = (scroll..=selected).map(height).sum::<usize>();
+    while used > viewport_height && scroll < selected {
+        used -= height(scroll);
+        scroll += 1;
+    }
+    scroll
 }
 
 pub(crate) fn render_rows(context: DiffRowsRenderContext<'_>) -> Vec<Line<'static>> {
     let DiffRowsRenderContext {
         file,
+        lines,
+        layout,
         width,
+        wrap,
+        column_offset,
         left_syntax,
         right_syntax,
         row_offset,
         row_limit,
         selected_line,
+        selected_side,
         selected_range,
         pending_comments,
+        thread_rows,
+        search_matches,
     } = context;
 
     let width = usize::from(width.max(1));
-    let marker_width = 2usize;
-    let separator = " │ ";
-    let available = width.saturating_sub(marker_width + separator.len());
-    let left_width = available / 2;
-    let right_width = available.saturating_sub(left_width);
+    let columns = DiffColumns::new(width);
 
-    file.rows
+    lines
         .iter()
-        .enumerate()
         .skip(row_offset)
-        .take(row_limit)
-        .map(|(row_index, row)| {
-            let in_selected_range =
-                selected_range.is_some_and(|(start, end)| row_index >= start && row_index <= end);
-            let has_pending = pending_comments
-                .iter()
-                .any(|comment| pending_comment_matches_row(comment, row));
-            let (marker, marker_style) = if row_index == selected_line {
+        .flat_map(|line| {
+            let (row_index, half) = match *line {
+                DiffLine::Row(row_index) => (row_index, None),
+                DiffLine::Half { row, side } => (row, Some(side)),
+                DiffLine::Fold { start, end } => {
+                    let is_selected = (start..=end).contains(&selected_line);
+                    return vec![render_fold(end - start + 1, is_selected, width)];
+                }
+            };
+            let Some(row) = file.rows.get(row_index) else {
+                return Vec::new();
+            };
+            let on_selected_side = half.is_none_or(|side| side == selected_side);
+            let is_selected = row_index == selected_line && on_selected_side;
+            let in_selected_range = on_selected_side
+                && selected_range
+                    .is_some_and(|(start, end)| row_index >= start && row_index <= end);
+            let has_pending = pending_comments.iter().any(|comment| {
+                half.is_none_or(|side| side == comment.side)
+                    && pending_comment_matches_row(comment, row)
+            });
+            let (marker, marker_style) = if is_selected {
                 ("▌ ", theme::open_thread())
             } else if has_pending {
                 ("● ", theme::resolved_thread())
+            } else if thread_rows.contains(&row_index) {
+                ("◆ ", theme::info())
+            } else if in_selected_range {
+                ("│ ", theme::dim())
+            } else {
+                ("  ", theme::dim())
+            };
+            // Continuation lines of a wrapped row keep only the selection bar.
+            let (continued_marker, continued_style) = if is_selected {
+                ("▌ ", theme::open_thread())
             } else if in_selected_range {
                 ("│ ", theme::dim())
             } else {
                 ("  ", theme::dim())
             };
 
-            let mut spans = Vec::new();
-            spans.push(Span::styled(marker.to_owned(), marker_style));
-            spans.extend(render_diff_side(DiffSideRenderContext {
-                line_number: row.left_line_number,
-                text: &row.left_text,
-                width: left_width,
-                syntax_fg: row
-                    .left_line_number
-                    .and_then(|line| left_syntax.get(line.saturating_sub(1)).map(Vec::as_slice)),
-                highlights: &row.left_highlights,
-                row_kind: row.kind,
-                side: DiffSide::Left,
-                in_selected_range,
-            }));
-            spans.push(Span::styled(separator.to_owned(), theme::dim()));
-            spans.extend(render_diff_side(DiffSideRenderContext {
-                line_number: row.right_line_number,
-                text: &row.right_text,
-                width: right_width,
-                syntax_fg: row
-                    .right_line_number
-                    .and_then(|line| right_syntax.get(line.saturating_sub(1)).map(Vec::as_slice)),
-                highlights: &row.right_highlights,
-                row_kind: row.kind,
-                side: DiffSide::Right,
-                in_selected_range,
-            }));
-            Line::from(spans)
+            let (left_matches, right_matches) = row_search_matches(search_matches, row_index, row);
+            let side_context = |side, width, part: usize| {
+                let (line_number, text, syntax, highlights, search_matches) = match side {
+                    DiffSide::Left => (
+                        row.left_line_number,
+                        &row.left_text,
+                        left_syntax,
+                        &row.left_highlights,
+                        &left_matches,
+                    ),
+                    DiffSide::Right => (
+                        row.right_line_number,
+                        &row.right_text,
+                        right_syntax,
+                        &row.right_highlights,
+                        &right_matches,
+                    ),
+                };
+                DiffSideRenderContext {
+                    line_number,
+                    show_line_number: part == 0,
+                    text,
+                    column_offset: if wrap {
+                        part * side_text_width(width)
+                    } else {
+                        column_offset
+                    },
+                    width,
+                    syntax_fg: line_number
+                        .and_then(|line| syntax.get(line.saturating_sub(1)).map(Vec::as_slice)),
+                    highlights,
+                    search_matches,
+                    row_kind: row.kind,
+                    side,
+                    in_selected_range,
+                }
+            };
+
+            let sides = columns.sides(layout, row, half);
+            let parts = if wrap {
+                sides
+                    .iter()
+                    .map(|(side, width)| wrapped_part_count(side_text(row, *side), *width))
+                    .max()
+                    .unwrap_or(1)
+            } else {
+                1
+            };
+            (0..parts)
+                .map(|part| {
+                    let mut spans = Vec::new();
+                    if part == 0 {
+                        spans.push(Span::styled(marker.to_owned(), marker_style));
+                    } else {
+                        spans.push(Span::styled(continued_marker.to_owned(), continued_style));
+                    }
+                    if layout == DiffLayout::Unified {
+                        let (side, width) = sides[0];
+                        let sign = match (part, side, row.kind) {
+                            (1.., _, _) | (_, _, PullRequestDiffRowKind::Context) => {
+                                Span::styled("  ", theme::dim())
+                            }
+                            (_, DiffSide::Left, _) => Span::styled("- ", theme::diff_remove()),
+                            (_, DiffSide::Right, _) => Span::styled("+ ", theme::diff_add()),
+                        };
+                        spans.push(sign);
+                        spans.extend(render_diff_side(side_context(side, width, part)));
+                    } else {
+                        spans.extend(render_diff_side(side_context(
+                            DiffSide::Left,
+                            columns.left,
+                            part,
+                        )));
+                        spans.push(Span::styled(SEPARATOR.to_owned(), theme::dim()));
+                        spans.extend(render_diff_side(side_context(
+                            DiffSide::Right,
+                            columns.right,
+                            part,
+                        )));
+                    }
+                    Line::from(spans)
+                })
+                .collect()
         })
+        .take(row_limit)
         .collect()
 }
 
+/// Search matches of one row as ranges of its old and new text. Unchanged
+/// rows show their new side matches on both sides.
+fn row_search_matches(
+    matches: &[DiffTextMatch],
+    row_index: usize,
+    row: &PullRequestDiffRow,
+) -> (
+    Vec<PullRequestDiffHighlightRange>,
+    Vec<PullRequestDiffHighlightRange>,
+) {
+    let start = matches.partition_point(|found| found.row < row_index);
+    let end = matches.partition_point(|found| found.row <= row_index);
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for found in &matches[start..end] {
+        let range = PullRequestDiffHighlightRange {
+            start: found.start,
+            end: found.end,
+        };
+        if found.side == PendingReviewCommentSide::Left
+            || row.kind == PullRequestDiffRowKind::Context
+        {
+            left.push(range);
+        }
+        if found.side == PendingReviewCommentSide::Right {
+            right.push(range);
+        }
+    }
+    (left, right)
+}
+
+fn side_text(row: &PullRequestDiffRow, side: DiffSide) -> &str {
+    match side {
+        DiffSide::Left => &row.left_text,
+        DiffSide::Right => &row.right_text,
+    }
+}
+
+/// Text columns of a side once its line number column is taken out.
+fn side_text_width(width: usize) -> usize {
+    width.saturating_sub(width.min(LINE_NUMBER_WIDTH))
+}
+
+/// Screen lines `text` wraps onto in a side `width` columns wide.
+fn wrapped_part_count(text: &str, width: usize) -> usize {
+    let text_width = side_text_width(width);
+    if text_width == 0 {
+        return 1;
+    }
+    text.chars().count().div_ceil(text_width).max(1)
+}
+
+/// Placeholder for a run of folded unchanged rows.
+fn render_fold(count: usize, is_selected: bool, width: usize) -> Line<'static> {
+    let (marker, marker_style) = if is_selected {
+        ("▌ ", theme::open_thread())
+    } else {
+        ("  ", theme::dim())
+    };
+    let label = if count == 1 {
+        " ⋯ 1 unchanged line ⋯ ".to_owned()
+    } else {
+        format!(" ⋯ {count} unchanged lines ⋯ ")
+    };
+    let filler = width.saturating_sub(2 + label.chars().count());
+    let left = filler / 2;
+    Line::from(vec![
+        Span::styled(marker.to_owned(), marker_style),
+        Span::styled("─".repeat(left), theme::dim()),
+        Span::styled(label, theme::dim()),
+        Span::styled("─".repeat(filler - left), theme::dim()),
+    ])
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum DiffSide {
     Left,
@@ -106,10 +379,16 @@ enum DiffSide {
 
 struct DiffSideRenderContext<'a> {
     line_number: Option<usize>,
+    /// Off on the continuation lines of a wrapped row.
+    show_line_number: bool,
     text: &'a str,
+    /// Characters of `text` before the visible part.
+    column_offset: usize,
     width: usize,
     syntax_fg: Option<&'a [Option<Color>]>,
     highlights: &'a [PullRequestDiffHighlightRange],
+    /// Diff text search matches, drawn over every other highlight.
+    search_matches: &'a [PullRequestDiffHighlightRange],
     row_kind: PullRequestDiffRowKind,
     side: DiffSide,
     in_selected_range: bool,
@@ -118,10 +397,13 @@ struct DiffSideRenderContext<'a> {
 fn render_diff_side(context: DiffSideRenderContext<'_>) -> Vec<Span<'static>> {
     let DiffSideRenderContext {
         line_number,
+        show_line_number,
         text,
+        column_offset,
         width,
         syntax_fg,
         highlights,
+        search_matches,
         row_kind,
         side,
         in_selected_range,
@@ -130,19 +412,31 @@ fn render_diff_side(context: DiffSideRenderContext<'_>) -> Vec<Span<'static>> {
         return Vec::new();
     }
 
-    let number_width = width.min(6);
-    let text_width = width.saturating_sub(number_width);
-    let number =
-        line_number.map_or_else(|| " ".repeat(number_width), |value| format!("{value:>5} "));
+    let number_width = width.min(LINE_NUMBER_WIDTH);
+    let text_width = side_text_width(width);
+    let number = match line_number {
+        Some(value) if show_line_number => format!("{value:>5} "),
+        _ => " ".repeat(number_width),
+    };
 
-    let visible_chars = text.chars().take(text_width).collect::<Vec<_>>();
+    let visible_chars = text
+        .chars()
+        .skip(column_offset)
+        .take(text_width)
+        .collect::<Vec<_>>();
     let visible_len = visible_chars.len();
+    let syntax_fg = syntax_fg.map(|fg| fg.get(column_offset..).unwrap_or_default());
     let color_set = DiffRowColors::new(row_kind, side);
-    let line_highlights = clip_and_merge_ranges(highlights, visible_len);
-    let has_line_highlights = !line_highlights.is_empty();
-    let full_line_highlight = line_highlights
+    let line_highlights = clip_and_merge_ranges(highlights, column_offset, visible_len);
+    // A full-line highlight also covers the padding past the end of the text.
+    let full_line_highlight = highlights
         .iter()
         .any(|range| PullRequestDiffHighlightRange::is_full_line(*range));
+    let has_line_highlights = full_line_highlight || !line_highlights.is_empty();
+    let match_mask = mask_highlight_ranges(
+        &clip_and_merge_ranges(search_matches, column_offset, visible_len),
+        visible_len,
+    );
     let alignment_gap = text.trim().is_empty()
         && line_number.is_none()
         && row_kind != PullRequestDiffRowKind::Context;
@@ -215,6 +509,7 @@ fn render_diff_side(context: DiffSideRenderContext<'_>) -> Vec<Span<'static>> {
             partial_base_style,
             highlight_style,
             Some(&highlight_mask),
+            &match_mask,
         ));
         if visible_len < text_width {
             spans.push(Span::styled(
@@ -232,6 +527,7 @@ fn render_diff_side(context: DiffSideRenderContext<'_>) -> Vec<Span<'static>> {
             highlight_style,
             highlight_style,
             None,
+            &match_mask,
         ));
         if visible_len < text_width {
             spans.push(Span::styled(
@@ -249,6 +545,7 @@ fn render_diff_side(context: DiffSideRenderContext<'_>) -> Vec<Span<'static>> {
         fill_style,
         fill_style,
         None,
+        &match_mask,
     ));
     if visible_len < text_width {
         spans.push(Span::styled(
@@ -338,8 +635,11 @@ fn row_line_for_pending_side(
     }
 }
 
+/// Ranges overlapping the `visible_len` characters from `offset`, relative to
+/// `offset` and merged.
 fn clip_and_merge_ranges(
     ranges: &[PullRequestDiffHighlightRange],
+    offset: usize,
     visible_len: usize,
 ) -> Vec<PullRequestDiffHighlightRange> {
     if ranges.is_empty() || visible_len == 0 {
@@ -352,8 +652,8 @@ fn clip_and_merge_ranges(
             if range.is_full_line() {
                 return Some(PullRequestDiffHighlightRange::full_line());
             }
-            let start = range.start.min(visible_len);
-            let end = range.end.min(visible_len);
+            let start = range.start.saturating_sub(offset).min(visible_len);
+            let end = range.end.saturating_sub(offset).min(visible_len);
             (start < end).then_some(PullRequestDiffHighlightRange { start, end })
         })
         .collect::<Vec<_>>();
@@ -404,10 +704,12 @@ fn render_syntax_cells(
     base_style: Style,
     highlight_style: Style,
     highlight_mask: Option<&[bool]>,
+    match_mask: &[bool],
 ) -> Vec<Span<'static>> {
     if chars.is_empty() {
         return Vec::new();
     }
+    let match_style = theme::selected();
 
     let mut spans = Vec::new();
     let mut buffer = String::new();
@@ -427,6 +729,9 @@ fn render_syntax_cells(
         if let Some(color) = syntax_fg.and_then(|fg| fg.get(index)).copied().flatten() {
             style = style.fg(color);
         }
+        if match_mask.get(index).copied().unwrap_or(false) {
+            style = match_style;
+        }
 
         if current_style == Some(style) {
             buffer.push(*ch);
@@ -454,3 +759,97 @@ fn render_syntax_cells(
 fn hatched_filler(width: usize) -> String {
     "╱".repeat(width)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffRowsRenderContext, clip_and_merge_ranges, render_rows, wrapped_scroll};
+    use crate::{
+        app::state::{DiffLayout, DiffLine, PendingReviewCommentSide},
+        domain::{
+            PullRequestDiffFile, PullRequestDiffFileStatus, PullRequestDiffHighlightRange,
+            PullRequestDiffRow, PullRequestDiffRowKind,
+        },
+    };
+    use std::collections::HashSet;
+
+    fn render(file: &PullRequestDiffFile, wrap: bool, column_offset: usize) -> Vec<String> {
+        render_rows(DiffRowsRenderContext {
+            file,
+            lines: &[DiffLine::Row(0)],
+            layout: DiffLayout::Split,
+            // Ten text columns per side.
+            width: 39,
+            wrap,
+            column_offset,
+            left_syntax: &[],
+            right_syntax: &[],
+            row_offset: 0,
+            row_limit: 10,
+            selected_line: 0,
+            selected_side: PendingReviewCommentSide::Left,
+            selected_range: None,
+            pending_comments: &[],
+            thread_rows: &HashSet::new(),
+            search_matches: &[],
+        })
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+    }
+
+    #[test]
+    fn wraps_long_rows_and_scrolls_both_sides_together() {
+        let file = PullRequestDiffFile {
+            path: "src/lib.rs".to_owned(),
+            status: PullRequestDiffFileStatus::Modified,
+            rows: vec![PullRequestDiffRow {
+                left_line_number: Some(1),
+                right_line_number: Some(1),
+                left_text: format!("{}{}", "a".repeat(20), "b".repeat(10)),
+                right_text: "c".repeat(10),
+                left_highlights: vec![PullRequestDiffHighlightRange { start: 20, end: 25 }],
+                right_highlights: Vec::new(),
+                kind: PullRequestDiffRowKind::Modified,
+            }],
+            hunk_starts: vec![0],
+        };
+
+        assert_eq!(
+            render(&file, false, 0),
+            vec!["▌     1 aaaaaaaaaa │     1 cccccccccc"]
+        );
+        assert_eq!(
+            render(&file, false, 15),
+            vec!["▌     1 aaaaabbbbb │     1           "]
+        );
+        assert_eq!(
+            render(&file, true, 0),
+            vec![
+                "▌     1 aaaaaaaaaa │     1 cccccccccc",
+                "▌       aaaaaaaaaa │                 ",
+                "▌       bbbbbbbbbb │                 ",
+            ]
+        );
+
+        assert_eq!(
+            clip_and_merge_ranges(&file.rows[0].left_highlights, 15, 10),
+            vec![PullRequestDiffHighlightRange { start: 5, end: 10 }]
+        );
+        assert!(clip_and_merge_ranges(&file.rows[0].left_highlights, 25, 10).is_empty());
+    }
+
+    #[test]
+    fn wrapped_scroll_handles_files_without_rows() {
+        let file = PullRequestDiffFile {
+            path: "pkg/__init__.py".to_owned(),
+            status: PullRequestDiffFileStatus::Added,
+            rows: Vec::new(),
+            hunk_starts: Vec::new(),
+        };
+
+        assert_eq!(
+            wrapped_scroll(&file, &[], DiffLayout::Split, 39, 0, 0, 10),
+            0
+        );
+    }
+}
@@ -253,6 +253,13 @@ fn render_results(frame: &mut Frame<'_>, area: Rect, state: &AppState) {
                 }
             };
 
+            // Unread comments lead the title so truncation never hides them.
+            let mut title_spans = Vec::with_capacity(2);
+            if let Some(unread) = state.seen.unread_count(pull).filter(|count| *count > 0) {
+                title_spans.push(Span::styled(format!("● {unread} "), theme::info()));
+            }
+            title_spans.push(Span::styled(pull.title.clone(), theme::text()));
+
             Row::new([
                 Cell::new(Span::styled(short_timestamp(age_ms), theme::dim())),
                 Cell::new(Line::from(vec![
@@ -264,7 +271,7 @@ fn render_results(frame: &mut Frame<'_>, area: Rect, state: &AppState) {
                     Line::styled(format!("#{}", pull.number), theme::strong_text())
                         .alignment(Alignment::Right),
                 ),
-                Cell::new(Span::styled(pull.title.clone(), theme::text())),
+                Cell::new(Line::from(title_spans)),
             ])
         })
         .collect();
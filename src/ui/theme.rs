@@ -100,6 +100,40 @@ impl ThemePalette {
             gauge_empty: Color::Gray,
         }
     }
+
+    /// Overrides the color for a config field name such as `diff_add`.
+    /// Returns `false` when `field` does not name a palette color.
+    pub fn set_color(&mut self, field: &str, color: Color) -> bool {
+        let slot = match field {
+            "border" => &mut self.border,
+            "title" => &mut self.title,
+            "dim" => &mut self.dim,
+            "text" => &mut self.text,
+            "selected_fg" => &mut self.selected_fg,
+            "selected_bg" => &mut self.selected_bg,
+            "issue" => &mut self.issue,
+            "open_thread" => &mut self.open_thread,
+            "resolved_thread" => &mut self.resolved_thread,
+            "error" => &mut self.error,
+            "info" => &mut self.info,
+            "link" => &mut self.link,
+            "inline_code_fg" => &mut self.inline_code_fg,
+            "inline_code_bg" => &mut self.inline_code_bg,
+            "section_title" => &mut self.section_title,
+            "author" => &mut self.author,
+            "outdated" => &mut self.outdated,
+            "diff_header" => &mut self.diff_header,
+            "diff_add" => &mut self.diff_add,
+            "diff_remove" => &mut self.diff_remove,
+            "diff_context" => &mut self.diff_context,
+            "gauge_label" => &mut self.gauge_label,
+            "gauge_fill" => &mut self.gauge_fill,
+            "gauge_empty" => &mut self.gauge_empty,
+            _ => return false,
+        };
+        *slot = color;
+        true
+    }
 }
 
 #[derive(Debug, Clone)]